@@ -0,0 +1,112 @@
+//! `tapsdk-cli doctor`
+
+use std::time::Duration;
+
+use tapsdk_pc::{ownership, CloudSave, TapEvent, TapSdk};
+
+use crate::{saves, wait_for, Result};
+
+/// Print a check result and remember failures
+struct Report {
+    failed: bool,
+}
+
+impl Report {
+    fn pass(&self, check: &str, detail: impl std::fmt::Display) {
+        println!("[ ok ] {}: {}", check, detail);
+    }
+
+    fn warn(&self, check: &str, detail: impl std::fmt::Display) {
+        println!("[warn] {}: {}", check, detail);
+    }
+
+    fn fail(&mut self, check: &str, detail: impl std::fmt::Display) {
+        println!("[FAIL] {}: {}", check, detail);
+        self.failed = true;
+    }
+}
+
+pub fn run(pub_key: Option<&str>, client_id: Option<&str>, timeout: Duration) -> Result<()> {
+    let mut report = Report { failed: false };
+
+    if cfg!(windows) {
+        report.pass("platform", std::env::consts::OS);
+    } else if cfg!(feature = "dev-sim") {
+        report.warn("platform", "not Windows, using the simulated platform");
+    } else {
+        report.fail(
+            "platform",
+            "the TapTap PC SDK only supports Windows (build with --features dev-sim to simulate)",
+        );
+        return Err("environment check failed".into());
+    }
+
+    match client_id {
+        Some(client_id) => match tapsdk_pc::restart_app_if_necessary(client_id) {
+            Ok(false) => report.pass("launch", "no restart required"),
+            Ok(true) => report.warn(
+                "launch",
+                "TapTap requested a restart, launch the game from the TapTap client",
+            ),
+            Err(e) => report.fail("launch", e),
+        },
+        None => report.warn("launch", "skipped, pass --client-id to check"),
+    }
+
+    let Some(pub_key) = pub_key else {
+        report.fail(
+            "init",
+            "missing public key, pass --pub-key or set TAPSDK_PUB_KEY",
+        );
+        return Err("environment check failed".into());
+    };
+    let sdk = match TapSdk::init(pub_key) {
+        Ok(sdk) => {
+            report.pass("init", "SDK initialized");
+            sdk
+        }
+        Err(e) => {
+            report.fail("init", e);
+            return Err("environment check failed".into());
+        }
+    };
+
+    match sdk.get_client_id() {
+        Some(id) => report.pass("client id", id),
+        None => report.warn("client id", "not available"),
+    }
+
+    if ownership::is_game_owned() {
+        report.pass("ownership", "the current user owns the game");
+    } else {
+        report.fail("ownership", "the current user does not own the game");
+    }
+
+    // The client reports its state shortly after init
+    match wait_for(
+        &sdk,
+        Duration::from_secs(3).min(timeout),
+        |event| match event {
+            TapEvent::SystemStateChanged(data) => Some(data.state),
+            _ => None,
+        },
+    ) {
+        Ok(state) => report.pass("platform state", state.to_string()),
+        Err(_) => report.warn("platform state", "no state notification received"),
+    }
+
+    match CloudSave::get() {
+        Some(cloud_save) => match saves::list(&sdk, &cloud_save, timeout) {
+            Ok(list) => report.pass("cloud save", format!("{} save(s)", list.len())),
+            Err(e) => report.fail("cloud save", e),
+        },
+        None => report.fail("cloud save", "handle not available"),
+    }
+
+    if report.failed {
+        Err("environment check failed".into())
+    } else {
+        println!("All checks passed");
+        Ok(())
+    }
+}
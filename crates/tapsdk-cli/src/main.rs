@@ -0,0 +1,216 @@
+//! Command-line tool for TapTap PC SDK
+//!
+//! Lets developers and QA inspect cloud saves and verify client connectivity
+//! without writing a throwaway program:
+//!
+//! ```text
+//! tapsdk-cli --pub-key <KEY> doctor
+//! tapsdk-cli --pub-key <KEY> auth --scopes public_profile
+//! tapsdk-cli --pub-key <KEY> saves list
+//! tapsdk-cli --pub-key <KEY> saves download <UUID> -o save.dat
+//! tapsdk-cli --pub-key <KEY> saves upload save.dat --name slot1 --summary "Chapter 1"
+//! tapsdk-cli --pub-key <KEY> saves delete <UUID>
+//! ```
+
+mod doctor;
+mod saves;
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand};
+use tapsdk_pc::{user, SaveUuid, TapEvent, TapSdk};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Parser)]
+#[command(name = "tapsdk-cli", version, about)]
+struct Cli {
+    /// Public key from the TapTap developer center
+    #[arg(long, env = "TAPSDK_PUB_KEY", global = true, hide_env_values = true)]
+    pub_key: Option<String>,
+
+    /// Client ID, used by `doctor` to check whether a restart is required
+    #[arg(long, env = "TAPSDK_CLIENT_ID", global = true)]
+    client_id: Option<String>,
+
+    /// Seconds to wait for each SDK response
+    #[arg(long, default_value_t = 30, global = true)]
+    timeout: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check that the TapTap client is reachable and the game is set up
+    Doctor,
+    /// Authorize the current user and print the token
+    Auth {
+        /// Permission scopes, comma-separated
+        #[arg(long, default_value = "public_profile")]
+        scopes: String,
+    },
+    /// Inspect and modify cloud saves
+    #[command(subcommand)]
+    Saves(SavesCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum SavesCommand {
+    /// List all cloud saves
+    List,
+    /// Download the data (or cover) file of a cloud save
+    Download {
+        /// UUID of the cloud save
+        uuid: SaveUuid,
+        /// Where to write the file (defaults to `<uuid>.dat` / `<uuid>.cover`)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Download the cover image instead of the data file
+        #[arg(long)]
+        cover: bool,
+    },
+    /// Create a cloud save from a file, or update one with `--uuid`
+    Upload {
+        /// Save data file (max 10MB)
+        file: PathBuf,
+        /// Save name
+        #[arg(long)]
+        name: String,
+        /// Save description, required by the SDK
+        #[arg(long)]
+        summary: String,
+        /// Developer-defined extra data
+        #[arg(long)]
+        extra: Option<String>,
+        /// Playtime in seconds
+        #[arg(long, default_value_t = 0)]
+        playtime: u64,
+        /// Cover image file (max 512KB)
+        #[arg(long)]
+        cover: Option<PathBuf>,
+        /// Update this cloud save instead of creating a new one
+        #[arg(long)]
+        uuid: Option<SaveUuid>,
+    },
+    /// Delete a cloud save
+    Delete {
+        /// UUID of the cloud save
+        uuid: SaveUuid,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let timeout = Duration::from_secs(cli.timeout);
+
+    let result = match cli.command {
+        Command::Doctor => doctor::run(cli.pub_key.as_deref(), cli.client_id.as_deref(), timeout),
+        command => init(cli.pub_key.as_deref()).and_then(|sdk| match command {
+            Command::Auth { scopes } => auth(&sdk, &scopes, timeout),
+            Command::Saves(command) => saves::run(&sdk, command, timeout),
+            Command::Doctor => unreachable!(),
+        }),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Initialize the SDK with the public key from the command line
+fn init(pub_key: Option<&str>) -> Result<TapSdk> {
+    let pub_key = pub_key.ok_or("missing public key, pass --pub-key or set TAPSDK_PUB_KEY")?;
+    Ok(TapSdk::init(pub_key)?)
+}
+
+/// Poll the SDK until `select` accepts an event or `timeout` elapses
+pub fn wait_for<T>(
+    sdk: &TapSdk,
+    timeout: Duration,
+    mut select: impl FnMut(TapEvent) -> Option<T>,
+) -> Result<T> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        for event in sdk.run_callbacks() {
+            if let Some(value) = select(event) {
+                return Ok(value);
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err("timed out waiting for the TapTap client to respond".into());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn auth(sdk: &TapSdk, scopes: &str, timeout: Duration) -> Result<()> {
+    user::authorize(scopes)?;
+    let data = wait_for(sdk, timeout, |event| match event {
+        TapEvent::AuthorizeFinished(data) => Some(data),
+        _ => None,
+    })?;
+
+    if data.is_cancel {
+        return Err("authorization was cancelled".into());
+    }
+    if let Some(error) = data.error {
+        return Err(format!("authorization failed: {}", error).into());
+    }
+    let token = data.token.ok_or("authorization returned no token")?;
+    println!("open_id:       {}", user::get_open_id().unwrap_or_default());
+    println!("token_type:    {}", token.token_type);
+    println!("kid:           {}", token.kid);
+    println!("mac_key:       {}", token.mac_key);
+    println!("mac_algorithm: {}", token.mac_algorithm);
+    println!("scope:         {}", token.scope);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_upload() {
+        let cli = Cli::try_parse_from([
+            "tapsdk-cli",
+            "saves",
+            "upload",
+            "save.dat",
+            "--name",
+            "slot1",
+            "--summary",
+            "Chapter 1",
+            "--pub-key",
+            "key",
+        ])
+        .unwrap();
+        assert_eq!(cli.pub_key.as_deref(), Some("key"));
+        assert!(matches!(
+            cli.command,
+            Command::Saves(SavesCommand::Upload { uuid: None, .. })
+        ));
+    }
+
+    #[test]
+    fn test_upload_requires_summary() {
+        let cli = Cli::try_parse_from(["tapsdk-cli", "saves", "upload", "save.dat", "--name", "a"]);
+        assert!(cli.is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! `tapsdk-cli saves`
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tapsdk_pc::callback::CloudSaveInfo;
+use tapsdk_pc::cloudsave::{next_request_id, CreateSaveRequest, UpdateSaveRequest};
+use tapsdk_pc::{CloudSave, TapEvent, TapSdk};
+
+use crate::{wait_for, Result, SavesCommand};
+
+pub fn run(sdk: &TapSdk, command: SavesCommand, timeout: Duration) -> Result<()> {
+    let cloud_save = CloudSave::get().ok_or("cloud save is not available")?;
+
+    match command {
+        SavesCommand::List => {
+            let saves = list(sdk, &cloud_save, timeout)?;
+            if saves.is_empty() {
+                println!("No cloud saves");
+            }
+            for save in saves {
+                print_save(&save);
+            }
+        }
+        SavesCommand::Download {
+            uuid,
+            output,
+            cover,
+        } => {
+            let save = list(sdk, &cloud_save, timeout)?
+                .into_iter()
+                .find(|save| save.uuid == uuid)
+                .ok_or_else(|| format!("no cloud save with UUID {}", uuid))?;
+
+            let request_id = next_request_id();
+            if cover {
+                cloud_save.get_cover(request_id, &save.uuid, &save.file_id)?;
+            } else {
+                cloud_save.get_data(request_id, &save.uuid, &save.file_id)?;
+            }
+            let data = wait_for(sdk, timeout, |event| match event {
+                TapEvent::CloudSaveGetData(data) | TapEvent::CloudSaveGetCover(data)
+                    if data.request_id == request_id =>
+                {
+                    Some(data)
+                }
+                _ => None,
+            })?;
+            if let Some((code, message)) = data.error {
+                return Err(format!("download failed ({}): {}", code, message).into());
+            }
+
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from(format!("{}.{}", uuid, if cover { "cover" } else { "dat" }))
+            });
+            std::fs::write(&output, &data.data)?;
+            println!("Wrote {} bytes to {}", data.data.len(), output.display());
+        }
+        SavesCommand::Upload {
+            file,
+            name,
+            summary,
+            extra,
+            playtime,
+            cover,
+            uuid,
+        } => {
+            let data_file_path = file.into_boxed_path();
+            let cover_file_path = cover.map(PathBuf::into_boxed_path);
+            let request_id = next_request_id();
+            match uuid {
+                Some(uuid) => cloud_save.update(
+                    request_id,
+                    &UpdateSaveRequest {
+                        uuid,
+                        name,
+                        summary,
+                        extra,
+                        playtime,
+                        data_file_path,
+                        cover_file_path,
+                    },
+                )?,
+                None => cloud_save.create(
+                    request_id,
+                    &CreateSaveRequest {
+                        name,
+                        summary,
+                        extra,
+                        playtime,
+                        data_file_path,
+                        cover_file_path,
+                    },
+                )?,
+            }
+            let data = wait_for(sdk, timeout, |event| match event {
+                TapEvent::CloudSaveCreate(data) | TapEvent::CloudSaveUpdate(data)
+                    if data.request_id == request_id =>
+                {
+                    Some(data)
+                }
+                _ => None,
+            })?;
+            if let Some((code, message)) = data.error {
+                return Err(format!("upload failed ({}): {}", code, message).into());
+            }
+            if let Some(save) = data.save {
+                print_save(&save);
+            }
+        }
+        SavesCommand::Delete { uuid } => {
+            let request_id = next_request_id();
+            cloud_save.delete(request_id, &uuid)?;
+            let data = wait_for(sdk, timeout, |event| match event {
+                TapEvent::CloudSaveDelete(data) if data.request_id == request_id => Some(data),
+                _ => None,
+            })?;
+            if let Some((code, message)) = data.error {
+                return Err(format!("delete failed ({}): {}", code, message).into());
+            }
+            println!("Deleted {}", data.uuid);
+        }
+    }
+    Ok(())
+}
+
+/// Fetch the list of cloud saves
+pub fn list(sdk: &TapSdk, cloud_save: &CloudSave, timeout: Duration) -> Result<Vec<CloudSaveInfo>> {
+    let request_id = next_request_id();
+    cloud_save.list(request_id)?;
+    let data = wait_for(sdk, timeout, |event| match event {
+        TapEvent::CloudSaveList(data) if data.request_id == request_id => Some(data),
+        _ => None,
+    })?;
+    match data.error {
+        Some((code, message)) => Err(format!("list failed ({}): {}", code, message).into()),
+        None => Ok(data.saves),
+    }
+}
+
+fn print_save(save: &CloudSaveInfo) {
+    println!("{}", save.uuid);
+    println!("  name:     {}", save.name);
+    if let Some(summary) = &save.summary {
+        println!("  summary:  {}", summary);
+    }
+    if let Some(extra) = &save.extra {
+        println!("  extra:    {}", extra);
+    }
+    println!("  file_id:  {}", save.file_id);
+    println!(
+        "  size:     {} bytes (cover {} bytes)",
+        save.save_size, save.cover_size
+    );
+    println!("  playtime: {}s", save.playtime.as_secs());
+    println!("  modified: {}", unix_seconds(save.modified_time));
+}
+
+/// Seconds since the Unix epoch
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
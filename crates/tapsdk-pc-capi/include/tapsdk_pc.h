@@ -0,0 +1,140 @@
+/*
+ * C interface to TapTap PC SDK
+ *
+ * Built from the tapsdk-pc-capi crate. Link against tapsdk_pc_capi (dll +
+ * import library, or the static library) and ship taptap_api.dll next to
+ * the executable.
+ *
+ * Functions returning int32_t return a TapPcStatus. On failure,
+ * tappc_last_error() describes the error on the calling thread.
+ * A panic inside the library never unwinds into the caller: the function
+ * returns TAPPC_ERROR_PANIC, false, 0 or nothing instead.
+ * All strings are UTF-8 and NUL-terminated.
+ */
+
+#ifndef TAPSDK_PC_H
+#define TAPSDK_PC_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum TapPcStatus {
+    TAPPC_OK = 0,
+    TAPPC_ERROR_NOT_INITIALIZED = 1,
+    TAPPC_ERROR_INVALID_ARGUMENT = 2,
+    TAPPC_ERROR_INIT_FAILED = 3,
+    TAPPC_ERROR_AUTHORIZE_FAILED = 4,
+    TAPPC_ERROR_CLOUD_SAVE_FAILED = 5,
+    TAPPC_ERROR_OTHER = 6,
+    TAPPC_ERROR_PLATFORM_OFFLINE = 7,
+    TAPPC_ERROR_PANIC = 8
+} TapPcStatus;
+
+/* Event IDs */
+#define TAPPC_EVENT_SYSTEM_STATE_CHANGED 1
+#define TAPPC_EVENT_AUTHORIZE_FINISHED 2002
+#define TAPPC_EVENT_GAME_PLAYABLE_STATUS_CHANGED 4001
+#define TAPPC_EVENT_DLC_PLAYABLE_STATUS_CHANGED 4002
+#define TAPPC_EVENT_CLOUD_SAVE_LIST 6001
+#define TAPPC_EVENT_CLOUD_SAVE_CREATE 6002
+#define TAPPC_EVENT_CLOUD_SAVE_UPDATE 6003
+#define TAPPC_EVENT_CLOUD_SAVE_DELETE 6004
+#define TAPPC_EVENT_CLOUD_SAVE_GET_DATA 6005
+#define TAPPC_EVENT_CLOUD_SAVE_GET_COVER 6006
+#define TAPPC_EVENT_INTERNAL_ERROR 90001
+#define TAPPC_EVENT_PARSE_ERROR 90002
+#define TAPPC_EVENT_CONNECTION_LOST 90004
+#define TAPPC_EVENT_CONNECTION_RESTORED 90005
+#define TAPPC_EVENT_CLOUD_SAVE_PROGRESS 90006
+
+/*
+ * An SDK event
+ *
+ * json holds the event fields as a JSON object, using the same field names
+ * as the JavaScript bindings (e.g. {"eventId":6001,"requestId":1,...}).
+ * data/data_len hold the file contents of CLOUD_SAVE_GET_DATA and
+ * CLOUD_SAVE_GET_COVER events and are NULL/0 otherwise.
+ */
+typedef struct TapPcEvent {
+    uint32_t event_id;
+    char* json;
+    uint8_t* data;
+    size_t data_len;
+} TapPcEvent;
+
+/* Parameters of a cloud save create/update request */
+typedef struct TapPcSaveRequest {
+    const char* name;
+    const char* summary;
+    const char* extra;           /* may be NULL */
+    uint64_t playtime;           /* seconds */
+    const char* data_file_path;  /* max 10MB */
+    const char* cover_file_path; /* may be NULL, max 512KB */
+} TapPcSaveRequest;
+
+/*
+ * Called from tappc_run_callbacks() on the calling thread. The event is
+ * released after the callback returns; copy anything you need to keep.
+ */
+typedef void (*TapPcEventCallback)(const TapPcEvent* event, void* user_data);
+
+/* Message of the last error on this thread, valid until the next failure */
+const char* tappc_last_error(void);
+
+/* Returns true if TapTap will relaunch the game; exit immediately */
+bool tappc_restart_app_if_necessary(const char* client_id);
+
+int32_t tappc_init(const char* pub_key);
+void tappc_shutdown(void);
+bool tappc_is_initialized(void);
+
+/*
+ * String getters copy into buffer like snprintf and return the buffer size
+ * needed including the NUL, or 0 if the value is unavailable. Pass
+ * buffer = NULL, len = 0 to query the size.
+ */
+size_t tappc_get_client_id(char* buffer, size_t len);
+size_t tappc_get_open_id(char* buffer, size_t len);
+
+bool tappc_is_game_owned(void);
+/* Last reported GAME_PLAYABLE_STATUS_CHANGED state, ownership until then */
+bool tappc_is_game_playable(void);
+bool tappc_is_dlc_owned(const char* dlc_id);
+int32_t tappc_show_dlc_store(const char* dlc_id);
+
+/* Result is delivered as TAPPC_EVENT_AUTHORIZE_FINISHED */
+int32_t tappc_authorize(const char* scopes);
+
+/* Results are delivered as TAPPC_EVENT_CLOUD_SAVE_* with the same request_id */
+int32_t tappc_cloudsave_list(int64_t request_id);
+int32_t tappc_cloudsave_create(int64_t request_id, const TapPcSaveRequest* request);
+int32_t tappc_cloudsave_update(int64_t request_id, const char* uuid,
+                               const TapPcSaveRequest* request);
+int32_t tappc_cloudsave_delete(int64_t request_id, const char* uuid);
+int32_t tappc_cloudsave_get_data(int64_t request_id, const char* uuid, const char* file_id);
+int32_t tappc_cloudsave_get_cover(int64_t request_id, const char* uuid, const char* file_id);
+
+/* Install (or remove, with NULL) the event callback */
+void tappc_set_event_callback(TapPcEventCallback callback, void* user_data);
+
+/*
+ * Poll the SDK; call once per frame. Events go to the event callback if one
+ * is installed, otherwise they are queued for tappc_poll_event().
+ * Returns the number of events received.
+ */
+size_t tappc_run_callbacks(void);
+
+/* Take the next queued event; release it with tappc_event_free() */
+bool tappc_poll_event(TapPcEvent* out);
+void tappc_event_free(TapPcEvent* event);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TAPSDK_PC_H */
@@ -0,0 +1,105 @@
+//! Events handed across the C boundary
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use tapsdk_pc::json::{self, JsonEvent};
+use tapsdk_pc::TapEvent;
+
+/// An SDK event
+///
+/// `json` and `data` are owned by the event and released with
+/// [`tappc_event_free`]. Events passed to the event callback are released
+/// after the callback returns.
+#[repr(C)]
+pub struct TapPcEvent {
+    pub event_id: u32,
+    /// Event fields as a NUL-terminated JSON object
+    pub json: *mut c_char,
+    /// File contents of `CloudSaveGetData` / `CloudSaveGetCover`, otherwise NULL
+    pub data: *mut u8,
+    pub data_len: usize,
+}
+
+impl TapPcEvent {
+    pub(crate) fn new(event: TapEvent) -> Self {
+        let event_id = event.event_id();
+        // File contents are returned separately instead of being encoded
+        let JsonEvent { value, data } = json::event_to_json(event);
+        // serde_json escapes control characters, so the output has no NUL
+        let json = CString::new(value.to_string()).unwrap_or_default();
+        let (data, data_len) = match data {
+            Some(data) => {
+                let len = data.len();
+                (Box::into_raw(data.into_boxed_slice()) as *mut u8, len)
+            }
+            None => (std::ptr::null_mut(), 0),
+        };
+        TapPcEvent {
+            event_id,
+            json: json.into_raw(),
+            data,
+            data_len,
+        }
+    }
+}
+
+// Events still owned by Rust (delivered to the callback, or left in the
+// queue at shutdown) release their buffers when dropped
+impl Drop for TapPcEvent {
+    fn drop(&mut self) {
+        unsafe { tappc_event_free(self) }
+    }
+}
+
+// The buffers are uniquely owned by the event
+unsafe impl Send for TapPcEvent {}
+
+/// Release an event returned by `tappc_poll_event()`
+#[no_mangle]
+pub unsafe extern "C" fn tappc_event_free(event: *mut TapPcEvent) {
+    crate::guard((), || {
+        let Some(event) = event.as_mut() else {
+            return;
+        };
+        if !event.json.is_null() {
+            drop(CString::from_raw(event.json));
+            event.json = std::ptr::null_mut();
+        }
+        if !event.data.is_null() {
+            let data = std::ptr::slice_from_raw_parts_mut(event.data, event.data_len);
+            drop(Box::from_raw(data));
+            event.data = std::ptr::null_mut();
+            event.data_len = 0;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::ffi::CStr;
+    use tapsdk_pc::callback::{event_id, CloudSaveGetFileData};
+
+    #[test]
+    fn test_file_event_round_trip() {
+        let mut event = TapPcEvent::new(TapEvent::CloudSaveGetData(CloudSaveGetFileData {
+            request_id: 1,
+            error: Some((400002, "missing".to_string())),
+            data: vec![1, 2, 3],
+        }));
+        assert_eq!(event.event_id, event_id::CLOUD_SAVE_GET_DATA);
+        let data = unsafe { std::slice::from_raw_parts(event.data, event.data_len) };
+        assert_eq!(data, [1, 2, 3]);
+        let json = unsafe { CStr::from_ptr(event.json) }.to_str().unwrap();
+        let json: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["error"]["code"], 400002);
+
+        unsafe { tappc_event_free(&mut event) };
+        assert!(event.json.is_null());
+        assert!(event.data.is_null());
+        // Freeing twice is harmless
+        unsafe { tappc_event_free(&mut event) };
+    }
+}
@@ -0,0 +1,500 @@
+//! C interface to TapTap PC SDK
+//!
+//! Exposes the safe `tapsdk-pc` layer through a small, stable C ABI so
+//! Unity, Unreal and custom C++ engines share the same callback parsing,
+//! validation and queueing the Node.js bindings use. The interface is
+//! declared in `include/tapsdk_pc.h`.
+//!
+//! Conventions:
+//! - Functions returning `int32_t` return a `TapPcStatus`; on failure,
+//!   `tappc_last_error()` describes the error on the calling thread.
+//! - A panic never unwinds into the caller: the function returns
+//!   `TAPPC_ERROR_PANIC`, `false`, `0` or nothing instead, and
+//!   `tappc_last_error()` describes the panic.
+//! - Strings are UTF-8 and NUL-terminated.
+//! - Events are delivered by `tappc_run_callbacks()`, either to the callback
+//!   installed with `tappc_set_event_callback()` or into a queue drained with
+//!   `tappc_poll_event()`. Event fields are encoded as a JSON object using the
+//!   same field names as the JavaScript bindings; file contents of
+//!   `CloudSaveGetData` / `CloudSaveGetCover` are passed as raw bytes instead.
+
+// Safety requirements are documented per function in the C header
+#![allow(clippy::missing_safety_doc)]
+
+mod event;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use tapsdk_pc::cloudsave::{CreateSaveRequest, UpdateSaveRequest};
+use tapsdk_pc::{dlc, ownership, user, CloudSave, TapSdk, TapSdkError};
+
+pub use event::{tappc_event_free, TapPcEvent};
+
+/// `TapPcStatus` values
+pub const TAPPC_OK: i32 = 0;
+pub const TAPPC_ERROR_NOT_INITIALIZED: i32 = 1;
+pub const TAPPC_ERROR_INVALID_ARGUMENT: i32 = 2;
+pub const TAPPC_ERROR_INIT_FAILED: i32 = 3;
+pub const TAPPC_ERROR_AUTHORIZE_FAILED: i32 = 4;
+pub const TAPPC_ERROR_CLOUD_SAVE_FAILED: i32 = 5;
+pub const TAPPC_ERROR_OTHER: i32 = 6;
+pub const TAPPC_ERROR_PLATFORM_OFFLINE: i32 = 7;
+pub const TAPPC_ERROR_PANIC: i32 = 8;
+
+/// Event callback installed with [`tappc_set_event_callback`]
+pub type TapPcEventCallback =
+    unsafe extern "C" fn(event: *const TapPcEvent, user_data: *mut c_void);
+
+/// The initialized SDK
+static SDK: Mutex<Option<TapSdk>> = Mutex::new(None);
+
+/// Events waiting for `tappc_poll_event()`
+static QUEUE: Mutex<VecDeque<TapPcEvent>> = Mutex::new(VecDeque::new());
+
+/// Installed event callback and its user data
+struct Callback {
+    callback: TapPcEventCallback,
+    user_data: *mut c_void,
+}
+
+// The user data is only handed back to the callback, never dereferenced
+unsafe impl Send for Callback {}
+
+static CALLBACK: Mutex<Option<Callback>> = Mutex::new(None);
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Run the body of an entry point, returning `on_panic` if it panics
+///
+/// Unwinding out of an `extern "C"` function aborts the host process.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.as_str()
+        } else {
+            "unknown panic"
+        };
+        set_last_error(format!("panic in tapsdk-pc: {}", message));
+        on_panic
+    })
+}
+
+/// Record an error and map it to a status code
+fn fail(error: TapSdkError) -> i32 {
+    set_last_error(error.to_string());
+    match error {
+        TapSdkError::NotInitialized => TAPPC_ERROR_NOT_INITIALIZED,
+        TapSdkError::InitFailed { .. } => TAPPC_ERROR_INIT_FAILED,
+        TapSdkError::AuthorizeFailed(_) => TAPPC_ERROR_AUTHORIZE_FAILED,
+        TapSdkError::CloudSaveRequestFailed { .. } => TAPPC_ERROR_CLOUD_SAVE_FAILED,
+        TapSdkError::PlatformOffline => TAPPC_ERROR_PLATFORM_OFFLINE,
+        TapSdkError::InvalidArgument(_)
+        | TapSdkError::InvalidField { .. }
+        | TapSdkError::Utf8Error(_)
+        | TapSdkError::NulError(_) => TAPPC_ERROR_INVALID_ARGUMENT,
+        _ => TAPPC_ERROR_OTHER,
+    }
+}
+
+fn status(result: tapsdk_pc::Result<()>) -> i32 {
+    match result {
+        Ok(()) => TAPPC_OK,
+        Err(e) => fail(e),
+    }
+}
+
+/// Read a required string argument
+unsafe fn arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, i32> {
+    if ptr.is_null() {
+        set_last_error(format!("{} must not be NULL", name));
+        return Err(TAPPC_ERROR_INVALID_ARGUMENT);
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| {
+        set_last_error(format!("{} is not valid UTF-8", name));
+        TAPPC_ERROR_INVALID_ARGUMENT
+    })
+}
+
+/// Read a save UUID or file ID argument
+unsafe fn id_arg<T: FromStr<Err = TapSdkError>>(ptr: *const c_char, name: &str) -> Result<T, i32> {
+    arg(ptr, name)?.parse().map_err(|e: TapSdkError| {
+        set_last_error(e.to_string());
+        TAPPC_ERROR_INVALID_ARGUMENT
+    })
+}
+
+/// Read an optional string argument
+unsafe fn optional_arg<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>, i32> {
+    if ptr.is_null() {
+        Ok(None)
+    } else {
+        arg(ptr, name).map(Some)
+    }
+}
+
+/// Copy a string into a caller buffer, `snprintf` style
+///
+/// Returns the buffer size needed to hold the string and its NUL.
+unsafe fn copy_out(value: &str, buffer: *mut c_char, len: usize) -> usize {
+    if !buffer.is_null() && len > 0 {
+        let count = value.len().min(len - 1);
+        std::ptr::copy_nonoverlapping(value.as_ptr() as *const c_char, buffer, count);
+        *buffer.add(count) = 0;
+    }
+    value.len() + 1
+}
+
+fn cloud_save() -> Result<CloudSave, i32> {
+    CloudSave::get().ok_or_else(|| fail(TapSdkError::NotInitialized))
+}
+
+/// Message of the last error on the calling thread
+///
+/// The pointer is valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn tappc_last_error() -> *const c_char {
+    guard(c"".as_ptr(), || {
+        LAST_ERROR.with(|last| last.borrow().as_ptr())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_restart_app_if_necessary(client_id: *const c_char) -> bool {
+    guard(false, || match arg(client_id, "client_id") {
+        Ok(client_id) => tapsdk_pc::restart_app_if_necessary(client_id).unwrap_or_else(|e| {
+            fail(e);
+            false
+        }),
+        Err(_) => false,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_init(pub_key: *const c_char) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let pub_key = match arg(pub_key, "pub_key") {
+            Ok(pub_key) => pub_key,
+            Err(code) => return code,
+        };
+        let mut sdk = lock(&SDK);
+        if sdk.is_some() {
+            return fail(TapSdkError::InvalidArgument(
+                "SDK already initialized".to_string(),
+            ));
+        }
+        match TapSdk::init(pub_key) {
+            Ok(instance) => {
+                *sdk = Some(instance);
+                TAPPC_OK
+            }
+            Err(e) => fail(e),
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tappc_shutdown() {
+    guard((), || {
+        // Dropping the SDK shuts it down
+        lock(&SDK).take();
+        lock(&QUEUE).clear();
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tappc_is_initialized() -> bool {
+    guard(false, tapsdk_pc::is_initialized)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_get_client_id(buffer: *mut c_char, len: usize) -> usize {
+    guard(0, || {
+        let client_id = lock(&SDK).as_ref().and_then(TapSdk::get_client_id);
+        match client_id {
+            Some(client_id) => copy_out(&client_id, buffer, len),
+            None => 0,
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tappc_is_game_owned() -> bool {
+    guard(false, ownership::is_game_owned)
+}
+
+#[no_mangle]
+pub extern "C" fn tappc_is_game_playable() -> bool {
+    guard(false, ownership::is_game_playable)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_is_dlc_owned(dlc_id: *const c_char) -> bool {
+    guard(false, || {
+        arg(dlc_id, "dlc_id")
+            .map(dlc::is_dlc_owned)
+            .unwrap_or(false)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_show_dlc_store(dlc_id: *const c_char) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || match arg(dlc_id, "dlc_id") {
+        Ok(dlc_id) => status(dlc::show_dlc_store(dlc_id).map(|_| ())),
+        Err(code) => code,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_authorize(scopes: *const c_char) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || match arg(scopes, "scopes") {
+        Ok(scopes) => status(user::authorize(scopes)),
+        Err(code) => code,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_get_open_id(buffer: *mut c_char, len: usize) -> usize {
+    guard(0, || match user::get_open_id() {
+        Some(open_id) => copy_out(&open_id, buffer, len),
+        None => 0,
+    })
+}
+
+/// Parameters of a cloud save create/update request
+#[repr(C)]
+pub struct TapPcSaveRequest {
+    pub name: *const c_char,
+    pub summary: *const c_char,
+    /// May be NULL
+    pub extra: *const c_char,
+    pub playtime: u64,
+    pub data_file_path: *const c_char,
+    /// May be NULL
+    pub cover_file_path: *const c_char,
+}
+
+/// Validated fields of a [`TapPcSaveRequest`]
+struct SaveFields {
+    name: String,
+    summary: String,
+    extra: Option<String>,
+    playtime: u64,
+    data_file_path: Box<Path>,
+    cover_file_path: Option<Box<Path>>,
+}
+
+unsafe fn save_fields(request: *const TapPcSaveRequest) -> Result<SaveFields, i32> {
+    let Some(request) = request.as_ref() else {
+        set_last_error("request must not be NULL");
+        return Err(TAPPC_ERROR_INVALID_ARGUMENT);
+    };
+    Ok(SaveFields {
+        name: arg(request.name, "name")?.to_string(),
+        summary: arg(request.summary, "summary")?.to_string(),
+        extra: optional_arg(request.extra, "extra")?.map(str::to_string),
+        playtime: request.playtime,
+        data_file_path: Path::new(arg(request.data_file_path, "data_file_path")?).into(),
+        cover_file_path: optional_arg(request.cover_file_path, "cover_file_path")?
+            .map(|path| Path::new(path).into()),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn tappc_cloudsave_list(request_id: i64) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || match cloud_save() {
+        Ok(cloud_save) => status(cloud_save.list(request_id)),
+        Err(code) => code,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_cloudsave_create(
+    request_id: i64,
+    request: *const TapPcSaveRequest,
+) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let result = save_fields(request).and_then(|fields| {
+            let request = CreateSaveRequest {
+                name: fields.name,
+                summary: fields.summary,
+                extra: fields.extra,
+                playtime: fields.playtime,
+                data_file_path: fields.data_file_path,
+                cover_file_path: fields.cover_file_path,
+            };
+            Ok(status(cloud_save()?.create(request_id, &request)))
+        });
+        result.unwrap_or_else(|code| code)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_cloudsave_update(
+    request_id: i64,
+    uuid: *const c_char,
+    request: *const TapPcSaveRequest,
+) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let result = id_arg(uuid, "uuid").and_then(|uuid| {
+            let fields = save_fields(request)?;
+            let request = UpdateSaveRequest {
+                uuid,
+                name: fields.name,
+                summary: fields.summary,
+                extra: fields.extra,
+                playtime: fields.playtime,
+                data_file_path: fields.data_file_path,
+                cover_file_path: fields.cover_file_path,
+            };
+            Ok(status(cloud_save()?.update(request_id, &request)))
+        });
+        result.unwrap_or_else(|code| code)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_cloudsave_delete(request_id: i64, uuid: *const c_char) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let result = id_arg(uuid, "uuid")
+            .and_then(|uuid| Ok(status(cloud_save()?.delete(request_id, &uuid))));
+        result.unwrap_or_else(|code| code)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_cloudsave_get_data(
+    request_id: i64,
+    uuid: *const c_char,
+    file_id: *const c_char,
+) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let result = id_arg(uuid, "uuid").and_then(|uuid| {
+            let file_id = id_arg(file_id, "file_id")?;
+            Ok(status(cloud_save()?.get_data(request_id, &uuid, &file_id)))
+        });
+        result.unwrap_or_else(|code| code)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_cloudsave_get_cover(
+    request_id: i64,
+    uuid: *const c_char,
+    file_id: *const c_char,
+) -> i32 {
+    guard(TAPPC_ERROR_PANIC, || {
+        let result = id_arg(uuid, "uuid").and_then(|uuid| {
+            let file_id = id_arg(file_id, "file_id")?;
+            Ok(status(cloud_save()?.get_cover(request_id, &uuid, &file_id)))
+        });
+        result.unwrap_or_else(|code| code)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_set_event_callback(
+    callback: Option<TapPcEventCallback>,
+    user_data: *mut c_void,
+) {
+    guard((), || {
+        *lock(&CALLBACK) = callback.map(|callback| Callback {
+            callback,
+            user_data,
+        });
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_run_callbacks() -> usize {
+    guard(0, || {
+        // Release the SDK lock before delivering so callbacks may call back in
+        let events = match lock(&SDK).as_ref() {
+            Some(sdk) => sdk.run_callbacks(),
+            None => return 0,
+        };
+        let count = events.len();
+        for event in events {
+            let event = TapPcEvent::new(event);
+            let callback = lock(&CALLBACK).as_ref().map(|c| (c.callback, c.user_data));
+            match callback {
+                Some((callback, user_data)) => {
+                    callback(&event, user_data);
+                }
+                None => lock(&QUEUE).push_back(event),
+            }
+        }
+        count
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tappc_poll_event(out: *mut TapPcEvent) -> bool {
+    guard(false, || {
+        if out.is_null() {
+            return false;
+        }
+        match lock(&QUEUE).pop_front() {
+            Some(event) => {
+                out.write(event);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_out_truncates() {
+        let mut buffer = [1 as c_char; 4];
+        let needed = unsafe { copy_out("abcdef", buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(needed, 7);
+        let copied = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(copied.to_str(), Ok("abc"));
+        assert_eq!(unsafe { copy_out("abc", std::ptr::null_mut(), 0) }, 4);
+    }
+
+    #[test]
+    fn test_errors_set_last_error() {
+        let code = unsafe { tappc_authorize(std::ptr::null()) };
+        assert_eq!(code, TAPPC_ERROR_INVALID_ARGUMENT);
+        let message = unsafe { CStr::from_ptr(tappc_last_error()) };
+        assert_eq!(message.to_str(), Ok("scopes must not be NULL"));
+
+        assert_eq!(tappc_cloudsave_list(1), TAPPC_ERROR_NOT_INITIALIZED);
+        let message = unsafe { CStr::from_ptr(tappc_last_error()) };
+        assert_eq!(message.to_str(), Ok("SDK not initialized"));
+    }
+
+    #[test]
+    fn test_guard_catches_panics() {
+        assert_eq!(guard(TAPPC_ERROR_PANIC, || TAPPC_OK), TAPPC_OK);
+        let code = guard(TAPPC_ERROR_PANIC, || panic!("poisoned"));
+        assert_eq!(code, TAPPC_ERROR_PANIC);
+        let message = unsafe { CStr::from_ptr(tappc_last_error()) };
+        assert_eq!(message.to_str(), Ok("panic in tapsdk-pc: poisoned"));
+    }
+}
@@ -0,0 +1,426 @@
+//! The SDK's C ABI, backed by the scripted state
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use crate::ffi::{
+    self, Callback, ITapCloudSave, TapCloudSaveCreateRequest, TapCloudSaveGetFileRequest,
+    TapCloudSaveUpdateRequest,
+};
+use crate::state::{self, read_str, state, FakeEvent, Save, ERROR_FILE_NOT_FOUND};
+
+/// `TapCloudSave_Result` values
+const RESULT_OK: u32 = 0;
+const RESULT_UNINITIALIZED: u32 = 1;
+const RESULT_INVALID_ARGUMENT: u32 = 4;
+const RESULT_FAILED_TO_READ_SAVE_FILE: u32 = 6;
+const RESULT_SAVE_FILE_TOO_LARGE: u32 = 7;
+const RESULT_FAILED_TO_READ_COVER_FILE: u32 = 8;
+const RESULT_COVER_FILE_TOO_LARGE: u32 = 9;
+
+/// Size limits enforced by the real SDK
+const MAX_SAVE_SIZE: usize = 10 * 1024 * 1024;
+const MAX_COVER_SIZE: usize = 512 * 1024;
+
+/// Length of the buffers passed to `TapSDK_GetClientID` / `TapUser_GetOpenID`
+const ID_BUFFER_LEN: usize = 256;
+
+/// `TapCloudSave()` always returns this handle
+static CLOUD_SAVE: ITapCloudSave = ITapCloudSave { _private: 0 };
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_RestartAppIfNecessary(_client_id: *const c_char) -> bool {
+    state().restart_required
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_Init(err_msg: *mut c_char, _pub_key: *const c_char) -> u32 {
+    let mut state = state();
+    if !err_msg.is_null() {
+        let buffer = std::slice::from_raw_parts_mut(err_msg, 1024);
+        ffi::fill(buffer, &state.init_message);
+    }
+    state.initialized = state.init_result == 0;
+    state.init_result
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_Shutdown() -> bool {
+    let mut state = state();
+    state.initialized = false;
+    state.pending.clear();
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_RunCallbacks() {
+    state::run_callbacks();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_GetClientID(buffer: *mut c_char) -> bool {
+    write_id(buffer, &state().client_id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_RegisterCallback(event_id: u32, callback: Option<Callback>) {
+    if let Some(callback) = callback {
+        let mut state = state();
+        if !state
+            .callbacks
+            .iter()
+            .any(|&(id, cb)| id == event_id && cb as usize == callback as usize)
+        {
+            state.callbacks.push((event_id, callback));
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapSDK_UnregisterCallback(event_id: u32, callback: Option<Callback>) {
+    if let Some(callback) = callback {
+        state()
+            .callbacks
+            .retain(|&(id, cb)| !(id == event_id && cb as usize == callback as usize));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapUser_AsyncAuthorize(scopes: *const c_char) -> u32 {
+    let mut state = state();
+    if !state.initialized {
+        return 0;
+    }
+    if state.authorize_result == 1 {
+        let cancelled = state.authorize_cancelled;
+        state.pending.push_back(FakeEvent::Authorize {
+            cancelled,
+            scope: read_str(scopes).unwrap_or_default(),
+        });
+    }
+    state.authorize_result
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapUser_GetOpenID(buffer: *mut c_char) -> bool {
+    let state = state();
+    state.initialized && write_id(buffer, &state.open_id)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapApps_IsOwned() -> bool {
+    let state = state();
+    state.initialized && state.game_owned
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapDLC_IsOwned(dlc_id: *const c_char) -> bool {
+    let state = state();
+    read_str(dlc_id)
+        .and_then(|id| state.dlcs.get(&id).copied())
+        .unwrap_or(false)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapDLC_ShowStore(dlc_id: *const c_char) -> bool {
+    state().initialized && !dlc_id.is_null()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave() -> *mut ITapCloudSave {
+    &CLOUD_SAVE as *const ITapCloudSave as *mut ITapCloudSave
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncList(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+) -> u32 {
+    let mut state = state();
+    if let Some(result) = check_request(&state, handle) {
+        return result;
+    }
+    let event = match state.take_error() {
+        Some(error) => FakeEvent::List {
+            request_id,
+            error: Some(error),
+            saves: Vec::new(),
+        },
+        None => FakeEvent::List {
+            request_id,
+            error: None,
+            saves: state.saves.clone(),
+        },
+    };
+    state.pending.push_back(event);
+    RESULT_OK
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncCreate(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    request: *const TapCloudSaveCreateRequest,
+) -> u32 {
+    let mut state = state();
+    if let Some(result) = check_request(&state, handle) {
+        return result;
+    }
+    let Some(request) = request.as_ref() else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+    let (Some(name), Some(summary)) = (read_str(request.name), read_str(request.summary)) else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+    let (data, cover) = match read_files(request.data_file_path, request.cover_file_path) {
+        Ok(files) => files,
+        Err(result) => return result,
+    };
+
+    let event = match state.take_error() {
+        Some(error) => FakeEvent::Save {
+            event_id: state::CLOUD_SAVE_CREATE,
+            request_id,
+            error: Some(error),
+            save: None,
+        },
+        None => {
+            let now = state::now();
+            let save = Save {
+                uuid: format!("fake-save-{}", state.next_id()),
+                file_id: format!("fake-file-{}", state.next_id()),
+                name,
+                summary,
+                extra: read_str(request.extra),
+                playtime: request.playtime,
+                created_time: now,
+                modified_time: now,
+                data,
+                cover,
+            };
+            state.saves.push(save.clone());
+            FakeEvent::Save {
+                event_id: state::CLOUD_SAVE_CREATE,
+                request_id,
+                error: None,
+                save: Some(save),
+            }
+        }
+    };
+    state.pending.push_back(event);
+    RESULT_OK
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncUpdate(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    request: *const TapCloudSaveUpdateRequest,
+) -> u32 {
+    let mut state = state();
+    if let Some(result) = check_request(&state, handle) {
+        return result;
+    }
+    let Some(request) = request.as_ref() else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+    let (Some(uuid), Some(name), Some(summary)) = (
+        read_str(request.uuid),
+        read_str(request.name),
+        read_str(request.summary),
+    ) else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+    let (data, cover) = match read_files(request.data_file_path, request.cover_file_path) {
+        Ok(files) => files,
+        Err(result) => return result,
+    };
+
+    let error = state
+        .take_error()
+        .or_else(|| (!state.saves.iter().any(|s| s.uuid == uuid)).then(|| not_found(&uuid)));
+    let event = match error {
+        Some(error) => FakeEvent::Save {
+            event_id: state::CLOUD_SAVE_UPDATE,
+            request_id,
+            error: Some(error),
+            save: None,
+        },
+        None => {
+            let file_id = format!("fake-file-{}", state.next_id());
+            let save = state
+                .saves
+                .iter_mut()
+                .find(|s| s.uuid == uuid)
+                .expect("checked above");
+            save.file_id = file_id;
+            save.name = name;
+            save.summary = summary;
+            save.extra = read_str(request.extra);
+            save.playtime = request.playtime;
+            save.modified_time = state::now();
+            save.data = data;
+            save.cover = cover;
+            FakeEvent::Save {
+                event_id: state::CLOUD_SAVE_UPDATE,
+                request_id,
+                error: None,
+                save: Some(save.clone()),
+            }
+        }
+    };
+    state.pending.push_back(event);
+    RESULT_OK
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncDelete(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    uuid: *const c_char,
+) -> u32 {
+    let mut state = state();
+    if let Some(result) = check_request(&state, handle) {
+        return result;
+    }
+    let Some(uuid) = read_str(uuid) else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+
+    let error = state.take_error().or_else(|| {
+        let before = state.saves.len();
+        state.saves.retain(|s| s.uuid != uuid);
+        (state.saves.len() == before).then(|| not_found(&uuid))
+    });
+    state.pending.push_back(FakeEvent::Delete {
+        request_id,
+        error,
+        uuid,
+    });
+    RESULT_OK
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncGetData(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    request: *const TapCloudSaveGetFileRequest,
+) -> u32 {
+    get_file(
+        handle,
+        request_id,
+        request,
+        state::CLOUD_SAVE_GET_DATA,
+        |save| Some(save.data.clone()),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn TapCloudSave_AsyncGetCover(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    request: *const TapCloudSaveGetFileRequest,
+) -> u32 {
+    get_file(
+        handle,
+        request_id,
+        request,
+        state::CLOUD_SAVE_GET_COVER,
+        |save| save.cover.clone(),
+    )
+}
+
+/// Shared implementation of `AsyncGetData` / `AsyncGetCover`
+unsafe fn get_file(
+    handle: *mut ITapCloudSave,
+    request_id: i64,
+    request: *const TapCloudSaveGetFileRequest,
+    event_id: u32,
+    contents: impl Fn(&Save) -> Option<Vec<u8>>,
+) -> u32 {
+    let mut state = state();
+    if let Some(result) = check_request(&state, handle) {
+        return result;
+    }
+    let Some(request) = request.as_ref() else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+    let (Some(uuid), Some(file_id)) = (read_str(request.uuid), read_str(request.file_id)) else {
+        return RESULT_INVALID_ARGUMENT;
+    };
+
+    let found = state
+        .saves
+        .iter()
+        .find(|s| s.uuid == uuid && s.file_id == file_id)
+        .and_then(&contents);
+    let (error, data) = match (state.take_error(), found) {
+        (Some(error), _) => (Some(error), Vec::new()),
+        (None, Some(data)) => (None, data),
+        (None, None) => (Some(not_found(&uuid)), Vec::new()),
+    };
+    state.pending.push_back(FakeEvent::File {
+        event_id,
+        request_id,
+        error,
+        data,
+    });
+    RESULT_OK
+}
+
+/// Common preconditions of every cloud save call
+fn check_request(state: &state::State, handle: *mut ITapCloudSave) -> Option<u32> {
+    if !state.initialized {
+        Some(RESULT_UNINITIALIZED)
+    } else if handle.is_null() {
+        Some(RESULT_INVALID_ARGUMENT)
+    } else if state.cloud_save_result != RESULT_OK {
+        Some(state.cloud_save_result)
+    } else {
+        None
+    }
+}
+
+/// Read the data and optional cover file of a create/update request
+unsafe fn read_files(
+    data_path: *const c_char,
+    cover_path: *const c_char,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), u32> {
+    let data_path = path(data_path).ok_or(RESULT_INVALID_ARGUMENT)?;
+    let data = std::fs::read(data_path).map_err(|_| RESULT_FAILED_TO_READ_SAVE_FILE)?;
+    if data.len() > MAX_SAVE_SIZE {
+        return Err(RESULT_SAVE_FILE_TOO_LARGE);
+    }
+    let cover = match path(cover_path) {
+        Some(cover_path) => {
+            let cover = std::fs::read(cover_path).map_err(|_| RESULT_FAILED_TO_READ_COVER_FILE)?;
+            if cover.len() > MAX_COVER_SIZE {
+                return Err(RESULT_COVER_FILE_TOO_LARGE);
+            }
+            Some(cover)
+        }
+        None => None,
+    };
+    Ok((data, cover))
+}
+
+/// Interpret a path argument; paths are UTF-8 like in the real SDK
+unsafe fn path(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok().map(str::to_owned)
+}
+
+fn not_found(uuid: &str) -> (i64, String) {
+    (ERROR_FILE_NOT_FOUND, format!("cloud save {uuid} not found"))
+}
+
+/// Write an ID into a caller-provided 256-byte buffer
+unsafe fn write_id(buffer: *mut c_char, value: &str) -> bool {
+    if buffer.is_null() {
+        return false;
+    }
+    ffi::fill(std::slice::from_raw_parts_mut(buffer, ID_BUFFER_LEN), value);
+    true
+}
@@ -0,0 +1,127 @@
+//! C types from `taptap_api.h` and `taptap_cloudsave.h`
+//!
+//! These mirror the real headers (not the stub bindings used on non-Windows
+//! platforms), so field order and buffer sizes must match the SDK exactly.
+
+use std::os::raw::{c_char, c_void};
+
+pub type Callback = unsafe extern "C" fn(event_id: u32, data: *mut c_void);
+
+/// Opaque cloud save handle returned by `TapCloudSave()`
+#[repr(C)]
+pub struct ITapCloudSave {
+    pub(crate) _private: u8,
+}
+
+#[repr(C)]
+pub struct TapSdkError {
+    pub code: i64,
+    pub message: *const c_char,
+}
+
+#[repr(C)]
+pub struct TapSystemStateNotification {
+    pub state: u32,
+}
+
+#[repr(C)]
+pub struct AuthorizeFinishedResponse {
+    pub is_cancel: bool,
+    pub error: [c_char; 1024],
+    pub token_type: [c_char; 32],
+    pub kid: [c_char; 8 * 1024],
+    pub mac_key: [c_char; 8 * 1024],
+    pub mac_algorithm: [c_char; 32],
+    pub scope: [c_char; 1024],
+}
+
+#[repr(C)]
+pub struct GamePlayableStatusChangedResponse {
+    pub is_playable: bool,
+}
+
+#[repr(C)]
+pub struct DlcPlayableStatusChangedResponse {
+    pub dlc_id: [c_char; 32],
+    pub is_playable: bool,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveInfo {
+    pub uuid: *const c_char,
+    pub file_id: *const c_char,
+    pub name: *const c_char,
+    pub save_size: u32,
+    pub cover_size: u32,
+    pub summary: *const c_char,
+    pub extra: *const c_char,
+    pub playtime: u32,
+    pub created_time: u32,
+    pub modified_time: u32,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveListResponse {
+    pub request_id: i64,
+    pub error: *const TapSdkError,
+    pub save_count: i32,
+    pub saves: *const TapCloudSaveInfo,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveCreateRequest {
+    pub name: *const c_char,
+    pub summary: *const c_char,
+    pub extra: *const c_char,
+    pub playtime: u32,
+    pub data_file_path: *const c_char,
+    pub cover_file_path: *const c_char,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveCreateResponse {
+    pub request_id: i64,
+    pub error: *const TapSdkError,
+    pub save: *const TapCloudSaveInfo,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveUpdateRequest {
+    pub uuid: *const c_char,
+    pub name: *const c_char,
+    pub summary: *const c_char,
+    pub extra: *const c_char,
+    pub playtime: u32,
+    pub data_file_path: *const c_char,
+    pub cover_file_path: *const c_char,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveDeleteResponse {
+    pub request_id: i64,
+    pub error: *const TapSdkError,
+    pub uuid: *const c_char,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveGetFileRequest {
+    pub uuid: *const c_char,
+    pub file_id: *const c_char,
+}
+
+#[repr(C)]
+pub struct TapCloudSaveGetFileResponse {
+    pub request_id: i64,
+    pub error: *const TapSdkError,
+    pub size: u32,
+    pub data: *const c_void,
+}
+
+/// Copy a string into a fixed-size, NUL-terminated buffer, truncating it
+pub fn fill(buffer: &mut [c_char], value: &str) {
+    let len = value.len().min(buffer.len() - 1);
+    for (dst, src) in buffer.iter_mut().zip(&value.as_bytes()[..len]) {
+        *dst = *src as c_char;
+    }
+    buffer[len] = 0;
+}
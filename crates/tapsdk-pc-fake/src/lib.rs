@@ -0,0 +1,299 @@
+//! Scriptable test double of `taptap_api.dll`
+//!
+//! This crate builds a library named `taptap_api` that exports the same C
+//! ABI as the TapTap PC SDK. Instead of talking to the TapTap client it keeps
+//! everything in memory, so `tapsdk-pc` can be exercised end to end (callback
+//! registration, request/response lifecycle and event parsing) on CI
+//! machines without the client installed.
+//!
+//! Tests script its behavior through the `TapFake_*` control functions, e.g.
+//! to make initialization fail, fail the next cloud save response, or emit
+//! platform events. Responses are queued when a request is made and delivered
+//! on the next `TapSDK_RunCallbacks()`, like the real SDK.
+//!
+//! The fake is never shipped; it is only linked when building `tapsdk-pc`
+//! with `TAPSDK_FAKE_LIB_DIR` pointing at the built library.
+
+// The exported functions mirror a C header, their safety contract is the SDK's
+#![allow(clippy::missing_safety_doc)]
+
+mod api;
+mod ffi;
+mod state;
+
+use std::os::raw::c_char;
+
+pub use api::*;
+use state::{read_str, state, FakeEvent, State};
+
+/// Restore the default state: uninitialized, no callbacks, no saves, and
+/// every call succeeding
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_Reset() {
+    *state() = State::default();
+}
+
+/// Script the result and error message of the next `TapSDK_Init()`
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetInitResult(result: u32, message: *const c_char) {
+    let mut state = state();
+    state.init_result = result;
+    state.init_message = read_str(message).unwrap_or_default();
+}
+
+/// Script the result of `TapSDK_RestartAppIfNecessary()`
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetRestartRequired(required: bool) {
+    state().restart_required = required;
+}
+
+/// Set the ID returned by `TapSDK_GetClientID()`
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetClientId(client_id: *const c_char) {
+    state().client_id = read_str(client_id).unwrap_or_default();
+}
+
+/// Set the ID returned by `TapUser_GetOpenID()`
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetOpenId(open_id: *const c_char) {
+    state().open_id = read_str(open_id).unwrap_or_default();
+}
+
+/// Set the result of `TapApps_IsOwned()`
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetGameOwned(owned: bool) {
+    state().game_owned = owned;
+}
+
+/// Set the result of `TapDLC_IsOwned()` for a DLC
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetDlcOwned(dlc_id: *const c_char, owned: bool) {
+    if let Some(dlc_id) = read_str(dlc_id) {
+        state().dlcs.insert(dlc_id, owned);
+    }
+}
+
+/// Script the result of `TapUser_AsyncAuthorize()`
+///
+/// Only `TapUser_AsyncAuthorize_Result_OK` (1) queues an `AuthorizeFinished`
+/// event.
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetAuthorizeResult(result: u32) {
+    state().authorize_result = result;
+}
+
+/// Make the user cancel the next authorizations
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetAuthorizeCancelled(cancelled: bool) {
+    state().authorize_cancelled = cancelled;
+}
+
+/// Make every cloud save call fail synchronously with a `TapCloudSave_Result`
+///
+/// Pass 0 to let calls succeed again.
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_SetCloudSaveResult(result: u32) {
+    state().cloud_save_result = result;
+}
+
+/// Fail the next cloud save response with the given error
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_FailNextResponse(code: i64, message: *const c_char) {
+    state().next_error = Some((code, read_str(message).unwrap_or_default()));
+}
+
+/// Queue a `SystemStateChanged` event
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_EmitSystemState(system_state: u32) {
+    state()
+        .pending
+        .push_back(FakeEvent::SystemState(system_state));
+}
+
+/// Queue a `GamePlayableStatusChanged` event
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_EmitGamePlayable(is_playable: bool) {
+    state()
+        .pending
+        .push_back(FakeEvent::GamePlayable(is_playable));
+}
+
+/// Queue a `DLCPlayableStatusChanged` event
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_EmitDlcPlayable(dlc_id: *const c_char, is_playable: bool) {
+    state().pending.push_back(FakeEvent::DlcPlayable {
+        dlc_id: read_str(dlc_id).unwrap_or_default(),
+        is_playable,
+    });
+}
+
+/// Queue an event with arbitrary payload bytes
+///
+/// Used to feed malformed data to the callback parser. A null `data`
+/// delivers a null pointer.
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_EmitRaw(event_id: u32, data: *const u8, len: usize) {
+    let data = (!data.is_null()).then(|| std::slice::from_raw_parts(data, len).to_vec());
+    state().pending.push_back(FakeEvent::Raw { event_id, data });
+}
+
+/// Number of callbacks currently registered for an event
+#[no_mangle]
+pub unsafe extern "C" fn TapFake_CallbackCount(event_id: u32) -> u32 {
+    state()
+        .callbacks
+        .iter()
+        .filter(|&&(id, _)| id == event_id)
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffi::{
+        TapCloudSaveCreateRequest, TapCloudSaveCreateResponse, TapCloudSaveGetFileRequest,
+        TapCloudSaveGetFileResponse, TapCloudSaveListResponse,
+    };
+    use std::ffi::{c_void, CStr, CString};
+    use std::sync::Mutex;
+
+    /// The fake has global state, run tests one at a time
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// Events seen by [`record`]: (event ID, summary)
+    static SEEN: Mutex<Vec<(u32, String)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn record(event_id: u32, data: *mut c_void) {
+        let summary = match event_id {
+            state::CLOUD_SAVE_LIST => {
+                let response = &*(data as *const TapCloudSaveListResponse);
+                format!("list {}", response.save_count)
+            }
+            state::CLOUD_SAVE_CREATE => {
+                let response = &*(data as *const TapCloudSaveCreateResponse);
+                if response.error.is_null() {
+                    let save = &*response.save;
+                    format!("created {}", CStr::from_ptr(save.uuid).to_str().unwrap())
+                } else {
+                    format!("error {}", (*response.error).code)
+                }
+            }
+            state::CLOUD_SAVE_GET_DATA => {
+                let response = &*(data as *const TapCloudSaveGetFileResponse);
+                let bytes =
+                    std::slice::from_raw_parts(response.data as *const u8, response.size as usize);
+                format!("data {}", String::from_utf8_lossy(bytes))
+            }
+            _ => format!("raw {}", data.is_null()),
+        };
+        SEEN.lock().unwrap().push((event_id, summary));
+    }
+
+    fn setup() -> std::sync::MutexGuard<'static, ()> {
+        let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            TapFake_Reset();
+            let mut err = [0 as c_char; 1024];
+            assert_eq!(TapSDK_Init(err.as_mut_ptr(), std::ptr::null()), 0);
+        }
+        SEEN.lock().unwrap().clear();
+        guard
+    }
+
+    fn seen() -> Vec<(u32, String)> {
+        std::mem::take(&mut *SEEN.lock().unwrap())
+    }
+
+    #[test]
+    fn test_init_failure_is_scripted() {
+        let _guard = setup();
+        let message = CString::new("not launched").unwrap();
+        unsafe {
+            TapFake_SetInitResult(3, message.as_ptr());
+            let mut err = [0 as c_char; 1024];
+            assert_eq!(TapSDK_Init(err.as_mut_ptr(), std::ptr::null()), 3);
+            assert_eq!(CStr::from_ptr(err.as_ptr()).to_str(), Ok("not launched"));
+            assert!(!TapApps_IsOwned());
+        }
+    }
+
+    #[test]
+    fn test_callbacks_registered_once() {
+        let _guard = setup();
+        unsafe {
+            TapSDK_RegisterCallback(6001, Some(record));
+            TapSDK_RegisterCallback(6001, Some(record));
+            assert_eq!(TapFake_CallbackCount(6001), 1);
+            TapSDK_UnregisterCallback(6001, Some(record));
+            assert_eq!(TapFake_CallbackCount(6001), 0);
+        }
+    }
+
+    #[test]
+    fn test_cloud_save_lifecycle() {
+        let _guard = setup();
+        let dir = std::env::temp_dir().join(format!("tapsdk-fake-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("save.dat");
+        std::fs::write(&file, b"hello").unwrap();
+
+        let name = CString::new("slot").unwrap();
+        let summary = CString::new("summary").unwrap();
+        let path = CString::new(file.to_str().unwrap()).unwrap();
+        let request = TapCloudSaveCreateRequest {
+            name: name.as_ptr(),
+            summary: summary.as_ptr(),
+            extra: std::ptr::null(),
+            playtime: 10,
+            data_file_path: path.as_ptr(),
+            cover_file_path: std::ptr::null(),
+        };
+
+        unsafe {
+            for id in [6001, 6002, 6005] {
+                TapSDK_RegisterCallback(id, Some(record));
+            }
+            let handle = TapCloudSave();
+            assert_eq!(TapCloudSave_AsyncCreate(handle, 1, &request), 0);
+            // Nothing is delivered before RunCallbacks
+            assert!(seen().is_empty());
+            TapSDK_RunCallbacks();
+            assert_eq!(seen(), vec![(6002, "created fake-save-1".to_string())]);
+
+            assert_eq!(TapCloudSave_AsyncList(handle, 2), 0);
+            let uuid = CString::new("fake-save-1").unwrap();
+            let file_id = CString::new("fake-file-2").unwrap();
+            let get = TapCloudSaveGetFileRequest {
+                uuid: uuid.as_ptr(),
+                file_id: file_id.as_ptr(),
+            };
+            assert_eq!(TapCloudSave_AsyncGetData(handle, 3, &get), 0);
+            TapFake_FailNextResponse(400006, std::ptr::null());
+            assert_eq!(TapCloudSave_AsyncCreate(handle, 4, &request), 0);
+            TapSDK_RunCallbacks();
+            assert_eq!(
+                seen(),
+                vec![
+                    (6001, "list 1".to_string()),
+                    (6005, "data hello".to_string()),
+                    (6002, "error 400006".to_string()),
+                ]
+            );
+
+            TapFake_SetCloudSaveResult(2);
+            assert_eq!(TapCloudSave_AsyncList(handle, 5), 2);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_emit_raw_null_data() {
+        let _guard = setup();
+        unsafe {
+            TapSDK_RegisterCallback(4001, Some(record));
+            TapFake_EmitRaw(4001, std::ptr::null(), 0);
+            TapSDK_RunCallbacks();
+        }
+        assert_eq!(seen(), vec![(4001, "raw true".to_string())]);
+    }
+}
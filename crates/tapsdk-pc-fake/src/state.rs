@@ -0,0 +1,400 @@
+//! Scripted SDK state and event delivery
+
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ffi::{self, Callback};
+
+pub const SYSTEM_STATE_CHANGED: u32 = 1;
+pub const AUTHORIZE_FINISHED: u32 = 2002;
+pub const GAME_PLAYABLE_STATUS_CHANGED: u32 = 4001;
+pub const DLC_PLAYABLE_STATUS_CHANGED: u32 = 4002;
+pub const CLOUD_SAVE_LIST: u32 = 6001;
+pub const CLOUD_SAVE_CREATE: u32 = 6002;
+pub const CLOUD_SAVE_UPDATE: u32 = 6003;
+pub const CLOUD_SAVE_DELETE: u32 = 6004;
+pub const CLOUD_SAVE_GET_DATA: u32 = 6005;
+pub const CLOUD_SAVE_GET_COVER: u32 = 6006;
+
+/// `TapSDK_ErrorCode_CloudSave_FileNotFound`
+pub const ERROR_FILE_NOT_FOUND: i64 = 400002;
+
+/// A stored cloud save
+#[derive(Debug, Clone)]
+pub struct Save {
+    pub uuid: String,
+    pub file_id: String,
+    pub name: String,
+    pub summary: String,
+    pub extra: Option<String>,
+    pub playtime: u32,
+    pub created_time: u32,
+    pub modified_time: u32,
+    pub data: Vec<u8>,
+    pub cover: Option<Vec<u8>>,
+}
+
+/// An event waiting for the next `TapSDK_RunCallbacks()`
+#[derive(Debug, Clone)]
+pub enum FakeEvent {
+    SystemState(u32),
+    Authorize {
+        cancelled: bool,
+        scope: String,
+    },
+    GamePlayable(bool),
+    DlcPlayable {
+        dlc_id: String,
+        is_playable: bool,
+    },
+    List {
+        request_id: i64,
+        error: Option<(i64, String)>,
+        saves: Vec<Save>,
+    },
+    Save {
+        event_id: u32,
+        request_id: i64,
+        error: Option<(i64, String)>,
+        save: Option<Save>,
+    },
+    Delete {
+        request_id: i64,
+        error: Option<(i64, String)>,
+        uuid: String,
+    },
+    File {
+        event_id: u32,
+        request_id: i64,
+        error: Option<(i64, String)>,
+        data: Vec<u8>,
+    },
+    /// Arbitrary bytes passed as the data pointer (`None` passes null)
+    Raw {
+        event_id: u32,
+        data: Option<Vec<u8>>,
+    },
+}
+
+/// Everything the fake SDK knows
+pub struct State {
+    pub init_result: u32,
+    pub init_message: String,
+    pub restart_required: bool,
+    pub initialized: bool,
+    pub client_id: String,
+    pub open_id: String,
+    pub game_owned: bool,
+    pub dlcs: BTreeMap<String, bool>,
+    pub authorize_result: u32,
+    pub authorize_cancelled: bool,
+    pub cloud_save_result: u32,
+    pub next_error: Option<(i64, String)>,
+    pub callbacks: Vec<(u32, Callback)>,
+    pub pending: VecDeque<FakeEvent>,
+    pub saves: Vec<Save>,
+    pub next_id: u64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            init_result: 0,
+            init_message: String::new(),
+            restart_required: false,
+            initialized: false,
+            client_id: "fake_client_id".to_string(),
+            open_id: "fake_open_id".to_string(),
+            game_owned: true,
+            dlcs: BTreeMap::new(),
+            authorize_result: 1,
+            authorize_cancelled: false,
+            cloud_save_result: 0,
+            next_error: None,
+            callbacks: Vec::new(),
+            pending: VecDeque::new(),
+            saves: Vec::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl State {
+    /// Take the error scripted for the next response, if any
+    pub fn take_error(&mut self) -> Option<(i64, String)> {
+        self.next_error.take()
+    }
+
+    /// Allocate a new unique ID
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(Mutex::default);
+
+/// Lock the fake SDK state
+///
+/// A panic in one test must not break the following ones, so poisoning is
+/// ignored.
+pub fn state() -> MutexGuard<'static, State> {
+    STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or_default()
+}
+
+/// Read a C string argument
+///
+/// # Safety
+/// `ptr` must be null or point to a NUL-terminated string.
+pub unsafe fn read_str(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+/// Deliver all pending events to the registered callbacks
+pub fn run_callbacks() {
+    // Release the lock while calling out, callbacks may call back into us
+    let (events, callbacks) = {
+        let mut state = state();
+        let events: Vec<FakeEvent> = state.pending.drain(..).collect();
+        (events, state.callbacks.clone())
+    };
+
+    for event in events {
+        deliver(&event, &callbacks);
+    }
+}
+
+/// Build the C representation of an event and pass it to its callbacks
+fn deliver(event: &FakeEvent, callbacks: &[(u32, Callback)]) {
+    let call = |event_id: u32, data: *mut c_void| {
+        for &(id, callback) in callbacks {
+            if id == event_id {
+                unsafe { callback(event_id, data) };
+            }
+        }
+    };
+
+    match event {
+        FakeEvent::SystemState(state) => {
+            let mut notification = ffi::TapSystemStateNotification { state: *state };
+            call(SYSTEM_STATE_CHANGED, as_data(&mut notification));
+        }
+        FakeEvent::Authorize { cancelled, scope } => {
+            let mut response: Box<ffi::AuthorizeFinishedResponse> = zeroed_box();
+            response.is_cancel = *cancelled;
+            if !cancelled {
+                ffi::fill(&mut response.token_type, "mac");
+                ffi::fill(&mut response.kid, "fake_kid");
+                ffi::fill(&mut response.mac_key, "fake_mac_key");
+                ffi::fill(&mut response.mac_algorithm, "hmac-sha-1");
+                ffi::fill(&mut response.scope, scope);
+            }
+            call(AUTHORIZE_FINISHED, as_data(&mut *response));
+        }
+        FakeEvent::GamePlayable(is_playable) => {
+            let mut response = ffi::GamePlayableStatusChangedResponse {
+                is_playable: *is_playable,
+            };
+            call(GAME_PLAYABLE_STATUS_CHANGED, as_data(&mut response));
+        }
+        FakeEvent::DlcPlayable {
+            dlc_id,
+            is_playable,
+        } => {
+            let mut response = ffi::DlcPlayableStatusChangedResponse {
+                dlc_id: [0; 32],
+                is_playable: *is_playable,
+            };
+            ffi::fill(&mut response.dlc_id, dlc_id);
+            call(DLC_PLAYABLE_STATUS_CHANGED, as_data(&mut response));
+        }
+        FakeEvent::List {
+            request_id,
+            error,
+            saves,
+        } => {
+            let error = error.as_ref().map(RawError::new);
+            let infos: Vec<RawSaveInfo> = saves.iter().map(RawSaveInfo::new).collect();
+            let raw: Vec<ffi::TapCloudSaveInfo> = infos.iter().map(RawSaveInfo::info).collect();
+            let mut response = ffi::TapCloudSaveListResponse {
+                request_id: *request_id,
+                error: RawError::ptr(&error),
+                save_count: raw.len() as i32,
+                saves: if raw.is_empty() {
+                    std::ptr::null()
+                } else {
+                    raw.as_ptr()
+                },
+            };
+            call(CLOUD_SAVE_LIST, as_data(&mut response));
+        }
+        FakeEvent::Save {
+            event_id,
+            request_id,
+            error,
+            save,
+        } => {
+            let error = error.as_ref().map(RawError::new);
+            let storage = save.as_ref().map(RawSaveInfo::new);
+            let info = storage.as_ref().map(RawSaveInfo::info);
+            let mut response = ffi::TapCloudSaveCreateResponse {
+                request_id: *request_id,
+                error: RawError::ptr(&error),
+                save: info
+                    .as_ref()
+                    .map(|info| info as *const _)
+                    .unwrap_or(std::ptr::null()),
+            };
+            call(*event_id, as_data(&mut response));
+        }
+        FakeEvent::Delete {
+            request_id,
+            error,
+            uuid,
+        } => {
+            let error = error.as_ref().map(RawError::new);
+            let uuid = c_string(uuid);
+            let mut response = ffi::TapCloudSaveDeleteResponse {
+                request_id: *request_id,
+                error: RawError::ptr(&error),
+                uuid: uuid.as_ptr(),
+            };
+            call(CLOUD_SAVE_DELETE, as_data(&mut response));
+        }
+        FakeEvent::File {
+            event_id,
+            request_id,
+            error,
+            data,
+        } => {
+            let error = error.as_ref().map(RawError::new);
+            let mut response = ffi::TapCloudSaveGetFileResponse {
+                request_id: *request_id,
+                error: RawError::ptr(&error),
+                size: data.len() as u32,
+                data: if data.is_empty() {
+                    std::ptr::null()
+                } else {
+                    data.as_ptr() as *const c_void
+                },
+            };
+            call(*event_id, as_data(&mut response));
+        }
+        FakeEvent::Raw { event_id, data } => match data {
+            Some(bytes) => {
+                // Keep the buffer 8-byte aligned like a real C struct
+                let mut buffer = vec![0u64; bytes.len().div_ceil(8).max(1)];
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        bytes.as_ptr(),
+                        buffer.as_mut_ptr() as *mut u8,
+                        bytes.len(),
+                    );
+                }
+                call(*event_id, buffer.as_mut_ptr() as *mut c_void);
+            }
+            None => call(*event_id, std::ptr::null_mut()),
+        },
+    }
+}
+
+fn as_data<T>(value: &mut T) -> *mut c_void {
+    value as *mut T as *mut c_void
+}
+
+/// Allocate a zero-initialized struct on the heap (some responses are large)
+fn zeroed_box<T>() -> Box<T> {
+    // SAFETY: only used for plain C structs made of integers and arrays
+    unsafe { Box::new(std::mem::zeroed()) }
+}
+
+/// Convert to a C string, dropping any interior NUL bytes
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+/// A `TapSDK_Error` and the message it points to
+struct RawError {
+    _message: CString,
+    error: ffi::TapSdkError,
+}
+
+impl RawError {
+    fn new((code, message): &(i64, String)) -> Box<Self> {
+        let message = c_string(message);
+        Box::new(RawError {
+            error: ffi::TapSdkError {
+                code: *code,
+                message: message.as_ptr(),
+            },
+            _message: message,
+        })
+    }
+
+    fn ptr(error: &Option<Box<Self>>) -> *const ffi::TapSdkError {
+        error
+            .as_ref()
+            .map(|e| &e.error as *const _)
+            .unwrap_or(std::ptr::null())
+    }
+}
+
+/// Strings backing a `TapCloudSaveInfo`
+struct RawSaveInfo {
+    uuid: CString,
+    file_id: CString,
+    name: CString,
+    summary: CString,
+    extra: Option<CString>,
+    save: Save,
+}
+
+impl RawSaveInfo {
+    fn new(save: &Save) -> Self {
+        RawSaveInfo {
+            uuid: c_string(&save.uuid),
+            file_id: c_string(&save.file_id),
+            name: c_string(&save.name),
+            summary: c_string(&save.summary),
+            extra: save.extra.as_deref().map(c_string),
+            save: save.clone(),
+        }
+    }
+
+    fn info(&self) -> ffi::TapCloudSaveInfo {
+        ffi::TapCloudSaveInfo {
+            uuid: self.uuid.as_ptr(),
+            file_id: self.file_id.as_ptr(),
+            name: self.name.as_ptr(),
+            save_size: self.save.data.len() as u32,
+            cover_size: self.save.cover.as_ref().map_or(0, |c| c.len() as u32),
+            summary: self.summary.as_ptr(),
+            extra: self
+                .extra
+                .as_ref()
+                .map(|e| e.as_ptr())
+                .unwrap_or(std::ptr::null()),
+            playtime: self.save.playtime,
+            created_time: self.save.created_time,
+            modified_time: self.save.modified_time,
+        }
+    }
+}
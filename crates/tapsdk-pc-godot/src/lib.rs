@@ -0,0 +1,451 @@
+//! Godot 4 GDExtension for TapTap PC SDK
+//!
+//! Registers a `TapSdk` node. Add it to an autoload scene, call
+//! `initialize()` once, and connect to its signals; the node polls the SDK
+//! every frame and turns each event into a signal:
+//!
+//! ```gdscript
+//! func _ready():
+//!     if TapSdk.restart_app_if_necessary("your_client_id"):
+//!         get_tree().quit()
+//!         return
+//!     $TapSdk.cloud_save_listed.connect(_on_saves)
+//!     $TapSdk.initialize("your_public_key")
+//!     $TapSdk.cloud_save_list(1)
+//!
+//! func _on_saves(request_id, error, saves):
+//!     if error.is_empty():
+//!         for save in saves:
+//!             print(save.name)
+//! ```
+//!
+//! Errors are reported through `error` dictionaries with `code` and
+//! `message` keys, which are empty on success. File paths may use `res://`
+//! and `user://`.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use godot::classes::{INode, Node, ProjectSettings};
+use godot::prelude::*;
+use tapsdk_pc::callback::{CloudSaveInfo, TransferStage};
+use tapsdk_pc::cloudsave::{CreateSaveRequest, UpdateSaveRequest};
+use tapsdk_pc::{dlc, ownership, user, CloudSave, TapEvent};
+
+struct TapSdkExtension;
+
+#[gdextension]
+unsafe impl ExtensionLibrary for TapSdkExtension {}
+
+/// TapTap PC SDK node
+#[derive(GodotClass)]
+#[class(base = Node)]
+pub struct TapSdk {
+    sdk: Option<tapsdk_pc::TapSdk>,
+    heartbeat: Option<tapsdk_pc::heartbeat::Heartbeat>,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl INode for TapSdk {
+    fn init(base: Base<Node>) -> Self {
+        TapSdk {
+            sdk: None,
+            heartbeat: None,
+            base,
+        }
+    }
+
+    fn process(&mut self, _delta: f64) {
+        let Some(sdk) = &self.sdk else {
+            return;
+        };
+        for event in sdk.run_callbacks() {
+            self.emit_event(event);
+        }
+    }
+}
+
+#[godot_api]
+impl TapSdk {
+    #[signal]
+    fn system_state_changed(state: i64);
+    #[signal]
+    fn authorize_finished(is_cancel: bool, error: GString, token: Dictionary);
+    #[signal]
+    fn game_playable_status_changed(is_playable: bool);
+    #[signal]
+    fn dlc_playable_status_changed(dlc_id: GString, is_playable: bool);
+    #[signal]
+    fn cloud_save_listed(request_id: i64, error: Dictionary, saves: Array<Dictionary>);
+    #[signal]
+    fn cloud_save_created(request_id: i64, error: Dictionary, save: Dictionary);
+    #[signal]
+    fn cloud_save_updated(request_id: i64, error: Dictionary, save: Dictionary);
+    #[signal]
+    fn cloud_save_deleted(request_id: i64, error: Dictionary, uuid: GString);
+    #[signal]
+    fn cloud_save_data_received(request_id: i64, error: Dictionary, data: PackedByteArray);
+    #[signal]
+    fn cloud_save_cover_received(request_id: i64, error: Dictionary, data: PackedByteArray);
+    #[signal]
+    fn cloud_save_progress(request_id: i64, stage: GString, bytes: i64, total: i64);
+    #[signal]
+    fn sdk_error(event_id: i64, message: GString);
+    #[signal]
+    fn connection_changed(connected: bool);
+
+    /// Check if the app needs to restart (call before `initialize`)
+    ///
+    /// If this returns true, TapTap will relaunch the game - quit immediately.
+    #[func]
+    fn restart_app_if_necessary(client_id: GString) -> bool {
+        tapsdk_pc::restart_app_if_necessary(&client_id.to_string()).unwrap_or_else(|e| {
+            godot_error!("TapSdk: {}", e);
+            false
+        })
+    }
+
+    /// Initialize the SDK and start emitting event signals
+    #[func]
+    fn initialize(&mut self, pub_key: GString) -> bool {
+        if self.sdk.is_some() {
+            godot_warn!("TapSdk: already initialized");
+            return true;
+        }
+        match tapsdk_pc::TapSdk::init(&pub_key.to_string()) {
+            Ok(sdk) => {
+                self.sdk = Some(sdk);
+                true
+            }
+            Err(e) => {
+                godot_error!("TapSdk: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Shut down the SDK; signals stop until `initialize` is called again
+    #[func]
+    fn shutdown(&mut self) {
+        self.sdk = None;
+    }
+
+    /// Check for the TapTap client every `interval_ms` and emit
+    /// `connection_changed` when it stops or starts running; 0 disables
+    #[func]
+    fn set_heartbeat_interval(&mut self, interval_ms: i64) {
+        self.heartbeat = (interval_ms > 0).then(|| {
+            tapsdk_pc::heartbeat::start(std::time::Duration::from_millis(interval_ms as u64))
+        });
+    }
+
+    #[func]
+    fn is_initialized(&self) -> bool {
+        self.sdk.is_some()
+    }
+
+    #[func]
+    fn get_client_id(&self) -> GString {
+        self.sdk
+            .as_ref()
+            .and_then(tapsdk_pc::TapSdk::get_client_id)
+            .unwrap_or_default()
+            .into()
+    }
+
+    #[func]
+    fn is_game_owned(&self) -> bool {
+        ownership::is_game_owned()
+    }
+
+    #[func]
+    fn is_game_playable(&self) -> bool {
+        ownership::is_game_playable()
+    }
+
+    #[func]
+    fn is_dlc_owned(&self, dlc_id: GString) -> bool {
+        dlc::is_dlc_owned(&dlc_id.to_string())
+    }
+
+    #[func]
+    fn show_dlc_store(&self, dlc_id: GString) -> bool {
+        report(dlc::show_dlc_store(&dlc_id.to_string()).map(|_| ()))
+    }
+
+    /// Request user authorization; the result arrives as `authorize_finished`
+    #[func]
+    fn authorize(&self, scopes: GString) -> bool {
+        report(user::authorize(&scopes.to_string()))
+    }
+
+    #[func]
+    fn get_open_id(&self) -> GString {
+        user::get_open_id().unwrap_or_default().into()
+    }
+
+    #[func]
+    fn cloud_save_list(&self, request_id: i64) -> bool {
+        with_cloud_save(|cloud_save| cloud_save.list(request_id))
+    }
+
+    /// Create a cloud save; pass empty strings to omit `extra` and the cover
+    #[func]
+    #[allow(clippy::too_many_arguments)]
+    fn cloud_save_create(
+        &self,
+        request_id: i64,
+        name: GString,
+        summary: GString,
+        extra: GString,
+        playtime: i64,
+        data_file_path: GString,
+        cover_file_path: GString,
+    ) -> bool {
+        let request = CreateSaveRequest {
+            name: name.to_string(),
+            summary: summary.to_string(),
+            extra: non_empty(&extra),
+            playtime: playtime.max(0) as u64,
+            data_file_path: globalize(&data_file_path).into_boxed_path(),
+            cover_file_path: non_empty(&cover_file_path)
+                .map(|_| globalize(&cover_file_path).into_boxed_path()),
+        };
+        with_cloud_save(|cloud_save| cloud_save.create(request_id, &request))
+    }
+
+    /// Update a cloud save; pass empty strings to omit `extra` and the cover
+    #[func]
+    #[allow(clippy::too_many_arguments)]
+    fn cloud_save_update(
+        &self,
+        request_id: i64,
+        uuid: GString,
+        name: GString,
+        summary: GString,
+        extra: GString,
+        playtime: i64,
+        data_file_path: GString,
+        cover_file_path: GString,
+    ) -> bool {
+        with_cloud_save(|cloud_save| {
+            let request = UpdateSaveRequest {
+                uuid: uuid.to_string().parse()?,
+                name: name.to_string(),
+                summary: summary.to_string(),
+                extra: non_empty(&extra),
+                playtime: playtime.max(0) as u64,
+                data_file_path: globalize(&data_file_path).into_boxed_path(),
+                cover_file_path: non_empty(&cover_file_path)
+                    .map(|_| globalize(&cover_file_path).into_boxed_path()),
+            };
+            cloud_save.update(request_id, &request)
+        })
+    }
+
+    #[func]
+    fn cloud_save_delete(&self, request_id: i64, uuid: GString) -> bool {
+        with_cloud_save(|cloud_save| cloud_save.delete(request_id, &uuid.to_string().parse()?))
+    }
+
+    #[func]
+    fn cloud_save_get_data(&self, request_id: i64, uuid: GString, file_id: GString) -> bool {
+        with_cloud_save(|cloud_save| {
+            let (uuid, file_id) = (uuid.to_string().parse()?, file_id.to_string().parse()?);
+            cloud_save.get_data(request_id, &uuid, &file_id)
+        })
+    }
+
+    #[func]
+    fn cloud_save_get_cover(&self, request_id: i64, uuid: GString, file_id: GString) -> bool {
+        with_cloud_save(|cloud_save| {
+            let (uuid, file_id) = (uuid.to_string().parse()?, file_id.to_string().parse()?);
+            cloud_save.get_cover(request_id, &uuid, &file_id)
+        })
+    }
+}
+
+impl TapSdk {
+    fn emit_event(&mut self, event: TapEvent) {
+        let (signal, args) = match event {
+            TapEvent::SystemStateChanged(data) => (
+                "system_state_changed",
+                vec![i64::from(data.state.as_code()).to_variant()],
+            ),
+            TapEvent::AuthorizeFinished(data) => {
+                let mut token = Dictionary::new();
+                if let Some(t) = data.token {
+                    token.set("token_type", t.token_type);
+                    token.set("kid", t.kid);
+                    token.set("mac_key", t.mac_key);
+                    token.set("mac_algorithm", t.mac_algorithm);
+                    token.set("scope", t.scope);
+                }
+                (
+                    "authorize_finished",
+                    vec![
+                        data.is_cancel.to_variant(),
+                        data.error.unwrap_or_default().to_variant(),
+                        token.to_variant(),
+                    ],
+                )
+            }
+            TapEvent::GamePlayableStatusChanged(data) => (
+                "game_playable_status_changed",
+                vec![data.is_playable.to_variant()],
+            ),
+            TapEvent::DlcPlayableStatusChanged(data) => (
+                "dlc_playable_status_changed",
+                vec![data.dlc_id.to_variant(), data.is_playable.to_variant()],
+            ),
+            TapEvent::CloudSaveList(data) => {
+                let saves: Array<Dictionary> = data.saves.into_iter().map(save_to_dict).collect();
+                (
+                    "cloud_save_listed",
+                    vec![
+                        data.request_id.to_variant(),
+                        error_to_dict(data.error).to_variant(),
+                        saves.to_variant(),
+                    ],
+                )
+            }
+            TapEvent::CloudSaveCreate(data) => (
+                "cloud_save_created",
+                vec![
+                    data.request_id.to_variant(),
+                    error_to_dict(data.error).to_variant(),
+                    data.save.map(save_to_dict).unwrap_or_default().to_variant(),
+                ],
+            ),
+            TapEvent::CloudSaveUpdate(data) => (
+                "cloud_save_updated",
+                vec![
+                    data.request_id.to_variant(),
+                    error_to_dict(data.error).to_variant(),
+                    data.save.map(save_to_dict).unwrap_or_default().to_variant(),
+                ],
+            ),
+            TapEvent::CloudSaveDelete(data) => (
+                "cloud_save_deleted",
+                vec![
+                    data.request_id.to_variant(),
+                    error_to_dict(data.error).to_variant(),
+                    data.uuid.as_str().to_variant(),
+                ],
+            ),
+            TapEvent::CloudSaveGetData(data) => (
+                "cloud_save_data_received",
+                vec![
+                    data.request_id.to_variant(),
+                    error_to_dict(data.error).to_variant(),
+                    PackedByteArray::from(data.data.as_slice()).to_variant(),
+                ],
+            ),
+            TapEvent::CloudSaveGetCover(data) => (
+                "cloud_save_cover_received",
+                vec![
+                    data.request_id.to_variant(),
+                    error_to_dict(data.error).to_variant(),
+                    PackedByteArray::from(data.data.as_slice()).to_variant(),
+                ],
+            ),
+            TapEvent::CloudSaveProgress(data) => (
+                "cloud_save_progress",
+                vec![
+                    data.request_id.to_variant(),
+                    transfer_stage_name(data.stage).to_variant(),
+                    (data.bytes as i64).to_variant(),
+                    (data.total as i64).to_variant(),
+                ],
+            ),
+            TapEvent::InternalError(data) => (
+                "sdk_error",
+                vec![
+                    (data.event_id as i64).to_variant(),
+                    data.message.to_variant(),
+                ],
+            ),
+            TapEvent::ParseError { event_id, reason } => (
+                "sdk_error",
+                vec![(event_id as i64).to_variant(), reason.to_variant()],
+            ),
+            TapEvent::ConnectionLost => ("connection_changed", vec![false.to_variant()]),
+            TapEvent::ConnectionRestored => ("connection_changed", vec![true.to_variant()]),
+            // Custom payloads are opaque Rust values with no Godot form
+            TapEvent::Custom(_) | TapEvent::Unknown { .. } => return,
+        };
+        self.base_mut()
+            .emit_signal(&StringName::from(signal), &args);
+    }
+}
+
+/// Log a failed request and return whether it was started
+fn report(result: tapsdk_pc::Result<()>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            godot_error!("TapSdk: {}", e);
+            false
+        }
+    }
+}
+
+fn with_cloud_save(request: impl FnOnce(&CloudSave) -> tapsdk_pc::Result<()>) -> bool {
+    match CloudSave::get() {
+        Some(cloud_save) => report(request(&cloud_save)),
+        None => report(Err(tapsdk_pc::TapSdkError::NotInitialized)),
+    }
+}
+
+fn non_empty(value: &GString) -> Option<String> {
+    Some(value.to_string()).filter(|value| !value.is_empty())
+}
+
+/// Resolve `res://` and `user://` paths to file system paths
+fn globalize(path: &GString) -> PathBuf {
+    PathBuf::from(
+        ProjectSettings::singleton()
+            .globalize_path(path)
+            .to_string(),
+    )
+}
+
+fn transfer_stage_name(stage: TransferStage) -> &'static str {
+    match stage {
+        TransferStage::Reading => "reading",
+        TransferStage::Uploading => "uploading",
+        TransferStage::WaitingForServer => "waiting_for_server",
+        TransferStage::Done => "done",
+    }
+}
+
+fn error_to_dict(error: Option<(i64, String)>) -> Dictionary {
+    let mut dict = Dictionary::new();
+    if let Some((code, message)) = error {
+        dict.set("code", code);
+        dict.set("message", message);
+    }
+    dict
+}
+
+fn save_to_dict(save: CloudSaveInfo) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.set("uuid", save.uuid.into_string());
+    dict.set("file_id", save.file_id.into_string());
+    dict.set("name", save.name);
+    dict.set("save_size", save.save_size as i64);
+    dict.set("cover_size", save.cover_size as i64);
+    dict.set("summary", save.summary.unwrap_or_default());
+    dict.set("extra", save.extra.unwrap_or_default());
+    dict.set("playtime", save.playtime.as_secs() as i64);
+    dict.set("created_time", unix_seconds(save.created_time));
+    dict.set("modified_time", unix_seconds(save.modified_time));
+    dict
+}
+
+/// Seconds since the Unix epoch, as sent by the SDK
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
@@ -2,6 +2,8 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
+    emit_upstream_version();
+
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
 
     // Only generate real bindings and link DLL on Windows
@@ -13,17 +15,41 @@ fn main() {
     }
 }
 
+/// Expose `package.metadata.tapsdk.upstream-version` as `SDK_VERSION`
+fn emit_upstream_version() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let manifest = std::fs::read_to_string(PathBuf::from(manifest_dir).join("Cargo.toml"))
+        .expect("Failed to read Cargo.toml");
+    let version = manifest
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("upstream-version"))
+        .and_then(|rest| rest.split('"').nth(1))
+        .expect("Missing package.metadata.tapsdk.upstream-version");
+    println!("cargo:rustc-env=TAPSDK_UPSTREAM_VERSION={}", version);
+}
+
 #[cfg(target_os = "windows")]
 fn build_windows() {
-    // Path to the SDK directory containing headers and lib (bundled with crate)
-    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let sdk_dir = PathBuf::from(&manifest_dir).join("sdk");
-    let sdk_dir = sdk_dir
-        .canonicalize()
-        .expect("Failed to find sdk directory");
+    // Path to the SDK directory containing headers and lib: an external SDK
+    // drop given by TAPSDK_LIB_DIR, or else the vendored or downloaded one
+    println!("cargo:rerun-if-env-changed=TAPSDK_LIB_DIR");
+    let sdk_dir = match env::var_os("TAPSDK_LIB_DIR") {
+        Some(dir) => PathBuf::from(dir)
+            .canonicalize()
+            .expect("Failed to find TAPSDK_LIB_DIR"),
+        None => default_sdk_dir(),
+    };
+
+    // Link against a test double of the SDK instead (see tapsdk-pc-fake)
+    println!("cargo:rerun-if-env-changed=TAPSDK_FAKE_LIB_DIR");
+    let lib_dir = match env::var_os("TAPSDK_FAKE_LIB_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => sdk_dir.clone(),
+    };
 
     // Tell cargo to link against taptap_api.lib
-    println!("cargo:rustc-link-search=native={}", sdk_dir.display());
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
     println!("cargo:rustc-link-lib=dylib=taptap_api");
 
     // Tell cargo to rerun if the headers change
@@ -84,11 +110,22 @@ fn build_windows() {
 
     // Go up from OUT_DIR to find the target directory
     // OUT_DIR is typically target/<profile>/build/<crate>/out
-    let dll_src = sdk_dir.join("taptap_api.dll");
-    if dll_src.exists() {
+    // Versioned DLLs (taptap_api*.dll) are copied too, for runtime probing
+    let dlls = std::fs::read_dir(&lib_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.to_ascii_lowercase())
+                .is_some_and(|name| name.starts_with("taptap_api") && name.ends_with(".dll"))
+        });
+    for dll_src in dlls {
         // Copy to multiple locations to ensure it's found at runtime
         if let Some(deps_dir) = target_path.ancestors().nth(3) {
-            let dll_dest = deps_dir.join("taptap_api.dll");
+            let dll_dest = deps_dir.join(dll_src.file_name().unwrap());
             if let Err(e) = std::fs::copy(&dll_src, &dll_dest) {
                 println!("cargo:warning=Failed to copy DLL to deps: {}", e);
             }
@@ -96,6 +133,176 @@ fn build_windows() {
     }
 }
 
+/// The SDK vendored in `sdk/`, which is only there in a checkout of the
+/// repository, or else the downloaded one
+///
+/// An explicit TAPSDK_SDK_URL is downloaded even in a checkout.
+#[cfg(target_os = "windows")]
+fn default_sdk_dir() -> PathBuf {
+    let vendored = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("sdk");
+    #[cfg(feature = "download-sdk")]
+    {
+        println!("cargo:rerun-if-env-changed=TAPSDK_SDK_URL");
+        if env::var_os("TAPSDK_SDK_URL").is_some() || !vendored.is_dir() {
+            return download::sdk().unwrap_or_else(|e| fail(&e));
+        }
+    }
+    match vendored.canonicalize() {
+        Ok(dir) => dir,
+        Err(_) => fail(
+            "The TapTap PC SDK is not included in the tapsdk-pc-sys package. Enable its \
+             download-sdk feature, or set TAPSDK_LIB_DIR to a directory containing \
+             taptap_api.h, taptap_cloudsave.h and taptap_api.lib",
+        ),
+    }
+}
+
+/// Stop the build with `message` instead of a panic and its backtrace
+#[cfg(target_os = "windows")]
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
+#[cfg(all(target_os = "windows", feature = "download-sdk"))]
+mod download {
+    use sha2::{Digest, Sha256};
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+
+    /// Files of the SDK release in `upstream-version`, with their SHA-256
+    const PINNED_FILES: &[(&str, &str)] = &[
+        (
+            "taptap_api.dll",
+            "60442f78fcd6c041adc0d744c70c31bd38b9b65c654f1cb548e221d921f59df0",
+        ),
+        (
+            "taptap_api.h",
+            "a584dfcf8d827d459caa6689eebf9a63140fba8cc0907f46102aa07946f53ddb",
+        ),
+        (
+            "taptap_api.lib",
+            "3b3a25a71d9ab3cbab7c723d579fb06a0a7cccce9cfec487bba1a76f10db6fd4",
+        ),
+        (
+            "taptap_cloudsave.h",
+            "c9d33a5e09831c673be8ae008310d0e1283c9963d45c0867b7d60dd0bd859e6c",
+        ),
+    ];
+
+    /// Largest download accepted, well above the size of any SDK zip
+    const MAX_DOWNLOAD: u64 = 256 * 1024 * 1024;
+
+    /// Download the SDK into OUT_DIR, returning the directory containing
+    /// `taptap_api.h`
+    ///
+    /// The zip at TAPSDK_SDK_URL if set, which must match TAPSDK_SDK_SHA256,
+    /// or else the pinned files from the `sdk/` directory of this crate's
+    /// release tag.
+    pub(super) fn sdk() -> Result<PathBuf, String> {
+        println!("cargo:rerun-if-env-changed=TAPSDK_SDK_SHA256");
+        let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("sdk-download");
+        match env::var("TAPSDK_SDK_URL") {
+            Ok(url) => {
+                let expected = env::var("TAPSDK_SDK_SHA256")
+                    .map_err(|_| "TAPSDK_SDK_URL requires TAPSDK_SDK_SHA256 to be set".to_string())?
+                    .to_ascii_lowercase();
+                zip(&url, &expected, &out_dir)
+            }
+            Err(_) => pinned(&out_dir),
+        }
+    }
+
+    fn zip(url: &str, expected: &str, out_dir: &Path) -> Result<PathBuf, String> {
+        let marker = out_dir.join(".sha256");
+
+        // Reuse an earlier download of the same zip
+        if fs::read_to_string(&marker).ok().as_deref() == Some(expected) {
+            if let Some(dir) = find_headers(out_dir) {
+                return Ok(dir);
+            }
+        }
+
+        let _ = fs::remove_dir_all(out_dir);
+        fs::create_dir_all(out_dir)
+            .map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+        let data = fetch(url, expected)?;
+        zip::ZipArchive::new(Cursor::new(data))
+            .and_then(|mut archive| archive.extract(out_dir))
+            .map_err(|e| format!("Failed to unpack {}: {}", url, e))?;
+
+        let dir = find_headers(out_dir)
+            .ok_or_else(|| format!("The SDK zip at {} contains no taptap_api.h", url))?;
+        fs::write(&marker, expected)
+            .map_err(|e| format!("Failed to write {}: {}", marker.display(), e))?;
+        Ok(dir)
+    }
+
+    fn pinned(out_dir: &Path) -> Result<PathBuf, String> {
+        let version = env::var("CARGO_PKG_VERSION").unwrap();
+        fs::create_dir_all(out_dir)
+            .map_err(|e| format!("Failed to create {}: {}", out_dir.display(), e))?;
+        for (name, expected) in PINNED_FILES {
+            let path = out_dir.join(name);
+            // Reuse an earlier download
+            if fs::read(&path).is_ok_and(|data| sha256(&data) == *expected) {
+                continue;
+            }
+            let url = format!(
+                "https://raw.githubusercontent.com/dsh0416/tapsdk-pc.js/v{}/crates/tapsdk-pc-sys/sdk/{}",
+                version, name
+            );
+            let data = fetch(&url, expected)?;
+            fs::write(&path, data)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(out_dir.to_path_buf())
+    }
+
+    /// Download `url`, which must match the SHA-256 `expected`
+    fn fetch(url: &str, expected: &str) -> Result<Vec<u8>, String> {
+        let data = ureq::get(url)
+            .call()
+            .and_then(|mut response| {
+                response
+                    .body_mut()
+                    .with_config()
+                    .limit(MAX_DOWNLOAD)
+                    .read_to_vec()
+            })
+            .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+        let actual = sha256(&data);
+        if actual != expected {
+            return Err(format!(
+                "SHA-256 mismatch for {}: expected {}, got {}",
+                url, expected, actual
+            ));
+        }
+        Ok(data)
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        Sha256::digest(data)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Directory under `dir` that contains `taptap_api.h`
+    fn find_headers(dir: &Path) -> Option<PathBuf> {
+        if dir.join("taptap_api.h").is_file() {
+            return Some(dir.to_path_buf());
+        }
+        fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .find_map(|entry| find_headers(&entry.path()))
+    }
+}
+
 #[cfg(not(target_os = "windows"))]
 fn build_windows() {
     build_stub();
@@ -7,6 +7,16 @@
 //!
 //! **Windows only.** This SDK only supports Windows (x86_64).
 //! On macOS and Linux, all functions will panic with an "unsupported platform" error.
+//!
+//! # SDK location
+//!
+//! Bindings are generated from the headers vendored in `sdk/`. Set
+//! `TAPSDK_LIB_DIR` to a directory holding another SDK drop's headers,
+//! `taptap_api.lib` and `taptap_api.dll` to build against that instead.
+//! The published crate does not include `sdk/`: with the opt-in
+//! `download-sdk` feature, the files of the same SDK release are downloaded
+//! and checked against pinned SHA-256 hashes instead. Set `TAPSDK_SDK_URL`
+//! and `TAPSDK_SDK_SHA256` to download and check another SDK zip.
 
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
@@ -17,6 +27,9 @@
 // Include the generated bindings (real on Windows, stubs on other platforms)
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+/// TapTap PC SDK release the bundled headers and import library come from
+pub const SDK_VERSION: &str = env!("TAPSDK_UPSTREAM_VERSION");
+
 // Re-export commonly used constants for convenience
 
 /// SDK initialization result codes
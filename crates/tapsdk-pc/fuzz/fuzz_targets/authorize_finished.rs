@@ -0,0 +1,49 @@
+//! Authorization responses whose fixed-size string fields hold arbitrary
+//! bytes, with or without a NUL terminator
+
+#![no_main]
+
+use std::ffi::c_void;
+use std::mem;
+
+use libfuzzer_sys::fuzz_target;
+use tapsdk_pc::callback::{self, event_id};
+use tapsdk_pc::TapEvent;
+use tapsdk_pc_sys::AuthorizeFinishedResponse;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, data)) = data.split_first() else {
+        return;
+    };
+    // Every field is a byte array apart from the flag, so fill the struct
+    // byte by byte, leaving what the input doesn't cover zeroed
+    let mut response: AuthorizeFinishedResponse = unsafe { mem::zeroed() };
+    let mut bytes = data.iter().map(|&b| b as _);
+    for field in [
+        &mut response.error[..],
+        &mut response.token_type[..],
+        &mut response.kid[..],
+        &mut response.mac_key[..],
+        &mut response.mac_algorithm[..],
+        &mut response.scope[..],
+    ] {
+        for (dst, src) in field.iter_mut().zip(bytes.by_ref()) {
+            *dst = src;
+        }
+    }
+    response.is_cancel = flags & 1 != 0;
+
+    let event = unsafe {
+        callback::fuzz_parse_event(
+            event_id::AUTHORIZE_FINISHED,
+            &mut response as *mut _ as *mut c_void,
+        )
+    };
+    match event {
+        TapEvent::AuthorizeFinished(data) => {
+            assert!(data.token.is_none() || !(response.is_cancel || data.error.is_some()));
+        }
+        TapEvent::ParseError { .. } => {}
+        event => panic!("parsed as {:?}", event),
+    }
+});
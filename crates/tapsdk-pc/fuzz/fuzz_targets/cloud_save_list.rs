@@ -0,0 +1,134 @@
+//! Cloud save list responses with out-of-range counts, null pointers and
+//! arbitrary string contents
+//!
+//! Every pointer handed to the parser is valid for what the response claims,
+//! unless the claim is one the parser has to reject before reading: a count
+//! out of range or a null save array.
+
+#![no_main]
+
+use std::ffi::{c_char, c_void, CString};
+use std::ptr;
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use tapsdk_pc::callback::{self, event_id};
+use tapsdk_pc::TapEvent;
+use tapsdk_pc_sys::{TapCloudSaveInfo, TapCloudSaveListResponse, TapSDK_Error};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    request_id: i64,
+    error: Option<(i64, Option<Vec<u8>>)>,
+    saves: Vec<Save>,
+    count: Count,
+    null_saves: bool,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Save {
+    uuid: Option<Vec<u8>>,
+    file_id: Option<Vec<u8>>,
+    name: Option<Vec<u8>>,
+    summary: Option<Vec<u8>>,
+    extra: Option<Vec<u8>>,
+    save_size: u32,
+    cover_size: u32,
+    playtime: u32,
+    created_time: u32,
+    modified_time: u32,
+}
+
+/// `save_count` of the response
+#[derive(Debug, Arbitrary)]
+enum Count {
+    /// The number of saves provided
+    Exact,
+    /// Below zero
+    Negative(u32),
+    /// Above the 1024 saves the parser accepts
+    TooMany(u32),
+}
+
+impl Count {
+    fn value(&self, saves: usize) -> i32 {
+        match *self {
+            Count::Exact => saves as i32,
+            Count::Negative(n) => -((n >> 1) as i32) - 1,
+            Count::TooMany(n) => ((n >> 1) as i32).saturating_add(1025),
+        }
+    }
+}
+
+/// Keeps the strings alive while the parser reads them
+#[derive(Default)]
+struct Strings(Vec<CString>);
+
+impl Strings {
+    /// A NUL-terminated copy of `bytes` up to the first NUL, or null
+    fn add(&mut self, bytes: &Option<Vec<u8>>) -> *const c_char {
+        let Some(bytes) = bytes else {
+            return ptr::null();
+        };
+        let bytes = bytes.split(|&b| b == 0).next().unwrap_or_default();
+        let string = CString::new(bytes).expect("no interior NUL");
+        let ptr = string.as_ptr();
+        self.0.push(string);
+        ptr
+    }
+}
+
+fuzz_target!(|input: Input| {
+    // At most the 1024 saves a valid response may hold
+    let saves = &input.saves[..input.saves.len().min(1024)];
+    let mut strings = Strings::default();
+
+    let error = input.error.as_ref().map(|(code, message)| TapSDK_Error {
+        code: *code,
+        message: strings.add(message),
+    });
+    let infos: Vec<TapCloudSaveInfo> = saves
+        .iter()
+        .map(|save| TapCloudSaveInfo {
+            uuid: strings.add(&save.uuid),
+            file_id: strings.add(&save.file_id),
+            name: strings.add(&save.name),
+            save_size: save.save_size,
+            cover_size: save.cover_size,
+            summary: strings.add(&save.summary),
+            extra: strings.add(&save.extra),
+            playtime: save.playtime,
+            created_time: save.created_time,
+            modified_time: save.modified_time,
+        })
+        .collect();
+
+    let response = TapCloudSaveListResponse {
+        request_id: input.request_id,
+        error: error
+            .as_ref()
+            .map_or(ptr::null(), |error| error as *const _),
+        saves: if input.null_saves {
+            ptr::null()
+        } else {
+            infos.as_ptr()
+        },
+        save_count: input.count.value(infos.len()),
+    };
+
+    let event = unsafe {
+        callback::fuzz_parse_event(
+            event_id::CLOUD_SAVE_LIST,
+            &response as *const _ as *mut c_void,
+        )
+    };
+    match (&input.count, event) {
+        (Count::Exact, TapEvent::CloudSaveList(data)) => {
+            assert!(!input.null_saves || infos.is_empty());
+            assert_eq!(data.request_id, input.request_id);
+            assert_eq!(data.saves.len(), infos.len());
+        }
+        (_, TapEvent::ParseError { .. }) => {}
+        (count, event) => panic!("{:?} parsed as {:?}", count, event),
+    }
+});
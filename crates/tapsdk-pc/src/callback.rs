@@ -1,10 +1,27 @@
 //! Callback registry and event handling for TapTap PC SDK
 
-use std::collections::VecDeque;
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
 use std::ffi::CStr;
-use std::sync::Mutex;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, LockResult, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::error::SystemState;
+use crossbeam_queue::SegQueue;
+
+use crate::cloudsave::{FileId, SaveUuid};
+use crate::error::{SystemState, TapSdkError};
+use crate::ffi;
+use crate::observer::{self, Operation};
+
+pub use crate::listener::{listen, EventListener};
+pub use crate::pump::{
+    channel, clear_spawner, poll_policy, set_poll_policy, set_spawner, PollPolicy, Spawner,
+};
+#[cfg(feature = "futures")]
+pub use crate::stream::{event_stream, response, EventStream, Response};
 
 /// Event IDs matching the C SDK
 pub mod event_id {
@@ -19,10 +36,23 @@ pub mod event_id {
     pub const CLOUD_SAVE_DELETE: u32 = 6004;
     pub const CLOUD_SAVE_GET_DATA: u32 = 6005;
     pub const CLOUD_SAVE_GET_COVER: u32 = 6006;
+
+    // Synthetic events generated by this crate use IDs from 90000 upward,
+    // outside the ranges reserved by the SDK
+    pub const SYNTHETIC_BASE: u32 = 90000;
+    pub const INTERNAL_ERROR: u32 = 90001;
+    pub const PARSE_ERROR: u32 = 90002;
+    /// Generated by bindings when a parsed event cannot be handed to the
+    /// host language
+    pub const EVENT_CONVERSION_FAILED: u32 = 90003;
+    pub const CONNECTION_LOST: u32 = 90004;
+    pub const CONNECTION_RESTORED: u32 = 90005;
+    pub const CLOUD_SAVE_PROGRESS: u32 = 90006;
 }
 
 /// Authorization token returned after successful authorization
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AuthToken {
     pub token_type: String,
     pub kid: String,
@@ -33,6 +63,7 @@ pub struct AuthToken {
 
 /// Authorization finished event data
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AuthorizeFinishedData {
     pub is_cancel: bool,
     pub error: Option<String>,
@@ -41,18 +72,21 @@ pub struct AuthorizeFinishedData {
 
 /// System state changed event data
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SystemStateChangedData {
     pub state: SystemState,
 }
 
 /// Game playable status changed event data
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GamePlayableStatusChangedData {
     pub is_playable: bool,
 }
 
 /// DLC playable status changed event data
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DlcPlayableStatusChangedData {
     pub dlc_id: String,
     pub is_playable: bool,
@@ -60,21 +94,49 @@ pub struct DlcPlayableStatusChangedData {
 
 /// Cloud save info
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CloudSaveInfo {
-    pub uuid: String,
-    pub file_id: String,
+    pub uuid: SaveUuid,
+    pub file_id: FileId,
     pub name: String,
-    pub save_size: u32,
-    pub cover_size: u32,
+    pub save_size: u64,
+    pub cover_size: u64,
     pub summary: Option<String>,
     pub extra: Option<String>,
-    pub playtime: u32,
-    pub created_time: u32,
-    pub modified_time: u32,
+    /// Total playtime recorded with the save
+    pub playtime: Duration,
+    /// When the save was created
+    pub created_time: SystemTime,
+    /// When the save was last modified
+    pub modified_time: SystemTime,
+}
+
+#[cfg(feature = "chrono")]
+impl CloudSaveInfo {
+    /// When the save was created, as a `chrono` timestamp
+    pub fn created_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.created_time.into()
+    }
+
+    /// When the save was last modified, as a `chrono` timestamp
+    pub fn modified_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.modified_time.into()
+    }
+
+    /// Time since the save was last modified
+    pub fn age(&self) -> chrono::TimeDelta {
+        chrono::Utc::now() - self.modified_at()
+    }
+
+    /// Total playtime recorded with the save, as a `chrono` duration
+    pub fn playtime_delta(&self) -> chrono::TimeDelta {
+        chrono::TimeDelta::from_std(self.playtime).unwrap_or(chrono::TimeDelta::MAX)
+    }
 }
 
 /// Cloud save list response
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CloudSaveListData {
     pub request_id: i64,
     pub error: Option<(i64, String)>,
@@ -83,6 +145,7 @@ pub struct CloudSaveListData {
 
 /// Cloud save create/update response
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CloudSaveCreateData {
     pub request_id: i64,
     pub error: Option<(i64, String)>,
@@ -91,22 +154,118 @@ pub struct CloudSaveCreateData {
 
 /// Cloud save delete response
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CloudSaveDeleteData {
     pub request_id: i64,
     pub error: Option<(i64, String)>,
-    pub uuid: String,
+    pub uuid: SaveUuid,
 }
 
 /// Cloud save get file response
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CloudSaveGetFileData {
     pub request_id: i64,
     pub error: Option<(i64, String)>,
     pub data: Vec<u8>,
 }
 
+impl CloudSaveGetFileData {
+    /// Detect the image type of downloaded cover data
+    ///
+    /// # Returns
+    /// `"image/png"`, `"image/jpeg"` or `"image/webp"`, or `None` if the
+    /// data is not one of these formats
+    pub fn content_type(&self) -> Option<&'static str> {
+        match self.data.as_slice() {
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+            [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+            _ => None,
+        }
+    }
+}
+
+/// Stage of a cloud save upload or download
+///
+/// The SDK reports no progress of its own, so stages mark the points this
+/// crate can observe: uploads go through `Reading`, `Uploading` and `Done`,
+/// downloads through `WaitingForServer`, `Reading` and `Done`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferStage {
+    /// The SDK accepted the save files, or downloaded data is being copied
+    /// out of it
+    Reading,
+    /// The SDK accepted the files and is uploading them
+    Uploading,
+    /// The download was requested and no data has arrived yet
+    WaitingForServer,
+    /// The response has arrived and follows this event
+    Done,
+}
+
+/// Progress of a cloud save upload or download
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloudSaveProgressData {
+    /// Request the progress belongs to
+    pub request_id: i64,
+    pub stage: TransferStage,
+    /// Bytes transferred so far
+    pub bytes: u64,
+    /// Total size in bytes, or 0 while unknown
+    pub total: u64,
+}
+
+/// Internal error event data
+///
+/// Emitted instead of the original event when handling an SDK callback
+/// panicked, so the panic never unwinds into the C caller. Also emitted
+/// once, with `event_id` 0, after recovering from a poisoned internal lock.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InternalErrorData {
+    /// ID of the SDK event that was being handled, or 0 if none
+    pub event_id: u32,
+    /// Description of the failure
+    pub message: String,
+}
+
+/// Custom event data produced by a parser installed with [`register_parser`]
+#[derive(Clone)]
+pub struct CustomEventData {
+    /// SDK event ID the event was delivered under
+    pub event_id: u32,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl CustomEventData {
+    /// Wrap a parsed payload
+    pub fn new<T: Any + Send + Sync>(event_id: u32, payload: T) -> Self {
+        CustomEventData {
+            event_id,
+            payload: Arc::new(payload),
+        }
+    }
+
+    /// Get the payload if it is of type `T`
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+}
+
+impl std::fmt::Debug for CustomEventData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomEventData")
+            .field("event_id", &self.event_id)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Events that can be received from the SDK
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TapEvent {
     /// System state changed
     SystemStateChanged(SystemStateChangedData),
@@ -128,352 +287,1970 @@ pub enum TapEvent {
     CloudSaveGetData(CloudSaveGetFileData),
     /// Cloud save get cover response
     CloudSaveGetCover(CloudSaveGetFileData),
+    /// Progress of a cloud save create, update or download
+    CloudSaveProgress(CloudSaveProgressData),
+    /// Handling an SDK callback failed inside this crate
+    InternalError(InternalErrorData),
+    /// An SDK event carried data that failed validation and was discarded
+    ParseError { event_id: u32, reason: String },
+    /// The TapTap client stopped running, see [`heartbeat`](crate::heartbeat)
+    ConnectionLost,
+    /// The TapTap client is running again after [`ConnectionLost`](Self::ConnectionLost)
+    ConnectionRestored,
+    /// Event decoded by a parser installed with [`register_parser`]
+    ///
+    /// Custom payloads are opaque, so they cannot be serialized or recorded
+    /// for replay.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomEventData),
     /// Unknown event
     Unknown { event_id: u32 },
 }
 
-/// Global event queue
-static EVENT_QUEUE: Mutex<VecDeque<TapEvent>> = Mutex::new(VecDeque::new());
+impl TapEvent {
+    /// Get the SDK event ID this event was delivered under
+    pub fn event_id(&self) -> u32 {
+        match self {
+            TapEvent::SystemStateChanged(_) => event_id::SYSTEM_STATE_CHANGED,
+            TapEvent::AuthorizeFinished(_) => event_id::AUTHORIZE_FINISHED,
+            TapEvent::GamePlayableStatusChanged(_) => event_id::GAME_PLAYABLE_STATUS_CHANGED,
+            TapEvent::DlcPlayableStatusChanged(_) => event_id::DLC_PLAYABLE_STATUS_CHANGED,
+            TapEvent::CloudSaveList(_) => event_id::CLOUD_SAVE_LIST,
+            TapEvent::CloudSaveCreate(_) => event_id::CLOUD_SAVE_CREATE,
+            TapEvent::CloudSaveUpdate(_) => event_id::CLOUD_SAVE_UPDATE,
+            TapEvent::CloudSaveDelete(_) => event_id::CLOUD_SAVE_DELETE,
+            TapEvent::CloudSaveGetData(_) => event_id::CLOUD_SAVE_GET_DATA,
+            TapEvent::CloudSaveGetCover(_) => event_id::CLOUD_SAVE_GET_COVER,
+            TapEvent::CloudSaveProgress(_) => event_id::CLOUD_SAVE_PROGRESS,
+            TapEvent::InternalError(_) => event_id::INTERNAL_ERROR,
+            TapEvent::ParseError { .. } => event_id::PARSE_ERROR,
+            TapEvent::ConnectionLost => event_id::CONNECTION_LOST,
+            TapEvent::ConnectionRestored => event_id::CONNECTION_RESTORED,
+            TapEvent::Custom(data) => data.event_id,
+            TapEvent::Unknown { event_id } => *event_id,
+        }
+    }
 
-/// Register the global callback handler with the SDK
-pub fn register_callbacks() {
-    unsafe {
-        // Register for all event types we care about
-        tapsdk_pc_sys::TapSDK_RegisterCallback(
-            event_id::SYSTEM_STATE_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_RegisterCallback(event_id::AUTHORIZE_FINISHED, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_RegisterCallback(
-            event_id::GAME_PLAYABLE_STATUS_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_RegisterCallback(
-            event_id::DLC_PLAYABLE_STATUS_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_RegisterCallback(event_id::CLOUD_SAVE_LIST, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_RegisterCallback(event_id::CLOUD_SAVE_CREATE, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_RegisterCallback(event_id::CLOUD_SAVE_UPDATE, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_RegisterCallback(event_id::CLOUD_SAVE_DELETE, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_RegisterCallback(
-            event_id::CLOUD_SAVE_GET_DATA,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_RegisterCallback(
-            event_id::CLOUD_SAVE_GET_COVER,
-            Some(global_callback),
-        );
+    /// Get the request ID of a cloud save response
+    pub fn request_id(&self) -> Option<i64> {
+        match self {
+            TapEvent::CloudSaveList(data) => Some(data.request_id),
+            TapEvent::CloudSaveCreate(data) | TapEvent::CloudSaveUpdate(data) => {
+                Some(data.request_id)
+            }
+            TapEvent::CloudSaveDelete(data) => Some(data.request_id),
+            TapEvent::CloudSaveGetData(data) | TapEvent::CloudSaveGetCover(data) => {
+                Some(data.request_id)
+            }
+            _ => None,
+        }
     }
 }
 
-/// Unregister the global callback handler
-pub fn unregister_callbacks() {
-    unsafe {
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::SYSTEM_STATE_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::AUTHORIZE_FINISHED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::GAME_PLAYABLE_STATUS_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::DLC_PLAYABLE_STATUS_CHANGED,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(event_id::CLOUD_SAVE_LIST, Some(global_callback));
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::CLOUD_SAVE_CREATE,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::CLOUD_SAVE_UPDATE,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::CLOUD_SAVE_DELETE,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::CLOUD_SAVE_GET_DATA,
-            Some(global_callback),
-        );
-        tapsdk_pc_sys::TapSDK_UnregisterCallback(
-            event_id::CLOUD_SAVE_GET_COVER,
-            Some(global_callback),
-        );
+/// An event together with the time it was received from the SDK
+///
+/// The timestamp is taken when the SDK invokes our callback, before the
+/// event is queued, so it can be used to measure delivery latency and to
+/// order events from batched polls.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    /// Monotonic receipt time, for measuring latency
+    pub received_at: Instant,
+    /// Wall-clock receipt time, for logging and correlation
+    pub received_at_system: SystemTime,
+    /// The event itself
+    pub event: TapEvent,
+}
+
+impl Envelope {
+    /// Wrap an event, stamping it with the current time
+    pub fn new(event: TapEvent) -> Self {
+        Envelope {
+            received_at: Instant::now(),
+            received_at_system: SystemTime::now(),
+            event,
+        }
     }
 }
 
-/// Poll for events from the SDK
-///
-/// This calls `TapSDK_RunCallbacks()` to process pending callbacks,
-/// then returns all events that were queued.
-pub fn poll_events() -> Vec<TapEvent> {
-    // First, run the SDK callbacks to trigger our callback handler
-    unsafe {
-        tapsdk_pc_sys::TapSDK_RunCallbacks();
+#[cfg(feature = "chrono")]
+impl Envelope {
+    /// Wall-clock receipt time, as a `chrono` timestamp
+    pub fn received_at_utc(&self) -> chrono::DateTime<chrono::Utc> {
+        self.received_at_system.into()
     }
+}
 
-    // Then drain the event queue
-    let mut queue = EVENT_QUEUE.lock().unwrap();
-    queue.drain(..).collect()
+impl From<TapEvent> for Envelope {
+    fn from(event: TapEvent) -> Self {
+        Envelope::new(event)
+    }
 }
 
-/// Global callback handler called by the SDK
-///
-/// # Safety
-/// This function is called from C code with raw pointers
-unsafe extern "C" fn global_callback(event_id: u32, data: *mut std::ffi::c_void) {
-    let event = parse_event(event_id, data);
+/// Snapshot of event pipeline metrics
+#[derive(Debug, Clone, Default)]
+pub struct CallbackStats {
+    /// Number of events received, keyed by event ID
+    pub events_by_type: BTreeMap<u32, u64>,
+    /// Total number of events received
+    pub total_events: u64,
+    /// Largest number of events that were waiting in the queue at once
+    pub queue_high_water_mark: usize,
+    /// Events with a known ID whose payload failed validation
+    pub dropped_conversions: u64,
+    /// Number of completed polls
+    pub poll_count: u64,
+    /// Average time spent in a single poll
+    pub average_poll_latency: Duration,
+    /// Events discarded because the queue was full
+    pub dropped_events: u64,
+}
 
-    if let Ok(mut queue) = EVENT_QUEUE.lock() {
-        queue.push_back(event);
-    }
+/// Default maximum number of events held in the queue
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4096;
+
+/// What to do when an event arrives while the queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room
+    #[default]
+    DropOldest = 0,
+    /// Discard the incoming event
+    DropNewest = 1,
+    /// Keep only the latest status event of each kind (system state, game
+    /// playable status, or the same DLC's playable status) once the queue is
+    /// full, delivering it in the order it arrived among the queued events;
+    /// other events fall back to dropping the oldest queued event
+    Coalesce = 2,
 }
 
-/// Parse an event from raw SDK data
-unsafe fn parse_event(event_id: u32, data: *mut std::ffi::c_void) -> TapEvent {
-    match event_id {
-        event_id::SYSTEM_STATE_CHANGED => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let notification = &*(data as *const tapsdk_pc_sys::TapSystemStateNotification);
-            TapEvent::SystemStateChanged(SystemStateChangedData {
-                state: SystemState::from(notification.state),
-            })
+/// Event queue configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConfig {
+    /// Maximum number of queued events, or `None` for an unbounded queue
+    pub capacity: Option<usize>,
+    /// Behavior when the queue is full
+    pub overflow: OverflowPolicy,
+}
+
+impl QueueConfig {
+    const DEFAULT: QueueConfig = QueueConfig {
+        capacity: Some(DEFAULT_QUEUE_CAPACITY),
+        overflow: OverflowPolicy::DropOldest,
+    };
+}
+
+impl OverflowPolicy {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => OverflowPolicy::DropNewest,
+            2 => OverflowPolicy::Coalesce,
+            _ => OverflowPolicy::DropOldest,
         }
+    }
+}
 
-        event_id::AUTHORIZE_FINISHED => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::AuthorizeFinishedResponse);
-
-            let error = {
-                let error_str = CStr::from_ptr(response.error.as_ptr())
-                    .to_string_lossy()
-                    .into_owned();
-                if error_str.is_empty() {
-                    None
-                } else {
-                    Some(error_str)
-                }
-            };
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig::DEFAULT
+    }
+}
 
-            let token = if !response.is_cancel && error.is_none() {
-                Some(AuthToken {
-                    token_type: CStr::from_ptr(response.token_type.as_ptr())
-                        .to_string_lossy()
-                        .into_owned(),
-                    kid: CStr::from_ptr(response.kid.as_ptr())
-                        .to_string_lossy()
-                        .into_owned(),
-                    mac_key: CStr::from_ptr(response.mac_key.as_ptr())
-                        .to_string_lossy()
-                        .into_owned(),
-                    mac_algorithm: CStr::from_ptr(response.mac_algorithm.as_ptr())
-                        .to_string_lossy()
-                        .into_owned(),
-                    scope: CStr::from_ptr(response.scope.as_ptr())
-                        .to_string_lossy()
-                        .into_owned(),
-                })
-            } else {
-                None
-            };
+/// Capacity value representing an unbounded queue
+const UNBOUNDED: usize = usize::MAX;
 
-            TapEvent::AuthorizeFinished(AuthorizeFinishedData {
-                is_cancel: response.is_cancel,
-                error,
-                token,
-            })
+/// Bounded FIFO of events waiting to be polled
+///
+/// The FIFO itself is a lock-free MPSC queue, so the SDK callback thread never
+/// blocks on the consumer. Coalesced status events are parked in a small side
+/// table that is only locked when the queue overflows under
+/// [`OverflowPolicy::Coalesce`] and when draining. Every event gets a
+/// sequence number, so draining merges parked events back in arrival order.
+///
+/// Each [`TapSdk`](crate::TapSdk) session owns one, see [`activate_queue`].
+pub(crate) struct EventQueue {
+    events: SegQueue<(u64, Envelope)>,
+    /// Slots taken in the FIFO, reserved before pushing
+    len: AtomicUsize,
+    capacity: AtomicUsize,
+    overflow: AtomicU8,
+    coalesced: Mutex<Vec<(u64, Envelope)>>,
+    has_coalesced: AtomicBool,
+    next_seq: AtomicU64,
+}
+
+impl EventQueue {
+    const fn new() -> Self {
+        EventQueue {
+            events: SegQueue::new(),
+            len: AtomicUsize::new(0),
+            capacity: AtomicUsize::new(DEFAULT_QUEUE_CAPACITY),
+            overflow: AtomicU8::new(OverflowPolicy::DropOldest as u8),
+            coalesced: Mutex::new(Vec::new()),
+            has_coalesced: AtomicBool::new(false),
+            next_seq: AtomicU64::new(0),
         }
+    }
 
-        event_id::GAME_PLAYABLE_STATUS_CHANGED => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::GamePlayableStatusChangedResponse);
-            TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData {
-                is_playable: response.is_playable,
-            })
+    pub(crate) fn with_config(config: QueueConfig) -> Self {
+        let queue = EventQueue::new();
+        queue.set_config(config);
+        queue
+    }
+
+    pub(crate) fn config(&self) -> QueueConfig {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        QueueConfig {
+            capacity: (capacity != UNBOUNDED).then_some(capacity),
+            overflow: OverflowPolicy::from_u8(self.overflow.load(Ordering::Relaxed)),
         }
+    }
 
-        event_id::DLC_PLAYABLE_STATUS_CHANGED => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
+    fn set_config(&self, config: QueueConfig) {
+        self.capacity
+            .store(config.capacity.unwrap_or(UNBOUNDED), Ordering::Relaxed);
+        self.overflow
+            .store(config.overflow as u8, Ordering::Relaxed);
+    }
+
+    /// Number of events waiting in the FIFO
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Push an event, applying the overflow policy
+    ///
+    /// Returns the number of events that were dropped.
+    fn push(&self, event: Envelope) -> u64 {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let mut dropped = 0;
+        // Checking the capacity and taking the slot is one atomic step, so
+        // concurrent producers cannot overfill the queue
+        while !self.reserve(capacity) {
+            match OverflowPolicy::from_u8(self.overflow.load(Ordering::Relaxed)) {
+                OverflowPolicy::DropNewest => return dropped + 1,
+                OverflowPolicy::Coalesce if coalesce_key(&event.event).is_some() => {
+                    return dropped + self.coalesce(seq, event);
+                }
+                OverflowPolicy::DropOldest | OverflowPolicy::Coalesce => {
+                    // A zero capacity queue can never hold anything
+                    if self.pop_front().is_none() {
+                        return dropped + 1;
+                    }
+                    dropped += 1;
+                }
             }
-            let response = &*(data as *const tapsdk_pc_sys::DLCPlayableStatusChangedResponse);
-            TapEvent::DlcPlayableStatusChanged(DlcPlayableStatusChangedData {
-                dlc_id: CStr::from_ptr(response.dlc_id.as_ptr())
-                    .to_string_lossy()
-                    .into_owned(),
-                is_playable: response.is_playable,
-            })
         }
+        self.events.push((seq, event));
+        dropped
+    }
 
-        event_id::CLOUD_SAVE_LIST => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::TapCloudSaveListResponse);
+    /// Take a slot in the FIFO if it holds fewer than `capacity` events
+    fn reserve(&self, capacity: usize) -> bool {
+        self.len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                (len < capacity).then_some(len + 1)
+            })
+            .is_ok()
+    }
 
-            let error = parse_sdk_error(response.error);
+    /// Park a status event in the side table, replacing a stale one of the
+    /// same kind
+    fn coalesce(&self, seq: u64, event: Envelope) -> u64 {
+        let mut coalesced = recover(self.coalesced.lock());
+        let key = coalesce_key(&event.event);
+        match coalesced
+            .iter()
+            .position(|(_, parked)| coalesce_key(&parked.event) == key)
+        {
+            Some(index) => {
+                coalesced[index] = (seq, event);
+                1
+            }
+            None => {
+                coalesced.push((seq, event));
+                self.has_coalesced.store(true, Ordering::Release);
+                0
+            }
+        }
+    }
 
-            let saves = if response.saves.is_null() || response.save_count <= 0 {
-                Vec::new()
-            } else {
-                let slice =
-                    std::slice::from_raw_parts(response.saves, response.save_count as usize);
-                slice.iter().map(|s| parse_cloud_save_info(s)).collect()
-            };
+    fn pop_front(&self) -> Option<(u64, Envelope)> {
+        let event = self.events.pop()?;
+        self.len.fetch_sub(1, Ordering::AcqRel);
+        Some(event)
+    }
 
-            TapEvent::CloudSaveList(CloudSaveListData {
-                request_id: response.request_id,
-                error,
-                saves,
-            })
+    /// Take every queued event, with coalesced status events merged back in
+    /// arrival order
+    fn drain(&self) -> Vec<Envelope> {
+        let mut events = Vec::new();
+        while let Some(event) = self.pop_front() {
+            events.push(event);
         }
+        // Skip the side table lock on the common path
+        if self.has_coalesced.swap(false, Ordering::Acquire) {
+            events.append(&mut *recover(self.coalesced.lock()));
+            events.sort_by_key(|&(seq, _)| seq);
+        }
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+}
 
-        event_id::CLOUD_SAVE_CREATE | event_id::CLOUD_SAVE_UPDATE => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::TapCloudSaveCreateResponse);
+/// Identity of a status notification for coalescing purposes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoalesceKey<'a> {
+    SystemState,
+    GamePlayable,
+    DlcPlayable(&'a str),
+}
 
-            let error = parse_sdk_error(response.error);
+/// Get the coalesce key of a status event, or `None` for other events
+fn coalesce_key(event: &TapEvent) -> Option<CoalesceKey<'_>> {
+    match event {
+        TapEvent::SystemStateChanged(_) => Some(CoalesceKey::SystemState),
+        TapEvent::GamePlayableStatusChanged(_) => Some(CoalesceKey::GamePlayable),
+        TapEvent::DlcPlayableStatusChanged(data) => Some(CoalesceKey::DlcPlayable(&data.dlc_id)),
+        _ => None,
+    }
+}
 
-            let save = if response.save.is_null() {
-                None
-            } else {
-                Some(parse_cloud_save_info(&*response.save))
-            };
+/// Queue of the active SDK session, which the SDK callback routes events into
+static ACTIVE_QUEUE: RwLock<Option<Arc<EventQueue>>> = RwLock::new(None);
 
-            let event_data = CloudSaveCreateData {
-                request_id: response.request_id,
-                error,
-                save,
-            };
+/// Queue used while no session is active, e.g. for replayed events
+static DETACHED_QUEUE: EventQueue = EventQueue::new();
 
-            if event_id == event_id::CLOUD_SAVE_CREATE {
-                TapEvent::CloudSaveCreate(event_data)
-            } else {
-                TapEvent::CloudSaveUpdate(event_data)
-            }
-        }
+/// Event counts keyed by event ID
+static EVENT_COUNTS: Mutex<BTreeMap<u32, u64>> = Mutex::new(BTreeMap::new());
+static TOTAL_EVENTS: AtomicU64 = AtomicU64::new(0);
+static QUEUE_HIGH_WATER_MARK: AtomicUsize = AtomicUsize::new(0);
+static DROPPED_CONVERSIONS: AtomicU64 = AtomicU64::new(0);
+static POLL_COUNT: AtomicU64 = AtomicU64::new(0);
+static POLL_NANOS: AtomicU64 = AtomicU64::new(0);
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
 
-        event_id::CLOUD_SAVE_DELETE => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::TapCloudSaveDeleteResponse);
+/// Recently delivered events, kept for bug reports
+static HISTORY: Mutex<VecDeque<Envelope>> = Mutex::new(VecDeque::new());
+/// Maximum number of events kept in [`HISTORY`] (0 = disabled)
+static HISTORY_CAPACITY: AtomicUsize = AtomicUsize::new(0);
 
-            let error = parse_sdk_error(response.error);
+/// Accepted cloud save requests still awaiting a response, once per
+/// request (IDs may be reused)
+static PENDING_REQUESTS: Mutex<Vec<PendingRequest>> = Mutex::new(Vec::new());
 
-            let uuid = if response.uuid.is_null() {
-                String::new()
-            } else {
-                CStr::from_ptr(response.uuid).to_string_lossy().into_owned()
-            };
+struct PendingRequest {
+    request_id: i64,
+    operation: Operation,
+    started: Instant,
+    /// Whether nobody waits for the response anymore, because the watchdog
+    /// synthesized it or the request was cancelled
+    abandoned: bool,
+}
 
-            TapEvent::CloudSaveDelete(CloudSaveDeleteData {
-                request_id: response.request_id,
-                error,
-                uuid,
-            })
-        }
+/// Handler run before a `PlatformShutdown` state change is delivered
+pub(crate) type PlatformShutdownHandler = Arc<dyn Fn() + Send + Sync>;
 
-        event_id::CLOUD_SAVE_GET_DATA | event_id::CLOUD_SAVE_GET_COVER => {
-            if data.is_null() {
-                return TapEvent::Unknown { event_id };
-            }
-            let response = &*(data as *const tapsdk_pc_sys::TapCloudSaveGetFileResponse);
+/// Currently installed platform shutdown handler
+static PLATFORM_SHUTDOWN_HANDLER: RwLock<Option<PlatformShutdownHandler>> = RwLock::new(None);
 
-            let error = parse_sdk_error(response.error);
+/// Number of events a lagging subscriber can fall behind before missing some
+#[cfg(feature = "tokio")]
+const BROADCAST_CAPACITY: usize = 256;
 
-            let file_data = if response.data.is_null() || response.size == 0 {
-                Vec::new()
-            } else {
-                let slice =
-                    std::slice::from_raw_parts(response.data as *const u8, response.size as usize);
-                slice.to_vec()
-            };
+/// Sender behind [`subscribe`]
+#[cfg(feature = "tokio")]
+static BROADCAST: std::sync::OnceLock<tokio::sync::broadcast::Sender<TapEvent>> =
+    std::sync::OnceLock::new();
 
-            let event_data = CloudSaveGetFileData {
-                request_id: response.request_id,
-                error,
-                data: file_data,
-            };
+/// Lock poisoning state: no poisoning seen yet
+const POISON_NONE: u8 = 0;
+/// Lock poisoning state: recovered, warning not yet delivered
+const POISON_PENDING: u8 = 1;
+/// Lock poisoning state: warning delivered
+const POISON_REPORTED: u8 = 2;
+static POISON_STATE: AtomicU8 = AtomicU8::new(POISON_NONE);
 
-            if event_id == event_id::CLOUD_SAVE_GET_DATA {
-                TapEvent::CloudSaveGetData(event_data)
-            } else {
-                TapEvent::CloudSaveGetCover(event_data)
-            }
-        }
+/// Take the guard out of a lock result, recovering from poisoning
+///
+/// A panic while holding one of our locks leaves the protected data in a
+/// usable state (counters and event lists), so the guard is reused instead
+/// of failing every later access. The first recovery schedules a warning
+/// event for the next [`poll_events`] call.
+pub(crate) fn recover<G>(result: LockResult<G>) -> G {
+    result.unwrap_or_else(|poisoned| {
+        let _ = POISON_STATE.compare_exchange(
+            POISON_NONE,
+            POISON_PENDING,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+        poisoned.into_inner()
+    })
+}
+
+/// Build the one-time warning event after a lock was recovered
+fn take_poison_warning() -> Option<TapEvent> {
+    POISON_STATE
+        .compare_exchange(
+            POISON_PENDING,
+            POISON_REPORTED,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        )
+        .ok()?;
+    Some(TapEvent::InternalError(InternalErrorData {
+        event_id: 0,
+        message: "recovered from a poisoned lock after a panic; events may have been lost"
+            .to_string(),
+    }))
+}
 
-        _ => TapEvent::Unknown { event_id },
+/// Run `f` with the queue events are currently routed into
+fn with_queue<R>(f: impl FnOnce(&EventQueue) -> R) -> R {
+    match &*recover(ACTIVE_QUEUE.read()) {
+        Some(queue) => f(queue),
+        None => f(&DETACHED_QUEUE),
     }
 }
 
-/// Parse SDK error from raw pointer
-unsafe fn parse_sdk_error(error: *const tapsdk_pc_sys::TapSDK_Error) -> Option<(i64, String)> {
-    if error.is_null() {
-        return None;
+/// Route events into `queue` until [`deactivate_queue`]
+pub(crate) fn activate_queue(queue: Arc<EventQueue>) {
+    *recover(ACTIVE_QUEUE.write()) = Some(queue);
+}
+
+/// Stop routing events into `queue`, if it is still the active one
+///
+/// Events left in it are dropped with it rather than showing up in the
+/// next session.
+pub(crate) fn deactivate_queue(queue: &Arc<EventQueue>) {
+    let mut active = recover(ACTIVE_QUEUE.write());
+    if active
+        .as_ref()
+        .is_some_and(|active| Arc::ptr_eq(active, queue))
+    {
+        *active = None;
     }
+}
 
-    let err = &*error;
-    let message = if err.message.is_null() {
-        String::new()
-    } else {
-        CStr::from_ptr(err.message).to_string_lossy().into_owned()
-    };
+/// Configure the event queue capacity and overflow policy
+///
+/// Applies to the queue of the running SDK session. Before initialization,
+/// it configures the queue that [`TapSdk::init`](crate::TapSdk::init)
+/// starts with; [`TapSdk::init_with`](crate::TapSdk::init_with) takes the
+/// configuration from its [`TapSdkConfig`](crate::TapSdkConfig) instead.
+///
+/// If the new capacity is smaller than the number of queued events, the
+/// excess is kept and further pushes apply the overflow policy.
+///
+/// # Example
+/// ```
+/// use tapsdk_pc::callback::{self, OverflowPolicy, QueueConfig};
+///
+/// callback::set_queue_config(QueueConfig {
+///     capacity: Some(256),
+///     overflow: OverflowPolicy::Coalesce,
+/// });
+/// ```
+pub fn set_queue_config(config: QueueConfig) {
+    with_queue(|queue| queue.set_config(config));
+}
 
-    Some((err.code, message))
+/// Get the current event queue configuration
+pub fn queue_config() -> QueueConfig {
+    with_queue(EventQueue::config)
 }
 
-/// Parse cloud save info from raw struct
-unsafe fn parse_cloud_save_info(info: &tapsdk_pc_sys::TapCloudSaveInfo) -> CloudSaveInfo {
-    CloudSaveInfo {
-        uuid: ptr_to_string(info.uuid),
-        file_id: ptr_to_string(info.file_id),
-        name: ptr_to_string(info.name),
-        save_size: info.save_size,
-        cover_size: info.cover_size,
-        summary: ptr_to_optional_string(info.summary),
-        extra: ptr_to_optional_string(info.extra),
-        playtime: info.playtime,
-        created_time: info.created_time,
-        modified_time: info.modified_time,
+/// Keep the last `capacity` received events for [`recent_events`]
+///
+/// History is disabled by default. Passing 0 disables it again and clears
+/// any recorded events. Recorded events are full clones, including cloud
+/// save file contents, so keep the capacity small.
+pub fn set_history_capacity(capacity: usize) {
+    HISTORY_CAPACITY.store(capacity, Ordering::Relaxed);
+    let mut history = recover(HISTORY.lock());
+    while history.len() > capacity {
+        history.pop_front();
     }
+    history.shrink_to(capacity);
 }
 
-/// Convert a C string pointer to a Rust String
-unsafe fn ptr_to_string(ptr: *const std::os::raw::c_char) -> String {
-    if ptr.is_null() {
-        String::new()
-    } else {
-        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+/// Get the configured event history capacity
+pub fn history_capacity() -> usize {
+    HISTORY_CAPACITY.load(Ordering::Relaxed)
+}
+
+/// Get the most recently received events, oldest first
+///
+/// Includes events that were later dropped by the queue overflow policy.
+/// Always empty unless enabled with [`set_history_capacity`].
+pub fn recent_events() -> Vec<Envelope> {
+    recover(HISTORY.lock()).iter().cloned().collect()
+}
+
+/// Get the request IDs of cloud save requests still awaiting a response
+///
+/// A request is pending from the moment the SDK accepts it until its
+/// response event is received from the SDK.
+pub fn pending_requests() -> Vec<i64> {
+    recover(PENDING_REQUESTS.lock())
+        .iter()
+        .filter(|request| !request.abandoned)
+        .map(|request| request.request_id)
+        .collect()
+}
+
+/// Subscribe to every event received from now on
+///
+/// Unlike [`poll_events`], which hands each event to a single caller, every
+/// receiver gets its own copy of every event, so independent subsystems can
+/// each listen without coordinating. Events are still queued for
+/// [`poll_events`] as usual.
+///
+/// A receiver that falls more than 256 events behind skips the oldest ones
+/// and gets [`RecvError::Lagged`](tokio::sync::broadcast::error::RecvError::Lagged).
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let mut events = tapsdk_pc::callback::subscribe();
+/// while let Ok(event) = events.recv().await {
+///     println!("{:?}", event);
+/// }
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub fn subscribe() -> tokio::sync::broadcast::Receiver<TapEvent> {
+    broadcast_sender().subscribe()
+}
+
+#[cfg(feature = "tokio")]
+fn broadcast_sender() -> &'static tokio::sync::broadcast::Sender<TapEvent> {
+    BROADCAST.get_or_init(|| tokio::sync::broadcast::channel(BROADCAST_CAPACITY).0)
+}
+
+/// Send an event to the [`subscribe`] receivers, if there are any
+#[cfg(feature = "tokio")]
+fn broadcast(event: &TapEvent) {
+    let sender = broadcast_sender();
+    if sender.receiver_count() > 0 {
+        let _ = sender.send(event.clone());
     }
 }
 
-/// Convert a C string pointer to an optional Rust String
-unsafe fn ptr_to_optional_string(ptr: *const std::os::raw::c_char) -> Option<String> {
-    if ptr.is_null() {
-        None
-    } else {
-        let s = CStr::from_ptr(ptr).to_string_lossy().into_owned();
-        if s.is_empty() {
-            None
-        } else {
-            Some(s)
+/// Mark a cloud save request as awaiting a response
+pub(crate) fn track_request(request_id: i64, operation: Operation) {
+    recover(PENDING_REQUESTS.lock()).push(PendingRequest {
+        request_id,
+        operation,
+        started: Instant::now(),
+        abandoned: false,
+    });
+}
+
+/// Stop waiting for the response to a cloud save request
+///
+/// The oldest request with this ID that is still pending no longer counts
+/// as pending, the [`watchdog`](crate::watchdog) no longer times it out,
+/// and its response is dropped when it arrives, so it cannot be mistaken
+/// for the response to a newer request reusing the ID.
+///
+/// # Returns
+/// `false` if no request with this ID is pending
+pub fn cancel_request(request_id: i64) -> bool {
+    let cancelled = recover(PENDING_REQUESTS.lock())
+        .iter_mut()
+        .find(|request| request.request_id == request_id && !request.abandoned)
+        .map(|request| request.abandoned = true)
+        .is_some();
+    if cancelled {
+        crate::cloudsave::abandon_transfer(request_id);
+    }
+    cancelled
+}
+
+/// Mark the oldest request with this ID as resolved
+///
+/// Returns `false` if that request was abandoned, so its response is late
+/// and must not be delivered.
+fn resolve_request(request_id: i64) -> bool {
+    let mut pending = recover(PENDING_REQUESTS.lock());
+    match pending.iter().position(|r| r.request_id == request_id) {
+        Some(index) => !pending.remove(index).abandoned,
+        None => true,
+    }
+}
+
+/// Mark the requests started more than `timeout` before `now` as timed out
+/// and return them
+///
+/// Their responses are dropped when they arrive, see [`dispatch`].
+pub(crate) fn time_out_requests(timeout: Duration, now: Instant) -> Vec<(i64, Operation)> {
+    recover(PENDING_REQUESTS.lock())
+        .iter_mut()
+        .filter(|request| {
+            !request.abandoned && now.saturating_duration_since(request.started) > timeout
+        })
+        .map(|request| {
+            request.abandoned = true;
+            (request.request_id, request.operation)
+        })
+        .collect()
+}
+
+/// Install or remove the platform shutdown handler
+pub(crate) fn set_platform_shutdown_handler(handler: Option<PlatformShutdownHandler>) {
+    *recover(PLATFORM_SHUTDOWN_HANDLER.write()) = handler;
+}
+
+/// Run the platform shutdown handler if this event announces a shutdown
+fn run_platform_shutdown_handler(event: &TapEvent) {
+    let TapEvent::SystemStateChanged(data) = event else {
+        return;
+    };
+    if data.state != SystemState::PlatformShutdown {
+        return;
+    }
+    let handler = recover(PLATFORM_SHUTDOWN_HANDLER.read()).clone();
+    if let Some(handler) = handler {
+        // A panicking handler must not swallow the event itself
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler()));
+    }
+}
+
+/// Append an event to the history, evicting the oldest one when full
+fn record_history(envelope: &Envelope) {
+    let capacity = HISTORY_CAPACITY.load(Ordering::Relaxed);
+    if capacity == 0 {
+        return;
+    }
+    let mut history = recover(HISTORY.lock());
+    while history.len() >= capacity {
+        history.pop_front();
+    }
+    history.push_back(envelope.clone());
+}
+
+/// Get a snapshot of the event pipeline metrics
+pub fn stats() -> CallbackStats {
+    let poll_count = POLL_COUNT.load(Ordering::Relaxed);
+    let average_poll_latency = POLL_NANOS
+        .load(Ordering::Relaxed)
+        .checked_div(poll_count)
+        .map(Duration::from_nanos)
+        .unwrap_or_default();
+
+    CallbackStats {
+        events_by_type: recover(EVENT_COUNTS.lock()).clone(),
+        total_events: TOTAL_EVENTS.load(Ordering::Relaxed),
+        queue_high_water_mark: QUEUE_HIGH_WATER_MARK.load(Ordering::Relaxed),
+        dropped_conversions: DROPPED_CONVERSIONS.load(Ordering::Relaxed),
+        poll_count,
+        average_poll_latency,
+        dropped_events: DROPPED_EVENTS.load(Ordering::Relaxed),
+    }
+}
+
+/// Parser for an SDK event ID that this crate does not model
+///
+/// Receives the raw data pointer passed to the SDK callback, which may be
+/// null. It is only valid for the duration of the call.
+pub type EventParser = unsafe fn(*mut std::ffi::c_void) -> TapEvent;
+
+/// Parsers installed with [`register_parser`], keyed by event ID
+static PARSERS: RwLock<BTreeMap<u32, EventParser>> = RwLock::new(BTreeMap::new());
+
+/// Install a parser for an event ID introduced by a newer TapTap client
+///
+/// Events with this ID are passed to `parser` instead of collapsing into
+/// [`TapEvent::Unknown`]. Parsers usually return [`TapEvent::Custom`].
+/// Registering the same ID again replaces the previous parser. Panics in
+/// the parser are reported as [`TapEvent::InternalError`].
+///
+/// # Arguments
+/// * `event_id` - SDK event ID to handle
+/// * `parser` - Function decoding the raw event data
+///
+/// # Returns
+/// An error if `event_id` is already handled by this crate.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::callback::{self, CustomEventData, TapEvent};
+///
+/// struct Achievement(u32);
+///
+/// unsafe fn parse_achievement(data: *mut std::ffi::c_void) -> TapEvent {
+///     let id = if data.is_null() { 0 } else { *(data as *const u32) };
+///     TapEvent::Custom(CustomEventData::new(8001, Achievement(id)))
+/// }
+///
+/// callback::register_parser(8001, parse_achievement)?;
+/// # Ok::<(), tapsdk_pc::TapSdkError>(())
+/// ```
+pub fn register_parser(event_id: u32, parser: EventParser) -> crate::error::Result<()> {
+    if is_modeled(event_id) || event_id >= event_id::SYNTHETIC_BASE {
+        return Err(TapSdkError::InvalidArgument(format!(
+            "event ID {} is reserved by tapsdk-pc",
+            event_id
+        )));
+    }
+
+    let previous = recover(PARSERS.write()).insert(event_id, parser);
+    // Callbacks registered during init only cover the IDs known back then
+    if previous.is_none() && crate::sdk::is_initialized() {
+        unsafe {
+            ffi::TapSDK_RegisterCallback(event_id, Some(global_callback));
+        }
+    }
+    Ok(())
+}
+
+/// Get the parser installed for an event ID
+fn custom_parser(event_id: u32) -> Option<EventParser> {
+    recover(PARSERS.read()).get(&event_id).copied()
+}
+
+/// Raw SDK callback signature
+pub type RawCallback = unsafe extern "C" fn(event_id: u32, data: *mut std::ffi::c_void);
+
+/// Register a raw callback directly with the SDK
+///
+/// The callback runs alongside the crate's own dispatcher, which keeps
+/// delivering events through [`poll_events`]. This is intended for engine
+/// integrations that need events this crate does not model yet. The
+/// callback is invoked on the thread calling [`poll_events`], with a data
+/// pointer that is only valid for the duration of the call, and must not
+/// unwind.
+///
+/// # Arguments
+/// * `event_id` - SDK event ID to listen for
+/// * `callback` - Function to invoke for each event
+///
+/// # Safety
+/// `callback` must correctly interpret the data the SDK passes for
+/// `event_id`.
+pub unsafe fn register_raw(event_id: u32, callback: RawCallback) -> crate::error::Result<()> {
+    if !crate::sdk::is_initialized() {
+        return Err(TapSdkError::NotInitialized);
+    }
+    ffi::TapSDK_RegisterCallback(event_id, Some(callback));
+    Ok(())
+}
+
+/// Unregister a raw callback previously passed to [`register_raw`]
+pub fn unregister_raw(event_id: u32, callback: RawCallback) -> crate::error::Result<()> {
+    if !crate::sdk::is_initialized() {
+        return Err(TapSdkError::NotInitialized);
+    }
+    unsafe {
+        ffi::TapSDK_UnregisterCallback(event_id, Some(callback));
+    }
+    Ok(())
+}
+
+/// Categories of SDK events to register the global callback handler for
+///
+/// Combine categories with `|`. Embedders that only use part of the SDK,
+/// such as a save tool that only needs [`CLOUD_SAVE`](Self::CLOUD_SAVE),
+/// can leave the other callbacks unregistered. Event IDs with a custom
+/// parser are always registered.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::callback::CallbackSet;
+/// use tapsdk_pc::{TapSdk, TapSdkConfig};
+///
+/// let config = TapSdkConfig::new("your_public_key_here")
+///     .callbacks(CallbackSet::SYSTEM | CallbackSet::CLOUD_SAVE);
+/// let sdk = TapSdk::init_with(config).expect("Failed to init SDK");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackSet(u8);
+
+impl CallbackSet {
+    /// No events
+    pub const NONE: CallbackSet = CallbackSet(0);
+    /// [`SYSTEM_STATE_CHANGED`](event_id::SYSTEM_STATE_CHANGED)
+    pub const SYSTEM: CallbackSet = CallbackSet(1 << 0);
+    /// [`AUTHORIZE_FINISHED`](event_id::AUTHORIZE_FINISHED)
+    pub const USER: CallbackSet = CallbackSet(1 << 1);
+    /// Game and DLC playable status changes (4001-4002)
+    pub const OWNERSHIP: CallbackSet = CallbackSet(1 << 2);
+    /// Cloud save responses (6001-6006)
+    pub const CLOUD_SAVE: CallbackSet = CallbackSet(1 << 3);
+    /// Every event modeled by this crate
+    pub const ALL: CallbackSet = CallbackSet(0b1111);
+
+    /// Whether every category in `other` is part of this set
+    pub fn contains(self, other: CallbackSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// SDK event IDs covered by this set
+    pub fn event_ids(self) -> Vec<u32> {
+        const CATEGORIES: [(CallbackSet, &[u32]); 4] = [
+            (CallbackSet::SYSTEM, &[event_id::SYSTEM_STATE_CHANGED]),
+            (CallbackSet::USER, &[event_id::AUTHORIZE_FINISHED]),
+            (
+                CallbackSet::OWNERSHIP,
+                &[
+                    event_id::GAME_PLAYABLE_STATUS_CHANGED,
+                    event_id::DLC_PLAYABLE_STATUS_CHANGED,
+                ],
+            ),
+            (
+                CallbackSet::CLOUD_SAVE,
+                &[
+                    event_id::CLOUD_SAVE_LIST,
+                    event_id::CLOUD_SAVE_CREATE,
+                    event_id::CLOUD_SAVE_UPDATE,
+                    event_id::CLOUD_SAVE_DELETE,
+                    event_id::CLOUD_SAVE_GET_DATA,
+                    event_id::CLOUD_SAVE_GET_COVER,
+                ],
+            ),
+        ];
+        CATEGORIES
+            .iter()
+            .filter(|(category, _)| self.contains(*category))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect()
+    }
+}
+
+impl Default for CallbackSet {
+    fn default() -> Self {
+        CallbackSet::ALL
+    }
+}
+
+impl std::ops::BitOr for CallbackSet {
+    type Output = CallbackSet;
+
+    fn bitor(self, other: CallbackSet) -> CallbackSet {
+        CallbackSet(self.0 | other.0)
+    }
+}
+
+/// Register the global callback handler with the SDK
+pub fn register_callbacks() {
+    register_callback_set(CallbackSet::ALL);
+}
+
+/// Register the global callback handler for the events in `set`
+pub fn register_callback_set(set: CallbackSet) {
+    unsafe {
+        for event_id in set.event_ids() {
+            ffi::TapSDK_RegisterCallback(event_id, Some(global_callback));
+        }
+
+        // Plus any event IDs with a custom parser
+        for &event_id in recover(PARSERS.read()).keys() {
+            ffi::TapSDK_RegisterCallback(event_id, Some(global_callback));
+        }
+    }
+}
+
+/// Unregister the global callback handler
+pub fn unregister_callbacks() {
+    unregister_callback_set(CallbackSet::ALL);
+    unsafe {
+        for &event_id in recover(PARSERS.read()).keys() {
+            ffi::TapSDK_UnregisterCallback(event_id, Some(global_callback));
+        }
+    }
+}
+
+/// Unregister the global callback handler for the events in `set`
+///
+/// Callbacks for event IDs with a custom parser stay registered.
+pub fn unregister_callback_set(set: CallbackSet) {
+    unsafe {
+        for event_id in set.event_ids() {
+            ffi::TapSDK_UnregisterCallback(event_id, Some(global_callback));
+        }
+    }
+}
+
+/// Downloaded data is copied in chunks of this size, reporting progress
+/// after each one
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Copy downloaded data out of the SDK's buffer
+fn read_chunked(request_id: i64, source: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(source.len());
+    for chunk in source.chunks(READ_CHUNK_SIZE) {
+        data.extend_from_slice(chunk);
+        crate::cloudsave::report_progress(
+            request_id,
+            TransferStage::Reading,
+            data.len() as u64,
+            source.len() as u64,
+        );
+    }
+    data
+}
+
+/// Drop every event queued outside of a session and every pending request
+/// without delivering them
+pub(crate) fn discard_events() {
+    DETACHED_QUEUE.drain();
+    recover(PENDING_REQUESTS.lock()).clear();
+    crate::staging::release_all();
+    crate::cloudsave::reset();
+    crate::ownership::reset();
+    crate::dlc::reset();
+    crate::sdk::reset();
+}
+
+/// Poll for events from the SDK
+///
+/// This calls `TapSDK_RunCallbacks()` to process pending callbacks,
+/// then returns all events that were queued.
+pub fn poll_events() -> Vec<TapEvent> {
+    poll_envelopes()
+        .into_iter()
+        .map(|envelope| envelope.event)
+        .collect()
+}
+
+/// Poll for events from the SDK, keeping their receipt timestamps
+///
+/// Like [`poll_events`], but each event is wrapped in an [`Envelope`].
+pub fn poll_envelopes() -> Vec<Envelope> {
+    let queue = recover(ACTIVE_QUEUE.read()).clone();
+    match queue {
+        Some(queue) => poll_queue(&queue),
+        None => poll_queue(&DETACHED_QUEUE),
+    }
+}
+
+/// Run the SDK callbacks, then take the events queued in `queue`
+pub(crate) fn poll_queue(queue: &EventQueue) -> Vec<Envelope> {
+    let started = Instant::now();
+
+    // First, run the SDK callbacks to trigger our callback handler
+    run_sdk_callbacks();
+
+    // Then drain the event queue
+    let mut events = queue.drain();
+    if let Some(warning) = take_poison_warning() {
+        record_event(event_id::INTERNAL_ERROR, &warning);
+        observer::event_received(&warning);
+        let warning = Envelope::new(warning);
+        record_history(&warning);
+        events.insert(0, warning);
+    }
+
+    POLL_COUNT.fetch_add(1, Ordering::Relaxed);
+    POLL_NANOS.fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+
+    events
+}
+
+/// Held while calling into the SDK from threads it may not expect, as the
+/// SDK is not thread-safe
+static SDK_LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    /// Whether this thread holds [`SDK_LOCK`]
+    static HOLDS_SDK_LOCK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` holding the lock serializing `TapSDK_RunCallbacks` with
+/// `TapSDK_Shutdown`
+///
+/// Reentrant, so that the SDK can be shut down from one of its callbacks.
+pub(crate) fn with_sdk_lock<R>(f: impl FnOnce() -> R) -> R {
+    struct Release;
+
+    impl Drop for Release {
+        fn drop(&mut self) {
+            HOLDS_SDK_LOCK.set(false);
+        }
+    }
+
+    if HOLDS_SDK_LOCK.get() {
+        return f();
+    }
+    let _guard = recover(SDK_LOCK.lock());
+    HOLDS_SDK_LOCK.set(true);
+    let _release = Release;
+    f()
+}
+
+/// Run the SDK callbacks, which queues and fans out the events they carry
+///
+/// Without an initialized SDK only replayed events can be queued, and an
+/// unloaded DLL must not be called into. The check and the call happen
+/// under [`with_sdk_lock`], so the SDK cannot be shut down in between, and
+/// the background pump never runs the callbacks alongside the game.
+pub(crate) fn run_sdk_callbacks() {
+    with_sdk_lock(|| {
+        if crate::sdk::is_initialized() && crate::crash::library_mapped() {
+            unsafe {
+                ffi::TapSDK_RunCallbacks();
+            }
+        }
+    })
+}
+
+/// Poll for events from the SDK, keeping only those with one of `event_ids`
+///
+/// Events with other IDs, including the synthetic ones in [`event_id`], are
+/// discarded. Use this in tools that only care about some events, such as
+/// the cloud save responses.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::callback::{self, event_id};
+///
+/// let saves = callback::poll_events_filtered(&[event_id::CLOUD_SAVE_LIST]);
+/// ```
+pub fn poll_events_filtered(event_ids: &[u32]) -> Vec<TapEvent> {
+    poll_envelopes_filtered(event_ids)
+        .into_iter()
+        .map(|envelope| envelope.event)
+        .collect()
+}
+
+/// Like [`poll_events_filtered`], keeping the receipt timestamps
+pub fn poll_envelopes_filtered(event_ids: &[u32]) -> Vec<Envelope> {
+    filter_envelopes(poll_envelopes(), event_ids)
+}
+
+fn filter_envelopes(mut events: Vec<Envelope>, event_ids: &[u32]) -> Vec<Envelope> {
+    events.retain(|envelope| event_ids.contains(&envelope.event.event_id()));
+    events
+}
+
+/// Keep only the latest status event of each kind among `events`
+///
+/// Of several system state changes, game playable status changes, or
+/// playable status changes of the same DLC, only the last one is kept, in
+/// its place; other events are untouched. Use this on each polled batch to
+/// act on the current status once instead of on every step towards it.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::callback;
+///
+/// let events = callback::coalesce_status_events(callback::poll_envelopes());
+/// ```
+pub fn coalesce_status_events(events: Vec<Envelope>) -> Vec<Envelope> {
+    let mut seen = Vec::new();
+    let mut keep = vec![true; events.len()];
+    for (index, envelope) in events.iter().enumerate().rev() {
+        if let Some(key) = coalesce_key(&envelope.event) {
+            keep[index] = !seen.contains(&key);
+            seen.push(key);
+        }
+    }
+    events
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(envelope, keep)| keep.then_some(envelope))
+        .collect()
+}
+
+/// Global callback handler called by the SDK
+///
+/// # Safety
+/// This function is called from C code with raw pointers
+unsafe extern "C" fn global_callback(event_id: u32, data: *mut std::ffi::c_void) {
+    // Unwinding across the `extern "C"` boundary is undefined behavior, so
+    // any panic while parsing or dispatching is turned into an event
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let event = parse_event(event_id, data);
+        dispatch(event_id, event);
+    }));
+
+    if let Err(payload) = result {
+        let message = panic_message(payload.as_ref());
+        log::error!(event_id; "{}", message);
+        let event = TapEvent::InternalError(InternalErrorData { event_id, message });
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            dispatch(event_id::INTERNAL_ERROR, event)
+        }));
+    }
+}
+
+/// Record, observe and queue an event
+pub(crate) fn dispatch(raw_event_id: u32, event: TapEvent) {
+    record_event(raw_event_id, &event);
+    if let Some(request_id) = event.request_id() {
+        crate::staging::release(request_id);
+        if !resolve_request(request_id) {
+            log::debug!(request_id; "Dropped the response to an abandoned request");
+            return;
+        }
+    }
+    deliver(raw_event_id, event);
+}
+
+/// Record and queue the response synthesized for a timed out request
+///
+/// The request stays pending until its real response arrives, so that
+/// response is dropped instead of resolving a newer request with the same
+/// ID.
+pub(crate) fn dispatch_timeout(event: TapEvent) {
+    let raw_event_id = event.event_id();
+    record_event(raw_event_id, &event);
+    deliver(raw_event_id, event);
+}
+
+/// Observe and queue an event that was already recorded
+#[cfg_attr(not(feature = "replay"), allow(unused_variables))]
+fn deliver(raw_event_id: u32, event: TapEvent) {
+    if let Some(done) = crate::cloudsave::finish_transfer(&event) {
+        dispatch(done.event_id(), done);
+    }
+    crate::cloudsave::cache_event(&event);
+    crate::ownership::record_event(&event);
+    crate::dlc::record_event(&event);
+    crate::sdk::record_event(&event);
+    observer::event_received(&event);
+    run_platform_shutdown_handler(&event);
+    crate::subscription::notify(&event);
+    #[cfg(feature = "tokio")]
+    broadcast(&event);
+
+    let envelope = Envelope::new(event);
+    record_history(&envelope);
+    crate::listener::fan_out(&envelope);
+    #[cfg(feature = "replay")]
+    crate::replay::capture(raw_event_id, &envelope);
+
+    let (dropped, len) = with_queue(|queue| (queue.push(envelope), queue.len()));
+    if dropped > 0 {
+        DROPPED_EVENTS.fetch_add(dropped, Ordering::Relaxed);
+        log::debug!(dropped; "Event queue full, dropped events");
+    }
+    QUEUE_HIGH_WATER_MARK.fetch_max(len, Ordering::Relaxed);
+}
+
+/// Extract a readable message from a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panic while handling SDK callback: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panic while handling SDK callback: {}", message)
+    } else {
+        "panic while handling SDK callback".to_string()
+    }
+}
+
+/// Update the pipeline metrics for a received event
+fn record_event(raw_event_id: u32, event: &TapEvent) {
+    TOTAL_EVENTS.fetch_add(1, Ordering::Relaxed);
+
+    *recover(EVENT_COUNTS.lock())
+        .entry(raw_event_id)
+        .or_insert(0) += 1;
+
+    // A modeled event that failed to parse had an unusable payload
+    if let TapEvent::ParseError { reason, .. } = event {
+        DROPPED_CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+        log::warn!(event_id = raw_event_id; "Failed to parse SDK event: {}", reason);
+    }
+    crate::crash::event_received(event);
+}
+
+/// Check whether an event ID has a dedicated `TapEvent` variant
+fn is_modeled(raw_event_id: u32) -> bool {
+    matches!(
+        raw_event_id,
+        event_id::SYSTEM_STATE_CHANGED
+            | event_id::AUTHORIZE_FINISHED
+            | event_id::GAME_PLAYABLE_STATUS_CHANGED
+            | event_id::DLC_PLAYABLE_STATUS_CHANGED
+            | event_id::CLOUD_SAVE_LIST
+            | event_id::CLOUD_SAVE_CREATE
+            | event_id::CLOUD_SAVE_UPDATE
+            | event_id::CLOUD_SAVE_DELETE
+            | event_id::CLOUD_SAVE_GET_DATA
+            | event_id::CLOUD_SAVE_GET_COVER
+    )
+}
+
+/// Largest number of saves accepted in a cloud save list response
+const MAX_SAVE_COUNT: usize = 1024;
+/// Largest file accepted in a cloud save data/cover response (64 MiB)
+///
+/// The service limits save data to 10MB and covers to 512KB; the extra
+/// headroom only guards against garbage sizes.
+const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+/// Longest C string read through a pointer field, in bytes
+const MAX_STRING_LEN: usize = 64 * 1024;
+
+/// Parse an event from raw SDK data
+///
+/// Counts, sizes and pointers provided by the SDK are validated before
+/// use; anything out of bounds yields [`TapEvent::ParseError`].
+pub(crate) unsafe fn parse_event(event_id: u32, data: *mut std::ffi::c_void) -> TapEvent {
+    if !is_modeled(event_id) {
+        return match custom_parser(event_id) {
+            Some(parser) => parser(data),
+            None => TapEvent::Unknown { event_id },
+        };
+    }
+    match try_parse_event(event_id, data) {
+        Ok(event) => event,
+        Err(reason) => TapEvent::ParseError { event_id, reason },
+    }
+}
+
+/// [`parse_event`] for the cargo-fuzz targets in `fuzz/`, not a stable API
+///
+/// # Safety
+/// `data` must be null or point to the response struct for `event_id`.
+/// Pointers in it must be valid for what the response claims, except where
+/// the parser is expected to reject the claim before reading.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub unsafe fn fuzz_parse_event(event_id: u32, data: *mut std::ffi::c_void) -> TapEvent {
+    parse_event(event_id, data)
+}
+
+/// Parse a modeled event, returning the reason on invalid data
+unsafe fn try_parse_event(event_id: u32, data: *mut std::ffi::c_void) -> Result<TapEvent, String> {
+    match event_id {
+        event_id::SYSTEM_STATE_CHANGED => {
+            let notification = deref(
+                data as *const tapsdk_pc_sys::TapSystemStateNotification,
+                "data",
+            )?;
+            Ok(TapEvent::SystemStateChanged(SystemStateChangedData {
+                state: SystemState::from(notification.state),
+            }))
+        }
+
+        event_id::AUTHORIZE_FINISHED => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::AuthorizeFinishedResponse,
+                "data",
+            )?;
+
+            let error = array_to_string(&response.error, "error")?;
+            let error = if error.is_empty() { None } else { Some(error) };
+
+            let token = if !response.is_cancel && error.is_none() {
+                Some(AuthToken {
+                    token_type: array_to_string(&response.token_type, "token_type")?,
+                    kid: array_to_string(&response.kid, "kid")?,
+                    mac_key: array_to_string(&response.mac_key, "mac_key")?,
+                    mac_algorithm: array_to_string(&response.mac_algorithm, "mac_algorithm")?,
+                    scope: array_to_string(&response.scope, "scope")?,
+                })
+            } else {
+                None
+            };
+
+            Ok(TapEvent::AuthorizeFinished(AuthorizeFinishedData {
+                is_cancel: response.is_cancel,
+                error,
+                token,
+            }))
+        }
+
+        event_id::GAME_PLAYABLE_STATUS_CHANGED => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::GamePlayableStatusChangedResponse,
+                "data",
+            )?;
+            Ok(TapEvent::GamePlayableStatusChanged(
+                GamePlayableStatusChangedData {
+                    is_playable: response.is_playable,
+                },
+            ))
+        }
+
+        event_id::DLC_PLAYABLE_STATUS_CHANGED => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::DLCPlayableStatusChangedResponse,
+                "data",
+            )?;
+            Ok(TapEvent::DlcPlayableStatusChanged(
+                DlcPlayableStatusChangedData {
+                    dlc_id: array_to_string(&response.dlc_id, "dlc_id")?,
+                    is_playable: response.is_playable,
+                },
+            ))
+        }
+
+        event_id::CLOUD_SAVE_LIST => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::TapCloudSaveListResponse,
+                "data",
+            )?;
+
+            let error = parse_sdk_error(response.error)?;
+
+            let count = usize::try_from(response.save_count)
+                .map_err(|_| format!("negative save_count {}", response.save_count))?;
+            if count > MAX_SAVE_COUNT {
+                return Err(format!(
+                    "save_count {} exceeds the maximum of {}",
+                    count, MAX_SAVE_COUNT
+                ));
+            }
+
+            let saves = if count == 0 {
+                Vec::new()
+            } else {
+                check_ptr(response.saves, "saves")?;
+                std::slice::from_raw_parts(response.saves, count)
+                    .iter()
+                    .map(|s| parse_cloud_save_info(s))
+                    .collect::<Result<_, _>>()?
+            };
+
+            Ok(TapEvent::CloudSaveList(CloudSaveListData {
+                request_id: response.request_id,
+                error,
+                saves,
+            }))
+        }
+
+        event_id::CLOUD_SAVE_CREATE | event_id::CLOUD_SAVE_UPDATE => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::TapCloudSaveCreateResponse,
+                "data",
+            )?;
+
+            let error = parse_sdk_error(response.error)?;
+
+            let save = if response.save.is_null() {
+                None
+            } else {
+                let save = deref(response.save, "save")?;
+                Some(parse_cloud_save_info(save)?)
+            };
+
+            let event_data = CloudSaveCreateData {
+                request_id: response.request_id,
+                error,
+                save,
+            };
+
+            if event_id == event_id::CLOUD_SAVE_CREATE {
+                Ok(TapEvent::CloudSaveCreate(event_data))
+            } else {
+                Ok(TapEvent::CloudSaveUpdate(event_data))
+            }
+        }
+
+        event_id::CLOUD_SAVE_DELETE => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::TapCloudSaveDeleteResponse,
+                "data",
+            )?;
+
+            let error = parse_sdk_error(response.error)?;
+            let uuid = SaveUuid::from_sdk(ptr_to_string(response.uuid, "uuid")?);
+
+            Ok(TapEvent::CloudSaveDelete(CloudSaveDeleteData {
+                request_id: response.request_id,
+                error,
+                uuid,
+            }))
+        }
+
+        event_id::CLOUD_SAVE_GET_DATA | event_id::CLOUD_SAVE_GET_COVER => {
+            let response = deref(
+                data as *const tapsdk_pc_sys::TapCloudSaveGetFileResponse,
+                "data",
+            )?;
+
+            let error = parse_sdk_error(response.error)?;
+
+            let size = response.size as usize;
+            if size > MAX_FILE_SIZE {
+                return Err(format!(
+                    "size {} exceeds the maximum of {}",
+                    size, MAX_FILE_SIZE
+                ));
+            }
+
+            let file_data = if size == 0 {
+                Vec::new()
+            } else {
+                check_ptr(response.data as *const u8, "data")?;
+                let source = std::slice::from_raw_parts(response.data as *const u8, size);
+                read_chunked(response.request_id, source)
+            };
+
+            let event_data = CloudSaveGetFileData {
+                request_id: response.request_id,
+                error,
+                data: file_data,
+            };
+
+            if event_id == event_id::CLOUD_SAVE_GET_DATA {
+                Ok(TapEvent::CloudSaveGetData(event_data))
+            } else {
+                Ok(TapEvent::CloudSaveGetCover(event_data))
+            }
+        }
+
+        _ => Ok(TapEvent::Unknown { event_id }),
+    }
+}
+
+/// Check that a required pointer is non-null and aligned for `T`
+fn check_ptr<T>(ptr: *const T, field: &str) -> Result<(), String> {
+    if ptr.is_null() {
+        Err(format!("{} is null", field))
+    } else if !ptr.is_aligned() {
+        Err(format!("{} is misaligned", field))
+    } else {
+        Ok(())
+    }
+}
+
+/// Dereference a required struct pointer after checking it
+unsafe fn deref<'a, T>(ptr: *const T, field: &str) -> Result<&'a T, String> {
+    check_ptr(ptr, field)?;
+    Ok(&*ptr)
+}
+
+/// Parse SDK error from raw pointer
+unsafe fn parse_sdk_error(
+    error: *const tapsdk_pc_sys::TapSDK_Error,
+) -> Result<Option<(i64, String)>, String> {
+    if error.is_null() {
+        return Ok(None);
+    }
+
+    let err = deref(error, "error")?;
+    let message = ptr_to_string(err.message, "error.message")?;
+
+    Ok(Some((err.code, message)))
+}
+
+/// Parse cloud save info from raw struct
+unsafe fn parse_cloud_save_info(
+    info: &tapsdk_pc_sys::TapCloudSaveInfo,
+) -> Result<CloudSaveInfo, String> {
+    Ok(CloudSaveInfo {
+        uuid: SaveUuid::from_sdk(ptr_to_string(info.uuid, "uuid")?),
+        file_id: FileId::from_sdk(ptr_to_string(info.file_id, "file_id")?),
+        name: ptr_to_string(info.name, "name")?,
+        save_size: info.save_size.into(),
+        cover_size: info.cover_size.into(),
+        summary: ptr_to_optional_string(info.summary, "summary")?,
+        extra: ptr_to_optional_string(info.extra, "extra")?,
+        // The SDK reports seconds, timestamps relative to the Unix epoch
+        playtime: Duration::from_secs(info.playtime.into()),
+        created_time: UNIX_EPOCH + Duration::from_secs(info.created_time.into()),
+        modified_time: UNIX_EPOCH + Duration::from_secs(info.modified_time.into()),
+    })
+}
+
+/// Convert a fixed-size C string buffer to a Rust String
+///
+/// Fails if the buffer is not NUL-terminated.
+fn array_to_string(array: &[std::os::raw::c_char], field: &str) -> Result<String, String> {
+    let bytes: Vec<u8> = array.iter().map(|&c| c as u8).collect();
+    CStr::from_bytes_until_nul(&bytes)
+        .map(|s| s.to_string_lossy().into_owned())
+        .map_err(|_| format!("{} is not NUL-terminated", field))
+}
+
+/// Convert a C string pointer to a Rust String
+///
+/// Reads at most [`MAX_STRING_LEN`] bytes looking for the terminator.
+unsafe fn ptr_to_string(ptr: *const std::os::raw::c_char, field: &str) -> Result<String, String> {
+    if ptr.is_null() {
+        return Ok(String::new());
+    }
+    let mut len = 0;
+    while *ptr.add(len) != 0 {
+        len += 1;
+        if len > MAX_STRING_LEN {
+            return Err(format!("{} is longer than {} bytes", field, MAX_STRING_LEN));
+        }
+    }
+    let bytes = std::slice::from_raw_parts(ptr as *const u8, len);
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Convert a C string pointer to an optional Rust String
+unsafe fn ptr_to_optional_string(
+    ptr: *const std::os::raw::c_char,
+    field: &str,
+) -> Result<Option<String>, String> {
+    let s = ptr_to_string(ptr, field)?;
+    Ok(if s.is_empty() { None } else { Some(s) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_playable(is_playable: bool) -> TapEvent {
+        TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData { is_playable })
+    }
+
+    fn bounded(capacity: usize, overflow: OverflowPolicy) -> EventQueue {
+        EventQueue::with_config(QueueConfig {
+            capacity: Some(capacity),
+            overflow,
+        })
+    }
+
+    fn ids(events: &[Envelope]) -> Vec<u32> {
+        events
+            .iter()
+            .map(|envelope| envelope.event.event_id())
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = bounded(2, OverflowPolicy::DropOldest);
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 1 }.into()), 0);
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 2 }.into()), 0);
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 3 }.into()), 1);
+
+        assert_eq!(ids(&queue.drain()), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let queue = bounded(2, OverflowPolicy::DropNewest);
+        queue.push(TapEvent::Unknown { event_id: 1 }.into());
+        queue.push(TapEvent::Unknown { event_id: 2 }.into());
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 3 }.into()), 1);
+
+        assert_eq!(ids(&queue.drain()), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_latest_status_event() {
+        let queue = bounded(1, OverflowPolicy::Coalesce);
+        queue.push(TapEvent::Unknown { event_id: 7 }.into());
+        assert_eq!(queue.push(game_playable(false).into()), 0);
+        assert_eq!(queue.push(game_playable(true).into()), 1);
+
+        let events = queue.drain();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event.event_id(), 7);
+        assert!(matches!(
+            events[1].event,
+            TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData {
+                is_playable: true
+            })
+        ));
+    }
+
+    #[test]
+    fn test_coalesce_keeps_arrival_order() {
+        let queue = bounded(1, OverflowPolicy::Coalesce);
+        queue.push(TapEvent::Unknown { event_id: 1 }.into());
+        assert_eq!(queue.push(game_playable(true).into()), 0);
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 2 }.into()), 1);
+
+        // The parked status event arrived before event 2
+        assert_eq!(ids(&queue.drain()), vec![4001, 2]);
+    }
+
+    #[test]
+    fn test_coalesce_falls_back_to_drop_oldest() {
+        let queue = bounded(1, OverflowPolicy::Coalesce);
+        queue.push(TapEvent::Unknown { event_id: 1 }.into());
+        assert_eq!(queue.push(TapEvent::Unknown { event_id: 2 }.into()), 1);
+        assert_eq!(ids(&queue.drain()), vec![2]);
+    }
+
+    #[test]
+    fn test_sdk_lock_is_reentrant() {
+        assert_eq!(with_sdk_lock(|| with_sdk_lock(|| 7)), 7);
+        // Released again, so another thread can take it
+        assert!(std::thread::spawn(|| with_sdk_lock(|| true))
+            .join()
+            .unwrap());
+        assert!(!HOLDS_SDK_LOCK.get());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("bad payload")).unwrap_err();
+        assert_eq!(
+            panic_message(payload.as_ref()),
+            "panic while handling SDK callback: bad payload"
+        );
+    }
+
+    #[test]
+    fn test_recover_poisoned_lock() {
+        let mutex = Mutex::new(vec![1]);
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _guard = mutex.lock().unwrap();
+            panic!("poison");
+        }));
+        assert!(mutex.is_poisoned());
+
+        recover(mutex.lock()).push(2);
+        assert_eq!(*recover(mutex.lock()), vec![1, 2]);
+
+        assert!(matches!(
+            take_poison_warning(),
+            Some(TapEvent::InternalError(InternalErrorData {
+                event_id: 0,
+                ..
+            }))
+        ));
+        assert!(take_poison_warning().is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_null_data() {
+        let event = unsafe { parse_event(event_id::CLOUD_SAVE_LIST, std::ptr::null_mut()) };
+        assert!(matches!(
+            event,
+            TapEvent::ParseError {
+                event_id: event_id::CLOUD_SAVE_LIST,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_save_count() {
+        for save_count in [-1, MAX_SAVE_COUNT as i32 + 1] {
+            let mut response = tapsdk_pc_sys::TapCloudSaveListResponse {
+                request_id: 1,
+                error: std::ptr::null(),
+                saves: std::ptr::null(),
+                save_count,
+            };
+            let data = &mut response as *mut _ as *mut std::ffi::c_void;
+            let event = unsafe { parse_event(event_id::CLOUD_SAVE_LIST, data) };
+            assert!(matches!(event, TapEvent::ParseError { .. }));
+        }
+    }
+
+    #[test]
+    fn test_parse_accepts_empty_save_list() {
+        let mut response = tapsdk_pc_sys::TapCloudSaveListResponse {
+            request_id: 7,
+            error: std::ptr::null(),
+            saves: std::ptr::null(),
+            save_count: 0,
+        };
+        let data = &mut response as *mut _ as *mut std::ffi::c_void;
+        let event = unsafe { parse_event(event_id::CLOUD_SAVE_LIST, data) };
+        match event {
+            TapEvent::CloudSaveList(data) => {
+                assert_eq!(data.request_id, 7);
+                assert!(data.error.is_none());
+                assert!(data.saves.is_empty());
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_oversized_file() {
+        let bytes = [0u8; 4];
+        let mut response = tapsdk_pc_sys::TapCloudSaveGetFileResponse {
+            request_id: 1,
+            error: std::ptr::null(),
+            data: bytes.as_ptr() as *const std::ffi::c_void,
+            size: MAX_FILE_SIZE as u32 + 1,
+        };
+        let data = &mut response as *mut _ as *mut std::ffi::c_void;
+        let event = unsafe { parse_event(event_id::CLOUD_SAVE_GET_DATA, data) };
+        assert!(matches!(event, TapEvent::ParseError { .. }));
+    }
+
+    #[test]
+    fn test_parse_rejects_unterminated_string() {
+        let mut response = tapsdk_pc_sys::DLCPlayableStatusChangedResponse {
+            dlc_id: [b'a' as std::os::raw::c_char; 256],
+            is_playable: true,
+        };
+        let data = &mut response as *mut _ as *mut std::ffi::c_void;
+        let event = unsafe { parse_event(event_id::DLC_PLAYABLE_STATUS_CHANGED, data) };
+        assert!(matches!(event, TapEvent::ParseError { .. }));
+
+        response.dlc_id[3] = 0;
+        let data = &mut response as *mut _ as *mut std::ffi::c_void;
+        let event = unsafe { parse_event(event_id::DLC_PLAYABLE_STATUS_CHANGED, data) };
+        match event {
+            TapEvent::DlcPlayableStatusChanged(data) => assert_eq!(data.dlc_id, "aaa"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_custom_parser() {
+        unsafe fn parse_number(data: *mut std::ffi::c_void) -> TapEvent {
+            TapEvent::Custom(CustomEventData::new(8001, *(data as *const u32)))
+        }
+
+        let mut value = 42u32;
+        let data = &mut value as *mut u32 as *mut std::ffi::c_void;
+        assert!(matches!(
+            unsafe { parse_event(8001, data) },
+            TapEvent::Unknown { event_id: 8001 }
+        ));
+
+        register_parser(8001, parse_number).unwrap();
+        match unsafe { parse_event(8001, data) } {
+            TapEvent::Custom(event) => {
+                assert_eq!(event.event_id, 8001);
+                assert_eq!(event.downcast_ref::<u32>(), Some(&42));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(register_parser(event_id::CLOUD_SAVE_LIST, parse_number).is_err());
+        assert!(register_parser(event_id::INTERNAL_ERROR, parse_number).is_err());
+    }
+
+    #[test]
+    fn test_raw_callback_requires_init() {
+        unsafe extern "C" fn raw(_event_id: u32, _data: *mut std::ffi::c_void) {}
+
+        assert!(matches!(
+            unsafe { register_raw(8002, raw) },
+            Err(TapSdkError::NotInitialized)
+        ));
+        assert!(matches!(
+            unregister_raw(8002, raw),
+            Err(TapSdkError::NotInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_envelopes_keep_receipt_order() {
+        let queue = bounded(8, OverflowPolicy::DropOldest);
+        for id in 1..=3 {
+            queue.push(TapEvent::Unknown { event_id: id }.into());
+        }
+
+        let events = queue.drain();
+        assert_eq!(ids(&events), vec![1, 2, 3]);
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].received_at <= pair[1].received_at));
+    }
+
+    #[test]
+    fn test_late_response_is_dropped() {
+        // The registry is global, so use an ID no real request gets
+        const ID: i64 = -4660;
+        track_request(ID, Operation::CloudSaveList);
+        std::thread::sleep(Duration::from_millis(2));
+        let now = Instant::now();
+        track_request(ID, Operation::CloudSaveDelete);
+
+        let timed_out: Vec<_> = time_out_requests(Duration::ZERO, now)
+            .into_iter()
+            .filter(|(request_id, _)| *request_id == ID)
+            .collect();
+        assert_eq!(timed_out, vec![(ID, Operation::CloudSaveList)]);
+        assert_eq!(pending_requests().iter().filter(|&&id| id == ID).count(), 1);
+
+        // The late response to the first request must not resolve the second
+        assert!(!resolve_request(ID));
+        assert!(pending_requests().contains(&ID));
+        assert!(resolve_request(ID));
+        assert!(!pending_requests().contains(&ID));
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        // The registry is global, so use an ID no real request gets
+        const ID: i64 = -4607;
+        assert!(!cancel_request(ID));
+        track_request(ID, Operation::CloudSaveList);
+        track_request(ID, Operation::CloudSaveList);
+        assert!(cancel_request(ID));
+        assert_eq!(pending_requests().iter().filter(|&&id| id == ID).count(), 1);
+
+        // The response to the cancelled request is dropped
+        assert!(!resolve_request(ID));
+        assert!(resolve_request(ID));
+        assert!(!cancel_request(ID));
+    }
+
+    #[test]
+    fn test_callback_set_event_ids() {
+        assert_eq!(CallbackSet::NONE.event_ids(), Vec::<u32>::new());
+        assert_eq!(CallbackSet::default(), CallbackSet::ALL);
+        assert_eq!(CallbackSet::ALL.event_ids().len(), 10);
+        assert_eq!(
+            (CallbackSet::SYSTEM | CallbackSet::CLOUD_SAVE).event_ids(),
+            vec![1, 6001, 6002, 6003, 6004, 6005, 6006]
+        );
+        assert!(CallbackSet::ALL.contains(CallbackSet::USER | CallbackSet::OWNERSHIP));
+        assert!(!CallbackSet::CLOUD_SAVE.contains(CallbackSet::SYSTEM));
+    }
+
+    #[test]
+    fn test_filter_envelopes() {
+        let events = (1..=4)
+            .map(|id| TapEvent::Unknown { event_id: id }.into())
+            .collect();
+        assert_eq!(ids(&filter_envelopes(events, &[2, 4, 5])), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_coalesce_status_events() {
+        let dlc = |dlc_id: &str, is_playable| {
+            TapEvent::DlcPlayableStatusChanged(DlcPlayableStatusChangedData {
+                dlc_id: dlc_id.to_string(),
+                is_playable,
+            })
+        };
+        let events = vec![
+            game_playable(false).into(),
+            dlc("a", false).into(),
+            TapEvent::Unknown { event_id: 7 }.into(),
+            dlc("b", true).into(),
+            game_playable(true).into(),
+            dlc("a", true).into(),
+            TapEvent::Unknown { event_id: 7 }.into(),
+        ];
+        let kept = coalesce_status_events(events);
+        assert_eq!(ids(&kept), vec![7, 4002, 4001, 4002, 7]);
+        assert!(matches!(
+            &kept[1].event,
+            TapEvent::DlcPlayableStatusChanged(data) if data.dlc_id == "b"
+        ));
+        assert!(matches!(
+            &kept[2].event,
+            TapEvent::GamePlayableStatusChanged(data) if data.is_playable
+        ));
+        assert!(matches!(
+            &kept[3].event,
+            TapEvent::DlcPlayableStatusChanged(data) if data.dlc_id == "a" && data.is_playable
+        ));
+    }
+
+    #[test]
+    fn test_history_keeps_latest_events() {
+        assert!(recent_events().is_empty());
+
+        set_history_capacity(2);
+        for id in 1..=3 {
+            record_history(&TapEvent::Unknown { event_id: id }.into());
+        }
+        assert_eq!(ids(&recent_events()), vec![2, 3]);
+
+        set_history_capacity(0);
+        assert!(recent_events().is_empty());
+    }
+
+    #[test]
+    fn test_platform_shutdown_handler() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        set_platform_shutdown_handler(Some(Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        let state = |state| TapEvent::SystemStateChanged(SystemStateChangedData { state });
+        run_platform_shutdown_handler(&state(SystemState::PlatformOffline));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        run_platform_shutdown_handler(&state(SystemState::PlatformShutdown));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        set_platform_shutdown_handler(None);
+        run_platform_shutdown_handler(&state(SystemState::PlatformShutdown));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cover_content_type() {
+        let file = |data: &[u8]| CloudSaveGetFileData {
+            request_id: 1,
+            error: None,
+            data: data.to_vec(),
+        };
+        assert_eq!(
+            file(b"\x89PNG\r\n\x1a\n\0\0").content_type(),
+            Some("image/png")
+        );
+        assert_eq!(file(b"\xff\xd8\xff\xe0").content_type(), Some("image/jpeg"));
+        assert_eq!(
+            file(b"RIFF\x10\0\0\0WEBPVP8 ").content_type(),
+            Some("image/webp")
+        );
+        assert_eq!(file(b"RIFF\x10\0\0\0WAVE").content_type(), None);
+        assert_eq!(file(b"").content_type(), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_accessors() {
+        let info = CloudSaveInfo {
+            uuid: SaveUuid::new("u").unwrap(),
+            file_id: FileId::new("f").unwrap(),
+            name: "slot".to_string(),
+            save_size: 0,
+            cover_size: 0,
+            summary: None,
+            extra: None,
+            playtime: Duration::from_secs(90),
+            created_time: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            modified_time: SystemTime::now() - Duration::from_secs(60),
+        };
+        assert_eq!(info.created_at().timestamp(), 1_700_000_000);
+        assert_eq!(info.playtime_delta().num_seconds(), 90);
+        assert!(info.age().num_seconds() >= 60);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let event = TapEvent::CloudSaveDelete(CloudSaveDeleteData {
+            request_id: 7,
+            error: Some((400, "bad request".to_string())),
+            uuid: SaveUuid::new("u").unwrap(),
+        });
+        let json = serde_json::to_string(&event).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            TapEvent::CloudSaveDelete(data) => {
+                assert_eq!(data.request_id, 7);
+                assert_eq!(data.error, Some((400, "bad request".to_string())));
+                assert_eq!(data.uuid, "u");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_subscribe_delivers_to_every_receiver() {
+        const ID: u32 = 0xB0AD;
+        let mut first = subscribe();
+        let mut second = subscribe();
+        dispatch(ID, TapEvent::Unknown { event_id: ID });
+
+        for receiver in [&mut first, &mut second] {
+            let received =
+                std::iter::from_fn(|| receiver.try_recv().ok()).any(|event| event.event_id() == ID);
+            assert!(received);
+        }
+    }
+
+    #[test]
+    fn test_unbounded_never_drops() {
+        let queue = EventQueue::new();
+        queue.set_config(QueueConfig {
+            capacity: None,
+            overflow: OverflowPolicy::DropNewest,
+        });
+        for id in 0..10_000 {
+            assert_eq!(queue.push(TapEvent::Unknown { event_id: id }.into()), 0);
         }
+        assert_eq!(queue.drain().len(), 10_000);
+        assert_eq!(queue.len(), 0);
     }
 }
@@ -1,17 +1,38 @@
 //! Cloud save functionality
 
+use std::borrow::Cow;
 use std::ffi::CString;
+use std::fmt;
+use std::fs;
 use std::path::Path;
+#[cfg(windows)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::error::{CloudSaveResult, Result, TapSdkError};
-use crate::sdk::is_initialized;
+use crate::callback::{
+    self, recover, CloudSaveInfo, CloudSaveProgressData, TapEvent, TransferStage,
+};
+use crate::error::SystemState;
+use crate::error::{c_string, check_text, CloudSaveResult, Result, TapSdkError};
+use crate::ffi;
+use crate::observer::{self, Operation};
+use crate::playtime::PlaytimeTracker;
+use crate::sdk::{generation, is_initialized, system_state};
+use crate::staging;
 
 /// Cloud save API handle
 ///
 /// This struct provides access to cloud save functionality.
-/// Get an instance via `CloudSave::get()`.
+/// Get an instance via `CloudSave::get()`. A handle only stays valid until
+/// the SDK is shut down; after that its methods fail with `NotInitialized`,
+/// even if the SDK has been initialized again.
 pub struct CloudSave {
     handle: *mut tapsdk_pc_sys::ITapCloudSave,
+    /// SDK session the handle was obtained in
+    generation: u64,
 }
 
 // The ITapCloudSave pointer is thread-safe according to the SDK documentation
@@ -28,12 +49,15 @@ impl CloudSave {
             return None;
         }
 
-        let handle = unsafe { tapsdk_pc_sys::TapCloudSave() };
+        let handle = unsafe { ffi::TapCloudSave() };
 
         if handle.is_null() {
             None
         } else {
-            Some(CloudSave { handle })
+            Some(CloudSave {
+                handle,
+                generation: generation(),
+            })
         }
     }
 
@@ -45,32 +69,97 @@ impl CloudSave {
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     pub fn list(&self, request_id: i64) -> Result<()> {
-        let result = unsafe { tapsdk_pc_sys::TapCloudSave_AsyncList(self.handle, request_id) };
+        self.check()?;
+        let result = unsafe { ffi::TapCloudSave_AsyncList(self.handle, request_id) };
 
-        check_cloudsave_result(result)
+        check_cloudsave_result(Operation::CloudSaveList, request_id, None, result)?;
+        recover(LIST_CACHE.lock()).list_started(request_id);
+        Ok(())
+    }
+
+    /// Get the saves from a recent list response without asking the SDK
+    ///
+    /// Every successful `CloudSaveList` response is cached, and the cache
+    /// is cleared by every successful create, update or delete. If the
+    /// cached list is older than `ttl`, it is still returned, and a list
+    /// request is sent to refresh it; its response is delivered like any
+    /// other `CloudSaveList` event. Refresh requests take request IDs
+    /// counting up from `i64::MIN`.
+    ///
+    /// # Returns
+    /// The cached saves, or `None` if there are none yet and a refresh
+    /// was started
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tapsdk_pc::CloudSave;
+    ///
+    /// let cloud_save = CloudSave::get().expect("SDK not initialized");
+    /// match cloud_save.cached_list(Duration::from_secs(30))? {
+    ///     Some(saves) => println!("{} saves", saves.len()),
+    ///     None => println!("loading..."),
+    /// }
+    /// # Ok::<(), tapsdk_pc::TapSdkError>(())
+    /// ```
+    pub fn cached_list(&self, ttl: Duration) -> Result<Option<Vec<CloudSaveInfo>>> {
+        self.check()?;
+        let (saves, request_id) = {
+            let mut cache = recover(LIST_CACHE.lock());
+            let (saves, fresh) = match &cache.saves {
+                Some((received, saves)) => (Some(saves.clone()), received.elapsed() < ttl),
+                None => (None, false),
+            };
+            if fresh || cache.refresh.is_some() {
+                return Ok(saves);
+            }
+            let request_id = next_request_id();
+            cache.refresh = Some(request_id);
+            (saves, request_id)
+        };
+
+        if let Err(e) = self.list(request_id) {
+            let mut cache = recover(LIST_CACHE.lock());
+            if cache.refresh == Some(request_id) {
+                cache.refresh = None;
+            }
+            return Err(e);
+        }
+        Ok(saves)
     }
 
     /// Create a new cloud save
     ///
     /// The result will be delivered via the `CloudSaveCreate` event
-    /// when calling `TapSdk::run_callbacks()`.
+    /// when calling `TapSdk::run_callbacks()`, preceded by
+    /// `CloudSaveProgress` events.
     ///
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     /// * `request` - The create request parameters
     pub fn create(&self, request_id: i64, request: &CreateSaveRequest) -> Result<()> {
-        let name_c = CString::new(request.name.as_str())?;
-        let summary_c = CString::new(request.summary.as_str())?;
+        let result = self.start_create(request_id, request);
+        if result.is_err() {
+            discard_staged(&request.data_file_path, request.cover_file_path.as_deref());
+        }
+        result
+    }
+
+    fn start_create(&self, request_id: i64, request: &CreateSaveRequest) -> Result<()> {
+        self.check()?;
+        request.validate()?;
+        let name_c = c_string("name", &request.name)?;
+        let summary_c = c_string("summary", &request.summary)?;
         let extra_c = request
             .extra
             .as_ref()
-            .map(|s| CString::new(s.as_str()))
+            .map(|s| c_string("extra", s))
             .transpose()?;
-        let data_path_c = CString::new(request.data_file_path.to_string_lossy().as_ref())?;
+        let data_path_c = path_to_cstring("data_file_path", &request.data_file_path)?;
         let cover_path_c = request
             .cover_file_path
             .as_ref()
-            .map(|p| CString::new(p.to_string_lossy().as_ref()))
+            .map(|p| path_to_cstring("cover_file_path", p))
             .transpose()?;
 
         let raw_request = tapsdk_pc_sys::TapCloudSaveCreateRequest {
@@ -80,7 +169,7 @@ impl CloudSave {
                 .as_ref()
                 .map(|s| s.as_ptr())
                 .unwrap_or(std::ptr::null()),
-            playtime: request.playtime,
+            playtime: sdk_playtime(request.playtime)?,
             data_file_path: data_path_c.as_ptr(),
             cover_file_path: cover_path_c
                 .as_ref()
@@ -89,35 +178,57 @@ impl CloudSave {
             __bindgen_padding_0: Default::default(),
         };
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapCloudSave_AsyncCreate(self.handle, request_id, &raw_request)
-        };
+        let total = upload_size(&request.data_file_path, request.cover_file_path.as_deref());
+        let result =
+            unsafe { ffi::TapCloudSave_AsyncCreate(self.handle, request_id, &raw_request) };
 
-        check_cloudsave_result(result)
+        check_cloudsave_result(
+            Operation::CloudSaveCreate,
+            request_id,
+            Some(&request.name),
+            result,
+        )?;
+        // Only accepted requests report progress, so every transfer that
+        // starts also reaches `Done`
+        transfer_started(request_id, total);
+        progress(request_id, TransferStage::Reading, 0, total);
+        progress(request_id, TransferStage::Uploading, 0, total);
+        Ok(())
     }
 
     /// Update an existing cloud save
     ///
     /// The result will be delivered via the `CloudSaveUpdate` event
-    /// when calling `TapSdk::run_callbacks()`.
+    /// when calling `TapSdk::run_callbacks()`, preceded by
+    /// `CloudSaveProgress` events.
     ///
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     /// * `request` - The update request parameters
     pub fn update(&self, request_id: i64, request: &UpdateSaveRequest) -> Result<()> {
-        let uuid_c = CString::new(request.uuid.as_str())?;
-        let name_c = CString::new(request.name.as_str())?;
-        let summary_c = CString::new(request.summary.as_str())?;
+        let result = self.start_update(request_id, request);
+        if result.is_err() {
+            discard_staged(&request.data_file_path, request.cover_file_path.as_deref());
+        }
+        result
+    }
+
+    fn start_update(&self, request_id: i64, request: &UpdateSaveRequest) -> Result<()> {
+        self.check()?;
+        request.validate()?;
+        let uuid_c = request.uuid.to_c_string();
+        let name_c = c_string("name", &request.name)?;
+        let summary_c = c_string("summary", &request.summary)?;
         let extra_c = request
             .extra
             .as_ref()
-            .map(|s| CString::new(s.as_str()))
+            .map(|s| c_string("extra", s))
             .transpose()?;
-        let data_path_c = CString::new(request.data_file_path.to_string_lossy().as_ref())?;
+        let data_path_c = path_to_cstring("data_file_path", &request.data_file_path)?;
         let cover_path_c = request
             .cover_file_path
             .as_ref()
-            .map(|p| CString::new(p.to_string_lossy().as_ref()))
+            .map(|p| path_to_cstring("cover_file_path", p))
             .transpose()?;
 
         let raw_request = tapsdk_pc_sys::TapCloudSaveUpdateRequest {
@@ -128,7 +239,7 @@ impl CloudSave {
                 .as_ref()
                 .map(|s| s.as_ptr())
                 .unwrap_or(std::ptr::null()),
-            playtime: request.playtime,
+            playtime: sdk_playtime(request.playtime)?,
             data_file_path: data_path_c.as_ptr(),
             cover_file_path: cover_path_c
                 .as_ref()
@@ -137,11 +248,20 @@ impl CloudSave {
             __bindgen_padding_0: Default::default(),
         };
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapCloudSave_AsyncUpdate(self.handle, request_id, &raw_request)
-        };
+        let total = upload_size(&request.data_file_path, request.cover_file_path.as_deref());
+        let result =
+            unsafe { ffi::TapCloudSave_AsyncUpdate(self.handle, request_id, &raw_request) };
 
-        check_cloudsave_result(result)
+        check_cloudsave_result(
+            Operation::CloudSaveUpdate,
+            request_id,
+            Some(&request.name),
+            result,
+        )?;
+        transfer_started(request_id, total);
+        progress(request_id, TransferStage::Reading, 0, total);
+        progress(request_id, TransferStage::Uploading, 0, total);
+        Ok(())
     }
 
     /// Delete a cloud save
@@ -152,109 +272,860 @@ impl CloudSave {
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     /// * `uuid` - The unique ID of the cloud save to delete
-    pub fn delete(&self, request_id: i64, uuid: &str) -> Result<()> {
-        let uuid_c = CString::new(uuid)?;
+    pub fn delete(&self, request_id: i64, uuid: &SaveUuid) -> Result<()> {
+        self.check()?;
+        let uuid_c = uuid.to_c_string();
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapCloudSave_AsyncDelete(self.handle, request_id, uuid_c.as_ptr())
-        };
+        let result =
+            unsafe { ffi::TapCloudSave_AsyncDelete(self.handle, request_id, uuid_c.as_ptr()) };
 
-        check_cloudsave_result(result)
+        check_cloudsave_result(Operation::CloudSaveDelete, request_id, None, result)
     }
 
     /// Get the data file for a cloud save
     ///
     /// The result will be delivered via the `CloudSaveGetData` event
-    /// when calling `TapSdk::run_callbacks()`.
+    /// when calling `TapSdk::run_callbacks()`, preceded by
+    /// `CloudSaveProgress` events.
     ///
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     /// * `uuid` - The unique ID of the cloud save
     /// * `file_id` - The file ID of the cloud save (from CloudSaveInfo)
-    pub fn get_data(&self, request_id: i64, uuid: &str, file_id: &str) -> Result<()> {
-        let uuid_c = CString::new(uuid)?;
-        let file_id_c = CString::new(file_id)?;
+    pub fn get_data(&self, request_id: i64, uuid: &SaveUuid, file_id: &FileId) -> Result<()> {
+        self.check()?;
+        let uuid_c = uuid.to_c_string();
+        let file_id_c = file_id.to_c_string();
 
         let raw_request = tapsdk_pc_sys::TapCloudSaveGetFileRequest {
             uuid: uuid_c.as_ptr(),
             file_id: file_id_c.as_ptr(),
         };
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapCloudSave_AsyncGetData(self.handle, request_id, &raw_request)
-        };
+        let result =
+            unsafe { ffi::TapCloudSave_AsyncGetData(self.handle, request_id, &raw_request) };
 
-        check_cloudsave_result(result)
+        check_cloudsave_result(Operation::CloudSaveGetData, request_id, None, result)?;
+        transfer_started(request_id, 0);
+        progress(request_id, TransferStage::WaitingForServer, 0, 0);
+        Ok(())
+    }
+
+    /// Get the data file for a cloud save, unless the local copy is current
+    ///
+    /// Looks the save up in the last list response (see
+    /// [`cached_list`](Self::cached_list)) and skips the download if the
+    /// cloud copy has the same `file_id` and was not modified after
+    /// `local_modified_time`. Without list metadata for the save, the data
+    /// is always downloaded.
+    ///
+    /// # Arguments
+    /// * `request_id` - A unique ID to identify this request in the callback
+    /// * `uuid` - The unique ID of the cloud save
+    /// * `file_id` - The file ID of the local copy (from CloudSaveInfo)
+    /// * `local_modified_time` - `modified_time` of the local copy
+    ///
+    /// # Returns
+    /// `Download::Started` if the data is downloaded as with
+    /// [`get_data`](Self::get_data), `Download::NotModified` if no request
+    /// was sent
+    pub fn get_data_if_newer(
+        &self,
+        request_id: i64,
+        uuid: &SaveUuid,
+        file_id: &FileId,
+        local_modified_time: SystemTime,
+    ) -> Result<Download> {
+        self.check()?;
+        if !recover(LIST_CACHE.lock()).is_newer(uuid, file_id, local_modified_time) {
+            return Ok(Download::NotModified);
+        }
+        self.get_data(request_id, uuid, file_id)?;
+        Ok(Download::Started)
     }
 
     /// Get the cover image for a cloud save
     ///
     /// The result will be delivered via the `CloudSaveGetCover` event
-    /// when calling `TapSdk::run_callbacks()`.
+    /// when calling `TapSdk::run_callbacks()`, preceded by
+    /// `CloudSaveProgress` events.
     ///
     /// # Arguments
     /// * `request_id` - A unique ID to identify this request in the callback
     /// * `uuid` - The unique ID of the cloud save
     /// * `file_id` - The file ID of the cloud save (from CloudSaveInfo)
-    pub fn get_cover(&self, request_id: i64, uuid: &str, file_id: &str) -> Result<()> {
-        let uuid_c = CString::new(uuid)?;
-        let file_id_c = CString::new(file_id)?;
+    pub fn get_cover(&self, request_id: i64, uuid: &SaveUuid, file_id: &FileId) -> Result<()> {
+        self.check()?;
+        let uuid_c = uuid.to_c_string();
+        let file_id_c = file_id.to_c_string();
 
         let raw_request = tapsdk_pc_sys::TapCloudSaveGetFileRequest {
             uuid: uuid_c.as_ptr(),
             file_id: file_id_c.as_ptr(),
         };
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapCloudSave_AsyncGetCover(self.handle, request_id, &raw_request)
-        };
+        let result =
+            unsafe { ffi::TapCloudSave_AsyncGetCover(self.handle, request_id, &raw_request) };
+
+        check_cloudsave_result(Operation::CloudSaveGetCover, request_id, None, result)?;
+        transfer_started(request_id, 0);
+        progress(request_id, TransferStage::WaitingForServer, 0, 0);
+        Ok(())
+    }
 
-        check_cloudsave_result(result)
+    /// Fail with `NotInitialized` once the session this handle belongs to has
+    /// ended, or with `PlatformOffline` while the client reports it is offline
+    ///
+    /// Requests sent while offline would only time out.
+    fn check(&self) -> Result<()> {
+        if !is_initialized() || generation() != self.generation {
+            return Err(TapSdkError::NotInitialized);
+        }
+        if system_state() == SystemState::PlatformOffline {
+            return Err(TapSdkError::PlatformOffline);
+        }
+        Ok(())
     }
 }
 
+/// Outcome of [`CloudSave::get_data_if_newer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Download {
+    /// The data is downloaded; the result is delivered via the
+    /// `CloudSaveGetData` event
+    Started,
+    /// The local copy is current, so no request was sent
+    NotModified,
+}
+
+macro_rules! string_id {
+    ($(#[$doc:meta])* $name:ident, $what:literal, $field:literal) => {
+        $(#[$doc])*
+        ///
+        /// Any string without NUL bytes is valid, as the SDK takes it as a C
+        /// string. Compare it with a plain string with `==`, or get one with
+        /// [`as_str`](Self::as_str).
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+        pub struct $name(String);
+
+        impl $name {
+            #[doc = concat!("Wrap a ", $what, ", failing with `InvalidField` if it contains a NUL byte or a control character")]
+            pub fn new(id: impl Into<String>) -> Result<Self> {
+                let id = id.into();
+                check_text($field, &id, false)?;
+                Ok($name(id))
+            }
+
+            /// Wrap a string read from the SDK, which can't contain NUL bytes
+            pub(crate) fn from_sdk(id: String) -> Self {
+                $name(id)
+            }
+
+            /// The ID as a string slice
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// The ID as a `String`
+            pub fn into_string(self) -> String {
+                self.0
+            }
+
+            fn to_c_string(&self) -> CString {
+                CString::new(self.0.as_str()).expect(concat!($what, " without NUL bytes"))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = TapSdkError;
+
+            fn from_str(id: &str) -> Result<Self> {
+                $name::new(id)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = TapSdkError;
+
+            fn try_from(id: String) -> Result<Self> {
+                $name::new(id)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = TapSdkError;
+
+            fn try_from(id: &str) -> Result<Self> {
+                $name::new(id)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> String {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+string_id!(
+    /// Unique ID of a cloud save, `uuid` in [`CloudSaveInfo`]
+    SaveUuid,
+    "save UUID",
+    "uuid"
+);
+
+string_id!(
+    /// ID of the current files of a cloud save, `file_id` in [`CloudSaveInfo`]
+    ///
+    /// It changes whenever the save is updated.
+    FileId,
+    "file ID",
+    "file_id"
+);
+
 /// Request parameters for creating a cloud save
 #[derive(Debug, Clone)]
 pub struct CreateSaveRequest {
     /// Save name (max 60 bytes, no Chinese characters)
     pub name: String,
-    /// Save description (max 500 bytes)
+    /// Save description (max 500 bytes, not empty)
     pub summary: String,
     /// Developer-defined extra data (max 1000 bytes, optional)
     pub extra: Option<String>,
-    /// Game playtime in seconds
-    pub playtime: u32,
+    /// Game playtime in seconds (at most `u32::MAX`, the SDK's limit)
+    pub playtime: u64,
     /// Path to the save data file (max 10MB)
     pub data_file_path: Box<Path>,
     /// Path to the cover image file (max 512KB, optional)
     pub cover_file_path: Option<Box<Path>>,
 }
 
+impl CreateSaveRequest {
+    /// Set `playtime` from a tracker
+    pub fn with_playtime(mut self, tracker: &PlaytimeTracker) -> Self {
+        self.playtime = tracker.seconds();
+        self
+    }
+
+    /// Check the text fields without sending the request
+    ///
+    /// Fails with `InvalidField` naming the first field that contains a NUL
+    /// byte or a control character, or for an empty `summary`. Only
+    /// `summary` and `extra` may contain tabs and line breaks. [`CloudSave::create`] runs this before
+    /// anything is handed to the SDK.
+    pub fn validate(&self) -> Result<()> {
+        check_save_text(
+            &self.name,
+            &self.summary,
+            self.extra.as_deref(),
+            &self.data_file_path,
+            self.cover_file_path.as_deref(),
+        )
+    }
+}
+
 /// Request parameters for updating a cloud save
 #[derive(Debug, Clone)]
 pub struct UpdateSaveRequest {
     /// UUID of the cloud save to update
-    pub uuid: String,
+    pub uuid: SaveUuid,
     /// Save name (max 60 bytes, no Chinese characters)
     pub name: String,
-    /// Save description (max 500 bytes)
+    /// Save description (max 500 bytes, not empty)
     pub summary: String,
     /// Developer-defined extra data (max 1000 bytes, optional)
     pub extra: Option<String>,
-    /// Game playtime in seconds
-    pub playtime: u32,
+    /// Game playtime in seconds (at most `u32::MAX`, the SDK's limit)
+    pub playtime: u64,
     /// Path to the save data file (max 10MB)
     pub data_file_path: Box<Path>,
     /// Path to the cover image file (max 512KB, optional)
     pub cover_file_path: Option<Box<Path>>,
 }
 
-/// Convert a CloudSaveResult to a Result
-fn check_cloudsave_result(result: u32) -> Result<()> {
+impl UpdateSaveRequest {
+    /// Set `playtime` from a tracker
+    pub fn with_playtime(mut self, tracker: &PlaytimeTracker) -> Self {
+        self.playtime = tracker.seconds();
+        self
+    }
+
+    /// Check the text fields without sending the request
+    ///
+    /// Like [`CreateSaveRequest::validate`], and [`CloudSave::update`] runs
+    /// it the same way.
+    pub fn validate(&self) -> Result<()> {
+        check_text("uuid", self.uuid.as_str(), false)?;
+        check_save_text(
+            &self.name,
+            &self.summary,
+            self.extra.as_deref(),
+            &self.data_file_path,
+            self.cover_file_path.as_deref(),
+        )
+    }
+}
+
+/// The checks shared by [`CreateSaveRequest::validate`] and
+/// [`UpdateSaveRequest::validate`]
+fn check_save_text(
+    name: &str,
+    summary: &str,
+    extra: Option<&str>,
+    data_file_path: &Path,
+    cover_file_path: Option<&Path>,
+) -> Result<()> {
+    check_text("name", name, false)?;
+    // The SDK rejects a save without a description
+    if summary.is_empty() {
+        return Err(TapSdkError::InvalidField {
+            field: "summary",
+            reason: "is empty".to_string(),
+        });
+    }
+    check_text("summary", summary, true)?;
+    if let Some(extra) = extra {
+        check_text("extra", extra, true)?;
+    }
+    // Paths that are not valid Unicode are rejected when converted
+    check_text("data_file_path", &data_file_path.to_string_lossy(), false)?;
+    if let Some(path) = cover_file_path {
+        check_text("cover_file_path", &path.to_string_lossy(), false)?;
+    }
+    Ok(())
+}
+
+/// Request IDs handed out by [`next_request_id`], counting up from
+/// `i64::MIN` so they do not collide with the small IDs games pick by hand
+static NEXT_REQUEST_ID: AtomicI64 = AtomicI64::new(i64::MIN);
+
+/// Take a request ID that no other request from this process got here
+///
+/// The crate takes the IDs of the requests it sends on its own from here
+/// too, so callers using it never confuse their responses with those.
+pub fn next_request_id() -> i64 {
+    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Cached list responses, see [`CloudSave::cached_list`]
+struct ListCache {
+    /// Last successful list response, and when it arrived
+    saves: Option<(Instant, Vec<CloudSaveInfo>)>,
+    /// Bumped whenever the saves change, so lists started before are ignored
+    epoch: u64,
+    /// List requests in flight, with the epoch they were started in
+    requests: Vec<(i64, u64)>,
+    /// Refresh request in flight
+    refresh: Option<i64>,
+}
+
+static LIST_CACHE: Mutex<ListCache> = Mutex::new(ListCache::new());
+
+impl ListCache {
+    const fn new() -> Self {
+        ListCache {
+            saves: None,
+            epoch: 0,
+            requests: Vec::new(),
+            refresh: None,
+        }
+    }
+
+    fn list_started(&mut self, request_id: i64) {
+        self.requests.push((request_id, self.epoch));
+    }
+
+    fn update(&mut self, event: &TapEvent, now: Instant) {
+        match event {
+            TapEvent::CloudSaveList(data) => {
+                if self.refresh == Some(data.request_id) {
+                    self.refresh = None;
+                }
+                let Some(index) = self
+                    .requests
+                    .iter()
+                    .position(|&(id, _)| id == data.request_id)
+                else {
+                    return;
+                };
+                let (_, epoch) = self.requests.swap_remove(index);
+                if data.error.is_none() && epoch == self.epoch {
+                    self.saves = Some((now, data.saves.clone()));
+                }
+            }
+            TapEvent::CloudSaveCreate(data) | TapEvent::CloudSaveUpdate(data)
+                if data.error.is_none() =>
+            {
+                self.invalidate();
+            }
+            TapEvent::CloudSaveDelete(data) if data.error.is_none() => self.invalidate(),
+            _ => {}
+        }
+    }
+
+    fn invalidate(&mut self) {
+        self.saves = None;
+        self.epoch += 1;
+    }
+
+    /// Whether the cloud copy of a save may differ from a local copy
+    /// modified at `local_modified_time`
+    fn is_newer(&self, uuid: &SaveUuid, file_id: &FileId, local_modified_time: SystemTime) -> bool {
+        let Some((_, saves)) = &self.saves else {
+            return true;
+        };
+        match saves.iter().find(|save| save.uuid == *uuid) {
+            Some(save) => save.file_id != *file_id || save.modified_time > local_modified_time,
+            None => true,
+        }
+    }
+}
+
+/// Update the list cache from an event received from the SDK
+pub(crate) fn cache_event(event: &TapEvent) {
+    recover(LIST_CACHE.lock()).update(event, Instant::now());
+}
+
+/// Uploads and downloads in flight, with their total size in bytes
+static TRANSFERS: Mutex<Vec<(i64, u64)>> = Mutex::new(Vec::new());
+
+fn transfer_started(request_id: i64, total: u64) {
+    recover(TRANSFERS.lock()).push((request_id, total));
+}
+
+/// Forget a transfer whose response nobody waits for anymore
+pub(crate) fn abandon_transfer(request_id: i64) {
+    let mut transfers = recover(TRANSFERS.lock());
+    if let Some(index) = transfers.iter().position(|&(id, _)| id == request_id) {
+        transfers.remove(index);
+    }
+}
+
+/// Queue a `CloudSaveProgress` event
+fn progress(request_id: i64, stage: TransferStage, bytes: u64, total: u64) {
+    let event = TapEvent::CloudSaveProgress(CloudSaveProgressData {
+        request_id,
+        stage,
+        bytes,
+        total,
+    });
+    callback::dispatch(event.event_id(), event);
+}
+
+/// Queue a `CloudSaveProgress` event if `request_id` is a transfer in flight
+pub(crate) fn report_progress(request_id: i64, stage: TransferStage, bytes: u64, total: u64) {
+    let in_flight = recover(TRANSFERS.lock())
+        .iter()
+        .any(|&(id, _)| id == request_id);
+    if in_flight {
+        progress(request_id, stage, bytes, total);
+    }
+}
+
+/// The `Done` progress event for the response to a transfer in flight
+pub(crate) fn finish_transfer(event: &TapEvent) -> Option<TapEvent> {
+    let (request_id, total) = match event {
+        TapEvent::CloudSaveCreate(data) | TapEvent::CloudSaveUpdate(data) => {
+            (data.request_id, None)
+        }
+        TapEvent::CloudSaveGetData(data) | TapEvent::CloudSaveGetCover(data) => {
+            (data.request_id, Some(data.data.len() as u64))
+        }
+        _ => return None,
+    };
+    let mut transfers = recover(TRANSFERS.lock());
+    let index = transfers.iter().position(|&(id, _)| id == request_id)?;
+    let (_, upload_total) = transfers.remove(index);
+    let total = total.unwrap_or(upload_total);
+    Some(TapEvent::CloudSaveProgress(CloudSaveProgressData {
+        request_id,
+        stage: TransferStage::Done,
+        bytes: total,
+        total,
+    }))
+}
+
+/// Combined size of the files of an upload, counting unreadable files as 0
+fn upload_size(data_file_path: &Path, cover_file_path: Option<&Path>) -> u64 {
+    let size = |path: &Path| fs::metadata(path).map_or(0, |metadata| metadata.len());
+    size(data_file_path) + cover_file_path.map_or(0, size)
+}
+
+/// Forget cached lists and transfers, at the start of a new SDK session
+pub(crate) fn reset() {
+    *recover(LIST_CACHE.lock()) = ListCache::new();
+    recover(TRANSFERS.lock()).clear();
+}
+
+/// Check that a request's playtime fits the SDK's 32-bit field
+fn sdk_playtime(playtime: u64) -> Result<u32> {
+    u32::try_from(playtime).map_err(|_| {
+        TapSdkError::InvalidArgument(format!(
+            "playtime of {}s exceeds the SDK limit of {}s",
+            playtime,
+            u32::MAX
+        ))
+    })
+}
+
+/// Delete staged files of a request that failed to start
+fn discard_staged(data_file_path: &Path, cover_file_path: Option<&Path>) {
+    staging::discard(data_file_path);
+    if let Some(path) = cover_file_path {
+        staging::discard(path);
+    }
+}
+
+/// Convert a path to the UTF-8 C string the SDK expects
+///
+/// Paths that are not valid Unicode (such as Windows paths with unpaired
+/// surrogates) are rejected rather than lossily converted, which would point
+/// the SDK at a different file.
+fn path_to_cstring(field: &'static str, path: &Path) -> Result<CString> {
+    #[cfg(windows)]
+    let path = &long_path(path)?;
+    let utf8 = path.to_str().ok_or_else(|| {
+        TapSdkError::InvalidArgument(format!("path is not valid Unicode: {}", path.display()))
+    })?;
+    c_string(field, utf8)
+}
+
+/// Paths at least this long can only be opened with the `\\?\` prefix
+const MAX_PATH: usize = 260;
+
+/// Make a path the SDK can open even when it exceeds `MAX_PATH`
+///
+/// Long paths are resolved to an absolute path and given the `\\?\`
+/// prefix; other paths are passed through unchanged.
+#[cfg(windows)]
+fn long_path(path: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(path).map_err(|e| {
+        TapSdkError::InvalidArgument(format!("cannot resolve path {}: {}", path.display(), e))
+    })?;
+    match absolute.to_str().map(verbatim_path) {
+        Some(Cow::Owned(verbatim)) => Ok(PathBuf::from(verbatim)),
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// Prefix a long absolute Windows path with `\\?\`
+///
+/// The prefix turns off `/` and `..` handling, so `path` must already be
+/// absolute and normalized.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn verbatim_path(path: &str) -> Cow<'_, str> {
+    if path.encode_utf16().count() < MAX_PATH || path.starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+    let path = path.replace('/', "\\");
+    match path.strip_prefix(r"\\") {
+        // \\server\share\... becomes \\?\UNC\server\share\...
+        Some(unc) => Cow::Owned(format!(r"\\?\UNC\{}", unc)),
+        None => Cow::Owned(format!(r"\\?\{}", path)),
+    }
+}
+
+/// Convert a CloudSaveResult to a Result, notifying the observer
+fn check_cloudsave_result(
+    operation: Operation,
+    request_id: i64,
+    save_name: Option<&str>,
+    result: u32,
+) -> Result<()> {
     let cloud_result = CloudSaveResult::from(result);
 
     match cloud_result {
-        CloudSaveResult::Ok => Ok(()),
-        _ => Err(TapSdkError::CloudSaveRequestFailed(cloud_result)),
+        CloudSaveResult::Ok => {
+            callback::track_request(request_id, operation);
+            observer::request_started(operation, Some(request_id));
+            Ok(())
+        }
+        _ => Err(observer::error(TapSdkError::CloudSaveRequestFailed {
+            result: cloud_result,
+            code: result,
+            operation,
+            request_id,
+            save_name: save_name.map(str::to_string),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(request_id: i64, error: Option<(i64, String)>) -> TapEvent {
+        TapEvent::CloudSaveList(callback::CloudSaveListData {
+            request_id,
+            error,
+            saves: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_list_cache_invalidation() {
+        let now = Instant::now();
+        let mut cache = ListCache::new();
+        cache.list_started(1);
+        cache.update(&list(1, None), now);
+        assert!(cache.saves.is_some());
+
+        // A list started before a delete must not refill the cache
+        cache.list_started(2);
+        cache.update(
+            &TapEvent::CloudSaveDelete(callback::CloudSaveDeleteData {
+                request_id: 3,
+                error: None,
+                uuid: SaveUuid::new("uuid").unwrap(),
+            }),
+            now,
+        );
+        assert!(cache.saves.is_none());
+        cache.update(&list(2, None), now);
+        assert!(cache.saves.is_none());
+
+        // Failed and untracked lists are ignored
+        cache.list_started(4);
+        cache.refresh = Some(4);
+        cache.update(&list(4, Some((1, "error".to_string()))), now);
+        cache.update(&list(5, None), now);
+        assert!(cache.saves.is_none());
+        assert_eq!(cache.refresh, None);
+        assert!(cache.requests.is_empty());
+    }
+
+    #[test]
+    fn test_string_ids() {
+        let uuid: SaveUuid = "save-1".parse().unwrap();
+        assert_eq!(uuid, "save-1");
+        assert_eq!(uuid.to_string(), "save-1");
+        assert_eq!(String::from(uuid), "save-1");
+        assert!(matches!(
+            FileId::new("file\0"),
+            Err(TapSdkError::InvalidField {
+                field: "file_id",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_request_failure_context() {
+        let error =
+            check_cloudsave_result(Operation::CloudSaveCreate, 12, Some("slot1"), 7).unwrap_err();
+        assert!(matches!(
+            error,
+            TapSdkError::CloudSaveRequestFailed {
+                result: CloudSaveResult::SaveFileTooLarge,
+                code: 7,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Cloud save request failed: save file larger than 10MB (code 7, CloudSaveCreate request 12, save \"slot1\")"
+        );
+    }
+
+    #[test]
+    fn test_sdk_playtime() {
+        assert_eq!(sdk_playtime(90).unwrap(), 90);
+        assert_eq!(sdk_playtime(u32::MAX.into()).unwrap(), u32::MAX);
+        assert!(matches!(
+            sdk_playtime(u64::from(u32::MAX) + 1),
+            Err(TapSdkError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_cache_is_newer() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let (uuid, file) = (SaveUuid::new("uuid").unwrap(), FileId::new("file").unwrap());
+        let mut cache = ListCache::new();
+        assert!(cache.is_newer(&uuid, &file, modified));
+
+        cache.list_started(1);
+        cache.update(
+            &TapEvent::CloudSaveList(callback::CloudSaveListData {
+                request_id: 1,
+                error: None,
+                saves: vec![CloudSaveInfo {
+                    uuid: SaveUuid::new("uuid").unwrap(),
+                    file_id: FileId::new("file").unwrap(),
+                    name: String::new(),
+                    save_size: 0,
+                    cover_size: 0,
+                    summary: None,
+                    extra: None,
+                    playtime: Duration::ZERO,
+                    created_time: modified,
+                    modified_time: modified,
+                }],
+            }),
+            Instant::now(),
+        );
+        assert!(!cache.is_newer(&uuid, &file, modified));
+        assert!(cache.is_newer(&uuid, &file, modified - Duration::from_secs(1)));
+        let other_file = FileId::new("other-file").unwrap();
+        assert!(cache.is_newer(&uuid, &other_file, modified));
+        let other_uuid = SaveUuid::new("other-uuid").unwrap();
+        assert!(cache.is_newer(&other_uuid, &file, modified));
+    }
+
+    #[test]
+    fn test_finish_transfer() {
+        transfer_started(-42, 1000);
+        let response = TapEvent::CloudSaveCreate(callback::CloudSaveCreateData {
+            request_id: -42,
+            error: None,
+            save: None,
+        });
+        let Some(TapEvent::CloudSaveProgress(done)) = finish_transfer(&response) else {
+            panic!("expected a progress event");
+        };
+        assert_eq!(done.stage, TransferStage::Done);
+        assert_eq!((done.bytes, done.total), (1000, 1000));
+
+        // Only the first response completes the transfer
+        assert!(finish_transfer(&response).is_none());
+        assert!(finish_transfer(&list(-42, None)).is_none());
+    }
+
+    #[test]
+    fn test_nul_names_field() {
+        let error = c_string("summary", "chapter\0 one").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid summary: contains a NUL byte at position 7"
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_control_characters() {
+        let mut request = CreateSaveRequest {
+            name: "Slot 1".to_string(),
+            summary: "Chapter 2\n\tThe forest".to_string(),
+            extra: None,
+            playtime: 0,
+            data_file_path: Path::new("save.dat").into(),
+            cover_file_path: None,
+        };
+        request.validate().unwrap();
+
+        request.extra = Some("a\u{1b}[0m".to_string());
+        assert_eq!(
+            request.validate().unwrap_err().to_string(),
+            "Invalid extra: contains control character U+001B at position 1"
+        );
+
+        request.extra = None;
+        request.name = "Slot\n1".to_string();
+        assert_eq!(
+            request.validate().unwrap_err().to_string(),
+            "Invalid name: contains control character U+000A at position 4"
+        );
+
+        request.name = "Slot 1".to_string();
+        request.cover_file_path = Some(Path::new("cover\0.png").into());
+        assert_eq!(
+            request.validate().unwrap_err().to_string(),
+            "Invalid cover_file_path: contains a NUL byte at position 5"
+        );
+
+        assert!(SaveUuid::new("abc\u{7f}").is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_summary() {
+        let request = CreateSaveRequest {
+            name: "Slot 1".to_string(),
+            summary: String::new(),
+            extra: None,
+            playtime: 0,
+            data_file_path: Path::new("save.dat").into(),
+            cover_file_path: None,
+        };
+        assert_eq!(
+            request.validate().unwrap_err().to_string(),
+            "Invalid summary: is empty"
+        );
+    }
+
+    #[test]
+    fn test_path_keeps_cjk_and_emoji() {
+        let path = Path::new("C:\\Users\\玩家\\存档 🎮\\save.dat");
+        let c_path = path_to_cstring("path", path).unwrap();
+        assert_eq!(c_path.to_str().unwrap(), path.to_str().unwrap());
+    }
+
+    #[test]
+    fn test_verbatim_path() {
+        assert_eq!(verbatim_path(r"C:\saves\a.dat"), r"C:\saves\a.dat");
+
+        let dir = "d".repeat(MAX_PATH);
+        assert_eq!(
+            verbatim_path(&format!(r"C:\{}/save.dat", dir)),
+            format!(r"\\?\C:\{}\save.dat", dir)
+        );
+        assert_eq!(
+            verbatim_path(&format!(r"\\server\share\{}", dir)),
+            format!(r"\\?\UNC\server\share\{}", dir)
+        );
+        let verbatim = format!(r"\\?\C:\{}", dir);
+        assert_eq!(verbatim_path(&verbatim), verbatim);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_rejects_invalid_unicode() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"saves/\xff.dat"));
+        assert!(matches!(
+            path_to_cstring("path", path),
+            Err(TapSdkError::InvalidArgument(_))
+        ));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_path_rejects_invalid_unicode() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        // Lone surrogate
+        let path = OsString::from_wide(&[0x73, 0xD800, 0x2E, 0x64]);
+        assert!(matches!(
+            path_to_cstring("path", Path::new(&path)),
+            Err(TapSdkError::InvalidArgument(_))
+        ));
     }
 }
@@ -0,0 +1,317 @@
+//! On-disk cache of cloud save covers
+//!
+//! Save-selection screens show the cover of every save each time they open.
+//! A [`CoverCache`] keeps downloaded covers in a directory, keyed by save
+//! UUID and modification time so that an updated save gets its new cover,
+//! and evicts the least recently used covers beyond a size cap.
+//!
+//! ```no_run
+//! use tapsdk_pc::covers::CoverCache;
+//! use tapsdk_pc::{CloudSave, TapSdk};
+//!
+//! # fn show(_: &[u8]) {}
+//! # fn main() -> tapsdk_pc::Result<()> {
+//! # let saves: Vec<tapsdk_pc::callback::CloudSaveInfo> = Vec::new();
+//! let sdk = TapSdk::init("your_public_key")?;
+//! let covers = CoverCache::new("cache/covers", 32 * 1024 * 1024)?;
+//! let cloud_save = CloudSave::get().expect("SDK not initialized");
+//! for (request_id, save) in (100..).zip(&saves) {
+//!     // Only covers missing from the cache are downloaded; they are stored
+//!     // when their responses arrive
+//!     if let Some(cover) = covers.fetch(&cloud_save, request_id, save)? {
+//!         show(&cover);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::callback::{recover, CloudSaveGetFileData, CloudSaveInfo};
+use crate::cloudsave::CloudSave;
+use crate::error::Result;
+use crate::subscription::{self, Subscription};
+
+/// File name extension of cached covers
+const EXTENSION: &str = "cover";
+
+/// Covers of cloud saves stored in a directory, see the [module docs](self)
+#[derive(Debug)]
+pub struct CoverCache {
+    inner: Arc<Inner>,
+    _subscription: Subscription,
+}
+
+#[derive(Debug)]
+struct Inner {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Cover downloads started by [`CoverCache::fetch`], with the file name
+    /// to store each one under
+    pending: Mutex<Vec<(i64, String)>>,
+}
+
+impl CoverCache {
+    /// Open the cache in `dir`, creating the directory if needed
+    ///
+    /// Once the covers in the directory take more than `max_bytes`, the
+    /// least recently used ones are deleted.
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let inner = Arc::new(Inner {
+            dir,
+            max_bytes,
+            pending: Mutex::new(Vec::new()),
+        });
+        let responses = inner.clone();
+        let subscription = subscription::subscribe(move |data: &CloudSaveGetFileData| {
+            responses.store_response(data);
+        });
+        Ok(CoverCache {
+            inner,
+            _subscription: subscription,
+        })
+    }
+
+    /// Directory holding the cached covers
+    pub fn dir(&self) -> &Path {
+        &self.inner.dir
+    }
+
+    /// Size cap of the cache, in bytes
+    pub fn max_bytes(&self) -> u64 {
+        self.inner.max_bytes
+    }
+
+    /// Get the cached cover of `save`, if any
+    ///
+    /// Covers cached for an older modification time of the save don't
+    /// count.
+    pub fn get(&self, save: &CloudSaveInfo) -> Option<Vec<u8>> {
+        let path = self.inner.dir.join(file_name(save));
+        let cover = fs::read(&path).ok()?;
+        // Mark it recently used
+        let _ = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(cover)
+    }
+
+    /// Store the cover of `save`, replacing older covers of the same save
+    pub fn insert(&self, save: &CloudSaveInfo, cover: &[u8]) -> Result<()> {
+        self.inner.store(&file_name(save), cover)
+    }
+
+    /// Get the cover of `save` from the cache, or start downloading it
+    ///
+    /// Returns `Ok(None)` while the cover is downloaded with `request_id`;
+    /// the response is stored in the cache when it arrives and delivered
+    /// like any other `CloudSaveGetCover` event. Saves without a cover are
+    /// not requested.
+    pub fn fetch(
+        &self,
+        cloud_save: &CloudSave,
+        request_id: i64,
+        save: &CloudSaveInfo,
+    ) -> Result<Option<Vec<u8>>> {
+        if let Some(cover) = self.get(save) {
+            return Ok(Some(cover));
+        }
+        if save.cover_size == 0 {
+            return Ok(None);
+        }
+        recover(self.inner.pending.lock()).push((request_id, file_name(save)));
+        if let Err(e) = cloud_save.get_cover(request_id, &save.uuid, &save.file_id) {
+            recover(self.inner.pending.lock()).retain(|(id, _)| *id != request_id);
+            return Err(e);
+        }
+        Ok(None)
+    }
+
+    /// Delete every cached cover
+    pub fn clear(&self) -> Result<()> {
+        for path in self.inner.covers()?.into_iter().map(|cover| cover.path) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// A cover file in the cache directory
+struct Cover {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+impl Inner {
+    /// Store a cover downloaded by [`CoverCache::fetch`]
+    fn store_response(&self, data: &CloudSaveGetFileData) {
+        let name = {
+            let mut pending = recover(self.pending.lock());
+            let Some(index) = pending.iter().position(|(id, _)| *id == data.request_id) else {
+                return;
+            };
+            pending.swap_remove(index).1
+        };
+        if data.error.is_some() || data.data.is_empty() {
+            return;
+        }
+        if let Err(e) = self.store(&name, &data.data) {
+            log::warn!(request_id = data.request_id; "Failed to cache cover: {}", e);
+        }
+    }
+
+    fn store(&self, name: &str, cover: &[u8]) -> Result<()> {
+        // Write under another name first so readers never see part of it
+        let path = self.dir.join(name);
+        let partial = path.with_extension("partial");
+        if let Err(e) = fs::write(&partial, cover).and_then(|()| fs::rename(&partial, &path)) {
+            let _ = fs::remove_file(&partial);
+            return Err(e.into());
+        }
+
+        // The save prefix is everything up to its modification time
+        let save_prefix = &name[..=name.find('.').unwrap_or(0)];
+        let mut covers = Vec::new();
+        for cover in self.covers()? {
+            let stale = cover.path != path
+                && cover
+                    .path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(save_prefix));
+            if stale {
+                let _ = fs::remove_file(&cover.path);
+            } else {
+                covers.push(cover);
+            }
+        }
+        self.evict(covers);
+        Ok(())
+    }
+
+    /// Delete the least recently used covers until the rest fit the cap
+    fn evict(&self, mut covers: Vec<Cover>) {
+        let mut total: u64 = covers.iter().map(|cover| cover.size).sum();
+        covers.sort_by_key(|cover| cover.last_used);
+        for cover in covers {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&cover.path).is_ok() {
+                total -= cover.size;
+            }
+        }
+    }
+
+    fn covers(&self) -> Result<Vec<Cover>> {
+        let mut covers = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            covers.push(Cover {
+                path,
+                size: metadata.len(),
+                last_used: metadata.modified().unwrap_or(UNIX_EPOCH),
+            });
+        }
+        Ok(covers)
+    }
+}
+
+/// `<uuid>.<modified seconds>.cover`, with characters that are not safe in
+/// file names, including `.`, escaped in the UUID
+fn file_name(save: &CloudSaveInfo) -> String {
+    let mut name = String::with_capacity(save.uuid.as_str().len() + 16);
+    for byte in save.uuid.as_str().bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    let modified = save
+        .modified_time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format!("{}.{}.{}", name, modified, EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsave::{FileId, SaveUuid};
+    use std::time::Duration;
+
+    fn save(uuid: &str, modified_secs: u64) -> CloudSaveInfo {
+        let time = UNIX_EPOCH + Duration::from_secs(modified_secs);
+        CloudSaveInfo {
+            uuid: SaveUuid::new(uuid).unwrap(),
+            file_id: FileId::default(),
+            name: String::new(),
+            save_size: 0,
+            cover_size: 0,
+            summary: None,
+            extra: None,
+            playtime: Duration::ZERO,
+            created_time: time,
+            modified_time: time,
+        }
+    }
+
+    fn set_last_used(cache: &CoverCache, save: &CloudSaveInfo, secs: u64) {
+        File::options()
+            .write(true)
+            .open(cache.dir().join(file_name(save)))
+            .and_then(|file| file.set_modified(UNIX_EPOCH + Duration::from_secs(secs)))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_file_name_escapes_uuid() {
+        assert_eq!(file_name(&save("ab-1_C", 7)), "ab-1_C.7.cover");
+        assert_eq!(file_name(&save("../a.b", 0)), "%2E%2E%2Fa%2Eb.0.cover");
+    }
+
+    #[test]
+    fn test_cover_cache_replaces_and_evicts() {
+        let dir = std::env::temp_dir().join(format!("tapsdk-pc-covers-{}", std::process::id()));
+        let cache = CoverCache::new(&dir, 8).unwrap();
+        cache.clear().unwrap();
+
+        let a = save("a", 1);
+        cache.insert(&a, b"aaaa").unwrap();
+        assert_eq!(cache.get(&a).as_deref(), Some(&b"aaaa"[..]));
+
+        // A newer version of the save replaces the old cover
+        let a2 = save("a", 2);
+        assert_eq!(cache.get(&a2), None);
+        cache.insert(&a2, b"AAAA").unwrap();
+        assert_eq!(cache.get(&a), None);
+        assert_eq!(cache.get(&a2).as_deref(), Some(&b"AAAA"[..]));
+
+        // Over the cap, the least recently used cover goes
+        let b = save("b", 1);
+        cache.insert(&b, b"bbbb").unwrap();
+        set_last_used(&cache, &a2, 100);
+        set_last_used(&cache, &b, 50);
+        cache.insert(&save("c", 1), b"cccc").unwrap();
+        assert_eq!(cache.get(&b), None);
+        assert!(cache.get(&a2).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
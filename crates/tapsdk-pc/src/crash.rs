@@ -0,0 +1,202 @@
+//! Reporting of fatal SDK conditions
+//!
+//! Some failures leave the SDK unusable without any error being returned:
+//! events stop arriving and the game may crash later for no visible reason.
+//! Install a handler with [`set_crash_handler`] to hand these conditions to
+//! your crash reporter while the process is still alive.
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::callback::{recover, TapEvent};
+
+/// Number of consecutive unparsable events reported as
+/// [`FatalCondition::RepeatedParseErrors`]
+pub const PARSE_ERROR_THRESHOLD: u32 = 16;
+
+/// A condition after which the SDK cannot be expected to work
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FatalCondition {
+    /// `taptap_api.dll` was unloaded while the SDK was initialized
+    ///
+    /// The SDK callbacks are no longer run, since calling into the unloaded
+    /// DLL would crash the process.
+    LibraryUnloaded,
+    /// Events failed to parse [`PARSE_ERROR_THRESHOLD`] times in a row,
+    /// usually because the DLL does not match the bundled headers
+    RepeatedParseErrors {
+        /// Raw ID of the last event that failed to parse
+        event_id: u32,
+        /// Why the last event failed to parse
+        reason: String,
+    },
+    /// A thread delivering SDK events stopped with a panic
+    CallbackThreadDied {
+        /// Which thread died
+        thread: String,
+        /// The panic message
+        message: String,
+    },
+}
+
+impl fmt::Display for FatalCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FatalCondition::LibraryUnloaded => {
+                write!(
+                    f,
+                    "taptap_api.dll was unloaded while the SDK was initialized"
+                )
+            }
+            FatalCondition::RepeatedParseErrors { event_id, reason } => write!(
+                f,
+                "{} SDK events in a row failed to parse, last event {}: {}",
+                PARSE_ERROR_THRESHOLD, event_id, reason
+            ),
+            FatalCondition::CallbackThreadDied { thread, message } => {
+                write!(f, "{} thread died: {}", thread, message)
+            }
+        }
+    }
+}
+
+type Handler = Arc<dyn Fn(&FatalCondition) + Send + Sync>;
+
+/// Currently installed crash handler
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Consecutive parse failures across all received events
+static PARSE_ERRORS: ParseErrorCounter = ParseErrorCounter(AtomicU32::new(0));
+
+/// Whether the unloaded DLL has been reported already
+#[cfg(windows)]
+static LIBRARY_UNLOADED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Install a handler for fatal SDK conditions, replacing any previous one
+///
+/// The handler runs synchronously on the thread that detected the
+/// condition, at most once per occurrence. A panic in the handler is caught.
+///
+/// # Example
+/// ```no_run
+/// tapsdk_pc::sdk::set_crash_handler(|condition| {
+///     eprintln!("TapTap SDK failure: {}", condition);
+/// });
+/// ```
+pub fn set_crash_handler(handler: impl Fn(&FatalCondition) + Send + Sync + 'static) {
+    *recover(HANDLER.write()) = Some(Arc::new(handler));
+}
+
+/// Remove the installed crash handler
+pub fn clear_crash_handler() {
+    *recover(HANDLER.write()) = None;
+}
+
+/// Pass a fatal condition to the crash handler
+///
+/// The crate reports the conditions it detects itself. Bindings that run
+/// their own event threads call this when such a thread dies.
+pub fn report(condition: FatalCondition) {
+    log::error!("{}", condition);
+    let handler = recover(HANDLER.read()).clone();
+    if let Some(handler) = handler {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(&condition)));
+    }
+}
+
+/// Report that `thread` died with the panic `payload`
+pub(crate) fn thread_died(thread: &str, payload: Box<dyn Any + Send>) {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    report(FatalCondition::CallbackThreadDied {
+        thread: thread.to_string(),
+        message,
+    });
+}
+
+/// Counts events that failed to parse since the last one that parsed
+struct ParseErrorCounter(AtomicU32);
+
+impl ParseErrorCounter {
+    /// Count `event`, returning the condition once the threshold is reached
+    fn record(&self, event: &TapEvent) -> Option<FatalCondition> {
+        match event {
+            TapEvent::ParseError { event_id, reason } => {
+                let count = self.0.fetch_add(1, Ordering::Relaxed) + 1;
+                (count == PARSE_ERROR_THRESHOLD).then(|| FatalCondition::RepeatedParseErrors {
+                    event_id: *event_id,
+                    reason: reason.clone(),
+                })
+            }
+            _ => {
+                self.0.store(0, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+/// Count parse failures, reporting once the threshold is reached
+pub(crate) fn event_received(event: &TapEvent) {
+    if let Some(condition) = PARSE_ERRORS.record(event) {
+        report(condition);
+    }
+}
+
+/// Check that the SDK library is still mapped, reporting it once if not
+pub(crate) fn library_mapped() -> bool {
+    #[cfg(windows)]
+    {
+        if crate::library::is_mapped() {
+            return true;
+        }
+        if !LIBRARY_UNLOADED.swap(true, Ordering::SeqCst) {
+            report(FatalCondition::LibraryUnloaded);
+        }
+        false
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_parse_errors_reported_once() {
+        let counter = ParseErrorCounter(AtomicU32::new(0));
+        let error = TapEvent::ParseError {
+            event_id: 6001,
+            reason: "bad".to_string(),
+        };
+
+        let reported: Vec<_> = (0..PARSE_ERROR_THRESHOLD * 2)
+            .filter_map(|_| counter.record(&error))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![FatalCondition::RepeatedParseErrors {
+                event_id: 6001,
+                reason: "bad".to_string(),
+            }]
+        );
+
+        // A parsed event starts the count over
+        assert_eq!(counter.record(&TapEvent::Unknown { event_id: 1 }), None);
+        let reported = (0..PARSE_ERROR_THRESHOLD)
+            .filter_map(|_| counter.record(&error))
+            .count();
+        assert_eq!(reported, 1);
+    }
+}
@@ -0,0 +1,112 @@
+//! Developer configuration file, like Steam's `steam_appid.txt`
+//!
+//! Available with the `dev-config` feature. Put a `taptap_dev.json` next to
+//! the executable during development:
+//!
+//! ```json
+//! {
+//!   "clientId": "your_client_id",
+//!   "pubKey": "your_public_key",
+//!   "historyCapacity": 32
+//! }
+//! ```
+//!
+//! [`TapSdk::init_dev`](crate::TapSdk::init_dev) then initializes from it
+//! without the [`restart_app_if_necessary`](crate::restart_app_if_necessary)
+//! check, so the game can be started from an editor or debugger instead of
+//! through TapTap. Don't ship the file with the game.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Result, TapSdkError};
+
+/// File name looked up next to the executable
+pub const FILE_NAME: &str = "taptap_dev.json";
+
+/// Contents of `taptap_dev.json`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevConfig {
+    /// The client ID from TapTap developer center
+    pub client_id: String,
+    /// The public key from TapTap developer center
+    pub pub_key: String,
+    /// Number of events kept for [`callback::recent_events`](crate::callback::recent_events)
+    #[serde(default)]
+    pub history_capacity: usize,
+    /// Event queue capacity, see [`QueueConfig`](crate::callback::QueueConfig)
+    #[serde(default)]
+    pub queue_capacity: Option<usize>,
+}
+
+impl DevConfig {
+    /// Path of `taptap_dev.json` next to the executable
+    pub fn path() -> Result<PathBuf> {
+        let exe = std::env::current_exe()?;
+        let dir = exe.parent().unwrap_or(Path::new("."));
+        Ok(dir.join(FILE_NAME))
+    }
+
+    /// Read `taptap_dev.json` from next to the executable
+    ///
+    /// # Returns
+    /// `None` if there is no such file
+    pub fn find() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::load(&path).map(Some)
+    }
+
+    /// Read a developer config file
+    ///
+    /// # Errors
+    /// [`TapSdkError::Io`] if the file cannot be read, or
+    /// [`TapSdkError::InvalidArgument`] if it is not a valid config
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+            .map_err(|e| TapSdkError::InvalidArgument(format!("{}: {}", path.display(), e)))
+    }
+
+    fn parse(contents: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dev_config() {
+        let config =
+            DevConfig::parse(r#"{"clientId": "client", "pubKey": "key", "historyCapacity": 8}"#)
+                .unwrap();
+        assert_eq!(
+            config,
+            DevConfig {
+                client_id: "client".to_string(),
+                pub_key: "key".to_string(),
+                history_capacity: 8,
+                queue_capacity: None,
+            }
+        );
+
+        assert!(DevConfig::parse(r#"{"clientId": "client"}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_reports_path() {
+        let path = std::env::temp_dir().join(format!("tapsdk-dev-{}.json", std::process::id()));
+        std::fs::write(&path, "not json").unwrap();
+        let error = DevConfig::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(error, TapSdkError::InvalidArgument(message) if message.contains("tapsdk-dev-"))
+        );
+    }
+}
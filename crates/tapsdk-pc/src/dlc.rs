@@ -1,9 +1,19 @@
 //! DLC (Downloadable Content) functionality
 
+use std::collections::BTreeMap;
 use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::error::{Result, TapSdkError};
+use crate::callback::{recover, DlcPlayableStatusChangedData, TapEvent};
+use crate::error::{c_string, check_text, Result, TapSdkError};
+use crate::ffi;
 use crate::sdk::is_initialized;
+use crate::subscription::{self, Subscription};
+
+/// State of the last `DlcPlayableStatusChanged` event for each DLC since
+/// initialization
+static DLC_PLAYABLE: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
 
 /// Check if the user owns a specific DLC
 ///
@@ -25,7 +35,39 @@ pub fn is_dlc_owned(dlc_id: &str) -> bool {
         Err(_) => return false,
     };
 
-    unsafe { tapsdk_pc_sys::TapDLC_IsOwned(dlc_id_c.as_ptr()) }
+    unsafe { ffi::TapDLC_IsOwned(dlc_id_c.as_ptr()) }
+}
+
+/// Check if the user can currently play a specific DLC
+///
+/// The SDK has no query for this, only `DlcPlayableStatusChanged` events.
+/// This returns the state of the last such event for `dlc_id` received
+/// since initialization, and [`is_dlc_owned`] until the first one arrives.
+///
+/// # Note
+/// This will return `false` if the SDK is not initialized.
+pub fn is_dlc_playable(dlc_id: &str) -> bool {
+    if !is_initialized() {
+        return false;
+    }
+
+    reported_playable(dlc_id).unwrap_or_else(|| is_dlc_owned(dlc_id))
+}
+
+fn reported_playable(dlc_id: &str) -> Option<bool> {
+    recover(DLC_PLAYABLE.lock()).get(dlc_id).copied()
+}
+
+/// Remember the state reported by a `DlcPlayableStatusChanged` event
+pub(crate) fn record_event(event: &TapEvent) {
+    if let TapEvent::DlcPlayableStatusChanged(data) = event {
+        recover(DLC_PLAYABLE.lock()).insert(data.dlc_id.clone(), data.is_playable);
+    }
+}
+
+/// Forget the reported states, when a new session starts
+pub(crate) fn reset() {
+    recover(DLC_PLAYABLE.lock()).clear();
 }
 
 /// Show the store page for a specific DLC
@@ -45,9 +87,156 @@ pub fn show_dlc_store(dlc_id: &str) -> Result<bool> {
         return Err(TapSdkError::NotInitialized);
     }
 
-    let dlc_id_c = CString::new(dlc_id)?;
+    check_text("dlc_id", dlc_id, false)?;
+    let dlc_id_c = c_string("dlc_id", dlc_id)?;
 
-    let result = unsafe { tapsdk_pc_sys::TapDLC_ShowStore(dlc_id_c.as_ptr()) };
+    let result = unsafe { ffi::TapDLC_ShowStore(dlc_id_c.as_ptr()) };
 
     Ok(result)
 }
+
+type ChangeHandler = Arc<dyn Fn(bool) + Send + Sync>;
+
+/// Tracks whether one DLC is playable, see [`watch`]
+///
+/// Dropping the watcher stops tracking.
+#[derive(Debug)]
+pub struct Watcher {
+    dlc_id: String,
+    state: Arc<WatchState>,
+    _subscription: Subscription,
+}
+
+struct WatchState {
+    playable: AtomicBool,
+    on_change: RwLock<Option<ChangeHandler>>,
+}
+
+impl std::fmt::Debug for WatchState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WatchState")
+            .field("playable", &self.playable)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Track whether the user can play a specific DLC
+///
+/// The state starts out as [`is_dlc_playable`] and follows
+/// `DlcPlayableStatusChanged` events for `dlc_id` from then on, until the
+/// SDK is shut down. Events are seen as they are received, before they are
+/// queued for polling.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::dlc;
+///
+/// let expansion = dlc::watch("expansion_1");
+/// expansion.on_change(|playable| println!("expansion playable: {}", playable));
+///
+/// if expansion.current() {
+///     // unlock the expansion content
+/// }
+/// ```
+pub fn watch(dlc_id: &str) -> Watcher {
+    let state = Arc::new(WatchState {
+        playable: AtomicBool::new(false),
+        on_change: RwLock::new(None),
+    });
+
+    // Subscribe before seeding so a change in between is not missed
+    let events = state.clone();
+    let id = dlc_id.to_string();
+    let subscription = subscription::subscribe(move |data: &DlcPlayableStatusChangedData| {
+        if data.dlc_id == id {
+            events.set(data.is_playable);
+        }
+    });
+    state.set(is_dlc_playable(dlc_id));
+
+    Watcher {
+        dlc_id: dlc_id.to_string(),
+        state,
+        _subscription: subscription,
+    }
+}
+
+impl Watcher {
+    /// The watched DLC
+    pub fn dlc_id(&self) -> &str {
+        &self.dlc_id
+    }
+
+    /// Whether the DLC is currently playable
+    pub fn current(&self) -> bool {
+        self.state.playable.load(Ordering::SeqCst)
+    }
+
+    /// Call `handler` with the new state whenever it changes, replacing any
+    /// previous handler
+    ///
+    /// The handler runs on the thread that runs the SDK callbacks.
+    pub fn on_change(&self, handler: impl Fn(bool) + Send + Sync + 'static) {
+        *recover(self.state.on_change.write()) = Some(Arc::new(handler));
+    }
+}
+
+impl WatchState {
+    fn set(&self, playable: bool) {
+        if self.playable.swap(playable, Ordering::SeqCst) == playable {
+            return;
+        }
+        let handler = recover(self.on_change.read()).clone();
+        if let Some(handler) = handler {
+            handler(playable);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callback::TapEvent;
+    use std::sync::atomic::AtomicUsize;
+
+    fn playable_event(dlc_id: &str, is_playable: bool) -> TapEvent {
+        TapEvent::DlcPlayableStatusChanged(DlcPlayableStatusChangedData {
+            dlc_id: dlc_id.to_string(),
+            is_playable,
+        })
+    }
+
+    #[test]
+    fn test_watcher_follows_events() {
+        let watcher = watch("watcher-test");
+        assert!(!watcher.current());
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        watcher.on_change(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        subscription::notify(&playable_event("watcher-other", true));
+        assert!(!watcher.current());
+
+        subscription::notify(&playable_event("watcher-test", true));
+        subscription::notify(&playable_event("watcher-test", true));
+        assert!(watcher.current());
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        drop(watcher);
+        subscription::notify(&playable_event("watcher-test", false));
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_record_reported_playable() {
+        assert_eq!(reported_playable("reported-test"), None);
+        record_event(&playable_event("reported-test", true));
+        assert_eq!(reported_playable("reported-test"), Some(true));
+        record_event(&playable_event("reported-test", false));
+        assert_eq!(reported_playable("reported-test"), Some(false));
+        assert_eq!(reported_playable("reported-other"), None);
+    }
+}
@@ -1,12 +1,19 @@
 //! Error types for TapTap PC SDK
 
+use std::ffi::CString;
+use std::fmt;
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::observer::Operation;
+
 /// Result type alias for TapSDK operations
 pub type Result<T> = std::result::Result<T, TapSdkError>;
 
 /// SDK initialization result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum InitResult {
     /// Initialization successful
     Ok,
@@ -35,8 +42,36 @@ impl From<u32> for InitResult {
     }
 }
 
+impl InitResult {
+    /// The result code reported by the SDK
+    pub fn as_code(self) -> u32 {
+        match self {
+            InitResult::Ok => 0,
+            InitResult::FailedGeneric => 1,
+            InitResult::NoPlatform => 2,
+            InitResult::NotLaunchedByPlatform => 3,
+            InitResult::PlatformVersionMismatch => 4,
+            InitResult::Unknown(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for InitResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InitResult::Ok => f.write_str("initialized"),
+            InitResult::FailedGeneric => f.write_str("initialization failed"),
+            InitResult::NoPlatform => f.write_str("TapTap client not found"),
+            InitResult::NotLaunchedByPlatform => f.write_str("game not launched by TapTap"),
+            InitResult::PlatformVersionMismatch => f.write_str("TapTap client version mismatch"),
+            InitResult::Unknown(code) => write!(f, "unknown result code {}", code),
+        }
+    }
+}
+
 /// Authorization request result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AuthorizeResult {
     /// Unknown error
     Unknown,
@@ -60,8 +95,32 @@ impl From<u32> for AuthorizeResult {
     }
 }
 
+impl AuthorizeResult {
+    /// The result code reported by the SDK; unknown codes read as 0
+    pub fn as_code(self) -> u32 {
+        match self {
+            AuthorizeResult::Unknown => 0,
+            AuthorizeResult::Ok => 1,
+            AuthorizeResult::Failed => 2,
+            AuthorizeResult::InFlight => 3,
+        }
+    }
+}
+
+impl fmt::Display for AuthorizeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthorizeResult::Unknown => "unknown error",
+            AuthorizeResult::Ok => "authorization started",
+            AuthorizeResult::Failed => "authorization could not be started",
+            AuthorizeResult::InFlight => "authorization already in progress",
+        })
+    }
+}
+
 /// Cloud save operation result
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum CloudSaveResult {
     /// Request initiated successfully
     Ok,
@@ -105,8 +164,47 @@ impl From<u32> for CloudSaveResult {
     }
 }
 
+impl CloudSaveResult {
+    /// The result code reported by the SDK
+    pub fn as_code(self) -> u32 {
+        match self {
+            CloudSaveResult::Ok => 0,
+            CloudSaveResult::Uninitialized => 1,
+            CloudSaveResult::NoTapTapClient => 2,
+            CloudSaveResult::TapTapClientOutdated => 3,
+            CloudSaveResult::InvalidArgument => 4,
+            CloudSaveResult::SdkFailed => 5,
+            CloudSaveResult::FailedToReadSaveFile => 6,
+            CloudSaveResult::SaveFileTooLarge => 7,
+            CloudSaveResult::FailedToReadCoverFile => 8,
+            CloudSaveResult::CoverFileTooLarge => 9,
+            CloudSaveResult::Unknown(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for CloudSaveResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CloudSaveResult::Ok => "request started",
+            CloudSaveResult::Uninitialized => "SDK not initialized",
+            CloudSaveResult::NoTapTapClient => "TapTap client not running",
+            CloudSaveResult::TapTapClientOutdated => "TapTap client outdated",
+            CloudSaveResult::InvalidArgument => "invalid argument",
+            CloudSaveResult::SdkFailed => "SDK internal failure",
+            CloudSaveResult::FailedToReadSaveFile => "save file could not be read",
+            CloudSaveResult::SaveFileTooLarge => "save file larger than 10MB",
+            CloudSaveResult::FailedToReadCoverFile => "cover file could not be read",
+            CloudSaveResult::CoverFileTooLarge => "cover file larger than 512KB",
+            CloudSaveResult::Unknown(code) => return write!(f, "unknown result code {}", code),
+        })
+    }
+}
+
 /// System state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum SystemState {
     /// Unknown state
     Unknown,
@@ -130,24 +228,62 @@ impl From<u32> for SystemState {
     }
 }
 
+impl SystemState {
+    /// The state code reported by the SDK; unknown codes read as 0
+    pub fn as_code(self) -> u32 {
+        match self {
+            SystemState::Unknown => 0,
+            SystemState::PlatformOnline => 1,
+            SystemState::PlatformOffline => 2,
+            SystemState::PlatformShutdown => 3,
+        }
+    }
+}
+
+impl fmt::Display for SystemState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SystemState::Unknown => "unknown",
+            SystemState::PlatformOnline => "TapTap client online",
+            SystemState::PlatformOffline => "TapTap client offline",
+            SystemState::PlatformShutdown => "TapTap client shutting down",
+        })
+    }
+}
+
 /// Main error type for TapSDK operations
 #[derive(Debug, Error)]
 pub enum TapSdkError {
     /// SDK initialization failed
-    #[error("SDK initialization failed: {result:?} - {message}")]
+    #[error("SDK initialization failed: {result} - {message}")]
     InitFailed { result: InitResult, message: String },
 
     /// SDK not initialized
     #[error("SDK not initialized")]
     NotInitialized,
 
+    /// The TapTap client reported it is offline, so the request was not sent
+    #[error("TapTap client is offline")]
+    PlatformOffline,
+
     /// Authorization failed
-    #[error("Authorization failed: {0:?}")]
+    #[error("Authorization failed: {0}")]
     AuthorizeFailed(AuthorizeResult),
 
     /// Cloud save operation failed to start
-    #[error("Cloud save request failed: {0:?}")]
-    CloudSaveRequestFailed(CloudSaveResult),
+    #[error(
+        "Cloud save request failed: {result} (code {code}, {operation:?} request {request_id}{})",
+        .save_name.as_ref().map(|name| format!(", save {:?}", name)).unwrap_or_default()
+    )]
+    CloudSaveRequestFailed {
+        result: CloudSaveResult,
+        /// Result code returned by the SDK
+        code: u32,
+        operation: Operation,
+        request_id: i64,
+        /// Name of the save being created or updated
+        save_name: Option<String>,
+    },
 
     /// API error returned from the SDK
     #[error("API error ({code}): {message}")]
@@ -157,6 +293,10 @@ pub enum TapSdkError {
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
 
+    /// A string field can't be passed to the SDK
+    #[error("Invalid {field}: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+
     /// Null pointer returned
     #[error("Null pointer returned from SDK")]
     NullPointer,
@@ -168,9 +308,33 @@ pub enum TapSdkError {
     /// String contains null byte
     #[error("String contains null byte: {0}")]
     NulError(#[from] std::ffi::NulError),
+
+    /// Reading or writing a local file failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The loaded `taptap_api.dll` failed a [`LibraryTrust`](crate::library::LibraryTrust) check
+    #[error("Untrusted SDK library: {0}")]
+    UntrustedLibrary(String),
+
+    /// None of the candidate SDK library files could be loaded
+    #[error("SDK library not found, searched: {searched:?}")]
+    LibraryMissing { searched: Vec<PathBuf> },
+
+    /// A MAC-signed request or TapTap's verification response was rejected
+    #[error("Token verification failed: {0}")]
+    TokenRejected(String),
 }
 
 impl TapSdkError {
+    /// `InvalidField` for a string with a NUL byte at `position`
+    pub(crate) fn nul_in(field: &'static str, position: usize) -> Self {
+        TapSdkError::InvalidField {
+            field,
+            reason: format!("contains a NUL byte at position {}", position),
+        }
+    }
+
     /// Create an API error from SDK error code and message
     pub fn from_api_error(code: i64, message: impl Into<String>) -> Self {
         TapSdkError::ApiError {
@@ -204,6 +368,33 @@ impl TapSdkError {
     }
 }
 
+/// Check the text in `field` before it is sent to the SDK
+///
+/// NUL bytes and control characters fail with `InvalidField`; `multiline`
+/// text may still contain tabs and line breaks.
+pub(crate) fn check_text(field: &'static str, value: &str, multiline: bool) -> Result<()> {
+    for (position, c) in value.char_indices() {
+        if c == '\0' {
+            return Err(TapSdkError::nul_in(field, position));
+        }
+        if c.is_control() && !(multiline && matches!(c, '\t' | '\n' | '\r')) {
+            return Err(TapSdkError::InvalidField {
+                field,
+                reason: format!(
+                    "contains control character U+{:04X} at position {}",
+                    c as u32, position
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Convert the string in `field` for the SDK
+pub(crate) fn c_string(field: &'static str, value: &str) -> Result<CString> {
+    CString::new(value).map_err(|e| TapSdkError::nul_in(field, e.nul_position()))
+}
+
 /// Error code constants matching the C SDK
 pub mod error_code {
     pub const SUCCESS: i64 = 0;
@@ -0,0 +1,159 @@
+//! Detecting a TapTap client that stopped running
+//!
+//! If the TapTap client crashes or is closed while the game runs, the SDK
+//! sends no event and later calls just fail. A [`Heartbeat`] checks for the
+//! client periodically and queues [`TapEvent::ConnectionLost`] and
+//! [`TapEvent::ConnectionRestored`] on every transition.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tapsdk_pc::{heartbeat, TapEvent, TapSdk};
+//!
+//! let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+//! let _heartbeat = heartbeat::start(heartbeat::DEFAULT_INTERVAL);
+//!
+//! for event in sdk.run_callbacks() {
+//!     if let TapEvent::ConnectionLost = event {
+//!         // Pause online features until ConnectionRestored
+//!     }
+//! }
+//! ```
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::callback::{self, TapEvent};
+
+/// Check interval used when there is no reason to pick another
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Handle to a running heartbeat thread, see [`start`]
+///
+/// Dropping the handle stops the thread.
+#[must_use = "the heartbeat stops as soon as the handle is dropped"]
+#[derive(Debug)]
+pub struct Heartbeat {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Check for the TapTap client every `interval`
+///
+/// Checks are skipped while the SDK is not initialized. The first check
+/// after initialization only records the state, so a client that is running
+/// produces no event. Only Windows can detect the client; elsewhere it is
+/// always considered running.
+pub fn start(interval: Duration) -> Heartbeat {
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = running.clone();
+    let thread = thread::spawn(move || {
+        let result = panic::catch_unwind(|| {
+            let mut monitor = Monitor::default();
+            while thread_running.load(Ordering::SeqCst) {
+                if crate::is_initialized() {
+                    if let Some(event) = monitor.check(platform_alive()) {
+                        callback::dispatch(event.event_id(), event);
+                    }
+                } else {
+                    monitor = Monitor::default();
+                }
+                thread::park_timeout(interval);
+            }
+        });
+        if let Err(payload) = result {
+            crate::crash::thread_died("tapsdk-pc heartbeat", payload);
+        }
+    });
+    Heartbeat {
+        running,
+        thread: Some(thread),
+    }
+}
+
+/// Turns successive checks into connection transitions
+#[derive(Debug, Default)]
+struct Monitor {
+    /// Result of the previous check, `None` before the first
+    alive: Option<bool>,
+}
+
+impl Monitor {
+    fn check(&mut self, alive: bool) -> Option<TapEvent> {
+        let previous = self.alive.replace(alive);
+        match (previous, alive) {
+            (Some(true), false) => Some(TapEvent::ConnectionLost),
+            (Some(false), true) => Some(TapEvent::ConnectionRestored),
+            // A client missing at the first check has been lost too
+            (None, false) => Some(TapEvent::ConnectionLost),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(windows)]
+fn platform_alive() -> bool {
+    crate::process::taptap_running()
+}
+
+#[cfg(not(windows))]
+fn platform_alive() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(events: impl IntoIterator<Item = Option<TapEvent>>) -> Vec<Option<u32>> {
+        events
+            .into_iter()
+            .map(|event| event.as_ref().map(TapEvent::event_id))
+            .collect()
+    }
+
+    #[test]
+    fn test_monitor_reports_transitions() {
+        let mut monitor = Monitor::default();
+        let events: Vec<_> = [true, true, false, false, true]
+            .into_iter()
+            .map(|alive| monitor.check(alive))
+            .collect();
+        assert_eq!(
+            ids(events),
+            vec![
+                None,
+                None,
+                Some(callback::event_id::CONNECTION_LOST),
+                None,
+                Some(callback::event_id::CONNECTION_RESTORED),
+            ]
+        );
+
+        let mut monitor = Monitor::default();
+        assert_eq!(
+            ids([monitor.check(false)]),
+            vec![Some(callback::event_id::CONNECTION_LOST)]
+        );
+    }
+
+    #[test]
+    fn test_drop_stops_thread() {
+        let heartbeat = start(Duration::from_secs(3600));
+        let running = heartbeat.running.clone();
+        drop(heartbeat);
+        assert!(!running.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,250 @@
+//! Event JSON shared by the Node.js, C and Tauri bindings
+//!
+//! Events are converted to the camelCase objects delivered by the Node.js
+//! bindings, so every binding (and game-side IPC) produces the same JSON.
+//! Times are milliseconds since the Unix epoch, as used by JavaScript
+//! `Date`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::callback::{event_id, CloudSaveInfo, Envelope, TapEvent, TransferStage};
+
+/// An event converted to JSON
+#[derive(Debug, Clone)]
+pub struct JsonEvent {
+    /// Event fields as a JSON object, always including `eventId`
+    pub value: Value,
+    /// File contents of cloud save data and cover downloads
+    ///
+    /// Kept out of `value` so bindings can hand the bytes over without
+    /// encoding them.
+    pub data: Option<Vec<u8>>,
+}
+
+/// Convert an event to JSON
+///
+/// # Example
+/// ```
+/// use tapsdk_pc::callback::{GamePlayableStatusChangedData, TapEvent};
+///
+/// let event = TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData {
+///     is_playable: true,
+/// });
+/// let json = tapsdk_pc::json::event_to_json(event);
+/// assert_eq!(json.value["eventId"], 4001);
+/// assert_eq!(json.value["isPlayable"], true);
+/// ```
+pub fn event_to_json(event: TapEvent) -> JsonEvent {
+    let id = event.event_id();
+    let (value, data) = match event {
+        TapEvent::SystemStateChanged(data) => (
+            json!({ "eventId": id, "state": data.state.as_code() }),
+            None,
+        ),
+        TapEvent::AuthorizeFinished(data) => (
+            json!({
+                "eventId": id,
+                "isCancel": data.is_cancel,
+                "error": data.error,
+                "token": data.token.map(|t| json!({
+                    "tokenType": t.token_type,
+                    "kid": t.kid,
+                    "macKey": t.mac_key,
+                    "macAlgorithm": t.mac_algorithm,
+                    "scope": t.scope,
+                })),
+            }),
+            None,
+        ),
+        TapEvent::GamePlayableStatusChanged(data) => (
+            json!({ "eventId": id, "isPlayable": data.is_playable }),
+            None,
+        ),
+        TapEvent::DlcPlayableStatusChanged(data) => (
+            json!({
+                "eventId": id,
+                "dlcId": data.dlc_id,
+                "isPlayable": data.is_playable,
+            }),
+            None,
+        ),
+        TapEvent::CloudSaveList(data) => (
+            json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "error": error_to_json(data.error),
+                "saves": data.saves.into_iter().map(save_to_json).collect::<Vec<_>>(),
+            }),
+            None,
+        ),
+        TapEvent::CloudSaveCreate(data) | TapEvent::CloudSaveUpdate(data) => (
+            json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "error": error_to_json(data.error),
+                "save": data.save.map(save_to_json),
+            }),
+            None,
+        ),
+        TapEvent::CloudSaveDelete(data) => (
+            json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "error": error_to_json(data.error),
+                "uuid": data.uuid.as_str(),
+            }),
+            None,
+        ),
+        TapEvent::CloudSaveGetData(data) => (
+            json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "error": error_to_json(data.error),
+            }),
+            Some(data.data),
+        ),
+        TapEvent::CloudSaveGetCover(data) => {
+            let content_type = data.content_type();
+            let mut value = json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "error": error_to_json(data.error),
+            });
+            if let Some(content_type) = content_type {
+                value["contentType"] = content_type.into();
+            }
+            (value, Some(data.data))
+        }
+        TapEvent::CloudSaveProgress(data) => (
+            json!({
+                "eventId": id,
+                "requestId": data.request_id,
+                "stage": transfer_stage_to_str(data.stage),
+                "bytes": data.bytes,
+                "total": data.total,
+            }),
+            None,
+        ),
+        TapEvent::InternalError(data) => (
+            json!({
+                "eventId": event_id::INTERNAL_ERROR,
+                "sourceEventId": data.event_id,
+                "message": data.message,
+            }),
+            None,
+        ),
+        TapEvent::ParseError {
+            event_id: source,
+            reason,
+        } => (
+            json!({
+                "eventId": event_id::PARSE_ERROR,
+                "sourceEventId": source,
+                "reason": reason,
+            }),
+            None,
+        ),
+        // Custom payloads are opaque Rust values with no JSON form
+        TapEvent::ConnectionLost
+        | TapEvent::ConnectionRestored
+        | TapEvent::Custom(_)
+        | TapEvent::Unknown { .. } => (json!({ "eventId": id }), None),
+    };
+    JsonEvent { value, data }
+}
+
+/// Convert an event to JSON, adding its receipt time as `receivedAt`
+pub fn envelope_to_json(envelope: Envelope) -> JsonEvent {
+    let received_at = unix_millis(envelope.received_at_system);
+    let mut event = event_to_json(envelope.event);
+    event.value["receivedAt"] = received_at.into();
+    event
+}
+
+fn transfer_stage_to_str(stage: TransferStage) -> &'static str {
+    match stage {
+        TransferStage::Reading => "reading",
+        TransferStage::Uploading => "uploading",
+        TransferStage::WaitingForServer => "waitingForServer",
+        TransferStage::Done => "done",
+    }
+}
+
+fn save_to_json(save: CloudSaveInfo) -> Value {
+    json!({
+        "uuid": save.uuid.as_str(),
+        "fileId": save.file_id.as_str(),
+        "name": save.name,
+        "saveSize": save.save_size,
+        "coverSize": save.cover_size,
+        "summary": save.summary,
+        "extra": save.extra,
+        "playtime": save.playtime.as_secs(),
+        "createdTime": unix_millis(save.created_time),
+        "modifiedTime": unix_millis(save.modified_time),
+    })
+}
+
+/// Milliseconds since the Unix epoch, as used by JavaScript `Date`
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+fn error_to_json(error: Option<(i64, String)>) -> Value {
+    match error {
+        Some((code, message)) => json!({ "code": code, "message": message }),
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callback::{CloudSaveGetFileData, CloudSaveListData};
+    use crate::cloudsave::{FileId, SaveUuid};
+    use std::time::Duration;
+
+    #[test]
+    fn test_list_event_json() {
+        let event = TapEvent::CloudSaveList(CloudSaveListData {
+            request_id: 7,
+            error: None,
+            saves: vec![CloudSaveInfo {
+                uuid: SaveUuid::new("u").unwrap(),
+                file_id: FileId::new("f").unwrap(),
+                name: "slot".to_string(),
+                save_size: 3,
+                cover_size: 0,
+                summary: None,
+                extra: Some("x".to_string()),
+                playtime: Duration::from_secs(60),
+                created_time: UNIX_EPOCH + Duration::from_secs(1),
+                modified_time: UNIX_EPOCH + Duration::from_secs(2),
+            }],
+        });
+        let JsonEvent { value, data } = event_to_json(event);
+        assert!(data.is_none());
+        assert_eq!(value["eventId"], event_id::CLOUD_SAVE_LIST);
+        assert_eq!(value["requestId"], 7);
+        assert!(value["error"].is_null());
+        assert_eq!(value["saves"][0]["fileId"], "f");
+        assert_eq!(value["saves"][0]["modifiedTime"], 2000);
+    }
+
+    #[test]
+    fn test_cover_event_json() {
+        let event = TapEvent::CloudSaveGetCover(CloudSaveGetFileData {
+            request_id: 1,
+            error: None,
+            data: vec![0xFF, 0xD8, 0xFF, 0xE0],
+        });
+        let JsonEvent { value, data } = envelope_to_json(Envelope::new(event));
+        assert_eq!(value["contentType"], "image/jpeg");
+        assert!(value["receivedAt"].as_u64().unwrap() > 0);
+        assert!(value.get("data").is_none());
+        assert_eq!(data.unwrap(), [0xFF, 0xD8, 0xFF, 0xE0]);
+    }
+}
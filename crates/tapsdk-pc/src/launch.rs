@@ -0,0 +1,143 @@
+//! Command-line parameters the game was launched with
+//!
+//! TapTap does not document a launch-argument protocol for PC games, so
+//! instead of named fields for the store source or campaign, a deep link
+//! passed on the command line is found and its query parameters decoded.
+//! A `mygame://continue?from=store&campaign=spring` link yields the
+//! parameters `from` and `campaign`.
+
+use std::collections::BTreeMap;
+
+/// Parameters the game was launched with, see [`launch_params`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchParams {
+    /// Command-line arguments, without the executable path
+    pub args: Vec<String>,
+    /// The first argument that looks like a URL (`scheme://...`)
+    pub deep_link: Option<String>,
+    /// Decoded query parameters of the deep link
+    pub params: BTreeMap<String, String>,
+    /// Whether the TapTap client started the game, see
+    /// [`is_launched_by_taptap`](crate::is_launched_by_taptap)
+    pub launched_by_taptap: bool,
+}
+
+/// Get the parameters the game was launched with
+///
+/// Arguments that are not valid Unicode are converted lossily.
+///
+/// # Example
+/// ```no_run
+/// let params = tapsdk_pc::sdk::launch_params();
+/// if params.params.get("from").map(String::as_str) == Some("store") {
+///     // Skip the title screen
+/// }
+/// ```
+pub fn launch_params() -> LaunchParams {
+    let args = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned());
+    LaunchParams {
+        launched_by_taptap: crate::is_launched_by_taptap(),
+        ..parse(args)
+    }
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> LaunchParams {
+    let args: Vec<String> = args.into_iter().collect();
+    let deep_link = args.iter().find(|arg| is_url(arg)).cloned();
+    let params = deep_link
+        .as_deref()
+        .and_then(|link| link.split_once('?'))
+        .map(|(_, query)| parse_query(query.split('#').next().unwrap_or_default()))
+        .unwrap_or_default();
+    LaunchParams {
+        args,
+        deep_link,
+        params,
+        launched_by_taptap: false,
+    }
+}
+
+/// Whether `arg` starts with a URL scheme followed by `://`
+fn is_url(arg: &str) -> bool {
+    arg.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+fn parse_query(query: &str) -> BTreeMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Decode `+` and `%XX` escapes, keeping malformed escapes as they are
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => out.push(b' '),
+            (byte, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_deep_link() {
+        let params = parse(args(&[
+            "--fullscreen",
+            "mygame://continue?from=store&campaign=spring%20sale&tag=a+b#top",
+        ]));
+        assert_eq!(
+            params.deep_link.as_deref(),
+            Some("mygame://continue?from=store&campaign=spring%20sale&tag=a+b#top")
+        );
+        assert_eq!(params.params["from"], "store");
+        assert_eq!(params.params["campaign"], "spring sale");
+        assert_eq!(params.params["tag"], "a b");
+        assert_eq!(params.args.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_without_deep_link() {
+        let params = parse(args(&["--path=C://games", "save://"]));
+        assert_eq!(params.deep_link.as_deref(), Some("save://"));
+        assert!(params.params.is_empty());
+
+        let params = parse(args(&["-windowed"]));
+        assert_eq!(params.deep_link, None);
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%41%+1"), "%zzA% 1");
+    }
+}
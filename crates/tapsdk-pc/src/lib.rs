@@ -55,17 +55,63 @@
 
 pub mod callback;
 pub mod cloudsave;
+pub mod covers;
+pub mod crash;
+#[cfg(feature = "dev-config")]
+pub mod dev;
 pub mod dlc;
 pub mod error;
+pub mod heartbeat;
+#[cfg(feature = "json")]
+pub mod json;
+mod launch;
+pub mod library;
+mod listener;
+pub mod observer;
 pub mod ownership;
+pub mod playtime;
+#[cfg(windows)]
+mod process;
+mod pump;
+#[cfg(windows)]
+mod registry;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod rotation;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub mod runtime;
 pub mod sdk;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(all(feature = "dev-sim", not(windows)))]
+pub mod sim;
+pub mod staging;
+mod state;
+#[cfg(feature = "futures")]
+mod stream;
+pub mod subscription;
 pub mod user;
+pub mod version;
+pub mod watchdog;
+
+// SDK entry points, served by the simulator in `dev-sim` builds
+#[cfg(all(feature = "dev-sim", not(windows)))]
+use sim::ffi;
+#[cfg(not(all(feature = "dev-sim", not(windows))))]
+use tapsdk_pc_sys as ffi;
 
 // Re-export commonly used types at the crate root
 pub use callback::TapEvent;
-pub use cloudsave::CloudSave;
+pub use cloudsave::{CloudSave, FileId, SaveUuid};
 pub use error::{Result, TapSdkError};
-pub use sdk::{is_initialized, restart_app_if_necessary, TapSdk};
+pub use observer::SdkObserver;
+pub use playtime::PlaytimeTracker;
+pub use sdk::{
+    acquire_single_instance, is_initialized, is_launched_by_taptap, platform_info,
+    restart_app_if_necessary, system_state, PlatformInfo, SingleInstance, TapSdk, TapSdkConfig,
+};
+pub use subscription::Subscription;
+pub use version::{version, VersionInfo};
 
 // Re-export the sys crate for advanced users
 pub use tapsdk_pc_sys as sys;
@@ -0,0 +1,609 @@
+//! Loading and authenticity checks for `taptap_api.dll`
+//!
+//! `taptap_api.dll` is normally linked at load time, so it is mapped, and its
+//! `DllMain` has run, before any code of the game does. Checking the mapped
+//! file with [`verify_library`] (or through
+//! [`TapSdkConfig::verify_library`](crate::TapSdkConfig::verify_library))
+//! before initializing keeps the SDK from being used with a replaced DLL, but
+//! cannot keep the DLL from running.
+//!
+//! Builds that delay-load the DLL, like the Node.js addon, map it with
+//! [`load`] instead, which can also probe versioned or renamed files. Given a
+//! [`LibraryTrust`], it checks each file before mapping it, so a replaced DLL
+//! never runs at all.
+
+use std::path::PathBuf;
+
+use crate::error::Result;
+
+/// File names of the SDK library tried by [`load`], in order
+pub const DEFAULT_NAMES: &[&str] = &["taptap_api.dll"];
+
+/// The SDK library mapped by [`load`]
+#[cfg(windows)]
+static LOADED: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Load the SDK library by the first of `names` that can be found
+///
+/// Each name is tried in each of `dirs`, then along the standard DLL search
+/// order. The file that loads is what [`loaded_path`], [`verify_library`] and
+/// [`version`](crate::version()) refer to from then on; later calls return it
+/// without probing again.
+///
+/// Only useful when `taptap_api.dll` is delay-loaded: the loader then has to
+/// resolve imports of `taptap_api.dll` to the returned file, e.g. from a
+/// `__pfnDliNotifyHook2` hook.
+///
+/// Unless `trust` is empty, only files found in `dirs` or the directories
+/// [`LibraryMissing`](crate::TapSdkError::LibraryMissing) reports are
+/// considered, and the first one found is checked against `trust` before it
+/// is mapped. The file is kept open without write sharing from the check
+/// until it is mapped, so it cannot be replaced in between.
+///
+/// # Returns
+/// The path of the loaded file, or `None` on platforms other than Windows
+///
+/// # Errors
+/// [`TapSdkError::LibraryMissing`](crate::TapSdkError::LibraryMissing) with
+/// the searched paths if no candidate loads, or
+/// [`TapSdkError::UntrustedLibrary`](crate::TapSdkError::UntrustedLibrary) if
+/// the file found fails the check; it is not loaded then.
+pub fn load(dirs: &[PathBuf], names: &[&str], trust: &LibraryTrust) -> Result<Option<PathBuf>> {
+    #[cfg(windows)]
+    {
+        match loader::load(dirs, names, trust) {
+            Ok(path) => {
+                log::debug!(path:? = path; "Loaded SDK library");
+                Ok(Some(path))
+            }
+            Err(e) => {
+                log::warn!("{}", e);
+                Err(e)
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (dirs, names, trust);
+        Ok(None)
+    }
+}
+
+/// Path of the SDK library in use, if it is loaded
+///
+/// The file mapped by [`load`], or else the load-time linked
+/// `taptap_api.dll`. Always `None` on platforms other than Windows.
+pub fn loaded_path() -> Option<PathBuf> {
+    #[cfg(windows)]
+    {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        let path = dll_path()?;
+        Some(PathBuf::from(OsString::from_wide(&path[..path.len() - 1])))
+    }
+    #[cfg(not(windows))]
+    {
+        None
+    }
+}
+
+/// Requirements the loaded `taptap_api.dll` must meet
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::library::{self, LibraryTrust};
+///
+/// let trust = LibraryTrust::default().publisher("Example Publisher Ltd.");
+/// library::verify_library(&trust).expect("untrusted taptap_api.dll");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryTrust {
+    /// Require a valid Authenticode signature from this signer
+    ///
+    /// Compared with the display name of the signing certificate's subject.
+    pub publisher: Option<String>,
+    /// Accepted SHA-256 hashes of the DLL file (empty accepts any file)
+    pub sha256: Vec<[u8; 32]>,
+}
+
+impl LibraryTrust {
+    /// Require a valid Authenticode signature from `publisher`
+    pub fn publisher(mut self, publisher: impl Into<String>) -> Self {
+        self.publisher = Some(publisher.into());
+        self
+    }
+
+    /// Accept a DLL file with this SHA-256 hash
+    ///
+    /// Once any hash is added, files with other hashes are rejected.
+    pub fn allow_sha256(mut self, hash: [u8; 32]) -> Self {
+        self.sha256.push(hash);
+        self
+    }
+
+    /// Whether any file is accepted
+    pub fn is_empty(&self) -> bool {
+        self.publisher.is_none() && self.sha256.is_empty()
+    }
+}
+
+/// Check the loaded `taptap_api.dll` against `trust`
+///
+/// Always succeeds on platforms other than Windows, where no DLL is loaded.
+///
+/// # Errors
+/// [`TapSdkError::UntrustedLibrary`] if the DLL cannot be found, is not
+/// signed by the required publisher, or its hash is not in the list.
+pub fn verify_library(trust: &LibraryTrust) -> Result<()> {
+    if trust.is_empty() {
+        return Ok(());
+    }
+    let result = platform::verify(trust);
+    if let Err(e) = &result {
+        log::error!("{}", e);
+    }
+    result
+}
+
+/// Full path of the loaded SDK library, nul-terminated
+#[cfg(windows)]
+pub(crate) fn dll_path() -> Option<Vec<u16>> {
+    if let Some(path) = LOADED.get() {
+        return Some(platform::wide(path));
+    }
+    let module = unsafe { loader::GetModuleHandleW(platform::wide("taptap_api.dll").as_ptr()) };
+    loader::module_path(module)
+}
+
+/// Check that the SDK library is still mapped into the process
+#[cfg(windows)]
+pub(crate) fn is_mapped() -> bool {
+    let name = match LOADED.get() {
+        Some(path) => platform::wide(path),
+        None => platform::wide("taptap_api.dll"),
+    };
+    !unsafe { loader::GetModuleHandleW(name.as_ptr()) }.is_null()
+}
+
+#[cfg(windows)]
+mod loader {
+    use std::env;
+    use std::ffi::{c_void, OsStr};
+    use std::fs::File;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::ptr;
+
+    use super::platform::{self, wide};
+    use super::{LibraryTrust, LOADED};
+    use crate::error::{Result, TapSdkError};
+
+    const LOAD_WITH_ALTERED_SEARCH_PATH: u32 = 0x8;
+    const FILE_SHARE_READ: u32 = 0x1;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub(super) fn GetModuleHandleW(module_name: *const u16) -> *mut c_void;
+        fn GetModuleFileNameW(module: *mut c_void, filename: *mut u16, size: u32) -> u32;
+        fn LoadLibraryExW(filename: *const u16, file: *mut c_void, flags: u32) -> *mut c_void;
+    }
+
+    /// Full path of `module`, nul-terminated
+    pub(super) fn module_path(module: *mut c_void) -> Option<Vec<u16>> {
+        if module.is_null() {
+            return None;
+        }
+        let mut path = vec![0u16; 32768];
+        let len = unsafe { GetModuleFileNameW(module, path.as_mut_ptr(), path.len() as u32) };
+        if len == 0 || len as usize >= path.len() {
+            return None;
+        }
+        path.truncate(len as usize);
+        path.push(0);
+        Some(path)
+    }
+
+    /// Load `file` and remember it as the SDK library
+    fn try_load(file: &OsStr, flags: u32) -> Option<PathBuf> {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+
+        let module = unsafe { LoadLibraryExW(wide(file).as_ptr(), ptr::null_mut(), flags) };
+        let path = module_path(module)?;
+        let path = PathBuf::from(OsString::from_wide(&path[..path.len() - 1]));
+        Some(LOADED.get_or_init(|| path).clone())
+    }
+
+    /// Check `file` against `trust`, then load it
+    ///
+    /// The file stays open without write or delete sharing until it is
+    /// mapped, so what gets loaded is what was checked.
+    fn load_trusted(file: &Path, trust: &LibraryTrust) -> Result<Option<PathBuf>> {
+        let Ok(_guard) = File::options()
+            .read(true)
+            .share_mode(FILE_SHARE_READ)
+            .open(file)
+        else {
+            return Ok(None);
+        };
+        platform::verify_file(&wide(file), trust)?;
+        Ok(try_load(file.as_os_str(), LOAD_WITH_ALTERED_SEARCH_PATH))
+    }
+
+    pub(super) fn load(dirs: &[PathBuf], names: &[&str], trust: &LibraryTrust) -> Result<PathBuf> {
+        if let Some(path) = LOADED.get() {
+            return Ok(path.clone());
+        }
+
+        // A full path with altered search order also finds the DLL's own
+        // dependencies next to it
+        let candidates = candidates(dirs, names);
+        if trust.is_empty() {
+            for dir in dirs {
+                for name in names {
+                    let file = dir.join(name);
+                    if let Some(path) = try_load(file.as_os_str(), LOAD_WITH_ALTERED_SEARCH_PATH) {
+                        return Ok(path);
+                    }
+                }
+            }
+            for name in names {
+                if let Some(path) = try_load(OsStr::new(name), 0) {
+                    return Ok(path);
+                }
+            }
+        } else {
+            // The loader's own search can't be checked beforehand, so only
+            // files found by full path are loaded
+            for file in candidates.iter().filter(|file| file.is_file()) {
+                if let Some(path) = load_trusted(file, trust)? {
+                    return Ok(path);
+                }
+            }
+        }
+        Err(TapSdkError::LibraryMissing {
+            searched: candidates,
+        })
+    }
+
+    /// Every name in `dirs`, then in the directories of the standard search
+    /// order apps control
+    fn candidates(dirs: &[PathBuf], names: &[&str]) -> Vec<PathBuf> {
+        let search_dirs = dirs
+            .iter()
+            .cloned()
+            .chain(
+                env::current_exe()
+                    .ok()
+                    .and_then(|exe| exe.parent().map(PathBuf::from)),
+            )
+            .chain(env::current_dir().ok())
+            .chain(
+                env::var_os("PATH")
+                    .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            );
+        let mut candidates = Vec::new();
+        for dir in search_dirs {
+            for name in names {
+                let file = dir.join(name);
+                if !candidates.contains(&file) {
+                    candidates.push(file);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::{c_void, OsStr, OsString};
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::ptr;
+
+    use super::{dll_path, LibraryTrust};
+    use crate::error::{Result, TapSdkError};
+
+    fn untrusted(reason: impl Into<String>) -> TapSdkError {
+        TapSdkError::UntrustedLibrary(reason.into())
+    }
+
+    pub(super) fn wide(s: impl AsRef<OsStr>) -> Vec<u16> {
+        s.as_ref().encode_wide().chain(Some(0)).collect()
+    }
+
+    #[repr(C)]
+    struct Guid {
+        data1: u32,
+        data2: u16,
+        data3: u16,
+        data4: [u8; 8],
+    }
+
+    /// WINTRUST_ACTION_GENERIC_VERIFY_V2
+    const GENERIC_VERIFY_V2: Guid = Guid {
+        data1: 0x00AA_C56B,
+        data2: 0xCD44,
+        data3: 0x11D0,
+        data4: [0x8C, 0xC2, 0x00, 0xC0, 0x4F, 0xC2, 0x95, 0xEE],
+    };
+
+    #[repr(C)]
+    struct WintrustFileInfo {
+        struct_size: u32,
+        file_path: *const u16,
+        file: *mut c_void,
+        known_subject: *const Guid,
+    }
+
+    #[repr(C)]
+    struct WintrustData {
+        struct_size: u32,
+        policy_callback_data: *mut c_void,
+        sip_client_data: *mut c_void,
+        ui_choice: u32,
+        revocation_checks: u32,
+        union_choice: u32,
+        file: *mut WintrustFileInfo,
+        state_action: u32,
+        state_data: *mut c_void,
+        url_reference: *mut u16,
+        prov_flags: u32,
+        ui_context: u32,
+        signature_settings: *mut c_void,
+    }
+
+    const WTD_UI_NONE: u32 = 2;
+    const WTD_REVOKE_NONE: u32 = 0;
+    const WTD_CHOICE_FILE: u32 = 1;
+    const WTD_STATEACTION_VERIFY: u32 = 1;
+    const WTD_STATEACTION_CLOSE: u32 = 2;
+
+    // Leading fields of CRYPT_PROVIDER_SGNR; only the chain is read
+    #[repr(C)]
+    struct ProviderSigner {
+        struct_size: u32,
+        verify_as_of: [u32; 2],
+        cert_count: u32,
+        certs: *const ProviderCert,
+    }
+
+    // Leading fields of CRYPT_PROVIDER_CERT
+    #[repr(C)]
+    struct ProviderCert {
+        struct_size: u32,
+        cert: *const c_void,
+    }
+
+    const CERT_NAME_SIMPLE_DISPLAY_TYPE: u32 = 4;
+
+    #[link(name = "wintrust")]
+    extern "system" {
+        fn WinVerifyTrust(window: *mut c_void, action: *const Guid, data: *mut c_void) -> i32;
+        fn WTHelperProvDataFromStateData(state_data: *mut c_void) -> *mut c_void;
+        fn WTHelperGetProvSignerFromChain(
+            prov_data: *mut c_void,
+            signer: u32,
+            counter_signer: i32,
+            counter_signer_index: u32,
+        ) -> *const ProviderSigner;
+    }
+
+    #[link(name = "crypt32")]
+    extern "system" {
+        fn CertGetNameStringW(
+            cert: *const c_void,
+            name_type: u32,
+            flags: u32,
+            type_para: *const c_void,
+            name: *mut u16,
+            name_len: u32,
+        ) -> u32;
+    }
+
+    #[link(name = "bcrypt")]
+    extern "system" {
+        fn BCryptOpenAlgorithmProvider(
+            algorithm: *mut *mut c_void,
+            algorithm_id: *const u16,
+            implementation: *const u16,
+            flags: u32,
+        ) -> i32;
+        fn BCryptHash(
+            algorithm: *mut c_void,
+            secret: *const u8,
+            secret_len: u32,
+            input: *const u8,
+            input_len: u32,
+            output: *mut u8,
+            output_len: u32,
+        ) -> i32;
+        fn BCryptCloseAlgorithmProvider(algorithm: *mut c_void, flags: u32) -> i32;
+    }
+
+    pub(super) fn verify(trust: &LibraryTrust) -> Result<()> {
+        let path = dll_path().ok_or_else(|| untrusted("taptap_api.dll is not loaded"))?;
+        verify_file(&path, trust)
+    }
+
+    /// Check the file at the nul-terminated `path` against `trust`
+    pub(super) fn verify_file(path: &[u16], trust: &LibraryTrust) -> Result<()> {
+        if let Some(publisher) = &trust.publisher {
+            let signer = signer(path)?;
+            if signer != *publisher {
+                return Err(untrusted(format!(
+                    "taptap_api.dll is signed by {:?}, expected {:?}",
+                    signer, publisher
+                )));
+            }
+        }
+
+        if !trust.sha256.is_empty() {
+            let file = OsString::from_wide(&path[..path.len() - 1]);
+            let contents = std::fs::read(file)?;
+            let hash = sha256(&contents)?;
+            if !trust.sha256.contains(&hash) {
+                return Err(untrusted("taptap_api.dll does not match any allowed hash"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify the Authenticode signature and get the signer's display name
+    fn signer(path: &[u16]) -> Result<String> {
+        let mut file = WintrustFileInfo {
+            struct_size: std::mem::size_of::<WintrustFileInfo>() as u32,
+            file_path: path.as_ptr(),
+            file: ptr::null_mut(),
+            known_subject: ptr::null(),
+        };
+        let mut data = WintrustData {
+            struct_size: std::mem::size_of::<WintrustData>() as u32,
+            policy_callback_data: ptr::null_mut(),
+            sip_client_data: ptr::null_mut(),
+            ui_choice: WTD_UI_NONE,
+            revocation_checks: WTD_REVOKE_NONE,
+            union_choice: WTD_CHOICE_FILE,
+            file: &mut file,
+            state_action: WTD_STATEACTION_VERIFY,
+            state_data: ptr::null_mut(),
+            url_reference: ptr::null_mut(),
+            prov_flags: 0,
+            ui_context: 0,
+            signature_settings: ptr::null_mut(),
+        };
+
+        let action = &GENERIC_VERIFY_V2 as *const Guid;
+        let status = unsafe {
+            WinVerifyTrust(
+                ptr::null_mut(),
+                action,
+                (&mut data as *mut WintrustData).cast(),
+            )
+        };
+        let name = if status == 0 {
+            unsafe { signer_name(data.state_data) }
+        } else {
+            None
+        };
+
+        // Release the state kept by the verify action
+        data.state_action = WTD_STATEACTION_CLOSE;
+        unsafe {
+            WinVerifyTrust(
+                ptr::null_mut(),
+                action,
+                (&mut data as *mut WintrustData).cast(),
+            )
+        };
+
+        if status != 0 {
+            return Err(untrusted(format!(
+                "taptap_api.dll has no valid signature (0x{:08X})",
+                status as u32
+            )));
+        }
+        name.ok_or_else(|| untrusted("failed to read the signer of taptap_api.dll"))
+    }
+
+    /// # Safety
+    /// `state_data` must come from a successful verify action not yet closed
+    unsafe fn signer_name(state_data: *mut c_void) -> Option<String> {
+        let prov_data = WTHelperProvDataFromStateData(state_data);
+        if prov_data.is_null() {
+            return None;
+        }
+        let signer = WTHelperGetProvSignerFromChain(prov_data, 0, 0, 0).as_ref()?;
+        if signer.cert_count == 0 || signer.certs.is_null() {
+            return None;
+        }
+        // The first certificate in the chain is the signer's own
+        let cert = (*signer.certs).cert;
+        if cert.is_null() {
+            return None;
+        }
+
+        let mut name = [0u16; 256];
+        let len = CertGetNameStringW(
+            cert,
+            CERT_NAME_SIMPLE_DISPLAY_TYPE,
+            0,
+            ptr::null(),
+            name.as_mut_ptr(),
+            name.len() as u32,
+        );
+        // The length includes the terminating nul
+        if len <= 1 {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&name[..len as usize - 1]))
+    }
+
+    fn sha256(data: &[u8]) -> Result<[u8; 32]> {
+        let mut algorithm = ptr::null_mut();
+        let status = unsafe {
+            BCryptOpenAlgorithmProvider(&mut algorithm, wide("SHA256").as_ptr(), ptr::null(), 0)
+        };
+        if status != 0 {
+            return Err(untrusted("SHA-256 is not available"));
+        }
+
+        let mut hash = [0u8; 32];
+        let status = u32::try_from(data.len()).map(|len| unsafe {
+            BCryptHash(
+                algorithm,
+                ptr::null(),
+                0,
+                data.as_ptr(),
+                len,
+                hash.as_mut_ptr(),
+                hash.len() as u32,
+            )
+        });
+        unsafe { BCryptCloseAlgorithmProvider(algorithm, 0) };
+
+        match status {
+            Ok(0) => Ok(hash),
+            _ => Err(untrusted("failed to hash taptap_api.dll")),
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use super::LibraryTrust;
+    use crate::error::Result;
+
+    pub(super) fn verify(_trust: &LibraryTrust) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_trust_accepts_anything() {
+        assert!(verify_library(&LibraryTrust::default()).is_ok());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_no_dll_outside_windows() {
+        let trust = LibraryTrust::default().allow_sha256([0; 32]);
+        assert!(verify_library(&trust).is_ok());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_load_outside_windows() {
+        assert_eq!(
+            load(&[], DEFAULT_NAMES, &LibraryTrust::default()).unwrap(),
+            None
+        );
+        assert_eq!(loaded_path(), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Copies of every event for consumers besides the one polling the queue
+//!
+//! [`poll_events`](crate::callback::poll_events) takes events out of the
+//! queue, so only one caller sees each of them. An [`EventListener`] gets a
+//! buffer of its own that every event is copied into as it is received,
+//! which lets diagnostics, a Rust-side listener and the bindings' own pump
+//! observe the same events.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+use crate::callback::{recover, Envelope, DEFAULT_QUEUE_CAPACITY};
+
+/// Events copied to one listener and not yet taken
+#[derive(Default)]
+struct Buffer {
+    events: Mutex<VecDeque<Envelope>>,
+    missed: AtomicU64,
+}
+
+impl Buffer {
+    /// Append an event, dropping the oldest one when full
+    fn push(&self, envelope: &Envelope) {
+        let mut events = recover(self.events.lock());
+        if events.len() >= DEFAULT_QUEUE_CAPACITY {
+            events.pop_front();
+            self.missed.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(envelope.clone());
+    }
+}
+
+impl std::fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Buffer")
+            .field("len", &recover(self.events.lock()).len())
+            .field("missed", &self.missed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Buffers of the live listeners
+static LISTENERS: RwLock<Vec<Weak<Buffer>>> = RwLock::new(Vec::new());
+
+/// Receives a copy of every event, see [`listen`]
+///
+/// Dropping the listener stops the copying.
+#[derive(Debug)]
+pub struct EventListener {
+    buffer: Arc<Buffer>,
+}
+
+/// Get a listener receiving a copy of every event from now on
+///
+/// Events still reach [`poll_events`](crate::callback::poll_events) and every
+/// other listener as usual. A listener does not run the SDK callbacks
+/// itself: it sees the events received whenever something else polls, such
+/// as the game loop or the Node.js bindings. A listener that falls more than
+/// [`DEFAULT_QUEUE_CAPACITY`] events behind loses the oldest ones, counted by
+/// [`missed`](EventListener::missed).
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::{callback, TapSdk};
+///
+/// let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+/// let diagnostics = callback::listen();
+/// loop {
+///     // The game loop takes events as usual
+///     for event in sdk.run_callbacks() {
+///         // ...
+///     }
+///     // And the diagnostics overlay still sees every one of them
+///     for envelope in diagnostics.poll() {
+///         println!("{:?}", envelope.event);
+///     }
+/// #   break;
+/// }
+/// ```
+pub fn listen() -> EventListener {
+    let buffer = Arc::new(Buffer::default());
+    let mut listeners = recover(LISTENERS.write());
+    listeners.retain(|listener| listener.strong_count() > 0);
+    listeners.push(Arc::downgrade(&buffer));
+    EventListener { buffer }
+}
+
+impl EventListener {
+    /// Take the events received since the last call, oldest first
+    pub fn poll(&self) -> Vec<Envelope> {
+        recover(self.buffer.events.lock()).drain(..).collect()
+    }
+
+    /// Number of events lost because the listener fell behind
+    pub fn missed(&self) -> u64 {
+        self.buffer.missed.load(Ordering::Relaxed)
+    }
+}
+
+/// Copy an event to every live listener
+pub(crate) fn fan_out(envelope: &Envelope) {
+    let listeners = recover(LISTENERS.read());
+    for buffer in listeners.iter().filter_map(Weak::upgrade) {
+        buffer.push(envelope);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callback::TapEvent;
+
+    fn ids(listener: &EventListener, id: u32) -> usize {
+        listener
+            .poll()
+            .iter()
+            .filter(|envelope| envelope.event.event_id() == id)
+            .count()
+    }
+
+    #[test]
+    fn test_every_listener_gets_every_event() {
+        const ID: u32 = 0x4680;
+        let first = listen();
+        let second = listen();
+        fan_out(&TapEvent::Unknown { event_id: ID }.into());
+
+        assert_eq!(ids(&first, ID), 1);
+        assert_eq!(ids(&second, ID), 1);
+        // Taken once per listener
+        assert_eq!(ids(&first, ID), 0);
+
+        drop(second);
+        fan_out(&TapEvent::Unknown { event_id: ID }.into());
+        assert_eq!(ids(&first, ID), 1);
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest_when_full() {
+        let buffer = Buffer::default();
+        for id in 0..DEFAULT_QUEUE_CAPACITY as u32 + 2 {
+            buffer.push(&TapEvent::Unknown { event_id: id }.into());
+        }
+        assert_eq!(buffer.missed.load(Ordering::Relaxed), 2);
+        let events = recover(buffer.events.lock());
+        assert_eq!(events.len(), DEFAULT_QUEUE_CAPACITY);
+        assert_eq!(events[0].event.event_id(), 2);
+    }
+}
@@ -0,0 +1,120 @@
+//! Observability hooks for TapTap PC SDK
+//!
+//! Install an [`SdkObserver`] with [`TapSdk::set_observer`](crate::TapSdk::set_observer)
+//! to feed SDK health metrics (request counts, event throughput, failures)
+//! into your own telemetry pipeline.
+
+use std::sync::{Arc, RwLock};
+
+use crate::callback::{recover, TapEvent};
+use crate::error::TapSdkError;
+
+/// Asynchronous operations that can be started through the SDK
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `user::authorize`
+    Authorize,
+    /// `CloudSave::list`
+    CloudSaveList,
+    /// `CloudSave::create`
+    CloudSaveCreate,
+    /// `CloudSave::update`
+    CloudSaveUpdate,
+    /// `CloudSave::delete`
+    CloudSaveDelete,
+    /// `CloudSave::get_data`
+    CloudSaveGetData,
+    /// `CloudSave::get_cover`
+    CloudSaveGetCover,
+}
+
+/// Receives notifications about SDK activity
+///
+/// All methods have empty default implementations, so implementors only
+/// need to override the hooks they care about. Hooks are invoked
+/// synchronously on the thread that triggered them and should return quickly.
+pub trait SdkObserver: Send + Sync + 'static {
+    /// Called after an asynchronous request was accepted by the SDK
+    ///
+    /// `request_id` is `None` for operations without a request ID (authorization).
+    fn on_request_started(&self, operation: Operation, request_id: Option<i64>) {
+        let _ = (operation, request_id);
+    }
+
+    /// Called for every event received from the SDK, before it is queued
+    fn on_event_received(&self, event: &TapEvent) {
+        let _ = event;
+    }
+
+    /// Called when an SDK call fails synchronously
+    fn on_error(&self, error: &TapSdkError) {
+        let _ = error;
+    }
+}
+
+/// Currently installed observer
+static OBSERVER: RwLock<Option<Arc<dyn SdkObserver>>> = RwLock::new(None);
+
+/// Install an observer, replacing any previous one
+pub(crate) fn set(observer: Arc<dyn SdkObserver>) {
+    *recover(OBSERVER.write()) = Some(observer);
+}
+
+/// Remove the installed observer
+pub(crate) fn clear() {
+    *recover(OBSERVER.write()) = None;
+}
+
+/// Get the installed observer, if any
+fn current() -> Option<Arc<dyn SdkObserver>> {
+    recover(OBSERVER.read()).clone()
+}
+
+/// Notify the observer that a request was started
+pub(crate) fn request_started(operation: Operation, request_id: Option<i64>) {
+    if let Some(observer) = current() {
+        observer.on_request_started(operation, request_id);
+    }
+}
+
+/// Notify the observer that an event was received
+pub(crate) fn event_received(event: &TapEvent) {
+    if let Some(observer) = current() {
+        observer.on_event_received(event);
+    }
+}
+
+/// Notify the observer about an error and hand the error back to the caller
+pub(crate) fn error(error: TapSdkError) -> TapSdkError {
+    if let Some(observer) = current() {
+        observer.on_error(&error);
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counter(Arc<AtomicUsize>);
+
+    impl SdkObserver for Counter {
+        fn on_request_started(&self, _operation: Operation, _request_id: Option<i64>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_observer_notified_until_cleared() {
+        let count = Arc::new(AtomicUsize::new(0));
+        set(Arc::new(Counter(count.clone())));
+
+        request_started(Operation::CloudSaveList, Some(1));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        clear();
+        request_started(Operation::CloudSaveList, Some(2));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,7 +1,19 @@
 //! Game ownership functionality
 
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::callback::TapEvent;
+use crate::ffi;
 use crate::sdk::is_initialized;
 
+/// No `GamePlayableStatusChanged` event received since initialization
+const UNREPORTED: u8 = 0;
+const NOT_PLAYABLE: u8 = 1;
+const PLAYABLE: u8 = 2;
+
+/// State of the last `GamePlayableStatusChanged` event
+static GAME_PLAYABLE: AtomicU8 = AtomicU8::new(UNREPORTED);
+
 /// Check if the user owns the current game
 ///
 /// # Returns
@@ -14,5 +26,74 @@ pub fn is_game_owned() -> bool {
         return false;
     }
 
-    unsafe { tapsdk_pc_sys::TapApps_IsOwned() }
+    unsafe { ffi::TapApps_IsOwned() }
+}
+
+/// Check if the user can currently play the game
+///
+/// The SDK has no query for this, only `GamePlayableStatusChanged` events.
+/// This returns the state of the last such event received since
+/// initialization, and [`is_game_owned`] until the first one arrives, so
+/// the game can gate its main menu at startup.
+///
+/// # Note
+/// This will return `false` if the SDK is not initialized.
+pub fn is_game_playable() -> bool {
+    if !is_initialized() {
+        return false;
+    }
+
+    playable(GAME_PLAYABLE.load(Ordering::SeqCst), is_game_owned)
+}
+
+fn playable(state: u8, owned: impl FnOnce() -> bool) -> bool {
+    match state {
+        PLAYABLE => true,
+        NOT_PLAYABLE => false,
+        _ => owned(),
+    }
+}
+
+/// The state reported by a `GamePlayableStatusChanged` event
+fn reported_state(event: &TapEvent) -> Option<u8> {
+    match event {
+        TapEvent::GamePlayableStatusChanged(data) if data.is_playable => Some(PLAYABLE),
+        TapEvent::GamePlayableStatusChanged(_) => Some(NOT_PLAYABLE),
+        _ => None,
+    }
+}
+
+/// Remember the state reported by a `GamePlayableStatusChanged` event
+pub(crate) fn record_event(event: &TapEvent) {
+    if let Some(state) = reported_state(event) {
+        GAME_PLAYABLE.store(state, Ordering::SeqCst);
+    }
+}
+
+/// Forget the reported state, when a new session starts
+pub(crate) fn reset() {
+    GAME_PLAYABLE.store(UNREPORTED, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callback::GamePlayableStatusChangedData;
+
+    fn changed(is_playable: bool) -> TapEvent {
+        TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData { is_playable })
+    }
+
+    #[test]
+    fn test_playable_prefers_reported_state() {
+        assert!(playable(UNREPORTED, || true));
+        assert!(!playable(UNREPORTED, || false));
+
+        let not_playable = reported_state(&changed(false)).unwrap();
+        assert!(!playable(not_playable, || true));
+        let playable_state = reported_state(&changed(true)).unwrap();
+        assert!(playable(playable_state, || false));
+
+        assert_eq!(reported_state(&TapEvent::Unknown { event_id: 4001 }), None);
+    }
 }
@@ -0,0 +1,140 @@
+//! Session playtime tracking for cloud saves
+//!
+//! A [`PlaytimeTracker`] accumulates time while the game is being played,
+//! so every save reports a consistent `playtime` without hand-maintained
+//! counters.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tapsdk_pc::cloudsave::CreateSaveRequest;
+//! use tapsdk_pc::playtime::PlaytimeTracker;
+//!
+//! // Continue from the playtime stored in the loaded save
+//! let tracker = PlaytimeTracker::starting_at(Duration::from_secs(3600));
+//!
+//! // On focus loss / regain
+//! tracker.pause();
+//! tracker.resume();
+//!
+//! let request = CreateSaveRequest {
+//!     name: "slot1".to_string(),
+//!     summary: "Chapter 2".to_string(),
+//!     extra: None,
+//!     playtime: 0,
+//!     data_file_path: std::path::Path::new("save.dat").into(),
+//!     cover_file_path: None,
+//! }
+//! .with_playtime(&tracker);
+//! ```
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::callback::recover;
+
+/// Accumulates playtime, excluding paused periods
+///
+/// All methods take `&self`, so a tracker can be shared between the code
+/// handling focus changes and the code writing saves.
+#[derive(Debug)]
+pub struct PlaytimeTracker {
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Time accumulated up to the last pause
+    accumulated: Duration,
+    /// When the current running period started, `None` while paused
+    resumed_at: Option<Instant>,
+}
+
+impl PlaytimeTracker {
+    /// Start tracking from zero
+    pub fn new() -> Self {
+        Self::starting_at(Duration::ZERO)
+    }
+
+    /// Start tracking from previously accumulated playtime
+    ///
+    /// Use this with the `playtime` of the save being continued.
+    pub fn starting_at(playtime: Duration) -> Self {
+        PlaytimeTracker {
+            state: Mutex::new(State {
+                accumulated: playtime,
+                resumed_at: Some(Instant::now()),
+            }),
+        }
+    }
+
+    /// Stop accumulating time, e.g. when the window loses focus
+    ///
+    /// Does nothing if already paused.
+    pub fn pause(&self) {
+        let mut state = recover(self.state.lock());
+        if let Some(resumed_at) = state.resumed_at.take() {
+            state.accumulated += resumed_at.elapsed();
+        }
+    }
+
+    /// Start accumulating time again
+    ///
+    /// Does nothing if not paused.
+    pub fn resume(&self) {
+        let mut state = recover(self.state.lock());
+        if state.resumed_at.is_none() {
+            state.resumed_at = Some(Instant::now());
+        }
+    }
+
+    /// Check whether the tracker is paused
+    pub fn is_paused(&self) -> bool {
+        recover(self.state.lock()).resumed_at.is_none()
+    }
+
+    /// Total playtime so far
+    pub fn elapsed(&self) -> Duration {
+        let state = recover(self.state.lock());
+        state.accumulated + state.resumed_at.map_or(Duration::ZERO, |at| at.elapsed())
+    }
+
+    /// Total playtime in whole seconds, as used by cloud save requests
+    pub fn seconds(&self) -> u64 {
+        self.elapsed().as_secs()
+    }
+}
+
+impl Default for PlaytimeTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_paused_tracker_does_not_advance() {
+        let tracker = PlaytimeTracker::starting_at(Duration::from_secs(90));
+        tracker.pause();
+        assert!(tracker.is_paused());
+
+        let paused = tracker.elapsed();
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(tracker.elapsed(), paused);
+        assert_eq!(tracker.seconds(), 90);
+
+        tracker.resume();
+        assert!(!tracker.is_paused());
+        thread::sleep(Duration::from_millis(5));
+        assert!(tracker.elapsed() > paused);
+    }
+
+    #[test]
+    fn test_seconds_not_truncated() {
+        let tracker = PlaytimeTracker::starting_at(Duration::from_secs(u64::from(u32::MAX) + 1));
+        assert_eq!(tracker.seconds(), u64::from(u32::MAX) + 1);
+    }
+}
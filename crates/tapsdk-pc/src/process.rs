@@ -0,0 +1,147 @@
+//! Minimal read-only access to the process list
+
+use std::ffi::{c_void, OsString};
+use std::io;
+use std::mem;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::ptr;
+
+const TH32CS_SNAPPROCESS: u32 = 0x0000_0002;
+const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+const INVALID_HANDLE_VALUE: *mut c_void = -1isize as *mut c_void;
+const MAX_PATH: usize = 260;
+const ERROR_ALREADY_EXISTS: u32 = 183;
+
+/// PROCESSENTRY32W
+#[repr(C)]
+struct ProcessEntry {
+    size: u32,
+    usage: u32,
+    process_id: u32,
+    default_heap_id: usize,
+    module_id: u32,
+    threads: u32,
+    parent_process_id: u32,
+    priority_class_base: i32,
+    flags: u32,
+    exe_file: [u16; MAX_PATH],
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetCurrentProcessId() -> u32;
+    fn CreateToolhelp32Snapshot(flags: u32, process_id: u32) -> *mut c_void;
+    fn Process32FirstW(snapshot: *mut c_void, entry: *mut ProcessEntry) -> i32;
+    fn Process32NextW(snapshot: *mut c_void, entry: *mut ProcessEntry) -> i32;
+    fn OpenProcess(access: u32, inherit: i32, process_id: u32) -> *mut c_void;
+    fn QueryFullProcessImageNameW(
+        process: *mut c_void,
+        flags: u32,
+        name: *mut u16,
+        size: *mut u32,
+    ) -> i32;
+    fn CloseHandle(handle: *mut c_void) -> i32;
+    fn CreateMutexW(attributes: *const c_void, initial_owner: i32, name: *const u16)
+        -> *mut c_void;
+    fn GetLastError() -> u32;
+}
+
+/// Find the first running process for which `f` returns a value
+fn find_process<T>(mut f: impl FnMut(&ProcessEntry) -> Option<T>) -> Option<T> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return None;
+    }
+
+    let mut entry: ProcessEntry = unsafe { mem::zeroed() };
+    entry.size = mem::size_of::<ProcessEntry>() as u32;
+    let mut found = None;
+    let mut more = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+    while more {
+        found = f(&entry);
+        if found.is_some() {
+            break;
+        }
+        more = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+    }
+    unsafe { CloseHandle(snapshot) };
+    found
+}
+
+/// ID of the process that started this one
+fn parent_process_id() -> Option<u32> {
+    let id = unsafe { GetCurrentProcessId() };
+    find_process(|entry| (entry.process_id == id).then_some(entry.parent_process_id))
+}
+
+/// Whether a process named `TapTap*.exe` is running
+pub(crate) fn taptap_running() -> bool {
+    find_process(|entry| {
+        let len = entry
+            .exe_file
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(MAX_PATH);
+        let name = OsString::from_wide(&entry.exe_file[..len]);
+        name.to_string_lossy()
+            .to_ascii_lowercase()
+            .starts_with("taptap")
+            .then_some(())
+    })
+    .is_some()
+}
+
+/// Executable path of the process that started this one
+///
+/// `None` if the parent has exited or cannot be queried. Parent IDs are not
+/// kept alive, so an exited parent's ID may have been reused.
+pub(crate) fn parent_image_path() -> Option<PathBuf> {
+    let process =
+        unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, parent_process_id()?) };
+    if process.is_null() {
+        return None;
+    }
+
+    let mut path = vec![0u16; 32768];
+    let mut len = path.len() as u32;
+    let found = unsafe { QueryFullProcessImageNameW(process, 0, path.as_mut_ptr(), &mut len) };
+    unsafe { CloseHandle(process) };
+    if found == 0 {
+        return None;
+    }
+    Some(PathBuf::from(OsString::from_wide(&path[..len as usize])))
+}
+
+/// A named mutex handle, closed when dropped
+#[derive(Debug)]
+pub(crate) struct NamedMutex(*mut c_void);
+
+// The handle is only closed, which any thread may do
+unsafe impl Send for NamedMutex {}
+unsafe impl Sync for NamedMutex {}
+
+impl NamedMutex {
+    /// Open or create the mutex `name` in the session namespace
+    ///
+    /// # Returns
+    /// The handle, and whether another process had already created it
+    pub(crate) fn create(name: &str) -> io::Result<(Self, bool)> {
+        let name: Vec<u16> = format!("Local\\{}", name.replace('\\', "_"))
+            .encode_utf16()
+            .chain(Some(0))
+            .collect();
+        let handle = unsafe { CreateMutexW(ptr::null(), 0, name.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let existed = unsafe { GetLastError() } == ERROR_ALREADY_EXISTS;
+        Ok((NamedMutex(handle), existed))
+    }
+}
+
+impl Drop for NamedMutex {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
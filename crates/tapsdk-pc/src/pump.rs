@@ -0,0 +1,339 @@
+//! Background thread that runs the SDK callbacks for streams and channels
+//!
+//! By default the pump runs on a thread of its own. An embedder with a
+//! thread pool or frame scheduler can run it there instead by installing a
+//! [`Spawner`].
+
+use std::panic;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::callback::{self, recover, EventListener, TapEvent};
+
+/// How often the pump runs the SDK callbacks by default
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Shortest interval [`set_poll_policy`] accepts, so the pump never spins
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How the event pump behind [`channel`] and event streams polls the SDK
+///
+/// Polling with `TapSdk::run_callbacks` is not affected.
+///
+/// [`channel`]: crate::callback::channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    /// Time between two polls, at least 1 ms (default 16 ms)
+    pub interval: Duration,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        PollPolicy {
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+/// The current poll interval in nanoseconds
+static INTERVAL_NANOS: AtomicU64 = AtomicU64::new(DEFAULT_INTERVAL.as_nanos() as u64);
+
+/// Set how the event pump polls the SDK
+///
+/// A running pump uses the new interval from its next poll on. Intervals
+/// shorter than 1 ms are raised to 1 ms.
+pub fn set_poll_policy(policy: PollPolicy) {
+    let nanos = policy.interval.max(MIN_INTERVAL).as_nanos();
+    INTERVAL_NANOS.store(nanos.min(u64::MAX as u128) as u64, Ordering::Relaxed);
+}
+
+/// Get how the event pump polls the SDK
+pub fn poll_policy() -> PollPolicy {
+    PollPolicy {
+        interval: interval(),
+    }
+}
+
+fn interval() -> Duration {
+    Duration::from_nanos(INTERVAL_NANOS.load(Ordering::Relaxed))
+}
+
+/// A consumer of pumped events
+pub(crate) enum Sink {
+    #[cfg(feature = "futures")]
+    Stream(std::sync::Weak<Mutex<crate::stream::Buffer>>),
+    Channel(Sender<TapEvent>),
+}
+
+impl Sink {
+    /// Whether the consumer may still want events
+    fn is_alive(&self) -> bool {
+        match self {
+            #[cfg(feature = "futures")]
+            Sink::Stream(buffer) => buffer.strong_count() > 0,
+            // A dropped receiver is only noticed when sending fails
+            Sink::Channel(_) => true,
+        }
+    }
+
+    /// Hand over a batch of events, returning whether the consumer is alive
+    fn deliver(&self, events: &[TapEvent]) -> bool {
+        match self {
+            #[cfg(feature = "futures")]
+            Sink::Stream(buffer) => match buffer.upgrade() {
+                Some(buffer) => {
+                    recover(buffer.lock()).push(events);
+                    true
+                }
+                None => false,
+            },
+            Sink::Channel(sender) => events
+                .iter()
+                .all(|event| sender.send(event.clone()).is_ok()),
+        }
+    }
+}
+
+/// Schedules the event pump, see [`set_spawner`]
+pub trait Spawner: Send + Sync + 'static {
+    /// Run `task` once, no earlier than `delay` from now
+    ///
+    /// Each task runs the SDK callbacks once and schedules the next one, so
+    /// a frame scheduler may simply run it on the next frame. The task must
+    /// not be run before `spawn` returns.
+    fn spawn(&self, delay: Duration, task: Box<dyn FnOnce() + Send>);
+}
+
+impl<F> Spawner for F
+where
+    F: Fn(Duration, Box<dyn FnOnce() + Send>) + Send + Sync + 'static,
+{
+    fn spawn(&self, delay: Duration, task: Box<dyn FnOnce() + Send>) {
+        self(delay, task)
+    }
+}
+
+static SPAWNER: RwLock<Option<Arc<dyn Spawner>>> = RwLock::new(None);
+
+/// Run the event pump on `spawner` instead of a thread of its own
+///
+/// Replaces any previous spawner. A pump already running moves over after
+/// its next poll.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// # fn run_next_frame(task: Box<dyn FnOnce() + Send>) {}
+/// tapsdk_pc::callback::set_spawner(|_delay: Duration, task: Box<dyn FnOnce() + Send>| {
+///     // Frames are 16ms apart anyway
+///     run_next_frame(task);
+/// });
+/// let events = tapsdk_pc::callback::channel();
+/// ```
+pub fn set_spawner(spawner: impl Spawner) {
+    *recover(SPAWNER.write()) = Some(Arc::new(spawner));
+}
+
+/// Go back to running the event pump on a thread of its own
+pub fn clear_spawner() {
+    *recover(SPAWNER.write()) = None;
+}
+
+fn spawner() -> Option<Arc<dyn Spawner>> {
+    recover(SPAWNER.read()).clone()
+}
+
+/// Live consumers and the listener feeding them while the pump is running
+struct Registry {
+    sinks: Vec<Sink>,
+    listener: Option<Arc<EventListener>>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    sinks: Vec::new(),
+    listener: None,
+});
+
+/// Add a consumer, starting the pump if it is not running
+pub(crate) fn register(sink: Sink) {
+    let start = {
+        let mut registry = recover(REGISTRY.lock());
+        registry.sinks.push(sink);
+        let start = registry.listener.is_none();
+        if start {
+            registry.listener = Some(Arc::new(callback::listen()));
+        }
+        start
+    };
+    // Outside the lock, in case the spawner runs the task right away
+    if start {
+        schedule(Duration::ZERO);
+    }
+}
+
+/// Run the next poll on the spawner, or on a new pump thread without one
+fn schedule(delay: Duration) {
+    schedule_on(spawner(), delay);
+}
+
+fn schedule_on(spawner: Option<Arc<dyn Spawner>>, delay: Duration) {
+    match spawner {
+        Some(spawner) => spawner.spawn(delay, Box::new(run_task)),
+        None => {
+            thread::spawn(move || {
+                thread::sleep(delay);
+                run_thread();
+            });
+        }
+    }
+}
+
+/// Poll on the dedicated thread until no consumer is left or a spawner
+/// takes over
+fn run_thread() {
+    while step() {
+        if spawner().is_some() {
+            schedule(interval());
+            return;
+        }
+        thread::sleep(interval());
+    }
+}
+
+/// Poll once on the spawner and schedule the next poll
+fn run_task() {
+    if step() {
+        schedule(interval());
+    }
+}
+
+/// Poll once, reporting a panic to the crash handler
+///
+/// Returns whether the pump should keep running.
+fn step() -> bool {
+    match panic::catch_unwind(poll) {
+        Ok(running) => running,
+        Err(payload) => {
+            // Let the next consumer start a new pump
+            recover(REGISTRY.lock()).listener = None;
+            crate::crash::thread_died("tapsdk-pc event pump", payload);
+            false
+        }
+    }
+}
+
+/// Run the SDK callbacks and hand events to the consumers
+///
+/// Returns false, and marks the pump stopped, once no consumer is left.
+fn poll() -> bool {
+    let listener = {
+        let mut registry = recover(REGISTRY.lock());
+        registry.sinks.retain(Sink::is_alive);
+        if registry.sinks.is_empty() {
+            registry.listener = None;
+            return false;
+        }
+        match &registry.listener {
+            Some(listener) => listener.clone(),
+            None => return false,
+        }
+    };
+
+    callback::run_sdk_callbacks();
+    let events: Vec<TapEvent> = listener
+        .poll()
+        .into_iter()
+        .map(|envelope| envelope.event)
+        .collect();
+    if !events.is_empty() {
+        recover(REGISTRY.lock())
+            .sinks
+            .retain(|sink| sink.deliver(&events));
+    }
+    true
+}
+
+/// Get a channel receiving every event from now on
+///
+/// While any channel or [`event_stream`](callback::event_stream) is alive,
+/// a background thread, or the [`Spawner`] if one is set, runs the SDK
+/// callbacks every 16ms and sends the events, so a dedicated thread can block on
+/// [`recv_timeout`](Receiver::recv_timeout) instead of polling every
+/// frame. Every channel gets its own copy of every event, and events still
+/// reach [`poll_events`](callback::poll_events) as usual.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let events = tapsdk_pc::callback::channel();
+/// std::thread::spawn(move || loop {
+///     if let Ok(event) = events.recv_timeout(Duration::from_secs(1)) {
+///         println!("{:?}", event);
+///     }
+/// });
+/// ```
+pub fn channel() -> Receiver<TapEvent> {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    register(Sink::Channel(sender));
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_sink() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let sink = Sink::Channel(sender);
+        let events = [
+            TapEvent::Unknown { event_id: 1 },
+            TapEvent::Unknown { event_id: 2 },
+        ];
+
+        assert!(sink.deliver(&events));
+        let received: Vec<u32> = receiver.try_iter().map(|event| event.event_id()).collect();
+        assert_eq!(received, vec![1, 2]);
+
+        // The pump drops channels whose receiver is gone
+        drop(receiver);
+        assert!(!sink.deliver(&events));
+    }
+
+    #[test]
+    fn test_spawner_runs_pump() {
+        type Tasks = Arc<Mutex<Vec<(Duration, Box<dyn FnOnce() + Send>)>>>;
+        let tasks: Tasks = Arc::default();
+        let queued = tasks.clone();
+        let spawner = move |delay, task| recover(queued.lock()).push((delay, task));
+
+        schedule_on(Some(Arc::new(spawner)), Duration::ZERO);
+        let (delay, task) = recover(tasks.lock()).pop().expect("a scheduled poll");
+        assert_eq!(delay, Duration::ZERO);
+        // Without consumers the poll stops the pump instead of rescheduling
+        task();
+        assert!(recover(tasks.lock()).is_empty());
+        assert!(recover(REGISTRY.lock()).listener.is_none());
+    }
+
+    #[test]
+    fn test_poll_policy() {
+        let interval = Duration::from_millis(40);
+        set_poll_policy(PollPolicy { interval });
+        assert_eq!(poll_policy().interval, interval);
+
+        set_poll_policy(PollPolicy {
+            interval: Duration::ZERO,
+        });
+        assert_eq!(poll_policy().interval, MIN_INTERVAL);
+
+        set_poll_policy(PollPolicy::default());
+        assert_eq!(poll_policy().interval, DEFAULT_INTERVAL);
+    }
+}
@@ -0,0 +1,164 @@
+//! Minimal read-only access to the Windows registry
+
+use std::ffi::c_void;
+use std::ptr;
+
+type Hkey = *mut c_void;
+
+// Predefined keys are sign-extended on 64-bit targets
+pub(crate) const HKEY_CURRENT_USER: Hkey = 0x8000_0001u32 as i32 as isize as Hkey;
+pub(crate) const HKEY_LOCAL_MACHINE: Hkey = 0x8000_0002u32 as i32 as isize as Hkey;
+
+const KEY_READ: u32 = 0x0002_0019;
+const KEY_WOW64_64KEY: u32 = 0x0100;
+const KEY_WOW64_32KEY: u32 = 0x0200;
+const ERROR_SUCCESS: i32 = 0;
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegOpenKeyExW(
+        key: Hkey,
+        sub_key: *const u16,
+        options: u32,
+        sam_desired: u32,
+        result: *mut Hkey,
+    ) -> i32;
+    fn RegEnumKeyExW(
+        key: Hkey,
+        index: u32,
+        name: *mut u16,
+        name_len: *mut u32,
+        reserved: *mut u32,
+        class: *mut u16,
+        class_len: *mut u32,
+        last_write_time: *mut c_void,
+    ) -> i32;
+    fn RegQueryValueExW(
+        key: Hkey,
+        value_name: *const u16,
+        reserved: *mut u32,
+        value_type: *mut u32,
+        data: *mut u8,
+        data_len: *mut u32,
+    ) -> i32;
+    fn RegCloseKey(key: Hkey) -> i32;
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Which registry view to open on 64-bit Windows
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum View {
+    Native64,
+    Wow32,
+}
+
+/// An open registry key, closed on drop
+pub(crate) struct Key(Hkey);
+
+impl Key {
+    /// Open `path` under `root` for reading
+    pub(crate) fn open(root: Hkey, path: &str, view: View) -> Option<Key> {
+        let view = match view {
+            View::Native64 => KEY_WOW64_64KEY,
+            View::Wow32 => KEY_WOW64_32KEY,
+        };
+        let mut key = ptr::null_mut();
+        let status =
+            unsafe { RegOpenKeyExW(root, wide(path).as_ptr(), 0, KEY_READ | view, &mut key) };
+        if status != ERROR_SUCCESS {
+            return None;
+        }
+        Some(Key(key))
+    }
+
+    /// Open a subkey of this key, in the same view
+    pub(crate) fn open_subkey(&self, name: &str) -> Option<Key> {
+        let mut key = ptr::null_mut();
+        let status = unsafe { RegOpenKeyExW(self.0, wide(name).as_ptr(), 0, KEY_READ, &mut key) };
+        if status != ERROR_SUCCESS {
+            return None;
+        }
+        Some(Key(key))
+    }
+
+    /// Names of the direct subkeys
+    pub(crate) fn subkeys(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        // Key names are limited to 255 characters
+        let mut buffer = [0u16; 256];
+        for index in 0.. {
+            let mut len = buffer.len() as u32;
+            let status = unsafe {
+                RegEnumKeyExW(
+                    self.0,
+                    index,
+                    buffer.as_mut_ptr(),
+                    &mut len,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            if status != ERROR_SUCCESS {
+                break;
+            }
+            names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+        }
+        names
+    }
+
+    /// Read a string value, or `None` if missing or not a string
+    pub(crate) fn string(&self, name: &str) -> Option<String> {
+        let name = wide(name);
+        let mut value_type = 0u32;
+        let mut len = 0u32;
+        let status = unsafe {
+            RegQueryValueExW(
+                self.0,
+                name.as_ptr(),
+                ptr::null_mut(),
+                &mut value_type,
+                ptr::null_mut(),
+                &mut len,
+            )
+        };
+        if status != ERROR_SUCCESS || !matches!(value_type, REG_SZ | REG_EXPAND_SZ) {
+            return None;
+        }
+
+        let mut data = vec![0u16; (len as usize).div_ceil(2)];
+        let status = unsafe {
+            RegQueryValueExW(
+                self.0,
+                name.as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                data.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        if status != ERROR_SUCCESS {
+            return None;
+        }
+        // The stored data may or may not include a terminating nul
+        data.truncate(len as usize / 2);
+        while data.last() == Some(&0) {
+            data.pop();
+        }
+        Some(String::from_utf16_lossy(&data))
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        unsafe {
+            RegCloseKey(self.0);
+        }
+    }
+}
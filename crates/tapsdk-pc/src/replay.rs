@@ -0,0 +1,173 @@
+//! Record-and-replay of SDK event streams
+//!
+//! Available with the `replay` feature. Capture the events a real TapTap
+//! client delivers, save them as JSON, and feed them back through the
+//! dispatcher later so game logic around authorization and cloud saves can
+//! be tested deterministically without the client.
+//!
+//! Replayed events go through the same pipeline as live ones (metrics,
+//! observer, history and queue) and are returned by
+//! [`poll_events`](crate::callback::poll_events). Polling does not require
+//! an initialized SDK, so replays also work in offline tests.
+//!
+//! # Example
+//! ```no_run
+//! use tapsdk_pc::{callback, replay};
+//!
+//! // While running against the real client
+//! replay::start_recording();
+//! // ... drive the game ...
+//! let json = replay::stop_recording().to_json()?;
+//!
+//! // Later, in a test
+//! let recording = replay::Recording::from_json(&json)?;
+//! replay::replay(&recording);
+//! for event in callback::poll_events() {
+//!     // ... assert on game state ...
+//! }
+//! # Ok::<(), serde_json::Error>(())
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::callback::{self, recover, Envelope, TapEvent};
+
+/// A single captured event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Event ID the SDK delivered the event under
+    pub event_id: u32,
+    /// Time since recording started, in milliseconds
+    pub offset_ms: u64,
+    /// The parsed event
+    pub event: TapEvent,
+}
+
+/// A captured event stream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    /// Events in the order they were received
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Serialize the recording to JSON
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Parse a recording from JSON
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+/// An in-progress recording
+struct Capture {
+    started: Instant,
+    recording: Recording,
+}
+
+/// Whether a recording is in progress (fast path for [`capture`])
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Start capturing every dispatched event
+///
+/// Restarts the recording if one is already in progress.
+pub fn start_recording() {
+    *recover(CAPTURE.lock()) = Some(Capture {
+        started: Instant::now(),
+        recording: Recording::default(),
+    });
+    RECORDING.store(true, Ordering::Release);
+}
+
+/// Stop capturing and return the recorded events
+///
+/// Returns an empty recording if none was in progress.
+pub fn stop_recording() -> Recording {
+    RECORDING.store(false, Ordering::Release);
+    recover(CAPTURE.lock())
+        .take()
+        .map(|capture| capture.recording)
+        .unwrap_or_default()
+}
+
+/// Check whether a recording is in progress
+pub fn is_recording() -> bool {
+    RECORDING.load(Ordering::Acquire)
+}
+
+/// Feed every event of a recording through the dispatcher
+pub fn replay(recording: &Recording) {
+    for event in &recording.events {
+        replay_event(event);
+    }
+}
+
+/// Feed a single recorded event through the dispatcher
+///
+/// Use this to step through a recording between polls.
+pub fn replay_event(event: &RecordedEvent) {
+    callback::dispatch(event.event_id, event.event.clone());
+}
+
+/// Append a dispatched event to the current recording
+pub(crate) fn capture(raw_event_id: u32, envelope: &Envelope) {
+    if !RECORDING.load(Ordering::Acquire) {
+        return;
+    }
+    // Custom payloads are opaque and cannot be serialized
+    if matches!(envelope.event, TapEvent::Custom(_)) {
+        return;
+    }
+    if let Some(capture) = recover(CAPTURE.lock()).as_mut() {
+        let offset = envelope
+            .received_at
+            .saturating_duration_since(capture.started);
+        capture.recording.events.push(RecordedEvent {
+            event_id: raw_event_id,
+            offset_ms: offset.as_millis() as u64,
+            event: envelope.event.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callback::{poll_events, GamePlayableStatusChangedData};
+
+    #[test]
+    fn test_record_and_replay_round_trip() {
+        start_recording();
+        callback::dispatch(
+            4001,
+            TapEvent::GamePlayableStatusChanged(GamePlayableStatusChangedData {
+                is_playable: true,
+            }),
+        );
+        callback::dispatch(8003, TapEvent::Unknown { event_id: 8003 });
+        let recording = stop_recording();
+        assert!(!is_recording());
+        // Discard the live copies
+        poll_events();
+
+        let json = recording.to_json().unwrap();
+        let loaded = Recording::from_json(&json).unwrap();
+        assert_eq!(loaded.events.len(), 2);
+
+        replay(&loaded);
+        let events: Vec<u32> = poll_events()
+            .iter()
+            .map(TapEvent::event_id)
+            .filter(|id| matches!(id, 4001 | 8003))
+            .collect();
+        assert_eq!(events, vec![4001, 8003]);
+    }
+}
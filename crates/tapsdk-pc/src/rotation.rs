@@ -0,0 +1,156 @@
+//! Automatic deletion of old cloud saves
+//!
+//! Each client can only store a limited number of cloud saves, so a game
+//! that creates a new autosave every few minutes eventually fails to create
+//! any. A [`Rotation`] keeps only the newest saves whose name starts with a
+//! prefix, deleting older ones after each successful create.
+//!
+//! ```no_run
+//! use tapsdk_pc::{rotation, TapSdk};
+//!
+//! let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+//! // Saves named "autosave-..." beyond the newest 5 are deleted
+//! let _rotation = rotation::keep_latest("autosave-", 5);
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use crate::callback::{recover, CloudSaveCreateData, CloudSaveInfo, CloudSaveListData};
+use crate::cloudsave::{next_request_id, CloudSave};
+use crate::subscription::{self, Subscription};
+
+/// Deletes old saves matching a prefix, see [`keep_latest`]
+///
+/// Dropping the rotation stops it; requests already sent still complete.
+#[derive(Debug)]
+pub struct Rotation {
+    prefix: String,
+    keep: usize,
+    _subscriptions: [Subscription; 2],
+}
+
+impl Rotation {
+    /// Name prefix of the rotated saves
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Number of saves kept
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+}
+
+/// Keep only the newest `keep` saves whose name starts with `prefix`
+///
+/// After every successful create of a matching save, the saves are listed
+/// and the oldest matching ones deleted, by modification time. Updates do
+/// not add a save, so they never trigger a rotation.
+/// Saves with other names are never touched. The list and delete requests
+/// take request IDs counting up from `i64::MIN`; their responses are
+/// delivered like any other event.
+pub fn keep_latest(prefix: &str, keep: usize) -> Rotation {
+    let pending: Arc<Mutex<Vec<i64>>> = Arc::default();
+
+    let created = pending.clone();
+    let name = prefix.to_string();
+    let on_create = subscription::subscribe(move |data: &CloudSaveCreateData| {
+        let matches = data
+            .save
+            .as_ref()
+            .is_some_and(|save| save.name.starts_with(&name));
+        if data.error.is_some() || !matches {
+            return;
+        }
+        let Some(cloud_save) = CloudSave::get() else {
+            return;
+        };
+        let request_id = next_request_id();
+        if cloud_save.list(request_id).is_ok() {
+            recover(created.lock()).push(request_id);
+        }
+    });
+
+    let name = prefix.to_string();
+    let on_list = subscription::subscribe(move |data: &CloudSaveListData| {
+        {
+            let mut pending = recover(pending.lock());
+            let Some(index) = pending.iter().position(|&id| id == data.request_id) else {
+                return;
+            };
+            pending.swap_remove(index);
+        }
+        if data.error.is_some() {
+            return;
+        }
+        let Some(cloud_save) = CloudSave::get() else {
+            return;
+        };
+        for save in expired(&data.saves, &name, keep) {
+            let request_id = next_request_id();
+            if let Err(e) = cloud_save.delete(request_id, &save.uuid) {
+                log::warn!(uuid = save.uuid.as_str(); "Failed to delete rotated save: {}", e);
+            }
+        }
+    });
+
+    Rotation {
+        prefix: prefix.to_string(),
+        keep,
+        _subscriptions: [on_create, on_list],
+    }
+}
+
+/// Saves matching `prefix` beyond the newest `keep`, oldest first
+fn expired<'a>(saves: &'a [CloudSaveInfo], prefix: &str, keep: usize) -> Vec<&'a CloudSaveInfo> {
+    let mut matching: Vec<&CloudSaveInfo> = saves
+        .iter()
+        .filter(|save| save.name.starts_with(prefix))
+        .collect();
+    matching.sort_by_key(|save| (save.modified_time, save.created_time));
+    let excess = matching.len().saturating_sub(keep);
+    matching.truncate(excess);
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsave::{FileId, SaveUuid};
+    use std::time::{Duration, SystemTime};
+
+    fn save(name: &str, modified_secs: u64) -> CloudSaveInfo {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(modified_secs);
+        CloudSaveInfo {
+            uuid: SaveUuid::new(format!("{}-{}", name, modified_secs)).unwrap(),
+            file_id: FileId::default(),
+            name: name.to_string(),
+            save_size: 0,
+            cover_size: 0,
+            summary: None,
+            extra: None,
+            playtime: Duration::ZERO,
+            created_time: time,
+            modified_time: time,
+        }
+    }
+
+    #[test]
+    fn test_expired_keeps_newest_matching() {
+        let saves = [
+            save("autosave-3", 30),
+            save("manual", 5),
+            save("autosave-1", 10),
+            save("autosave-4", 40),
+            save("autosave-2", 20),
+        ];
+        let names: Vec<&str> = expired(&saves, "autosave-", 2)
+            .iter()
+            .map(|save| save.name.as_str())
+            .collect();
+        assert_eq!(names, ["autosave-1", "autosave-2"]);
+
+        assert!(expired(&saves, "autosave-", 10).is_empty());
+        assert_eq!(expired(&saves, "autosave-", 0).len(), 4);
+    }
+}
@@ -0,0 +1,74 @@
+//! Running the event pump on an async runtime
+//!
+//! [`event_stream`](crate::callback::event_stream) and
+//! [`response`](crate::callback::response) are plain futures woken by the
+//! event pump, so they work on any executor. By default the pump polls on a
+//! thread of its own; the spawners here run it as tasks of the game's
+//! runtime instead. Other runtimes can implement
+//! [`Spawner`] directly.
+//!
+//! ```no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn example() {
+//! use tapsdk_pc::{callback, runtime::TokioSpawner};
+//!
+//! callback::set_spawner(TokioSpawner::current());
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use crate::callback::Spawner;
+
+/// Runs the event pump as tasks on a tokio runtime
+///
+/// Available with the `tokio` feature. The runtime must have its timer
+/// enabled, as `#[tokio::main]` does.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone)]
+pub struct TokioSpawner(tokio::runtime::Handle);
+
+#[cfg(feature = "tokio")]
+impl TokioSpawner {
+    /// Spawn onto the runtime the caller is running on
+    ///
+    /// # Panics
+    /// If called outside of a tokio runtime
+    pub fn current() -> Self {
+        TokioSpawner(tokio::runtime::Handle::current())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<tokio::runtime::Handle> for TokioSpawner {
+    fn from(handle: tokio::runtime::Handle) -> Self {
+        TokioSpawner(handle)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Spawner for TokioSpawner {
+    fn spawn(&self, delay: Duration, task: Box<dyn FnOnce() + Send>) {
+        self.0.spawn(async move {
+            tokio::time::sleep(delay).await;
+            task();
+        });
+    }
+}
+
+/// Runs the event pump as tasks on the async-std executor
+///
+/// Available with the `async-std` feature.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdSpawner;
+
+#[cfg(feature = "async-std")]
+impl Spawner for AsyncStdSpawner {
+    fn spawn(&self, delay: Duration, task: Box<dyn FnOnce() + Send>) {
+        async_std::task::spawn(async move {
+            async_std::task::sleep(delay).await;
+            task();
+        });
+    }
+}
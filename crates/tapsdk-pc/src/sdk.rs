@@ -1,19 +1,43 @@
 //! Core SDK functionality
 
 use std::ffi::{CStr, CString};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::callback::{self, TapEvent};
+use crate::callback::{self, CallbackSet, EventQueue, PollPolicy, QueueConfig, TapEvent};
 use crate::error::{InitResult, Result, TapSdkError};
+use crate::ffi;
+use crate::library::LibraryTrust;
+use crate::observer::{self, SdkObserver};
+use crate::subscription::{self, EventData, Subscription};
+
+pub use crate::crash::{clear_crash_handler, set_crash_handler};
+pub use crate::launch::{launch_params, LaunchParams};
+pub(crate) use crate::state::{record_event, reset};
+pub use crate::state::{state_watch, system_state, StateChanged, StateChanges};
 
 /// Global flag to track if SDK is initialized
 static SDK_INITIALIZED: AtomicBool = AtomicBool::new(false);
 
+/// How often [`TapSdk::shutdown_graceful`] polls while waiting
+const GRACEFUL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Incremented on every successful initialization
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
 /// Check if the SDK has been initialized
 pub fn is_initialized() -> bool {
     SDK_INITIALIZED.load(Ordering::SeqCst)
 }
 
+/// Identifies the current init/shutdown session, for invalidating handles
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
 /// Check if the app needs to restart (should be called before init)
 ///
 /// This function should be called before `TapSdk::init()` to check if the game
@@ -27,17 +51,276 @@ pub fn is_initialized() -> bool {
 /// `true` if the app needs to restart (exit immediately), `false` otherwise
 pub fn restart_app_if_necessary(client_id: &str) -> Result<bool> {
     let client_id_c = CString::new(client_id)?;
-    let result = unsafe { tapsdk_pc_sys::TapSDK_RestartAppIfNecessary(client_id_c.as_ptr()) };
+    let result = unsafe { ffi::TapSDK_RestartAppIfNecessary(client_id_c.as_ptr()) };
+    log::debug!(restart = result; "Checked whether TapTap needs to relaunch the game");
     Ok(result)
 }
 
+/// Installed TapTap client details, see [`platform_info`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformInfo {
+    /// Client version, e.g. to show in a "please update TapTap" prompt
+    pub version: Option<String>,
+    /// Client installation directory
+    pub install_path: Option<PathBuf>,
+    /// SDK library loaded by this process, see [`loaded_path`](crate::library::loaded_path)
+    pub library_path: Option<PathBuf>,
+}
+
+/// Look up the installed TapTap client
+///
+/// The SDK has no call for this, so the client's uninstall entry is read
+/// from the registry. Useful when `init()` fails with
+/// `InitResult::PlatformVersionMismatch` or a cloud save request with
+/// `CloudSaveResult::TapTapClientOutdated`.
+///
+/// # Returns
+/// The client details, or `None` if no TapTap client is installed or on
+/// platforms other than Windows
+pub fn platform_info() -> Option<PlatformInfo> {
+    platform_info_impl()
+}
+
+#[cfg(windows)]
+fn platform_info_impl() -> Option<PlatformInfo> {
+    use crate::registry::{Key, View, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    const UNINSTALL: &str = r"Software\Microsoft\Windows\CurrentVersion\Uninstall";
+
+    let locations = [
+        (HKEY_CURRENT_USER, View::Native64),
+        (HKEY_LOCAL_MACHINE, View::Native64),
+        (HKEY_LOCAL_MACHINE, View::Wow32),
+    ];
+    locations.into_iter().find_map(|(root, view)| {
+        let uninstall = Key::open(root, UNINSTALL, view)?;
+        uninstall.subkeys().into_iter().find_map(|name| {
+            let entry = uninstall.open_subkey(&name)?;
+            let display_name = entry.string("DisplayName")?;
+            if !display_name.trim().starts_with("TapTap") {
+                return None;
+            }
+            Some(PlatformInfo {
+                version: entry.string("DisplayVersion"),
+                install_path: entry
+                    .string("InstallLocation")
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from),
+                library_path: crate::library::loaded_path(),
+            })
+        })
+    })
+}
+
+#[cfg(not(windows))]
+fn platform_info_impl() -> Option<PlatformInfo> {
+    None
+}
+
+/// Check whether the game was started by the TapTap client
+///
+/// Unlike [`restart_app_if_necessary`], this never relaunches the game and
+/// does not call into the SDK, so launchers and crash reporters can branch
+/// on it safely. The parent process counts as TapTap if its executable lies
+/// in the client's installation directory (see [`platform_info`]) or is named
+/// `TapTap*.exe`.
+///
+/// # Returns
+/// `false` if the parent process has exited or cannot be inspected, and on
+/// platforms other than Windows
+pub fn is_launched_by_taptap() -> bool {
+    is_launched_by_taptap_impl()
+}
+
+#[cfg(windows)]
+fn is_launched_by_taptap_impl() -> bool {
+    let Some(parent) = crate::process::parent_image_path() else {
+        return false;
+    };
+
+    let named_taptap = parent
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.to_ascii_lowercase().starts_with("taptap"));
+    // Paths are case-insensitive on Windows
+    let lowercase = |path: &std::path::Path| PathBuf::from(path.to_string_lossy().to_lowercase());
+    let in_install_dir = platform_info()
+        .and_then(|info| info.install_path)
+        .is_some_and(|dir| lowercase(&parent).starts_with(lowercase(&dir)));
+    named_taptap || in_install_dir
+}
+
+#[cfg(not(windows))]
+fn is_launched_by_taptap_impl() -> bool {
+    false
+}
+
+/// Proof that this is the only running copy of the game, see
+/// [`acquire_single_instance`]
+///
+/// Other copies can detect this one until it is dropped.
+#[derive(Debug)]
+pub struct SingleInstance {
+    #[cfg(windows)]
+    _mutex: crate::process::NamedMutex,
+}
+
+/// Claim the single-instance lock of `client_id`
+///
+/// Players often launch a game twice from TapTap. Call this early and exit,
+/// or bring the existing window to the front, when another copy holds the
+/// lock. Keep the returned guard alive for as long as the game runs.
+///
+/// # Returns
+/// `None` if another copy of the game holds the lock. Always `Some` on
+/// platforms other than Windows.
+///
+/// # Example
+/// ```no_run
+/// let Some(_instance) = tapsdk_pc::acquire_single_instance("your_client_id")? else {
+///     // Another copy is already running
+///     std::process::exit(0);
+/// };
+/// # Ok::<(), tapsdk_pc::TapSdkError>(())
+/// ```
+pub fn acquire_single_instance(client_id: &str) -> Result<Option<SingleInstance>> {
+    if client_id.contains('\0') {
+        return Err(TapSdkError::InvalidArgument(
+            "client ID contains a null byte".to_string(),
+        ));
+    }
+    acquire_single_instance_impl(client_id)
+}
+
+#[cfg(windows)]
+fn acquire_single_instance_impl(client_id: &str) -> Result<Option<SingleInstance>> {
+    let name = format!("tapsdk-pc-{}", client_id);
+    let (mutex, existed) = crate::process::NamedMutex::create(&name)?;
+    Ok((!existed).then_some(SingleInstance { _mutex: mutex }))
+}
+
+#[cfg(not(windows))]
+fn acquire_single_instance_impl(_client_id: &str) -> Result<Option<SingleInstance>> {
+    Ok(Some(SingleInstance {}))
+}
+
+/// Initialization options for [`TapSdk::init_with`]
+///
+/// There is no log directory option: `TapSDK_Init` takes only the public
+/// key, and the SDK has no call to choose where `taptap_api.dll` writes its
+/// logs. This crate logs through the [`log`] facade,
+/// so the logger the game installs decides where its records go.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::callback::{OverflowPolicy, QueueConfig};
+/// use tapsdk_pc::{TapSdk, TapSdkConfig};
+///
+/// let config = TapSdkConfig::new("your_public_key_here")
+///     .queue(QueueConfig {
+///         capacity: Some(256),
+///         overflow: OverflowPolicy::Coalesce,
+///     })
+///     .history_capacity(32);
+/// let sdk = TapSdk::init_with(config).expect("Failed to init SDK");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapSdkConfig {
+    /// The public key from TapTap developer center
+    pub pub_key: String,
+    /// Event queue capacity and overflow policy, see [`callback::set_queue_config`]
+    pub queue: QueueConfig,
+    /// Number of events kept for [`callback::recent_events`] (0 disables)
+    pub history_capacity: usize,
+    /// Directory for staged upload files, see [`staging::set_dir`]
+    ///
+    /// [`staging::set_dir`]: crate::staging::set_dir
+    pub staging_dir: Option<PathBuf>,
+    /// Checks the loaded `taptap_api.dll` must pass before initialization
+    pub library_trust: LibraryTrust,
+    /// Events to register callbacks for (default all)
+    pub callbacks: CallbackSet,
+    /// How often [`callback::channel`] and event streams poll the SDK, see
+    /// [`callback::set_poll_policy`]
+    pub poll_policy: PollPolicy,
+}
+
+impl TapSdkConfig {
+    /// Create a config with the default queue and no event history
+    pub fn new(pub_key: impl Into<String>) -> Self {
+        TapSdkConfig {
+            pub_key: pub_key.into(),
+            queue: QueueConfig::default(),
+            history_capacity: 0,
+            staging_dir: None,
+            library_trust: LibraryTrust::default(),
+            callbacks: CallbackSet::ALL,
+            poll_policy: PollPolicy::default(),
+        }
+    }
+
+    /// Set the event queue capacity and overflow policy
+    pub fn queue(mut self, queue: QueueConfig) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Set the number of events kept for [`callback::recent_events`]
+    pub fn history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Set the directory for staged upload files
+    pub fn staging_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.staging_dir = Some(dir.into());
+        self
+    }
+
+    /// Refuse to initialize unless the loaded `taptap_api.dll` passes `trust`
+    pub fn verify_library(mut self, trust: LibraryTrust) -> Self {
+        self.library_trust = trust;
+        self
+    }
+
+    /// Only register callbacks for the events in `set`
+    pub fn callbacks(mut self, set: CallbackSet) -> Self {
+        self.callbacks = set;
+        self
+    }
+
+    /// Set how often the event pump polls the SDK
+    pub fn poll_policy(mut self, policy: PollPolicy) -> Self {
+        self.poll_policy = policy;
+        self
+    }
+}
+
 /// Main TapTap PC SDK wrapper
 ///
 /// This struct represents an initialized SDK instance. Only one instance
-/// can exist at a time. When dropped, it will shut down the SDK.
-#[derive(Debug)]
+/// can exist at a time, but the handle can be cloned and shared across
+/// threads. When the last clone is dropped, it will shut down the SDK.
+///
+/// Each instance has an event queue of its own, so events left unpolled
+/// when it shuts down never reach the next instance.
+#[derive(Debug, Clone)]
 pub struct TapSdk {
-    _private: (), // Prevent direct construction
+    session: Arc<Session>,
+}
+
+/// Shuts the SDK down when the last [`TapSdk`] handle is dropped
+struct Session {
+    /// Where the SDK callback queues events while this session is active
+    queue: Arc<EventQueue>,
+}
+
+impl std::fmt::Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("queue", &self.queue.config())
+            .finish()
+    }
 }
 
 impl TapSdk {
@@ -56,6 +339,10 @@ impl TapSdk {
     /// let sdk = TapSdk::init("your_public_key_here").expect("Failed to init SDK");
     /// ```
     pub fn init(pub_key: &str) -> Result<Self> {
+        Self::init_registering(pub_key, callback::queue_config(), CallbackSet::ALL)
+    }
+
+    fn init_registering(pub_key: &str, queue: QueueConfig, callbacks: CallbackSet) -> Result<Self> {
         if SDK_INITIALIZED.swap(true, Ordering::SeqCst) {
             return Err(TapSdkError::InvalidArgument(
                 "SDK already initialized".to_string(),
@@ -65,9 +352,8 @@ impl TapSdk {
         let pub_key_c = CString::new(pub_key)?;
         let mut err_msg: [std::os::raw::c_char; 1024] = [0; 1024];
 
-        let result = unsafe {
-            tapsdk_pc_sys::TapSDK_Init(err_msg.as_mut_ptr() as *mut _, pub_key_c.as_ptr())
-        };
+        let result =
+            unsafe { ffi::TapSDK_Init(err_msg.as_mut_ptr() as *mut _, pub_key_c.as_ptr()) };
 
         let init_result = InitResult::from(result);
 
@@ -80,16 +366,61 @@ impl TapSdk {
                     .into_owned()
             };
 
+            log::warn!(result:? = init_result; "SDK initialization failed: {}", error_message);
             return Err(TapSdkError::InitFailed {
                 result: init_result,
                 message: error_message,
             });
         }
 
+        let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+        log::info!(generation; "SDK initialized");
+
+        // Events left unpolled by a previous session are stale now
+        callback::discard_events();
+        crate::staging::sweep();
+
+        let queue = Arc::new(EventQueue::with_config(queue));
+        callback::activate_queue(queue.clone());
+
         // Register our callback handlers
-        callback::register_callbacks();
+        callback::register_callback_set(callbacks);
+
+        Ok(TapSdk {
+            session: Arc::new(Session { queue }),
+        })
+    }
+
+    /// Initialize from `taptap_dev.json` next to the executable
+    ///
+    /// For development only: there is no need to call
+    /// [`restart_app_if_necessary`] first, so the game can be started
+    /// without TapTap. See [`dev`](crate::dev) for the file format.
+    ///
+    /// # Errors
+    /// [`TapSdkError::Io`] if there is no `taptap_dev.json`, or any error
+    /// of [`TapSdk::init_with`]
+    #[cfg(feature = "dev-config")]
+    pub fn init_dev() -> Result<Self> {
+        let dev = crate::dev::DevConfig::load(&crate::dev::DevConfig::path()?)?;
+        let mut config = TapSdkConfig::new(dev.pub_key).history_capacity(dev.history_capacity);
+        config.queue.capacity = dev.queue_capacity.or(config.queue.capacity);
+        Self::init_with(config)
+    }
 
-        Ok(TapSdk { _private: () })
+    /// Initialize the TapTap PC SDK with [`TapSdkConfig`] options
+    ///
+    /// The options are applied only if initialization succeeds.
+    pub fn init_with(config: TapSdkConfig) -> Result<Self> {
+        crate::library::verify_library(&config.library_trust)?;
+        let sdk = Self::init_registering(&config.pub_key, config.queue, config.callbacks)?;
+        callback::set_history_capacity(config.history_capacity);
+        callback::set_poll_policy(config.poll_policy);
+        if let Some(dir) = config.staging_dir {
+            crate::staging::set_dir(dir);
+            crate::staging::sweep();
+        }
+        Ok(sdk)
     }
 
     /// Get the client ID
@@ -99,7 +430,7 @@ impl TapSdk {
     pub fn get_client_id(&self) -> Option<String> {
         let mut buffer: [std::os::raw::c_char; 256] = [0; 256];
 
-        let success = unsafe { tapsdk_pc_sys::TapSDK_GetClientID(buffer.as_mut_ptr()) };
+        let success = unsafe { ffi::TapSDK_GetClientID(buffer.as_mut_ptr()) };
 
         if success {
             let client_id = unsafe {
@@ -125,31 +456,153 @@ impl TapSdk {
     /// # Returns
     /// A vector of events that have occurred since the last poll
     pub fn run_callbacks(&self) -> Vec<TapEvent> {
-        callback::poll_events()
+        callback::poll_queue(&self.session.queue)
+            .into_iter()
+            .map(|envelope| envelope.event)
+            .collect()
+    }
+
+    /// Install an observer that receives SDK activity notifications
+    ///
+    /// Replaces any previously installed observer. The observer stays
+    /// installed until [`clear_observer`](Self::clear_observer) is called
+    /// or the SDK is shut down.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use tapsdk_pc::{SdkObserver, TapEvent, TapSdk};
+    ///
+    /// struct Metrics;
+    ///
+    /// impl SdkObserver for Metrics {
+    ///     fn on_event_received(&self, event: &TapEvent) {
+    ///         println!("event: {:?}", event);
+    ///     }
+    /// }
+    ///
+    /// let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+    /// sdk.set_observer(Metrics);
+    /// ```
+    pub fn set_observer(&self, observer: impl SdkObserver) {
+        observer::set(Arc::new(observer));
+    }
+
+    /// Remove the installed observer, if any
+    pub fn clear_observer(&self) {
+        observer::clear();
+    }
+
+    /// Run `handler` when TapTap announces it is shutting down
+    ///
+    /// The handler is invoked on the polling thread when a
+    /// [`SystemState::PlatformShutdown`] state change is received, before the
+    /// event is queued and before control returns to the SDK. This is the
+    /// place to start a final cloud save; follow up with
+    /// [`shutdown_graceful`](Self::shutdown_graceful) to wait for it. Keep
+    /// it short, since the client only waits so long before closing.
+    ///
+    /// Replaces any previously installed handler. The handler stays
+    /// installed until the SDK is shut down.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use tapsdk_pc::TapSdk;
+    ///
+    /// let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+    /// sdk.on_platform_shutdown(|| {
+    ///     // Write the save file and start uploading it
+    /// });
+    /// ```
+    ///
+    /// [`SystemState::PlatformShutdown`]: crate::error::SystemState::PlatformShutdown
+    pub fn on_platform_shutdown(&self, handler: impl Fn() + Send + Sync + 'static) {
+        callback::set_platform_shutdown_handler(Some(Arc::new(handler)));
+    }
+
+    /// Run `handler` for every event carrying data of type `T`
+    ///
+    /// The handler runs on the polling thread when the event is received,
+    /// before it is queued, and stays installed until the returned
+    /// [`Subscription`] is dropped or the SDK is shut down.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use tapsdk_pc::callback::AuthorizeFinishedData;
+    /// use tapsdk_pc::TapSdk;
+    ///
+    /// let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+    /// let subscription = sdk.on(|data: &AuthorizeFinishedData| {
+    ///     println!("authorized: {}", !data.is_cancel);
+    /// });
+    /// // ...
+    /// drop(subscription);
+    /// ```
+    pub fn on<T: EventData>(&self, handler: impl Fn(&T) + Send + Sync + 'static) -> Subscription {
+        subscription::subscribe(handler)
+    }
+
+    /// Shut down the SDK once outstanding cloud save requests have completed
+    ///
+    /// Keeps polling until every request accepted by [`CloudSave`] has
+    /// received its response, or until `timeout` elapses, then shuts down
+    /// like [`shutdown`](Self::shutdown).
+    ///
+    /// # Returns
+    /// The events received while waiting
+    ///
+    /// [`CloudSave`]: crate::CloudSave
+    pub fn shutdown_graceful(self, timeout: Duration) -> Vec<TapEvent> {
+        let deadline = Instant::now() + timeout;
+        let mut events = self.run_callbacks();
+        while !callback::pending_requests().is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            thread::sleep(GRACEFUL_POLL_INTERVAL.min(deadline - now));
+            events.extend(self.run_callbacks());
+        }
+        self.shutdown();
+        events
     }
 
     /// Shut down the SDK
     ///
-    /// This is called automatically when the `TapSdk` instance is dropped,
-    /// but can be called explicitly if needed.
+    /// This is called automatically when the last `TapSdk` handle is
+    /// dropped, but can be called explicitly if needed. While other clones
+    /// of this handle exist, this only releases this one.
     pub fn shutdown(self) {
         // The Drop implementation will handle cleanup
         drop(self);
     }
 }
 
-impl Drop for TapSdk {
+impl Drop for Session {
     fn drop(&mut self) {
-        // Unregister callbacks first
-        callback::unregister_callbacks();
+        // Wait for callbacks running on other threads, such as the event
+        // pump, and keep them from starting until the SDK is marked shut down
+        callback::with_sdk_lock(|| {
+            // Unregister callbacks first
+            callback::unregister_callbacks();
 
-        // Shut down the SDK
-        unsafe {
-            tapsdk_pc_sys::TapSDK_Shutdown();
-        }
+            // Shut down the SDK
+            unsafe {
+                ffi::TapSDK_Shutdown();
+            }
+
+            // Observers and handlers are scoped to the SDK instance
+            observer::clear();
+            callback::set_platform_shutdown_handler(None);
+            subscription::clear();
 
-        // Mark SDK as not initialized
-        SDK_INITIALIZED.store(false, Ordering::SeqCst);
+            // No responses will arrive for requests still in flight
+            crate::staging::release_all();
+            callback::deactivate_queue(&self.queue);
+
+            // Mark SDK as not initialized
+            SDK_INITIALIZED.store(false, Ordering::SeqCst);
+        });
+        log::info!(generation = generation(); "SDK shut down");
     }
 }
 
@@ -161,4 +614,37 @@ mod tests {
     fn test_not_initialized() {
         assert!(!is_initialized());
     }
+
+    #[test]
+    fn test_handle_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<TapSdk>();
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_platform_info_unsupported() {
+        assert_eq!(platform_info(), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_is_launched_by_taptap_unsupported() {
+        assert!(!is_launched_by_taptap());
+    }
+
+    #[test]
+    fn test_single_instance() {
+        {
+            let first = acquire_single_instance("single-instance-test").unwrap();
+            assert!(first.is_some());
+            let second = acquire_single_instance("single-instance-test").unwrap();
+            assert_eq!(second.is_none(), cfg!(windows));
+        }
+        // Released once the guards are dropped
+        assert!(acquire_single_instance("single-instance-test")
+            .unwrap()
+            .is_some());
+        assert!(acquire_single_instance("bad\0id").is_err());
+    }
 }
@@ -0,0 +1,339 @@
+//! Verification of authorization tokens on a game server
+//!
+//! Available with the `server` feature. Nothing here calls into the SDK, so
+//! it builds and runs on any platform.
+//!
+//! After [`user::authorize`](crate::user::authorize) succeeds, the game
+//! sends the token's `kid` and `mac_key` to its server. The server proves
+//! the token is genuine by asking TapTap who it belongs to:
+//!
+//! ```no_run
+//! use tapsdk_pc::server::{self, Region};
+//!
+//! # fn http_get(url: &str, authorization: &str) -> String {
+//! #     r#"{"data":{"name":"","avatar":"","openid":"o1","unionid":"u1"},"success":true}"#.to_string()
+//! # }
+//! # let (kid, mac_key) = ("", "");
+//! let request = server::profile_request(kid, mac_key, "your_client_id", Region::China)?;
+//! // Send with any HTTP client
+//! let body = http_get(&request.url, &request.authorization);
+//! let profile = server::parse_profile(&body)?;
+//! println!("verified player {}", profile.openid);
+//! # Ok::<(), tapsdk_pc::TapSdkError>(())
+//! ```
+//!
+//! The game can then sign its own requests to the server with the same key
+//! using [`sign`], which the server checks with [`verify`].
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use sha1::Sha1;
+
+use crate::error::{Result, TapSdkError};
+
+/// TapTap Open API deployment to verify tokens against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// `open.tapapis.cn`, for games published on TapTap China
+    China,
+    /// `open.tapapis.com`, for games published on TapTap Global
+    Global,
+}
+
+impl Region {
+    /// Open API host name
+    pub fn host(self) -> &'static str {
+        match self {
+            Region::China => "open.tapapis.cn",
+            Region::Global => "open.tapapis.com",
+        }
+    }
+}
+
+/// The parts of an HTTP request covered by a MAC signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacRequest<'a> {
+    /// HTTP method, e.g. `GET`
+    pub method: &'a str,
+    /// Host name, without the port
+    pub host: &'a str,
+    /// Port, 443 for HTTPS
+    pub port: u16,
+    /// Path including the query string, e.g. `/account/profile/v1?client_id=...`
+    pub path: &'a str,
+}
+
+/// A profile request ready to be sent, see [`profile_request`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileRequest {
+    /// URL to `GET`
+    pub url: String,
+    /// Value of the `Authorization` header
+    pub authorization: String,
+}
+
+/// The account a token belongs to, as reported by TapTap
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Profile {
+    /// Player ID, unique to your game
+    pub openid: String,
+    /// Player ID shared by the games of one developer
+    pub unionid: String,
+    /// Display name
+    #[serde(default)]
+    pub name: String,
+    /// Avatar URL
+    #[serde(default)]
+    pub avatar: String,
+}
+
+#[derive(Deserialize)]
+struct ProfileResponse {
+    success: bool,
+    data: serde_json::Value,
+}
+
+/// Build the `Authorization` header value for `request`
+///
+/// # Arguments
+/// * `kid` - The token's `kid`
+/// * `mac_key` - The token's `mac_key`
+/// * `ts` - Current Unix time in seconds
+/// * `nonce` - A random string, unique per request
+pub fn sign(kid: &str, mac_key: &str, request: &MacRequest, ts: u64, nonce: &str) -> String {
+    let mac = mac(mac_key, request, &ts.to_string(), nonce);
+    format!(
+        r#"MAC id="{}",ts="{}",nonce="{}",mac="{}""#,
+        kid, ts, nonce, mac
+    )
+}
+
+/// Check a MAC `Authorization` header signed with [`sign`]
+///
+/// `mac_key` looks up the key for the header's `kid`, e.g. from the tokens
+/// the server has already verified with [`profile_request`]. Headers more
+/// than `max_skew` seconds away from `now` are rejected to limit replay.
+///
+/// # Returns
+/// The `kid` the request was signed with
+///
+/// # Errors
+/// [`TapSdkError::InvalidArgument`] if the header is malformed, or
+/// [`TapSdkError::TokenRejected`] if the `kid` is unknown, the timestamp is
+/// out of range or the signature does not match
+pub fn verify(
+    authorization: &str,
+    request: &MacRequest,
+    now: u64,
+    max_skew: u64,
+    mac_key: impl FnOnce(&str) -> Option<String>,
+) -> Result<String> {
+    let header = parse_header(authorization)?;
+
+    let ts: u64 = header
+        .ts
+        .parse()
+        .map_err(|_| TapSdkError::InvalidArgument(format!("invalid ts: {}", header.ts)))?;
+    if ts.abs_diff(now) > max_skew {
+        return Err(TapSdkError::TokenRejected(format!(
+            "timestamp {} is more than {}s from {}",
+            ts, max_skew, now
+        )));
+    }
+
+    let key = mac_key(header.id)
+        .ok_or_else(|| TapSdkError::TokenRejected(format!("unknown kid: {}", header.id)))?;
+    let expected = mac(&key, request, header.ts, header.nonce);
+    if !constant_time_eq(expected.as_bytes(), header.mac.as_bytes()) {
+        return Err(TapSdkError::TokenRejected("signature mismatch".to_string()));
+    }
+    Ok(header.id.to_string())
+}
+
+/// Build a signed request for the account profile endpoint
+///
+/// A successful response proves that the token was issued by TapTap for
+/// `client_id`. Parse it with [`parse_profile`].
+///
+/// Fails with [`TapSdkError::Io`] only if the operating system cannot
+/// provide randomness for the nonce.
+pub fn profile_request(
+    kid: &str,
+    mac_key: &str,
+    client_id: &str,
+    region: Region,
+) -> Result<ProfileRequest> {
+    let path = format!(
+        "/account/profile/v1?client_id={}",
+        utf8_percent_encode(client_id, NON_ALPHANUMERIC)
+    );
+    let request = MacRequest {
+        method: "GET",
+        host: region.host(),
+        port: 443,
+        path: &path,
+    };
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let authorization = sign(kid, mac_key, &request, ts, &nonce()?);
+    Ok(ProfileRequest {
+        url: format!("https://{}{}", region.host(), path),
+        authorization,
+    })
+}
+
+/// Parse the response body of a [`profile_request`]
+///
+/// # Errors
+/// [`TapSdkError::TokenRejected`] with TapTap's error description if the
+/// token was not accepted, or [`TapSdkError::InvalidArgument`] if the body
+/// is not a profile response
+pub fn parse_profile(body: &str) -> Result<Profile> {
+    let response: ProfileResponse = serde_json::from_str(body)
+        .map_err(|e| TapSdkError::InvalidArgument(format!("invalid profile response: {}", e)))?;
+    if !response.success {
+        let description = response.data["error_description"]
+            .as_str()
+            .or_else(|| response.data["error"].as_str())
+            .unwrap_or("unknown error");
+        return Err(TapSdkError::TokenRejected(description.to_string()));
+    }
+    serde_json::from_value(response.data)
+        .map_err(|e| TapSdkError::InvalidArgument(format!("invalid profile response: {}", e)))
+}
+
+/// Fields of a `MAC id="...",ts="...",nonce="...",mac="..."` header
+struct MacHeader<'a> {
+    id: &'a str,
+    ts: &'a str,
+    nonce: &'a str,
+    mac: &'a str,
+}
+
+fn parse_header(authorization: &str) -> Result<MacHeader<'_>> {
+    let invalid = || TapSdkError::InvalidArgument(format!("invalid MAC header: {}", authorization));
+    let params = authorization.strip_prefix("MAC ").ok_or_else(invalid)?;
+
+    let (mut id, mut ts, mut nonce, mut mac) = (None, None, None, None);
+    for param in params.split(',') {
+        let (name, value) = param.trim().split_once('=').ok_or_else(invalid)?;
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .ok_or_else(invalid)?;
+        match name {
+            "id" => id = Some(value),
+            "ts" => ts = Some(value),
+            "nonce" => nonce = Some(value),
+            "mac" => mac = Some(value),
+            _ => {}
+        }
+    }
+    Ok(MacHeader {
+        id: id.ok_or_else(invalid)?,
+        ts: ts.ok_or_else(invalid)?,
+        nonce: nonce.ok_or_else(invalid)?,
+        mac: mac.ok_or_else(invalid)?,
+    })
+}
+
+/// Base64 HMAC-SHA1 of the normalized request string
+fn mac(mac_key: &str, request: &MacRequest, ts: &str, nonce: &str) -> String {
+    let normalized = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n\n",
+        ts, nonce, request.method, request.path, request.host, request.port
+    );
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(mac_key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(normalized.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// A random nonce; unpredictability, not secrecy, is what matters here
+/// A random nonce from the operating system's generator
+fn nonce() -> Result<String> {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).map_err(std::io::Error::from)?;
+    Ok(format!("{:016x}", u64::from_le_bytes(bytes)))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUEST: MacRequest = MacRequest {
+        method: "GET",
+        host: "game.example.com",
+        port: 443,
+        path: "/api/save?slot=1",
+    };
+
+    #[test]
+    fn test_sign_and_verify() {
+        let header = sign("kid-1", "secret", &REQUEST, 1_700_000_000, "abc");
+        assert!(header.starts_with(r#"MAC id="kid-1",ts="1700000000",nonce="abc",mac=""#));
+
+        let lookup = |kid: &str| (kid == "kid-1").then(|| "secret".to_string());
+        let kid = verify(&header, &REQUEST, 1_700_000_010, 60, lookup).unwrap();
+        assert_eq!(kid, "kid-1");
+
+        // Wrong key, other path, stale timestamp, unknown kid
+        let wrong = |_: &str| Some("other".to_string());
+        assert!(matches!(
+            verify(&header, &REQUEST, 1_700_000_000, 60, wrong),
+            Err(TapSdkError::TokenRejected(_))
+        ));
+        let other = MacRequest {
+            path: "/api/save?slot=2",
+            ..REQUEST
+        };
+        assert!(verify(&header, &other, 1_700_000_000, 60, lookup).is_err());
+        assert!(verify(&header, &REQUEST, 1_700_001_000, 60, lookup).is_err());
+        assert!(verify(&header, &REQUEST, 1_700_000_000, 60, |_| None).is_err());
+        assert!(matches!(
+            verify("Bearer token", &REQUEST, 0, 60, lookup),
+            Err(TapSdkError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_profile_request() {
+        let request = profile_request("kid", "key", "a&b=c", Region::China).unwrap();
+        assert!(request
+            .url
+            .ends_with("/account/profile/v1?client_id=a%26b%3Dc"));
+
+        let header = parse_header(&request.authorization).unwrap();
+        let other = profile_request("kid", "key", "a&b=c", Region::China).unwrap();
+        assert_ne!(
+            header.nonce,
+            parse_header(&other.authorization).unwrap().nonce
+        );
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let profile = parse_profile(
+            r#"{"data":{"name":"Player","avatar":"","openid":"o1","unionid":"u1"},"success":true}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.openid, "o1");
+        assert_eq!(profile.unionid, "u1");
+
+        let error = parse_profile(
+            r#"{"data":{"code":-1,"error":"invalid_token","error_description":"expired"},"success":false}"#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, TapSdkError::TokenRejected(message) if message == "expired"));
+    }
+}
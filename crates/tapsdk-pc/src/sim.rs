@@ -0,0 +1,827 @@
+//! Developer simulation of the TapTap platform
+//!
+//! Available with the `dev-sim` feature on non-Windows platforms, where the
+//! real SDK does not exist. [`TapSdk::init`](crate::TapSdk::init) then
+//! succeeds against a simulated client:
+//!
+//! - the platform reports [`SystemState::PlatformOnline`](crate::error::SystemState)
+//!   right after init
+//! - the game and every DLC are owned (restrict DLCs with `TAPSDK_SIM_DLCS`,
+//!   a comma-separated list of owned DLC IDs)
+//! - authorization succeeds immediately with a fake token
+//! - cloud saves are stored on disk under [`data_dir`]
+//!
+//! Events go through the same callback parsing and queue as with the real
+//! SDK, so game code runs unchanged. On Windows the feature has no effect.
+
+use std::collections::VecDeque;
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::callback::{event_id, recover};
+
+/// Size limits enforced by the real SDK
+const MAX_SAVE_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_COVER_SIZE: u64 = 512 * 1024;
+
+/// `TapCloudSave_Result` values
+const RESULT_OK: u32 = 0;
+const RESULT_UNINITIALIZED: u32 = 1;
+const RESULT_INVALID_ARGUMENT: u32 = 4;
+const RESULT_FAILED_TO_READ_SAVE_FILE: u32 = 6;
+const RESULT_SAVE_FILE_TOO_LARGE: u32 = 7;
+const RESULT_FAILED_TO_READ_COVER_FILE: u32 = 8;
+const RESULT_COVER_FILE_TOO_LARGE: u32 = 9;
+
+/// `TapSDK_ErrorCode_CloudSave_FileNotFound`
+const ERROR_FILE_NOT_FOUND: i64 = 400002;
+/// `TapSDK_ErrorCode_InternalSdkError`
+const ERROR_INTERNAL: i64 = 9;
+
+/// Overridden cloud save directory
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Get the directory simulated cloud saves are stored in
+///
+/// Defaults to `$TAPSDK_SIM_DIR`, or `tapsdk-sim` in the system temp
+/// directory.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = recover(DATA_DIR.lock()).clone() {
+        return dir;
+    }
+    std::env::var_os("TAPSDK_SIM_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("tapsdk-sim"))
+}
+
+/// Store simulated cloud saves in `dir` from now on
+///
+/// Useful to give each test its own empty save storage.
+pub fn set_data_dir(dir: impl Into<PathBuf>) {
+    *recover(DATA_DIR.lock()) = Some(dir.into());
+}
+
+/// Metadata of a simulated cloud save, stored as `save.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SaveMeta {
+    uuid: String,
+    file_id: String,
+    name: String,
+    summary: String,
+    extra: Option<String>,
+    playtime: u32,
+    created_time: u32,
+    modified_time: u32,
+    save_size: u32,
+    cover_size: u32,
+}
+
+/// A response waiting for the next `TapSDK_RunCallbacks()`
+enum SimEvent {
+    SystemState(u32),
+    GamePlayable(bool),
+    Authorize {
+        scope: String,
+    },
+    List {
+        request_id: i64,
+        result: Result<Vec<SaveMeta>, (i64, String)>,
+    },
+    Save {
+        event_id: u32,
+        request_id: i64,
+        result: Result<SaveMeta, (i64, String)>,
+    },
+    Delete {
+        request_id: i64,
+        error: Option<(i64, String)>,
+        uuid: String,
+    },
+    File {
+        event_id: u32,
+        request_id: i64,
+        result: Result<Vec<u8>, (i64, String)>,
+    },
+}
+
+struct SimState {
+    initialized: bool,
+    callbacks: Vec<(u32, tapsdk_pc_sys::TapCallback)>,
+    pending: VecDeque<SimEvent>,
+}
+
+static STATE: Mutex<SimState> = Mutex::new(SimState {
+    initialized: false,
+    callbacks: Vec::new(),
+    pending: VecDeque::new(),
+});
+
+/// Queue a response if the simulated SDK is initialized
+fn push(event: SimEvent) {
+    let mut state = recover(STATE.lock());
+    if state.initialized {
+        state.pending.push_back(event);
+    }
+}
+
+fn is_initialized() -> bool {
+    recover(STATE.lock()).initialized
+}
+
+fn now() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or_default()
+}
+
+/// Generate a unique ID for saves and files
+fn new_id(prefix: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{:x}-{:x}", prefix, nanos, count)
+}
+
+unsafe fn read_str(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+/// Write a NUL-terminated string into a caller-provided buffer
+unsafe fn write_str(buffer: *mut c_char, len: usize, value: &str) -> bool {
+    if buffer.is_null() {
+        return false;
+    }
+    fill(std::slice::from_raw_parts_mut(buffer, len), value);
+    true
+}
+
+fn fill(buffer: &mut [c_char], value: &str) {
+    if buffer.is_empty() {
+        return;
+    }
+    let len = value.len().min(buffer.len() - 1);
+    for (dst, src) in buffer.iter_mut().zip(&value.as_bytes()[..len]) {
+        *dst = *src as c_char;
+    }
+    buffer[len] = 0;
+}
+
+fn io_error(error: std::io::Error) -> (i64, String) {
+    (ERROR_INTERNAL, error.to_string())
+}
+
+/// The directory of a save, or `None` for a UUID the simulator never
+/// generates, such as one that would escape [`data_dir`]
+fn save_dir(uuid: &str) -> Option<PathBuf> {
+    let valid = !uuid.is_empty()
+        && uuid
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    valid.then(|| data_dir().join(uuid))
+}
+
+/// The directory of a save that must exist, failing with `FileNotFound`
+fn existing_save_dir(uuid: &str) -> Result<PathBuf, (i64, String)> {
+    save_dir(uuid).ok_or_else(|| not_found(uuid))
+}
+
+fn load_meta(uuid: &str) -> Option<SaveMeta> {
+    let json = std::fs::read_to_string(save_dir(uuid)?.join("save.json")).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn store_meta(meta: &SaveMeta) -> Result<(), (i64, String)> {
+    let json = serde_json::to_string_pretty(meta).map_err(|e| (ERROR_INTERNAL, e.to_string()))?;
+    std::fs::write(existing_save_dir(&meta.uuid)?.join("save.json"), json).map_err(io_error)
+}
+
+fn list_saves() -> Result<Vec<SaveMeta>, (i64, String)> {
+    let entries = match std::fs::read_dir(data_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error(e)),
+    };
+    let mut saves: Vec<SaveMeta> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| load_meta(&entry.file_name().to_string_lossy()))
+        .collect();
+    saves.sort_by_key(|save| save.created_time);
+    Ok(saves)
+}
+
+/// Validate the files of a create/update request like the real SDK does
+unsafe fn check_files(data: *const c_char, cover: *const c_char) -> Result<(), u32> {
+    let data = read_str(data).ok_or(RESULT_INVALID_ARGUMENT)?;
+    let size = std::fs::metadata(&data)
+        .map_err(|_| RESULT_FAILED_TO_READ_SAVE_FILE)?
+        .len();
+    if size > MAX_SAVE_SIZE {
+        return Err(RESULT_SAVE_FILE_TOO_LARGE);
+    }
+    if let Some(cover) = read_str(cover) {
+        let size = std::fs::metadata(&cover)
+            .map_err(|_| RESULT_FAILED_TO_READ_COVER_FILE)?
+            .len();
+        if size > MAX_COVER_SIZE {
+            return Err(RESULT_COVER_FILE_TOO_LARGE);
+        }
+    }
+    Ok(())
+}
+
+/// Copy the request files into the save directory and update sizes
+fn write_files(
+    meta: &mut SaveMeta,
+    data: &Path,
+    cover: Option<&Path>,
+) -> Result<(), (i64, String)> {
+    let dir = existing_save_dir(&meta.uuid)?;
+    std::fs::create_dir_all(&dir).map_err(io_error)?;
+    meta.save_size = std::fs::copy(data, dir.join("data")).map_err(io_error)? as u32;
+    meta.cover_size = match cover {
+        Some(cover) => std::fs::copy(cover, dir.join("cover")).map_err(io_error)? as u32,
+        None => {
+            let _ = std::fs::remove_file(dir.join("cover"));
+            0
+        }
+    };
+    store_meta(meta)
+}
+
+fn not_found(uuid: &str) -> (i64, String) {
+    (
+        ERROR_FILE_NOT_FOUND,
+        format!("cloud save {} not found", uuid),
+    )
+}
+
+/// Simulated SDK entry points, mirroring `tapsdk_pc_sys`
+#[allow(non_snake_case)]
+pub(crate) mod ffi {
+    use super::*;
+    use tapsdk_pc_sys::{
+        ITapCloudSave, TapCallback, TapCloudSaveCreateRequest, TapCloudSaveGetFileRequest,
+        TapCloudSaveUpdateRequest,
+    };
+
+    pub unsafe fn TapSDK_RestartAppIfNecessary(_client_id: *const c_char) -> bool {
+        false
+    }
+
+    pub unsafe fn TapSDK_Init(_err_msg: *mut c_char, _pub_key: *const c_char) -> u32 {
+        let mut state = recover(STATE.lock());
+        state.initialized = true;
+        state.pending.push_back(SimEvent::SystemState(1));
+        state.pending.push_back(SimEvent::GamePlayable(true));
+        0
+    }
+
+    pub unsafe fn TapSDK_Shutdown() {
+        let mut state = recover(STATE.lock());
+        state.initialized = false;
+        state.pending.clear();
+    }
+
+    pub unsafe fn TapSDK_RunCallbacks() {
+        // Release the lock while calling out, callbacks may call back into us
+        let (events, callbacks) = {
+            let mut state = recover(STATE.lock());
+            let events: Vec<SimEvent> = state.pending.drain(..).collect();
+            (events, state.callbacks.clone())
+        };
+        for event in events {
+            deliver(event, &callbacks);
+        }
+    }
+
+    pub unsafe fn TapSDK_GetClientID(buffer: *mut c_char) -> bool {
+        write_str(buffer, 256, "dev-sim")
+    }
+
+    pub unsafe fn TapSDK_RegisterCallback(event_id: u32, callback: TapCallback) {
+        let mut state = recover(STATE.lock());
+        // Registering again after a re-init must not deliver events twice
+        if callback.is_some()
+            && !state
+                .callbacks
+                .iter()
+                .any(|&entry| same(entry, event_id, callback))
+        {
+            state.callbacks.push((event_id, callback));
+        }
+    }
+
+    pub unsafe fn TapSDK_UnregisterCallback(event_id: u32, callback: TapCallback) {
+        let mut state = recover(STATE.lock());
+        if let Some(index) = state
+            .callbacks
+            .iter()
+            .position(|&entry| same(entry, event_id, callback))
+        {
+            state.callbacks.remove(index);
+        }
+    }
+
+    fn same((id, cb): (u32, TapCallback), event_id: u32, callback: TapCallback) -> bool {
+        id == event_id && cb.map(|f| f as usize) == callback.map(|f| f as usize)
+    }
+
+    pub unsafe fn TapUser_AsyncAuthorize(scopes: *const c_char) -> u32 {
+        if !is_initialized() {
+            return 0;
+        }
+        push(SimEvent::Authorize {
+            scope: read_str(scopes).unwrap_or_default(),
+        });
+        1
+    }
+
+    pub unsafe fn TapUser_GetOpenID(buffer: *mut c_char) -> bool {
+        is_initialized() && write_str(buffer, 256, "dev-sim-user")
+    }
+
+    pub unsafe fn TapApps_IsOwned() -> bool {
+        is_initialized()
+    }
+
+    pub unsafe fn TapDLC_IsOwned(dlc_id: *const c_char) -> bool {
+        if !is_initialized() {
+            return false;
+        }
+        let Some(dlc_id) = read_str(dlc_id) else {
+            return false;
+        };
+        match std::env::var("TAPSDK_SIM_DLCS") {
+            Ok(owned) => owned.split(',').any(|id| id.trim() == dlc_id),
+            Err(_) => true,
+        }
+    }
+
+    pub unsafe fn TapDLC_ShowStore(dlc_id: *const c_char) -> bool {
+        is_initialized() && !dlc_id.is_null()
+    }
+
+    pub unsafe fn TapCloudSave() -> *mut ITapCloudSave {
+        std::ptr::NonNull::<ITapCloudSave>::dangling().as_ptr()
+    }
+
+    pub unsafe fn TapCloudSave_AsyncList(_handle: *mut ITapCloudSave, request_id: i64) -> u32 {
+        if !is_initialized() {
+            return RESULT_UNINITIALIZED;
+        }
+        push(SimEvent::List {
+            request_id,
+            result: list_saves(),
+        });
+        RESULT_OK
+    }
+
+    pub unsafe fn TapCloudSave_AsyncCreate(
+        _handle: *mut ITapCloudSave,
+        request_id: i64,
+        request: *const TapCloudSaveCreateRequest,
+    ) -> u32 {
+        if !is_initialized() {
+            return RESULT_UNINITIALIZED;
+        }
+        let Some(request) = request.as_ref() else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+        if let Err(result) = check_files(request.data_file_path, request.cover_file_path) {
+            return result;
+        }
+        let (Some(name), Some(summary)) = (read_str(request.name), read_str(request.summary))
+        else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+
+        let now = now();
+        let mut meta = SaveMeta {
+            uuid: new_id("save"),
+            file_id: new_id("file"),
+            name,
+            summary,
+            extra: read_str(request.extra),
+            playtime: request.playtime,
+            created_time: now,
+            modified_time: now,
+            save_size: 0,
+            cover_size: 0,
+        };
+        let data = PathBuf::from(read_str(request.data_file_path).unwrap_or_default());
+        let cover = read_str(request.cover_file_path).map(PathBuf::from);
+        let result = write_files(&mut meta, &data, cover.as_deref()).map(|_| meta);
+        push(SimEvent::Save {
+            event_id: event_id::CLOUD_SAVE_CREATE,
+            request_id,
+            result,
+        });
+        RESULT_OK
+    }
+
+    pub unsafe fn TapCloudSave_AsyncUpdate(
+        _handle: *mut ITapCloudSave,
+        request_id: i64,
+        request: *const TapCloudSaveUpdateRequest,
+    ) -> u32 {
+        if !is_initialized() {
+            return RESULT_UNINITIALIZED;
+        }
+        let Some(request) = request.as_ref() else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+        if let Err(result) = check_files(request.data_file_path, request.cover_file_path) {
+            return result;
+        }
+        let (Some(uuid), Some(name), Some(summary)) = (
+            read_str(request.uuid),
+            read_str(request.name),
+            read_str(request.summary),
+        ) else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+
+        let data = PathBuf::from(read_str(request.data_file_path).unwrap_or_default());
+        let cover = read_str(request.cover_file_path).map(PathBuf::from);
+        let result = match load_meta(&uuid) {
+            Some(mut meta) => {
+                meta.file_id = new_id("file");
+                meta.name = name;
+                meta.summary = summary;
+                meta.extra = read_str(request.extra);
+                meta.playtime = request.playtime;
+                meta.modified_time = now();
+                write_files(&mut meta, &data, cover.as_deref()).map(|_| meta)
+            }
+            None => Err(not_found(&uuid)),
+        };
+        push(SimEvent::Save {
+            event_id: event_id::CLOUD_SAVE_UPDATE,
+            request_id,
+            result,
+        });
+        RESULT_OK
+    }
+
+    pub unsafe fn TapCloudSave_AsyncDelete(
+        _handle: *mut ITapCloudSave,
+        request_id: i64,
+        uuid: *const c_char,
+    ) -> u32 {
+        if !is_initialized() {
+            return RESULT_UNINITIALIZED;
+        }
+        let Some(uuid) = read_str(uuid) else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+        let error = match (load_meta(&uuid), save_dir(&uuid)) {
+            (Some(_), Some(dir)) => std::fs::remove_dir_all(dir).err().map(io_error),
+            _ => Some(not_found(&uuid)),
+        };
+        push(SimEvent::Delete {
+            request_id,
+            error,
+            uuid,
+        });
+        RESULT_OK
+    }
+
+    pub unsafe fn TapCloudSave_AsyncGetData(
+        _handle: *mut ITapCloudSave,
+        request_id: i64,
+        request: *const TapCloudSaveGetFileRequest,
+    ) -> u32 {
+        get_file(request_id, request, event_id::CLOUD_SAVE_GET_DATA, "data")
+    }
+
+    pub unsafe fn TapCloudSave_AsyncGetCover(
+        _handle: *mut ITapCloudSave,
+        request_id: i64,
+        request: *const TapCloudSaveGetFileRequest,
+    ) -> u32 {
+        get_file(request_id, request, event_id::CLOUD_SAVE_GET_COVER, "cover")
+    }
+
+    unsafe fn get_file(
+        request_id: i64,
+        request: *const TapCloudSaveGetFileRequest,
+        event_id: u32,
+        file: &str,
+    ) -> u32 {
+        if !is_initialized() {
+            return RESULT_UNINITIALIZED;
+        }
+        let Some(request) = request.as_ref() else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+        let (Some(uuid), Some(file_id)) = (read_str(request.uuid), read_str(request.file_id))
+        else {
+            return RESULT_INVALID_ARGUMENT;
+        };
+        let result = match (load_meta(&uuid), save_dir(&uuid)) {
+            (Some(meta), Some(dir)) if meta.file_id == file_id => {
+                std::fs::read(dir.join(file)).map_err(|_| not_found(&uuid))
+            }
+            _ => Err(not_found(&uuid)),
+        };
+        push(SimEvent::File {
+            event_id,
+            request_id,
+            result,
+        });
+        RESULT_OK
+    }
+}
+
+/// Build the C representation of a response and pass it to its callbacks
+fn deliver(event: SimEvent, callbacks: &[(u32, tapsdk_pc_sys::TapCallback)]) {
+    let call = |event_id: u32, data: *mut c_void| {
+        for &(id, callback) in callbacks {
+            if let (true, Some(callback)) = (id == event_id, callback) {
+                unsafe { callback(event_id, data) };
+            }
+        }
+    };
+
+    match event {
+        SimEvent::SystemState(state) => {
+            let mut data = tapsdk_pc_sys::TapSystemStateNotification { state };
+            call(event_id::SYSTEM_STATE_CHANGED, as_data(&mut data));
+        }
+        SimEvent::GamePlayable(is_playable) => {
+            let mut data = tapsdk_pc_sys::GamePlayableStatusChangedResponse { is_playable };
+            call(event_id::GAME_PLAYABLE_STATUS_CHANGED, as_data(&mut data));
+        }
+        SimEvent::Authorize { scope } => {
+            let mut data: tapsdk_pc_sys::AuthorizeFinishedResponse = unsafe { std::mem::zeroed() };
+            fill(&mut data.token_type, "mac");
+            fill(&mut data.kid, "dev-sim-kid");
+            fill(&mut data.mac_key, "dev-sim-mac-key");
+            fill(&mut data.mac_algorithm, "hmac-sha-1");
+            fill(&mut data.scope, &scope);
+            call(event_id::AUTHORIZE_FINISHED, as_data(&mut data));
+        }
+        SimEvent::List { request_id, result } => {
+            let (saves, error) = split(result);
+            let saves = saves.unwrap_or_default();
+            let infos: Vec<RawSaveInfo> = saves.iter().map(RawSaveInfo::new).collect();
+            let raw: Vec<tapsdk_pc_sys::TapCloudSaveInfo> =
+                infos.iter().map(RawSaveInfo::info).collect();
+            let mut data: tapsdk_pc_sys::TapCloudSaveListResponse = unsafe { std::mem::zeroed() };
+            data.request_id = request_id;
+            data.error = RawError::ptr(&error);
+            data.save_count = raw.len() as i32;
+            if !raw.is_empty() {
+                data.saves = raw.as_ptr();
+            }
+            call(event_id::CLOUD_SAVE_LIST, as_data(&mut data));
+        }
+        SimEvent::Save {
+            event_id,
+            request_id,
+            result,
+        } => {
+            let (save, error) = split(result);
+            let storage = save.as_ref().map(RawSaveInfo::new);
+            let info = storage.as_ref().map(RawSaveInfo::info);
+            let mut data: tapsdk_pc_sys::TapCloudSaveCreateResponse = unsafe { std::mem::zeroed() };
+            data.request_id = request_id;
+            data.error = RawError::ptr(&error);
+            if let Some(info) = &info {
+                data.save = info;
+            }
+            call(event_id, as_data(&mut data));
+        }
+        SimEvent::Delete {
+            request_id,
+            error,
+            uuid,
+        } => {
+            let error = error.map(RawError::new);
+            let uuid = c_string(&uuid);
+            let mut data: tapsdk_pc_sys::TapCloudSaveDeleteResponse = unsafe { std::mem::zeroed() };
+            data.request_id = request_id;
+            data.error = RawError::ptr(&error);
+            data.uuid = uuid.as_ptr();
+            call(event_id::CLOUD_SAVE_DELETE, as_data(&mut data));
+        }
+        SimEvent::File {
+            event_id,
+            request_id,
+            result,
+        } => {
+            let (bytes, error) = split(result);
+            let bytes = bytes.unwrap_or_default();
+            let mut data: tapsdk_pc_sys::TapCloudSaveGetFileResponse =
+                unsafe { std::mem::zeroed() };
+            data.request_id = request_id;
+            data.error = RawError::ptr(&error);
+            data.size = bytes.len() as u32;
+            if !bytes.is_empty() {
+                data.data = bytes.as_ptr() as *const c_void;
+            }
+            call(event_id, as_data(&mut data));
+        }
+    }
+}
+
+fn as_data<T>(value: &mut T) -> *mut c_void {
+    value as *mut T as *mut c_void
+}
+
+/// Split a response result into its payload and C error
+fn split<T>(result: Result<T, (i64, String)>) -> (Option<T>, Option<Box<RawError>>) {
+    match result {
+        Ok(value) => (Some(value), None),
+        Err(error) => (None, Some(RawError::new(error))),
+    }
+}
+
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+/// A `TapSDK_Error` and the message it points to
+struct RawError {
+    _message: CString,
+    error: tapsdk_pc_sys::TapSDK_Error,
+}
+
+impl RawError {
+    fn new((code, message): (i64, String)) -> Box<Self> {
+        let message = c_string(&message);
+        Box::new(RawError {
+            error: tapsdk_pc_sys::TapSDK_Error {
+                code,
+                message: message.as_ptr(),
+            },
+            _message: message,
+        })
+    }
+
+    fn ptr(error: &Option<Box<Self>>) -> *const tapsdk_pc_sys::TapSDK_Error {
+        error
+            .as_ref()
+            .map(|e| &e.error as *const _)
+            .unwrap_or(std::ptr::null())
+    }
+}
+
+/// Strings backing a `TapCloudSaveInfo`
+struct RawSaveInfo {
+    uuid: CString,
+    file_id: CString,
+    name: CString,
+    summary: CString,
+    extra: Option<CString>,
+    meta: SaveMeta,
+}
+
+impl RawSaveInfo {
+    fn new(meta: &SaveMeta) -> Self {
+        RawSaveInfo {
+            uuid: c_string(&meta.uuid),
+            file_id: c_string(&meta.file_id),
+            name: c_string(&meta.name),
+            summary: c_string(&meta.summary),
+            extra: meta.extra.as_deref().map(c_string),
+            meta: meta.clone(),
+        }
+    }
+
+    fn info(&self) -> tapsdk_pc_sys::TapCloudSaveInfo {
+        tapsdk_pc_sys::TapCloudSaveInfo {
+            uuid: self.uuid.as_ptr(),
+            file_id: self.file_id.as_ptr(),
+            name: self.name.as_ptr(),
+            save_size: self.meta.save_size,
+            cover_size: self.meta.cover_size,
+            summary: self.summary.as_ptr(),
+            extra: self
+                .extra
+                .as_ref()
+                .map(|e| e.as_ptr())
+                .unwrap_or(std::ptr::null()),
+            playtime: self.meta.playtime,
+            created_time: self.meta.created_time,
+            modified_time: self.meta.modified_time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ffi::*;
+    use super::*;
+    use crate::callback::{parse_event, TapEvent};
+
+    static EVENTS: Mutex<Vec<TapEvent>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn collect(event_id: u32, data: *mut c_void) {
+        EVENTS.lock().unwrap().push(parse_event(event_id, data));
+    }
+
+    fn run() -> Vec<TapEvent> {
+        unsafe { TapSDK_RunCallbacks() };
+        std::mem::take(&mut *EVENTS.lock().unwrap())
+    }
+
+    #[test]
+    fn test_simulated_cloud_save_round_trip() {
+        let dir = std::env::temp_dir().join(format!("tapsdk-sim-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        set_data_dir(dir.join("saves"));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("input.dat");
+        std::fs::write(&file, b"progress").unwrap();
+
+        let event_ids = [
+            event_id::SYSTEM_STATE_CHANGED,
+            event_id::GAME_PLAYABLE_STATUS_CHANGED,
+            event_id::CLOUD_SAVE_CREATE,
+            event_id::CLOUD_SAVE_LIST,
+            event_id::CLOUD_SAVE_GET_DATA,
+        ];
+        unsafe {
+            // Registered twice, like after a re-init
+            for id in event_ids.iter().chain(&event_ids) {
+                TapSDK_RegisterCallback(*id, Some(collect));
+            }
+            assert_eq!(TapSDK_Init(std::ptr::null_mut(), std::ptr::null()), 0);
+            assert!(TapApps_IsOwned());
+        }
+        let events = run();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], TapEvent::SystemStateChanged(_)));
+
+        let name = c_string("slot");
+        let path = c_string(file.to_str().unwrap());
+        let mut request: tapsdk_pc_sys::TapCloudSaveCreateRequest = unsafe { std::mem::zeroed() };
+        request.name = name.as_ptr();
+        request.summary = name.as_ptr();
+        request.data_file_path = path.as_ptr();
+        let handle = unsafe { TapCloudSave() };
+        assert_eq!(unsafe { TapCloudSave_AsyncCreate(handle, 1, &request) }, 0);
+        let save = match run().pop() {
+            Some(TapEvent::CloudSaveCreate(data)) => data.save.expect("save"),
+            other => panic!("unexpected event: {:?}", other),
+        };
+        assert_eq!(save.save_size, 8);
+
+        let uuid = c_string(save.uuid.as_str());
+        let file_id = c_string(save.file_id.as_str());
+        let get = tapsdk_pc_sys::TapCloudSaveGetFileRequest {
+            uuid: uuid.as_ptr(),
+            file_id: file_id.as_ptr(),
+        };
+        unsafe {
+            assert_eq!(TapCloudSave_AsyncList(handle, 2), 0);
+            assert_eq!(TapCloudSave_AsyncGetData(handle, 3, &get), 0);
+        }
+        match run().as_slice() {
+            [TapEvent::CloudSaveList(list), TapEvent::CloudSaveGetData(file)] => {
+                assert_eq!(list.saves.len(), 1);
+                assert_eq!(file.data, b"progress");
+            }
+            other => panic!("unexpected events: {:?}", other),
+        }
+
+        unsafe {
+            TapSDK_Shutdown();
+            for id in event_ids {
+                TapSDK_UnregisterCallback(id, Some(collect));
+            }
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fill_truncates() {
+        let mut buffer = [1 as c_char; 4];
+        fill(&mut buffer, "dev-sim");
+        assert_eq!(buffer, [b'd' as c_char, b'e' as c_char, b'v' as c_char, 0]);
+        fill(&mut [], "dev-sim");
+    }
+
+    #[test]
+    fn test_save_dir_rejects_paths() {
+        assert!(save_dir("save-1a2b-0").is_some());
+        for uuid in ["", "..", "../escape", "a/b", "a\\b", "/abs"] {
+            assert!(save_dir(uuid).is_none(), "{:?}", uuid);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Temporary files for uploading in-memory save data
+//!
+//! The SDK only uploads from files on disk. [`stage`] writes bytes to a file
+//! owned by this crate, which is deleted once the response for its request
+//! arrives, when the request fails to start, or when the SDK shuts down.
+//! Files left behind by a crashed process are swept from the staging
+//! directory on the next initialization.
+//!
+//! ```no_run
+//! use tapsdk_pc::cloudsave::CreateSaveRequest;
+//! use tapsdk_pc::{staging, CloudSave};
+//!
+//! # fn main() -> tapsdk_pc::Result<()> {
+//! let cloud_save = CloudSave::get().expect("SDK not initialized");
+//! let request = CreateSaveRequest {
+//!     name: "slot1".to_string(),
+//!     summary: "Chapter 2".to_string(),
+//!     extra: None,
+//!     playtime: 0,
+//!     data_file_path: staging::stage(1, b"save contents")?.into_boxed_path(),
+//!     cover_file_path: None,
+//! };
+//! // The staged file is deleted even if this fails
+//! cloud_save.create(1, &request)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::callback::recover;
+use crate::error::Result;
+
+/// Staged files and the request each one belongs to
+static STAGED: Mutex<Vec<(i64, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Makes file names unique within the process
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Directory set with [`set_dir`]
+static DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Files from other processes are only swept once they are this old, in
+/// case another game is still uploading from a shared directory
+const SWEEP_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Set the directory staged files are written to
+///
+/// Defaults to `tapsdk-pc` in the system temp directory. Files staged
+/// before the change stay where they are and are still cleaned up.
+pub fn set_dir(dir: impl Into<PathBuf>) {
+    *recover(DIR.write()) = Some(dir.into());
+}
+
+/// Get the directory staged files are written to
+pub fn dir() -> PathBuf {
+    recover(DIR.read())
+        .clone()
+        .unwrap_or_else(|| std::env::temp_dir().join("tapsdk-pc"))
+}
+
+/// Process ID encoded in a staged file name, `None` for other files
+fn owner_pid(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".bin")?;
+    let mut parts = name.splitn(3, '-');
+    let pid = parts.next()?.parse().ok()?;
+    // Request IDs may be negative, so only check that the parts exist
+    parts.next()?;
+    parts.next()?;
+    Some(pid)
+}
+
+/// Delete staged files left behind by processes that exited without
+/// cleaning up, e.g. because they crashed
+pub(crate) fn sweep() {
+    let Ok(entries) = fs::read_dir(dir()) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if owner_pid(&path).is_none_or(|pid| pid == std::process::id()) {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok());
+        if age.is_some_and(|age| age >= SWEEP_MIN_AGE) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Write `data` to a temporary file for the request with `request_id`
+///
+/// The file is deleted when the response event for `request_id` is
+/// received, or right away if the request fails to start. Call [`release`]
+/// if the file ends up not being used in a request.
+///
+/// # Returns
+/// The path to pass as `data_file_path` or `cover_file_path`
+pub fn stage(request_id: i64, data: &[u8]) -> Result<PathBuf> {
+    let dir = dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}-{}-{}.bin",
+        std::process::id(),
+        request_id,
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    if let Err(e) = fs::write(&path, data) {
+        // Don't leave a partial file behind
+        let _ = fs::remove_file(&path);
+        return Err(e.into());
+    }
+    recover(STAGED.lock()).push((request_id, path.clone()));
+    Ok(path)
+}
+
+/// Delete the files staged for `request_id`
+pub fn release(request_id: i64) {
+    let released: Vec<PathBuf> = {
+        let mut staged = recover(STAGED.lock());
+        let (released, kept) = staged.drain(..).partition(|(id, _)| *id == request_id);
+        *staged = kept;
+        released.into_iter().map(|(_, path)| path).collect()
+    };
+    for path in released {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Delete `path` if it is a staged file
+pub(crate) fn discard(path: &Path) {
+    let mut staged = recover(STAGED.lock());
+    if let Some(index) = staged.iter().position(|(_, staged)| staged == path) {
+        staged.remove(index);
+        drop(staged);
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Delete every staged file
+pub(crate) fn release_all() {
+    let released = std::mem::take(&mut *recover(STAGED.lock()));
+    for (_, path) in released {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_deletes_only_that_request() {
+        let first = stage(-1001, b"one").unwrap();
+        let second = stage(-1002, b"two").unwrap();
+        assert_eq!(fs::read(&first).unwrap(), b"one");
+
+        release(-1001);
+        assert!(!first.exists());
+        assert!(second.exists());
+
+        release(-1002);
+        assert!(!second.exists());
+    }
+
+    #[test]
+    fn test_owner_pid() {
+        assert_eq!(owner_pid(Path::new("/tmp/tapsdk-pc/42-7-0.bin")), Some(42));
+        assert_eq!(owner_pid(Path::new("/tmp/tapsdk-pc/42--7-3.bin")), Some(42));
+        assert_eq!(owner_pid(Path::new("/tmp/tapsdk-pc/notes.txt")), None);
+        assert_eq!(owner_pid(Path::new("/tmp/tapsdk-pc/x-7-0.bin")), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Watching the TapTap client state
+//!
+//! `SystemStateChanged` events arrive mixed with every other event.
+//! Subsystems that only care whether the client is online read the current
+//! state and wait on a [`StateChanges`] for the next transition, instead of
+//! scanning the event stream.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::callback::{recover, TapEvent};
+use crate::error::SystemState;
+
+/// The reported state and the waiters for its next transition
+#[derive(Debug)]
+struct Watch {
+    inner: Mutex<Inner>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: SystemState,
+    /// Incremented on every transition
+    version: u64,
+    /// Tasks waiting in [`StateChanged`]
+    wakers: Vec<Waker>,
+}
+
+impl Watch {
+    const fn new() -> Self {
+        Watch {
+            inner: Mutex::new(Inner {
+                state: SystemState::Unknown,
+                version: 0,
+                wakers: Vec::new(),
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        recover(self.inner.lock())
+    }
+
+    /// Store `state` and wake every waiter if it differs from the last one
+    fn set(&self, state: SystemState) {
+        let wakers = {
+            let mut inner = self.lock();
+            if inner.state == state {
+                return;
+            }
+            inner.state = state;
+            inner.version += 1;
+            std::mem::take(&mut inner.wakers)
+        };
+        self.changed.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    fn changes(&'static self) -> (SystemState, StateChanges) {
+        let inner = self.lock();
+        let changes = StateChanges {
+            watch: self,
+            seen: inner.version,
+        };
+        (inner.state, changes)
+    }
+}
+
+static STATE: Watch = Watch::new();
+
+/// The TapTap client state reported by the last `SystemStateChanged` event
+///
+/// [`SystemState::Unknown`] until the first one arrives after
+/// initialization. While it is [`SystemState::PlatformOffline`], cloud save
+/// requests fail with [`TapSdkError::PlatformOffline`](crate::TapSdkError::PlatformOffline)
+/// without being sent.
+pub fn system_state() -> SystemState {
+    STATE.lock().state
+}
+
+/// Get the current client state and a receiver of its transitions
+///
+/// Works like a `tokio::sync::watch` channel: the receiver reports the
+/// latest state once it differs from the one last seen, skipping any
+/// intermediate ones. Transitions are seen as the SDK callbacks run, so
+/// something else must keep polling events, such as the game loop or the
+/// Node.js bindings; don't block the polling thread waiting on them.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// use tapsdk_pc::error::SystemState;
+/// use tapsdk_pc::sdk;
+///
+/// let (mut state, mut changes) = sdk::state_watch();
+/// loop {
+///     if state == SystemState::PlatformOnline {
+///         // Sync cloud saves
+///     }
+///     state = changes.changed().await;
+/// }
+/// # }
+/// ```
+pub fn state_watch() -> (SystemState, StateChanges) {
+    STATE.changes()
+}
+
+/// Remember the state reported by a `SystemStateChanged` event
+pub(crate) fn record_event(event: &TapEvent) {
+    if let TapEvent::SystemStateChanged(data) = event {
+        STATE.set(data.state);
+    }
+}
+
+/// Forget the reported state, when a new session starts
+pub(crate) fn reset() {
+    STATE.set(SystemState::Unknown);
+}
+
+/// Receiver of client state transitions, see [`state_watch`]
+#[derive(Debug, Clone)]
+pub struct StateChanges {
+    watch: &'static Watch,
+    /// Version of the last state seen
+    seen: u64,
+}
+
+impl StateChanges {
+    /// The current state, without marking it seen
+    pub fn current(&self) -> SystemState {
+        self.watch.lock().state
+    }
+
+    /// Whether the state changed since it was last seen
+    pub fn has_changed(&self) -> bool {
+        self.watch.lock().version != self.seen
+    }
+
+    /// Take the new state if it changed since it was last seen
+    pub fn try_changed(&mut self) -> Option<SystemState> {
+        let inner = self.watch.lock();
+        self.take(&inner)
+    }
+
+    /// Block until the state changes, for at most `timeout`
+    ///
+    /// Returns `None` on timeout.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Option<SystemState> {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.watch.lock();
+        loop {
+            if let Some(state) = self.take(&inner) {
+                return Some(state);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            inner = recover(self.watch.changed.wait_timeout(inner, remaining)).0;
+        }
+    }
+
+    /// Wait for the state to change, resolving to the new state
+    pub fn changed(&mut self) -> StateChanged<'_> {
+        StateChanged { changes: self }
+    }
+
+    fn take(&mut self, inner: &Inner) -> Option<SystemState> {
+        if inner.version == self.seen {
+            return None;
+        }
+        self.seen = inner.version;
+        Some(inner.state)
+    }
+}
+
+/// Future returned by [`StateChanges::changed`]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct StateChanged<'a> {
+    changes: &'a mut StateChanges,
+}
+
+impl Future for StateChanged<'_> {
+    type Output = SystemState;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<SystemState> {
+        let watch = self.changes.watch;
+        let mut inner = watch.lock();
+        if let Some(state) = self.changes.take(&inner) {
+            return Poll::Ready(state);
+        }
+        if !inner.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            inner.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_state_watch_reports_transitions() {
+        let watch: &'static Watch = Box::leak(Box::new(Watch::new()));
+        let (state, mut changes) = watch.changes();
+        assert_eq!(state, SystemState::Unknown);
+        assert_eq!(changes.try_changed(), None);
+
+        // Repeating the same state is not a transition
+        watch.set(SystemState::Unknown);
+        assert!(!changes.has_changed());
+
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut changes.changed()).poll(&mut cx).is_pending());
+        watch.set(SystemState::PlatformOffline);
+        watch.set(SystemState::PlatformOnline);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            Pin::new(&mut changes.changed()).poll(&mut cx),
+            Poll::Ready(SystemState::PlatformOnline)
+        );
+
+        assert_eq!(changes.wait_timeout(Duration::from_millis(1)), None);
+        let mut other = changes.clone();
+        let waiter = std::thread::spawn(move || other.wait_timeout(Duration::from_secs(10)));
+        watch.set(SystemState::PlatformShutdown);
+        assert_eq!(waiter.join().unwrap(), Some(SystemState::PlatformShutdown));
+        assert_eq!(changes.current(), SystemState::PlatformShutdown);
+    }
+}
@@ -0,0 +1,208 @@
+//! Async stream of events for games built on an async runtime
+//!
+//! Everything here is woken by the event pump, so it works on any executor.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::callback::{recover, TapEvent};
+use crate::pump::{self, Sink};
+
+/// Events waiting to be taken from one stream
+#[derive(Default)]
+pub(crate) struct Buffer {
+    events: VecDeque<TapEvent>,
+    waker: Option<Waker>,
+    /// Only keep the response to this request
+    request_id: Option<i64>,
+}
+
+impl Buffer {
+    /// Queue events and wake the task waiting on the stream
+    pub(crate) fn push(&mut self, events: &[TapEvent]) {
+        let before = self.events.len();
+        match self.request_id {
+            Some(request_id) => self.events.extend(
+                events
+                    .iter()
+                    .filter(|event| event.request_id() == Some(request_id))
+                    .cloned(),
+            ),
+            None => self.events.extend(events.iter().cloned()),
+        }
+        if self.events.len() > before {
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Take the next event, or remember to wake the task when one arrives
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<TapEvent> {
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(event),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Stream of events received from the SDK
+///
+/// Created with [`event_stream`]. The stream never ends; drop it to stop
+/// listening.
+pub struct EventStream {
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+/// Get a stream of every event received from now on
+///
+/// While any stream is alive, a background thread runs the SDK callbacks
+/// every 16ms and wakes the streams when events arrive, so no manual
+/// polling is needed and any async runtime can drive the stream. Every
+/// stream gets its own copy of every event, and events still reach
+/// [`poll_events`](crate::callback::poll_events) as usual.
+///
+/// # Example
+/// ```no_run
+/// use futures_util::StreamExt;
+///
+/// # async fn example() {
+/// let mut events = tapsdk_pc::callback::event_stream();
+/// while let Some(event) = events.next().await {
+///     println!("{:?}", event);
+/// }
+/// # }
+/// ```
+pub fn event_stream() -> EventStream {
+    let buffer = Arc::new(Mutex::new(Buffer::default()));
+    pump::register(Sink::Stream(Arc::downgrade(&buffer)));
+    EventStream { buffer }
+}
+
+impl Stream for EventStream {
+    type Item = TapEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TapEvent>> {
+        recover(self.buffer.lock()).poll_event(cx).map(Some)
+    }
+}
+
+/// Future resolving to the response to one request
+///
+/// Created with [`response`]. Dropping it stops waiting; the response is
+/// still delivered to any other consumer.
+pub struct Response {
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+/// Wait for the response to the cloud save request `request_id`
+///
+/// Resolves to the response event, which may carry an error. Like
+/// [`event_stream`], it is woken by the event pump, so any async runtime can
+/// drive it. Create it before the response can arrive, right after starting
+/// the request.
+///
+/// # Example
+/// ```no_run
+/// use tapsdk_pc::{callback, CloudSave, TapEvent};
+///
+/// # async fn example() -> tapsdk_pc::Result<()> {
+/// let cloud_save = CloudSave::get().expect("SDK not initialized");
+/// cloud_save.list(1)?;
+/// if let TapEvent::CloudSaveList(data) = callback::response(1).await {
+///     println!("{} saves", data.saves.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn response(request_id: i64) -> Response {
+    let buffer = Arc::new(Mutex::new(Buffer {
+        request_id: Some(request_id),
+        ..Buffer::default()
+    }));
+    pump::register(Sink::Stream(Arc::downgrade(&buffer)));
+    Response { buffer }
+}
+
+impl Future for Response {
+    type Output = TapEvent;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<TapEvent> {
+        recover(self.buffer.lock()).poll_event(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_event_stream_wakes_on_push() {
+        let mut stream = EventStream {
+            buffer: Arc::new(Mutex::new(Buffer::default())),
+        };
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        recover(stream.buffer.lock()).push(&[TapEvent::Unknown { event_id: 7 }]);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+
+        match Pin::new(&mut stream).poll_next(&mut cx) {
+            Poll::Ready(Some(event)) => assert_eq!(event.event_id(), 7),
+            _ => panic!("expected the pushed event"),
+        }
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn test_response_ignores_other_requests() {
+        let mut response = Response {
+            buffer: Arc::new(Mutex::new(Buffer {
+                request_id: Some(2),
+                ..Buffer::default()
+            })),
+        };
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut response).poll(&mut cx).is_pending());
+        let delete = |request_id| {
+            TapEvent::CloudSaveDelete(crate::callback::CloudSaveDeleteData {
+                request_id,
+                error: None,
+                uuid: crate::cloudsave::SaveUuid::default(),
+            })
+        };
+        recover(response.buffer.lock()).push(&[TapEvent::Unknown { event_id: 7 }, delete(1)]);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+        assert!(Pin::new(&mut response).poll(&mut cx).is_pending());
+
+        recover(response.buffer.lock()).push(&[delete(2)]);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        match Pin::new(&mut response).poll(&mut cx) {
+            Poll::Ready(event) => assert_eq!(event.request_id(), Some(2)),
+            Poll::Pending => panic!("expected the response"),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Handlers for a single kind of event, removed when their handle is dropped
+//!
+//! Register a handler with [`TapSdk::on`](crate::TapSdk::on) and keep the
+//! returned [`Subscription`] for as long as the handler should run.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::callback::{
+    recover, AuthorizeFinishedData, CloudSaveCreateData, CloudSaveDeleteData, CloudSaveGetFileData,
+    CloudSaveListData, CloudSaveProgressData, DlcPlayableStatusChangedData,
+    GamePlayableStatusChangedData, InternalErrorData, SystemStateChangedData, TapEvent,
+};
+
+/// Event data that handlers can subscribe to with [`TapSdk::on`](crate::TapSdk::on)
+///
+/// Data types shared by several events match all of them:
+/// [`CloudSaveCreateData`] covers both create and update responses, and
+/// [`CloudSaveGetFileData`] both data and cover downloads.
+pub trait EventData: Any + Send + Sync {
+    /// Get the data if the event carries this type
+    fn from_event(event: &TapEvent) -> Option<&Self>;
+}
+
+macro_rules! event_data {
+    ($($data:ty => $($variant:ident)|+;)*) => {
+        $(impl EventData for $data {
+            fn from_event(event: &TapEvent) -> Option<&Self> {
+                match event {
+                    $(TapEvent::$variant(data))|+ => Some(data),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+event_data! {
+    SystemStateChangedData => SystemStateChanged;
+    AuthorizeFinishedData => AuthorizeFinished;
+    GamePlayableStatusChangedData => GamePlayableStatusChanged;
+    DlcPlayableStatusChangedData => DlcPlayableStatusChanged;
+    CloudSaveListData => CloudSaveList;
+    CloudSaveCreateData => CloudSaveCreate | CloudSaveUpdate;
+    CloudSaveDeleteData => CloudSaveDelete;
+    CloudSaveGetFileData => CloudSaveGetData | CloudSaveGetCover;
+    CloudSaveProgressData => CloudSaveProgress;
+    InternalErrorData => InternalError;
+}
+
+type Handler = Arc<dyn Fn(&TapEvent) + Send + Sync>;
+
+/// Installed handlers, keyed by subscription ID
+static HANDLERS: RwLock<Vec<(u64, Handler)>> = RwLock::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Handle to a handler installed with [`TapSdk::on`](crate::TapSdk::on)
+///
+/// Dropping the handle removes the handler.
+#[must_use = "the handler is removed as soon as the subscription is dropped"]
+#[derive(Debug)]
+pub struct Subscription {
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        recover(HANDLERS.write()).retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Install a handler for events carrying `T`
+pub(crate) fn subscribe<T: EventData>(
+    handler: impl Fn(&T) + Send + Sync + 'static,
+) -> Subscription {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let handler: Handler = Arc::new(move |event| {
+        if let Some(data) = T::from_event(event) {
+            handler(data);
+        }
+    });
+    recover(HANDLERS.write()).push((id, handler));
+    Subscription { id }
+}
+
+/// Remove every handler
+pub(crate) fn clear() {
+    recover(HANDLERS.write()).clear();
+}
+
+/// Run the installed handlers for an event
+pub(crate) fn notify(event: &TapEvent) {
+    // Handlers may subscribe or unsubscribe, so run them without the lock
+    let handlers: Vec<Handler> = recover(HANDLERS.read())
+        .iter()
+        .map(|(_, handler)| handler.clone())
+        .collect();
+    for handler in handlers {
+        // A panicking handler must not swallow the event itself
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(event)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_subscription_is_removed_on_drop() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let event = TapEvent::DlcPlayableStatusChanged(DlcPlayableStatusChangedData {
+            dlc_id: "subscription-test".to_string(),
+            is_playable: true,
+        });
+
+        let subscription = subscribe(|data: &DlcPlayableStatusChangedData| {
+            if data.dlc_id == "subscription-test" {
+                CALLS.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        notify(&event);
+        notify(&TapEvent::Unknown { event_id: 0 });
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+
+        drop(subscription);
+        notify(&event);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,8 +1,10 @@
 //! User authentication functionality
 
-use std::ffi::{CStr, CString};
+use std::ffi::CStr;
 
-use crate::error::{AuthorizeResult, Result, TapSdkError};
+use crate::error::{c_string, check_text, AuthorizeResult, Result, TapSdkError};
+use crate::ffi;
+use crate::observer::{self, Operation};
 use crate::sdk::is_initialized;
 
 /// Request user authorization
@@ -34,15 +36,19 @@ pub fn authorize(scopes: &str) -> Result<()> {
         return Err(TapSdkError::NotInitialized);
     }
 
-    let scopes_c = CString::new(scopes)?;
+    check_text("scopes", scopes, false)?;
+    let scopes_c = c_string("scopes", scopes)?;
 
-    let result = unsafe { tapsdk_pc_sys::TapUser_AsyncAuthorize(scopes_c.as_ptr()) };
+    let result = unsafe { ffi::TapUser_AsyncAuthorize(scopes_c.as_ptr()) };
 
     let auth_result = AuthorizeResult::from(result);
 
     match auth_result {
-        AuthorizeResult::Ok => Ok(()),
-        _ => Err(TapSdkError::AuthorizeFailed(auth_result)),
+        AuthorizeResult::Ok => {
+            observer::request_started(Operation::Authorize, None);
+            Ok(())
+        }
+        _ => Err(observer::error(TapSdkError::AuthorizeFailed(auth_result))),
     }
 }
 
@@ -59,7 +65,7 @@ pub fn get_open_id() -> Option<String> {
 
     let mut buffer: [std::os::raw::c_char; 256] = [0; 256];
 
-    let success = unsafe { tapsdk_pc_sys::TapUser_GetOpenID(buffer.as_mut_ptr()) };
+    let success = unsafe { ffi::TapUser_GetOpenID(buffer.as_mut_ptr()) };
 
     if success {
         let open_id = unsafe {
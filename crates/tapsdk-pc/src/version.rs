@@ -0,0 +1,133 @@
+//! Version information for diagnosing binding/SDK mismatches
+
+/// Versions of these bindings and of the SDK they talk to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// Version of the `tapsdk-pc` crate
+    pub crate_version: &'static str,
+    /// TapTap PC SDK release the bindings were generated from
+    pub sdk_header_version: &'static str,
+    /// File version of the loaded `taptap_api.dll`
+    ///
+    /// `None` if the DLL is not loaded, has no version resource, or on
+    /// platforms other than Windows.
+    pub dll_version: Option<String>,
+}
+
+/// Get the binding, SDK header and runtime DLL versions
+///
+/// # Example
+/// ```no_run
+/// let version = tapsdk_pc::version();
+/// println!(
+///     "tapsdk-pc {} (SDK {}), DLL {:?}",
+///     version.crate_version, version.sdk_header_version, version.dll_version
+/// );
+/// ```
+pub fn version() -> VersionInfo {
+    VersionInfo {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        sdk_header_version: tapsdk_pc_sys::SDK_VERSION,
+        dll_version: dll_version(),
+    }
+}
+
+#[cfg(windows)]
+fn dll_version() -> Option<String> {
+    use std::ffi::c_void;
+    use std::ptr;
+
+    // VS_FIXEDFILEINFO; only the file version is read
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct VsFixedFileInfo {
+        signature: u32,
+        struc_version: u32,
+        file_version_ms: u32,
+        file_version_ls: u32,
+        product_version_ms: u32,
+        product_version_ls: u32,
+        file_flags_mask: u32,
+        file_flags: u32,
+        file_os: u32,
+        file_type: u32,
+        file_subtype: u32,
+        file_date_ms: u32,
+        file_date_ls: u32,
+    }
+
+    #[link(name = "version")]
+    extern "system" {
+        fn GetFileVersionInfoSizeW(filename: *const u16, handle: *mut u32) -> u32;
+        fn GetFileVersionInfoW(
+            filename: *const u16,
+            handle: u32,
+            len: u32,
+            data: *mut c_void,
+        ) -> i32;
+        fn VerQueryValueW(
+            block: *const c_void,
+            sub_block: *const u16,
+            buffer: *mut *mut c_void,
+            len: *mut u32,
+        ) -> i32;
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    // The DLL is linked at load time or mapped by `library::load`
+    let path = crate::library::dll_path()?;
+
+    let size = unsafe { GetFileVersionInfoSizeW(path.as_ptr(), ptr::null_mut()) };
+    if size == 0 {
+        return None;
+    }
+    let mut data = vec![0u8; size as usize];
+    if unsafe { GetFileVersionInfoW(path.as_ptr(), 0, size, data.as_mut_ptr().cast()) } == 0 {
+        return None;
+    }
+
+    let mut info: *mut c_void = ptr::null_mut();
+    let mut info_len = 0u32;
+    let found = unsafe {
+        VerQueryValueW(
+            data.as_ptr().cast(),
+            wide("\\").as_ptr(),
+            &mut info,
+            &mut info_len,
+        )
+    };
+    if found == 0 || info.is_null() || (info_len as usize) < std::mem::size_of::<VsFixedFileInfo>()
+    {
+        return None;
+    }
+
+    // Points into `data`, which is still alive
+    let info = unsafe { &*(info as *const VsFixedFileInfo) };
+    Some(format!(
+        "{}.{}.{}.{}",
+        info.file_version_ms >> 16,
+        info.file_version_ms & 0xffff,
+        info.file_version_ls >> 16,
+        info.file_version_ls & 0xffff,
+    ))
+}
+
+#[cfg(not(windows))]
+fn dll_version() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let version = version();
+        assert_eq!(version.crate_version, env!("CARGO_PKG_VERSION"));
+        assert!(!version.sdk_header_version.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+//! Timing out cloud save requests that never get a response
+//!
+//! If the TapTap client drops a callback, a request stays pending forever,
+//! and so does any future or promise waiting for its response. A
+//! [`Watchdog`] synthesizes the response of every request that has been
+//! pending for too long, with the error code
+//! [`CLOUD_SAVE_TIMEOUT`](crate::error::error_code::CLOUD_SAVE_TIMEOUT).
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use tapsdk_pc::error::error_code;
+//! use tapsdk_pc::{watchdog, TapEvent, TapSdk};
+//!
+//! let sdk = TapSdk::init("your_public_key").expect("Failed to init SDK");
+//! let _watchdog = watchdog::start(Duration::from_secs(60));
+//!
+//! for event in sdk.run_callbacks() {
+//!     if let TapEvent::CloudSaveList(data) = event {
+//!         if let Some((error_code::CLOUD_SAVE_TIMEOUT, _)) = data.error {
+//!             // Offer to retry
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::callback::{
+    self, CloudSaveCreateData, CloudSaveDeleteData, CloudSaveGetFileData, CloudSaveListData,
+    TapEvent,
+};
+use crate::cloudsave::SaveUuid;
+use crate::error::error_code;
+use crate::observer::Operation;
+
+/// Longest time between two checks
+const MAX_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Handle to a running watchdog thread, see [`start`]
+///
+/// Dropping the handle stops the thread.
+#[must_use = "the watchdog stops as soon as the handle is dropped"]
+#[derive(Debug)]
+pub struct Watchdog {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Synthesize a response for every cloud save request pending longer than
+/// `timeout`
+///
+/// The synthesized response is the event the request would have produced,
+/// with the error code `CLOUD_SAVE_TIMEOUT` and no data. If the real
+/// response arrives later anyway, it is dropped.
+pub fn start(timeout: Duration) -> Watchdog {
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = running.clone();
+    let interval = (timeout / 4).clamp(Duration::from_millis(10), MAX_CHECK_INTERVAL);
+    let thread = thread::spawn(move || {
+        let result = panic::catch_unwind(|| {
+            while thread_running.load(Ordering::SeqCst) {
+                for (request_id, operation) in callback::time_out_requests(timeout, Instant::now())
+                {
+                    if let Some(event) = timeout_response(operation, request_id, timeout) {
+                        callback::dispatch_timeout(event);
+                    }
+                }
+                thread::park_timeout(interval);
+            }
+        });
+        if let Err(payload) = result {
+            crate::crash::thread_died("tapsdk-pc watchdog", payload);
+        }
+    });
+    Watchdog {
+        running,
+        thread: Some(thread),
+    }
+}
+
+/// The response to `operation` reporting a timeout
+fn timeout_response(operation: Operation, request_id: i64, timeout: Duration) -> Option<TapEvent> {
+    let error = Some((
+        error_code::CLOUD_SAVE_TIMEOUT,
+        format!("no response within {}ms", timeout.as_millis()),
+    ));
+    let event = match operation {
+        Operation::Authorize => return None,
+        Operation::CloudSaveList => TapEvent::CloudSaveList(CloudSaveListData {
+            request_id,
+            error,
+            saves: Vec::new(),
+        }),
+        Operation::CloudSaveCreate => TapEvent::CloudSaveCreate(CloudSaveCreateData {
+            request_id,
+            error,
+            save: None,
+        }),
+        Operation::CloudSaveUpdate => TapEvent::CloudSaveUpdate(CloudSaveCreateData {
+            request_id,
+            error,
+            save: None,
+        }),
+        Operation::CloudSaveDelete => TapEvent::CloudSaveDelete(CloudSaveDeleteData {
+            request_id,
+            error,
+            uuid: SaveUuid::default(),
+        }),
+        Operation::CloudSaveGetData => TapEvent::CloudSaveGetData(CloudSaveGetFileData {
+            request_id,
+            error,
+            data: Vec::new(),
+        }),
+        Operation::CloudSaveGetCover => TapEvent::CloudSaveGetCover(CloudSaveGetFileData {
+            request_id,
+            error,
+            data: Vec::new(),
+        }),
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_response() {
+        let event = timeout_response(Operation::CloudSaveDelete, 7, Duration::from_secs(2));
+        let Some(TapEvent::CloudSaveDelete(data)) = event else {
+            panic!("expected a delete response");
+        };
+        assert_eq!(data.request_id, 7);
+        assert_eq!(
+            data.error,
+            Some((
+                error_code::CLOUD_SAVE_TIMEOUT,
+                "no response within 2000ms".to_string()
+            ))
+        );
+
+        let event = timeout_response(Operation::CloudSaveGetCover, 8, Duration::from_secs(2));
+        assert_eq!(event.map(|e| e.request_id()), Some(Some(8)));
+        assert!(timeout_response(Operation::Authorize, 9, Duration::from_secs(2)).is_none());
+    }
+}
@@ -0,0 +1,346 @@
+//! End-to-end tests against the fake SDK from `tapsdk-pc-fake`
+//!
+//! These exercise callback registration, parsing and the full
+//! request/response lifecycle without the TapTap client. They only build
+//! when linked against the fake library:
+//!
+//! ```text
+//! cargo build -p tapsdk-pc-fake
+//! # copy taptap_api.dll and taptap_api.dll.lib (as taptap_api.lib) to <dir>
+//! TAPSDK_FAKE_LIB_DIR=<dir> RUSTFLAGS="--cfg tapsdk_fake" \
+//!     cargo test -p tapsdk-pc --test fake_dll
+//! ```
+#![cfg(all(windows, tapsdk_fake))]
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use tapsdk_pc::callback::{event_id, OverflowPolicy, QueueConfig};
+use tapsdk_pc::cloudsave::{CloudSave, CreateSaveRequest};
+use tapsdk_pc::error::{InitResult, SystemState, TapSdkError};
+use tapsdk_pc::{dlc, ownership, user, TapEvent, TapSdk, TapSdkConfig};
+
+#[link(name = "taptap_api")]
+extern "C" {
+    fn TapFake_Reset();
+    fn TapFake_SetInitResult(result: u32, message: *const c_char);
+    fn TapFake_SetDlcOwned(dlc_id: *const c_char, owned: bool);
+    fn TapFake_FailNextResponse(code: i64, message: *const c_char);
+    fn TapFake_EmitSystemState(state: u32);
+    fn TapFake_EmitRaw(event_id: u32, data: *const u8, len: usize);
+    fn TapFake_CallbackCount(event_id: u32) -> u32;
+}
+
+/// The SDK is a process-wide singleton, run tests one at a time
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn reset() -> MutexGuard<'static, ()> {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    unsafe { TapFake_Reset() };
+    // Drop events left over from a previous test
+    tapsdk_pc::callback::poll_events();
+    guard
+}
+
+fn init() -> TapSdk {
+    TapSdk::init("fake_public_key").expect("fake SDK should initialize")
+}
+
+#[test]
+fn test_init_and_shutdown() {
+    let _guard = reset();
+    let sdk = init();
+    assert!(tapsdk_pc::is_initialized());
+    assert_eq!(sdk.get_client_id().as_deref(), Some("fake_client_id"));
+    assert_eq!(
+        unsafe { TapFake_CallbackCount(event_id::CLOUD_SAVE_LIST) },
+        1
+    );
+
+    sdk.shutdown();
+    assert!(!tapsdk_pc::is_initialized());
+    assert_eq!(
+        unsafe { TapFake_CallbackCount(event_id::CLOUD_SAVE_LIST) },
+        0
+    );
+}
+
+#[test]
+fn test_reinit() {
+    let _guard = reset();
+    let sdk = init();
+    let stale = CloudSave::get().expect("cloud save handle");
+    sdk.shutdown();
+
+    let sdk = init();
+    // Registered once, not once per init
+    assert_eq!(
+        unsafe { TapFake_CallbackCount(event_id::CLOUD_SAVE_LIST) },
+        1
+    );
+    assert!(matches!(stale.list(1), Err(TapSdkError::NotInitialized)));
+    let cloud_save = CloudSave::get().expect("cloud save handle");
+    cloud_save.list(2).unwrap();
+    assert!(matches!(
+        sdk.run_callbacks().as_slice(),
+        [TapEvent::CloudSaveList(data)] if data.request_id == 2
+    ));
+    sdk.shutdown();
+}
+
+#[test]
+fn test_queue_belongs_to_session() {
+    let _guard = reset();
+    let queue = QueueConfig {
+        capacity: Some(1),
+        overflow: OverflowPolicy::DropNewest,
+    };
+    let sdk = TapSdk::init_with(TapSdkConfig::new("fake_public_key").queue(queue)).unwrap();
+    assert_eq!(tapsdk_pc::callback::queue_config(), queue);
+    unsafe {
+        TapFake_EmitSystemState(1);
+        TapFake_EmitSystemState(2);
+    }
+    assert_eq!(sdk.run_callbacks().len(), 1);
+    sdk.shutdown();
+
+    // The next session starts with a queue of its own
+    assert_eq!(tapsdk_pc::callback::queue_config(), QueueConfig::default());
+    let sdk = init();
+    assert_eq!(tapsdk_pc::callback::queue_config(), QueueConfig::default());
+    unsafe {
+        TapFake_EmitSystemState(1);
+        TapFake_EmitSystemState(2);
+    }
+    assert_eq!(sdk.run_callbacks().len(), 2);
+    sdk.shutdown();
+}
+
+#[test]
+fn test_shutdown_graceful() {
+    let _guard = reset();
+    let sdk = init();
+    let cloud_save = CloudSave::get().expect("cloud save handle");
+    cloud_save.list(1).unwrap();
+    assert_eq!(tapsdk_pc::callback::pending_requests(), vec![1]);
+
+    let events = sdk.shutdown_graceful(Duration::from_secs(1));
+    assert!(matches!(
+        events.as_slice(),
+        [TapEvent::CloudSaveList(data)] if data.request_id == 1
+    ));
+    assert!(tapsdk_pc::callback::pending_requests().is_empty());
+    assert!(!tapsdk_pc::is_initialized());
+}
+
+#[test]
+fn test_platform_shutdown_handler() {
+    let _guard = reset();
+    let sdk = init();
+    let called = Arc::new(AtomicBool::new(false));
+    let flag = called.clone();
+    sdk.on_platform_shutdown(move || flag.store(true, Ordering::SeqCst));
+
+    unsafe { TapFake_EmitSystemState(3) };
+    let events = sdk.run_callbacks();
+    assert!(called.load(Ordering::SeqCst));
+    assert!(matches!(
+        events.as_slice(),
+        [TapEvent::SystemStateChanged(data)] if data.state == SystemState::PlatformShutdown
+    ));
+}
+
+#[test]
+fn test_init_failure() {
+    let _guard = reset();
+    let message = CString::new("not launched by TapTap").unwrap();
+    unsafe { TapFake_SetInitResult(3, message.as_ptr()) };
+
+    match TapSdk::init("fake_public_key") {
+        Err(TapSdkError::InitFailed { result, message }) => {
+            assert_eq!(result, InitResult::NotLaunchedByPlatform);
+            assert_eq!(message, "not launched by TapTap");
+        }
+        other => panic!("expected InitFailed, got {:?}", other),
+    }
+    assert!(!tapsdk_pc::is_initialized());
+}
+
+#[test]
+fn test_ownership() {
+    let _guard = reset();
+    let dlc_id = CString::new("dlc_1").unwrap();
+    unsafe { TapFake_SetDlcOwned(dlc_id.as_ptr(), true) };
+    let _sdk = init();
+
+    assert!(ownership::is_game_owned());
+    assert!(dlc::is_dlc_owned("dlc_1"));
+    assert!(!dlc::is_dlc_owned("dlc_2"));
+}
+
+#[test]
+fn test_authorize_and_system_state() {
+    let _guard = reset();
+    let sdk = init();
+
+    user::authorize("public_profile").unwrap();
+    unsafe { TapFake_EmitSystemState(2) };
+    let events = sdk.run_callbacks();
+    assert_eq!(events.len(), 2);
+
+    match &events[0] {
+        TapEvent::AuthorizeFinished(data) => {
+            assert!(!data.is_cancel);
+            let token = data.token.as_ref().expect("token");
+            assert_eq!(token.kid, "fake_kid");
+            assert_eq!(token.scope, "public_profile");
+        }
+        other => panic!("expected AuthorizeFinished, got {:?}", other),
+    }
+    match &events[1] {
+        TapEvent::SystemStateChanged(data) => {
+            assert_eq!(data.state, SystemState::PlatformOffline)
+        }
+        other => panic!("expected SystemStateChanged, got {:?}", other),
+    }
+    assert_eq!(user::get_open_id().as_deref(), Some("fake_open_id"));
+}
+
+#[test]
+fn test_cloud_save_fails_fast_offline() {
+    let _guard = reset();
+    let sdk = init();
+    let cloud_save = CloudSave::get().expect("cloud save handle");
+
+    unsafe { TapFake_EmitSystemState(2) };
+    sdk.run_callbacks();
+    assert_eq!(tapsdk_pc::system_state(), SystemState::PlatformOffline);
+    assert!(matches!(
+        cloud_save.list(1),
+        Err(TapSdkError::PlatformOffline)
+    ));
+    assert!(tapsdk_pc::callback::pending_requests().is_empty());
+
+    unsafe { TapFake_EmitSystemState(1) };
+    sdk.run_callbacks();
+    cloud_save.list(2).unwrap();
+    sdk.shutdown();
+}
+
+#[test]
+fn test_cloud_save_lifecycle() {
+    let _guard = reset();
+    let sdk = init();
+    let cloud_save = CloudSave::get().expect("cloud save handle");
+
+    let dir = std::env::temp_dir().join(format!("tapsdk-fake-dll-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let data_path = dir.join("save.dat");
+    std::fs::write(&data_path, b"save contents").unwrap();
+
+    let request = CreateSaveRequest {
+        name: "slot1".to_string(),
+        summary: "Chapter 1".to_string(),
+        extra: None,
+        playtime: 42,
+        data_file_path: data_path.into_boxed_path(),
+        cover_file_path: None,
+    };
+    cloud_save.create(1, &request).unwrap();
+    let save = match sdk.run_callbacks().as_slice() {
+        [TapEvent::CloudSaveCreate(data)] => {
+            assert_eq!(data.request_id, 1);
+            assert_eq!(data.error, None);
+            data.save.clone().expect("created save")
+        }
+        other => panic!("expected CloudSaveCreate, got {:?}", other),
+    };
+    assert_eq!(save.name, "slot1");
+    assert_eq!(save.save_size, 13);
+
+    cloud_save.list(2).unwrap();
+    cloud_save.get_data(3, &save.uuid, &save.file_id).unwrap();
+    cloud_save.delete(4, &save.uuid).unwrap();
+    let events = sdk.run_callbacks();
+    match events.as_slice() {
+        [TapEvent::CloudSaveList(list), TapEvent::CloudSaveGetData(file), TapEvent::CloudSaveDelete(deleted)] =>
+        {
+            assert_eq!(list.saves.len(), 1);
+            assert_eq!(file.data, b"save contents");
+            assert_eq!(deleted.uuid, save.uuid);
+            assert_eq!(deleted.error, None);
+        }
+        other => panic!("unexpected events {:?}", other),
+    }
+
+    // Scripted server-side failure
+    let message = CString::new("rate limited").unwrap();
+    unsafe { TapFake_FailNextResponse(400001, message.as_ptr()) };
+    cloud_save.list(5).unwrap();
+    match sdk.run_callbacks().as_slice() {
+        [TapEvent::CloudSaveList(list)] => {
+            assert_eq!(list.error, Some((400001, "rate limited".to_string())));
+        }
+        other => panic!("expected CloudSaveList, got {:?}", other),
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_cloud_save_unicode_path() {
+    let _guard = reset();
+    let sdk = init();
+    let cloud_save = CloudSave::get().expect("cloud save handle");
+
+    let dir = std::env::temp_dir().join(format!("tapsdk-存档-🎮-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let data_path = dir.join("进度.dat");
+    std::fs::write(&data_path, b"unicode").unwrap();
+
+    let request = CreateSaveRequest {
+        name: "slot1".to_string(),
+        summary: "Chapter 1".to_string(),
+        extra: None,
+        playtime: 0,
+        data_file_path: data_path.into_boxed_path(),
+        cover_file_path: None,
+    };
+    cloud_save.create(1, &request).unwrap();
+    match sdk.run_callbacks().as_slice() {
+        [TapEvent::CloudSaveCreate(data)] => {
+            assert_eq!(data.error, None);
+            assert_eq!(data.save.as_ref().map(|save| save.save_size), Some(7));
+        }
+        other => panic!("expected CloudSaveCreate, got {:?}", other),
+    }
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn test_malformed_event_data() {
+    let _guard = reset();
+    let sdk = init();
+
+    unsafe {
+        TapFake_EmitRaw(event_id::CLOUD_SAVE_LIST, std::ptr::null(), 0);
+        // request_id, error = null, save_count = -1, saves = null
+        let mut list = [0u8; 32];
+        list[16..20].copy_from_slice(&(-1i32).to_ne_bytes());
+        TapFake_EmitRaw(event_id::CLOUD_SAVE_LIST, list.as_ptr(), list.len());
+    }
+
+    let events = sdk.run_callbacks();
+    assert_eq!(events.len(), 2);
+    for event in events {
+        assert!(
+            matches!(event, TapEvent::ParseError { event_id: 6001, .. }),
+            "expected ParseError, got {:?}",
+            event
+        );
+    }
+}
@@ -0,0 +1,20 @@
+const COMMANDS: &[&str] = &[
+    "get_init_error",
+    "authorize",
+    "get_open_id",
+    "get_client_id",
+    "is_game_owned",
+    "is_game_playable",
+    "is_dlc_owned",
+    "show_dlc_store",
+    "cloud_save_list",
+    "cloud_save_create",
+    "cloud_save_update",
+    "cloud_save_delete",
+    "cloud_save_get_data",
+    "cloud_save_get_cover",
+];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS).build();
+}
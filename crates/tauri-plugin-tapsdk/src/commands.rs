@@ -0,0 +1,195 @@
+//! Commands invoked from the webview as `plugin:tapsdk|<name>`
+
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use tapsdk_pc::cloudsave::{CreateSaveRequest, UpdateSaveRequest};
+use tapsdk_pc::{dlc, ownership, staging, user, CloudSave, SaveUuid, TapSdkError};
+use tauri::{command, State};
+
+use crate::{Error, Result, TapSdkState};
+
+/// Cloud save parameters, matching `CreateSaveRequest` in the Node.js bindings
+///
+/// The save file and cover are passed as base64 contents rather than paths,
+/// so the webview cannot upload arbitrary files.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SaveRequest {
+    name: String,
+    summary: String,
+    extra: Option<String>,
+    playtime: u64,
+    /// Base64 contents of the save file
+    data: String,
+    /// Base64 contents of the cover image
+    cover: Option<String>,
+}
+
+/// Save and cover files staged for a request, see [`stage_files`]
+struct StagedFiles {
+    data_file_path: Box<Path>,
+    cover_file_path: Option<Box<Path>>,
+}
+
+/// Decode the contents of a save request and write them to staged files
+///
+/// The files are deleted once the response arrives; a request that fails
+/// to start is released by [`staged`].
+fn stage_files(request_id: i64, request: &SaveRequest) -> Result<StagedFiles> {
+    let data = BASE64
+        .decode(&request.data)
+        .map_err(|_| Error::InvalidBase64("data"))?;
+    let cover = request
+        .cover
+        .as_ref()
+        .map(|cover| {
+            BASE64
+                .decode(cover)
+                .map_err(|_| Error::InvalidBase64("cover"))
+        })
+        .transpose()?;
+    Ok(StagedFiles {
+        data_file_path: staging::stage(request_id, &data)?.into_boxed_path(),
+        cover_file_path: cover
+            .map(|cover| staging::stage(request_id, &cover).map(PathBuf::into_boxed_path))
+            .transpose()?,
+    })
+}
+
+/// Run a request that stages files, deleting them if it fails to start
+fn staged(request_id: i64, start: impl FnOnce() -> Result<()>) -> Result<()> {
+    start().map_err(|e| {
+        staging::release(request_id);
+        e
+    })
+}
+
+fn cloud_save(state: &TapSdkState) -> Result<CloudSave> {
+    state.ready()?;
+    Ok(CloudSave::get().ok_or(TapSdkError::NotInitialized)?)
+}
+
+/// Why the SDK failed to initialize, or `null` if it is running
+#[command]
+pub(crate) fn get_init_error(state: State<'_, TapSdkState>) -> Option<String> {
+    state.init_error.clone()
+}
+
+#[command]
+pub(crate) fn authorize(state: State<'_, TapSdkState>, scopes: String) -> Result<()> {
+    state.ready()?;
+    Ok(user::authorize(&scopes)?)
+}
+
+#[command]
+pub(crate) fn get_open_id() -> Option<String> {
+    user::get_open_id()
+}
+
+#[command]
+pub(crate) fn get_client_id(state: State<'_, TapSdkState>) -> Option<String> {
+    state.sdk().as_ref().and_then(|sdk| sdk.get_client_id())
+}
+
+#[command]
+pub(crate) fn is_game_owned() -> bool {
+    ownership::is_game_owned()
+}
+
+#[command]
+pub(crate) fn is_game_playable() -> bool {
+    ownership::is_game_playable()
+}
+
+#[command]
+pub(crate) fn is_dlc_owned(dlc_id: String) -> bool {
+    dlc::is_dlc_owned(&dlc_id)
+}
+
+#[command]
+pub(crate) fn show_dlc_store(state: State<'_, TapSdkState>, dlc_id: String) -> Result<bool> {
+    state.ready()?;
+    Ok(dlc::show_dlc_store(&dlc_id)?)
+}
+
+#[command]
+pub(crate) fn cloud_save_list(state: State<'_, TapSdkState>, request_id: i64) -> Result<()> {
+    Ok(cloud_save(&state)?.list(request_id)?)
+}
+
+#[command]
+pub(crate) fn cloud_save_create(
+    state: State<'_, TapSdkState>,
+    request_id: i64,
+    request: SaveRequest,
+) -> Result<()> {
+    let cloud_save = cloud_save(&state)?;
+    staged(request_id, || {
+        let files = stage_files(request_id, &request)?;
+        let request = CreateSaveRequest {
+            name: request.name,
+            summary: request.summary,
+            extra: request.extra,
+            playtime: request.playtime,
+            data_file_path: files.data_file_path,
+            cover_file_path: files.cover_file_path,
+        };
+        Ok(cloud_save.create(request_id, &request)?)
+    })
+}
+
+#[command]
+pub(crate) fn cloud_save_update(
+    state: State<'_, TapSdkState>,
+    request_id: i64,
+    uuid: String,
+    request: SaveRequest,
+) -> Result<()> {
+    let cloud_save = cloud_save(&state)?;
+    let uuid: SaveUuid = uuid.parse()?;
+    staged(request_id, || {
+        let files = stage_files(request_id, &request)?;
+        let request = UpdateSaveRequest {
+            uuid,
+            name: request.name,
+            summary: request.summary,
+            extra: request.extra,
+            playtime: request.playtime,
+            data_file_path: files.data_file_path,
+            cover_file_path: files.cover_file_path,
+        };
+        Ok(cloud_save.update(request_id, &request)?)
+    })
+}
+
+#[command]
+pub(crate) fn cloud_save_delete(
+    state: State<'_, TapSdkState>,
+    request_id: i64,
+    uuid: String,
+) -> Result<()> {
+    Ok(cloud_save(&state)?.delete(request_id, &uuid.parse()?)?)
+}
+
+#[command]
+pub(crate) fn cloud_save_get_data(
+    state: State<'_, TapSdkState>,
+    request_id: i64,
+    uuid: String,
+    file_id: String,
+) -> Result<()> {
+    Ok(cloud_save(&state)?.get_data(request_id, &uuid.parse()?, &file_id.parse()?)?)
+}
+
+#[command]
+pub(crate) fn cloud_save_get_cover(
+    state: State<'_, TapSdkState>,
+    request_id: i64,
+    uuid: String,
+    file_id: String,
+) -> Result<()> {
+    Ok(cloud_save(&state)?.get_cover(request_id, &uuid.parse()?, &file_id.parse()?)?)
+}
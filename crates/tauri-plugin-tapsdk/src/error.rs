@@ -0,0 +1,25 @@
+//! Error type returned by the plugin commands
+
+use serde::{Serialize, Serializer};
+use tapsdk_pc::TapSdkError;
+
+/// Plugin error, serialized to the webview as its message
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Sdk(#[from] TapSdkError),
+    /// `TapSdk::init` failed when the app started
+    #[error("TapTap SDK failed to initialize: {0}")]
+    InitFailed(String),
+    /// A save file's contents are not valid base64
+    #[error("{0} is not valid base64")]
+    InvalidBase64(&'static str),
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
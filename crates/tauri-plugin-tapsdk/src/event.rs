@@ -0,0 +1,19 @@
+//! Conversion of SDK events to webview payloads
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::Value;
+use tapsdk_pc::json::{self, JsonEvent};
+use tapsdk_pc::TapEvent;
+
+/// Convert an event to the JSON shape used by the Node.js bindings
+///
+/// File contents are sent as a base64 string, like the Node.js bindings'
+/// `binaryEncoding: 'base64'` option.
+pub(crate) fn to_json(event: TapEvent) -> Value {
+    let JsonEvent { mut value, data } = json::event_to_json(event);
+    if let Some(data) = data {
+        value["data"] = BASE64.encode(data).into();
+    }
+    value
+}
@@ -0,0 +1,147 @@
+//! Tauri plugin for TapTap PC SDK
+//!
+//! Registers the SDK commands (authorization, ownership, cloud save) and
+//! forwards every SDK event to the webview as a `tapsdk://event` event whose
+//! payload has the same shape as the Node.js bindings' events.
+//!
+//! ```ignore
+//! fn main() {
+//!     if tapsdk_pc::restart_app_if_necessary("your_client_id").unwrap_or(false) {
+//!         return;
+//!     }
+//!     tauri::Builder::default()
+//!         .plugin(tauri_plugin_tapsdk::init("your_public_key"))
+//!         .run(tauri::generate_context!())
+//!         .expect("error while running tauri application");
+//! }
+//! ```
+//!
+//! From the webview:
+//!
+//! ```js
+//! import { invoke } from '@tauri-apps/api/core';
+//! import { listen } from '@tauri-apps/api/event';
+//!
+//! await listen('tapsdk://event', ({ payload }) => console.log(payload.eventId));
+//! await invoke('plugin:tapsdk|cloud_save_list', { requestId: 1 });
+//! ```
+//!
+//! Grant access with the `tapsdk:default` permission in your capabilities.
+//!
+//! If the SDK fails to initialize, the app keeps running: `get_init_error`
+//! returns the reason, and the SDK commands fail with it.
+//!
+//! Save files cross the IPC boundary as base64 strings, never as paths, so
+//! the webview cannot make the SDK read arbitrary files.
+
+mod commands;
+mod error;
+mod event;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tapsdk_pc::TapSdk;
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{Emitter, Manager, RunEvent, Runtime};
+
+pub use error::{Error, Result};
+
+/// Name of the webview event carrying SDK events
+pub const EVENT_NAME: &str = "tapsdk://event";
+
+/// How often the SDK is polled for events
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// SDK state managed by the plugin
+pub(crate) struct TapSdkState {
+    sdk: Mutex<Option<TapSdk>>,
+    running: Arc<AtomicBool>,
+    /// Why `TapSdk::init` failed, if it did
+    init_error: Option<String>,
+}
+
+impl TapSdkState {
+    fn sdk(&self) -> std::sync::MutexGuard<'_, Option<TapSdk>> {
+        self.sdk.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Fail with the initialization error if the SDK did not start
+    fn ready(&self) -> Result<()> {
+        match &self.init_error {
+            Some(message) => Err(Error::InitFailed(message.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Initialize the SDK and create the plugin
+///
+/// Call `tapsdk_pc::restart_app_if_necessary` before building the app.
+pub fn init<R: Runtime>(pub_key: impl Into<String>) -> TauriPlugin<R> {
+    let pub_key = pub_key.into();
+    Builder::new("tapsdk")
+        .invoke_handler(tauri::generate_handler![
+            commands::get_init_error,
+            commands::authorize,
+            commands::get_open_id,
+            commands::get_client_id,
+            commands::is_game_owned,
+            commands::is_game_playable,
+            commands::is_dlc_owned,
+            commands::show_dlc_store,
+            commands::cloud_save_list,
+            commands::cloud_save_create,
+            commands::cloud_save_update,
+            commands::cloud_save_delete,
+            commands::cloud_save_get_data,
+            commands::cloud_save_get_cover,
+        ])
+        .setup(move |app, _api| {
+            let running = Arc::new(AtomicBool::new(true));
+            let sdk = match TapSdk::init(&pub_key) {
+                Ok(sdk) => sdk,
+                Err(e) => {
+                    // Keep the app running; the commands report the failure
+                    app.manage(TapSdkState {
+                        sdk: Mutex::new(None),
+                        running,
+                        init_error: Some(e.to_string()),
+                    });
+                    return Ok(());
+                }
+            };
+            app.manage(TapSdkState {
+                sdk: Mutex::new(Some(sdk)),
+                running: running.clone(),
+                init_error: None,
+            });
+
+            // Poll on a background thread and push events to the webview
+            let app = app.clone();
+            thread::spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    let events = match app.state::<TapSdkState>().sdk().as_ref() {
+                        Some(sdk) => sdk.run_callbacks(),
+                        None => break,
+                    };
+                    for event in events {
+                        let _ = app.emit(EVENT_NAME, event::to_json(event));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            });
+            Ok(())
+        })
+        .on_event(|app, event| {
+            if let RunEvent::Exit = event {
+                let state = app.state::<TapSdkState>();
+                state.running.store(false, Ordering::SeqCst);
+                // Dropping the SDK shuts it down
+                state.sdk().take();
+            }
+        })
+        .build()
+}
@@ -1,5 +1,16 @@
 extern crate napi_build;
 
+use std::env;
+
 fn main() {
     napi_build::setup();
+
+    // Load taptap_api.dll on first use rather than with the addon, so a
+    // missing DLL surfaces as a catchable error (see src/library.rs)
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows")
+        && env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc")
+    {
+        println!("cargo:rustc-link-arg=/DELAYLOAD:taptap_api.dll");
+        println!("cargo:rustc-link-lib=delayimp");
+    }
 }
@@ -5,17 +5,24 @@
 
 #![deny(clippy::all)]
 
+mod library;
+mod logger;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use napi::bindgen_prelude::*;
 use napi::threadsafe_function::ThreadsafeFunctionCallMode;
 use napi_derive::napi;
-use serde::Serialize;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use tapsdk_pc::callback::CloudSaveInfo as RustCloudSaveInfo;
-use tapsdk_pc::callback::TapEvent;
-use tapsdk_pc::error::SystemState;
+use tapsdk_pc::callback::Envelope;
+use tapsdk_pc::json::{self, JsonEvent};
+use tapsdk_pc::observer::Operation;
+use tapsdk_pc::{FileId, SaveUuid, TapSdkError};
 
 #[napi]
 pub mod event_id {
@@ -41,6 +48,18 @@ pub mod event_id {
     pub const CLOUD_SAVE_GET_DATA: u32 = 6005;
     #[napi]
     pub const CLOUD_SAVE_GET_COVER: u32 = 6006;
+    #[napi]
+    pub const INTERNAL_ERROR: u32 = 90001;
+    #[napi]
+    pub const PARSE_ERROR: u32 = 90002;
+    #[napi]
+    pub const EVENT_CONVERSION_FAILED: u32 = 90003;
+    #[napi]
+    pub const CONNECTION_LOST: u32 = 90004;
+    #[napi]
+    pub const CONNECTION_RESTORED: u32 = 90005;
+    #[napi]
+    pub const CLOUD_SAVE_PROGRESS: u32 = 90006;
 }
 
 #[napi]
@@ -55,10 +74,31 @@ pub mod system_state {
     pub const PLATFORM_SHUTDOWN: u32 = 3;
 }
 
+#[napi]
+pub mod error_code {
+    /// `code` of the error thrown by methods called after `shutdown()`
+    #[napi]
+    pub const SDK_SHUT_DOWN: &str = "SdkShutDown";
+    /// `code` of the error thrown when `taptap_api.dll` cannot be loaded
+    #[napi]
+    pub const SDK_LIBRARY_MISSING: &str = "SdkLibraryMissing";
+    /// `code` of the error thrown by cloud save methods while the TapTap
+    /// client reports it is offline
+    #[napi]
+    pub const PLATFORM_OFFLINE: &str = "PlatformOffline";
+}
+
+#[napi]
+pub mod sdk_error_code {
+    /// SDK `error.code` of a timed out cloud save request, also set on the
+    /// responses synthesized when the real one did not arrive within
+    /// `requestTimeoutMs`
+    #[napi]
+    pub const CLOUD_SAVE_TIMEOUT: i64 = tapsdk_pc::error::error_code::CLOUD_SAVE_TIMEOUT;
+}
+
 /// Authorization token
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct AuthToken {
     pub token_type: String,
     pub kid: String,
@@ -69,45 +109,29 @@ pub struct AuthToken {
 
 /// SDK Error info
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct SdkError {
     pub code: i64,
     pub message: String,
 }
 
 /// Cloud save information
+///
+/// Sizes and playtime are 64-bit, but the SDK reports 32-bit values, so
+/// they are always safe integers in JS.
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct CloudSaveInfo {
     pub uuid: String,
     pub file_id: String,
     pub name: String,
-    pub save_size: u32,
-    pub cover_size: u32,
+    pub save_size: i64,
+    pub cover_size: i64,
     pub summary: Option<String>,
     pub extra: Option<String>,
-    pub playtime: u32,
-    pub created_time: u32,
-    pub modified_time: u32,
-}
-
-impl From<RustCloudSaveInfo> for CloudSaveInfo {
-    fn from(info: RustCloudSaveInfo) -> Self {
-        CloudSaveInfo {
-            uuid: info.uuid,
-            file_id: info.file_id,
-            name: info.name,
-            save_size: info.save_size,
-            cover_size: info.cover_size,
-            summary: info.summary,
-            extra: info.extra,
-            playtime: info.playtime,
-            created_time: info.created_time,
-            modified_time: info.modified_time,
-        }
-    }
+    pub playtime: i64,
+    /// Milliseconds since the Unix epoch
+    pub created_time: i64,
+    /// Milliseconds since the Unix epoch
+    pub modified_time: i64,
 }
 
 /// Request to create a cloud save
@@ -119,12 +143,12 @@ pub struct CreateSaveRequest {
     pub summary: String,
     /// Developer-defined extra data (max 1000 bytes, optional)
     pub extra: Option<String>,
-    /// Game playtime in seconds
-    pub playtime: u32,
-    /// Path to the save data file (max 10MB)
-    pub data_file_path: String,
-    /// Path to the cover image file (max 512KB, optional)
-    pub cover_file_path: Option<String>,
+    /// Game playtime in seconds (at most 2^32 - 1, the SDK's limit)
+    pub playtime: i64,
+    /// Path to the save data file, or its contents (max 10MB)
+    pub data_file_path: Either<String, Buffer>,
+    /// Path to the cover image file, or its contents (max 512KB, optional)
+    pub cover_file_path: Option<Either<String, Buffer>>,
 }
 
 /// Request to update a cloud save
@@ -138,28 +162,29 @@ pub struct UpdateSaveRequest {
     pub summary: String,
     /// Developer-defined extra data (max 1000 bytes, optional)
     pub extra: Option<String>,
-    /// Game playtime in seconds
-    pub playtime: u32,
-    /// Path to the save data file (max 10MB)
-    pub data_file_path: String,
-    /// Path to the cover image file (max 512KB, optional)
-    pub cover_file_path: Option<String>,
+    /// Game playtime in seconds (at most 2^32 - 1, the SDK's limit)
+    pub playtime: i64,
+    /// Path to the save data file, or its contents (max 10MB)
+    pub data_file_path: Either<String, Buffer>,
+    /// Path to the cover image file, or its contents (max 512KB, optional)
+    pub cover_file_path: Option<Either<String, Buffer>>,
 }
 
+// Event shapes for the generated typings. The values passed to JavaScript
+// are built by `tapsdk_pc::json`.
+
 /// System state changed event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct SystemStateChangedEvent {
+    #[napi(ts_type = "1")]
     pub event_id: u32,
     pub state: u32,
 }
 
 /// Authorization finished event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct AuthorizeFinishedEvent {
+    #[napi(ts_type = "2002")]
     pub event_id: u32,
     pub is_cancel: bool,
     pub error: Option<String>,
@@ -168,18 +193,16 @@ pub struct AuthorizeFinishedEvent {
 
 /// Game playable status changed event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct GamePlayableStatusChangedEvent {
+    #[napi(ts_type = "4001")]
     pub event_id: u32,
     pub is_playable: bool,
 }
 
 /// DLC playable status changed event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct DlcPlayableStatusChangedEvent {
+    #[napi(ts_type = "4002")]
     pub event_id: u32,
     pub dlc_id: String,
     pub is_playable: bool,
@@ -187,9 +210,8 @@ pub struct DlcPlayableStatusChangedEvent {
 
 /// Cloud save list event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct CloudSaveListEvent {
+    #[napi(ts_type = "6001")]
     pub event_id: u32,
     pub request_id: i64,
     pub error: Option<SdkError>,
@@ -198,9 +220,8 @@ pub struct CloudSaveListEvent {
 
 /// Cloud save create/update event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct CloudSaveCreateEvent {
+    #[napi(ts_type = "6002 | 6003")]
     pub event_id: u32,
     pub request_id: i64,
     pub error: Option<SdkError>,
@@ -209,9 +230,8 @@ pub struct CloudSaveCreateEvent {
 
 /// Cloud save delete event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct CloudSaveDeleteEvent {
+    #[napi(ts_type = "6004")]
     pub event_id: u32,
     pub request_id: i64,
     pub error: Option<SdkError>,
@@ -220,170 +240,635 @@ pub struct CloudSaveDeleteEvent {
 
 /// Cloud save get file event
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct CloudSaveGetFileEvent {
+    #[napi(ts_type = "6005 | 6006")]
     pub event_id: u32,
     pub request_id: i64,
     pub error: Option<SdkError>,
-    #[serde(skip)]
+    /// Detected image type of cover data (`image/png`, `image/jpeg` or `image/webp`)
+    #[napi(ts_type = "'image/png' | 'image/jpeg' | 'image/webp'")]
+    pub content_type: Option<String>,
     pub data: Buffer,
 }
 
-/// Unknown event
+/// Cloud save upload or download progress event
+#[napi(object)]
+pub struct CloudSaveProgressEvent {
+    #[napi(ts_type = "90006")]
+    pub event_id: u32,
+    pub request_id: i64,
+    #[napi(ts_type = "'reading' | 'uploading' | 'waitingForServer' | 'done'")]
+    pub stage: String,
+    /// Bytes transferred so far
+    pub bytes: i64,
+    /// Total size in bytes, or 0 while unknown
+    pub total: i64,
+}
+
+/// Internal error event
+#[napi(object)]
+pub struct InternalErrorEvent {
+    #[napi(ts_type = "90001")]
+    pub event_id: u32,
+    pub source_event_id: u32,
+    pub message: String,
+}
+
+/// Parse error event
+#[napi(object)]
+pub struct ParseErrorEvent {
+    #[napi(ts_type = "90002")]
+    pub event_id: u32,
+    pub source_event_id: u32,
+    pub reason: String,
+}
+
+/// Event that could not be converted to a JavaScript value
+#[napi(object)]
+pub struct EventConversionFailedEvent {
+    #[napi(ts_type = "90003")]
+    pub event_id: u32,
+    pub source_event_id: u32,
+    pub reason: String,
+}
+
+/// The TapTap client stopped running, or is running again
+#[napi(object)]
+pub struct ConnectionEvent {
+    #[napi(ts_type = "90004 | 90005")]
+    pub event_id: u32,
+}
+
+/// Event with an ID these bindings don't know, carrying the raw SDK event ID
 #[napi(object)]
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct UnknownEvent {
     pub event_id: u32,
 }
 
-/// Convert a TapEvent into a serde_json::Value for passing to JavaScript
-fn convert_event_to_json(event: TapEvent) -> serde_json::Result<serde_json::Value> {
-    match event {
-        TapEvent::SystemStateChanged(data) => serde_json::to_value(SystemStateChangedEvent {
-            event_id: event_id::SYSTEM_STATE_CHANGED,
-            state: system_state_to_u32(data.state),
-        }),
-        TapEvent::AuthorizeFinished(data) => serde_json::to_value(AuthorizeFinishedEvent {
-            event_id: event_id::AUTHORIZE_FINISHED,
-            is_cancel: data.is_cancel,
-            error: data.error,
-            token: data.token.map(|t| AuthToken {
-                token_type: t.token_type,
-                kid: t.kid,
-                mac_key: t.mac_key,
-                mac_algorithm: t.mac_algorithm,
-                scope: t.scope,
-            }),
-        }),
-        TapEvent::GamePlayableStatusChanged(data) => {
-            serde_json::to_value(GamePlayableStatusChangedEvent {
-                event_id: event_id::GAME_PLAYABLE_STATUS_CHANGED,
-                is_playable: data.is_playable,
-            })
+/// Any event passed to JavaScript, discriminated by `eventId`
+#[napi(js_name = "TapEvent")]
+pub type JsTapEvent = Either14<
+    SystemStateChangedEvent,
+    AuthorizeFinishedEvent,
+    GamePlayableStatusChangedEvent,
+    DlcPlayableStatusChangedEvent,
+    CloudSaveListEvent,
+    CloudSaveCreateEvent,
+    CloudSaveDeleteEvent,
+    CloudSaveGetFileEvent,
+    CloudSaveProgressEvent,
+    InternalErrorEvent,
+    ParseErrorEvent,
+    EventConversionFailedEvent,
+    ConnectionEvent,
+    UnknownEvent,
+>;
+
+/// Event pipeline diagnostics for support tooling
+#[napi(object)]
+pub struct Diagnostics {
+    /// Number of events received, keyed by event ID
+    pub events_by_type: HashMap<String, i64>,
+    /// Total number of events received
+    pub total_events: i64,
+    /// Largest number of events that were waiting in the queue at once
+    pub queue_high_water_mark: u32,
+    /// Events that could not be converted and were dropped
+    pub dropped_conversions: i64,
+    /// Number of completed polls
+    pub poll_count: i64,
+    /// Average time spent in a single poll, in milliseconds
+    pub average_poll_latency_ms: f64,
+    /// Events discarded because the queue was full
+    pub dropped_events: i64,
+}
+
+/// Background event loop statistics
+#[napi(object)]
+pub struct EventLoopStats {
+    /// Interval between polls, in milliseconds
+    pub poll_interval_ms: u32,
+    /// Time of the last completed poll, in milliseconds since the Unix epoch
+    pub last_poll_time: Option<f64>,
+    /// Number of events delivered to the JavaScript callback
+    pub events_delivered: i64,
+    /// Number of events whose JSON conversion failed
+    pub conversion_failures: i64,
+    /// Whether the background thread is still running
+    pub is_alive: bool,
+}
+
+/// Versions of the bindings, SDK headers and runtime DLL
+#[napi(object)]
+pub struct VersionInfo {
+    /// Version of the native bindings
+    pub binding_version: String,
+    /// TapTap PC SDK release the bindings were generated from
+    pub sdk_header_version: String,
+    /// File version of the loaded `taptap_api.dll`, if it could be read
+    pub dll_version: Option<String>,
+}
+
+/// Command-line parameters the process was started with
+#[napi(object)]
+pub struct LaunchParams {
+    /// Command-line arguments, without the executable path
+    pub args: Vec<String>,
+    /// The first argument that looks like a URL (`scheme://...`)
+    pub deep_link: Option<String>,
+    /// Decoded query parameters of the deep link
+    pub params: HashMap<String, String>,
+    /// Whether the TapTap client started the process
+    pub launched_by_taptap: bool,
+}
+
+/// What to do when an event arrives while the event queue is full
+#[napi(string_enum = "camelCase")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room
+    DropOldest,
+    /// Discard the incoming event
+    DropNewest,
+    /// Keep only the latest status event of each kind
+    Coalesce,
+}
+
+impl From<OverflowPolicy> for tapsdk_pc::callback::OverflowPolicy {
+    fn from(policy: OverflowPolicy) -> Self {
+        match policy {
+            OverflowPolicy::DropOldest => tapsdk_pc::callback::OverflowPolicy::DropOldest,
+            OverflowPolicy::DropNewest => tapsdk_pc::callback::OverflowPolicy::DropNewest,
+            OverflowPolicy::Coalesce => tapsdk_pc::callback::OverflowPolicy::Coalesce,
         }
-        TapEvent::DlcPlayableStatusChanged(data) => {
-            serde_json::to_value(DlcPlayableStatusChangedEvent {
-                event_id: event_id::DLC_PLAYABLE_STATUS_CHANGED,
-                dlc_id: data.dlc_id,
-                is_playable: data.is_playable,
-            })
+    }
+}
+
+/// How the file contents of cloud save data and cover events reach JavaScript
+#[napi(string_enum = "camelCase")]
+#[derive(Clone, Copy, Default)]
+pub enum BinaryEncoding {
+    /// A `Buffer` wrapping the downloaded bytes
+    #[default]
+    Buffer,
+    /// A copy in a plain `ArrayBuffer`
+    ArrayBuffer,
+    /// A base64 string
+    Base64,
+}
+
+/// Initialization and background event loop options
+#[napi(object)]
+#[derive(Default)]
+pub struct TapSdkOptions {
+    /// Maximum number of events passed to the callback at once
+    ///
+    /// Defaults to every event received in one poll. Set to 1 to receive
+    /// one event per call.
+    pub max_batch_size: Option<u32>,
+    /// Maximum number of events waiting to be polled (default 4096)
+    pub queue_capacity: Option<u32>,
+    /// What to do when the queue is full (default `dropOldest`)
+    pub overflow_policy: Option<OverflowPolicy>,
+    /// Number of events kept for `getRecentEvents()` (default 0, disabled)
+    pub history_capacity: Option<u32>,
+    /// Directory for temporary files holding `Buffer` save contents
+    pub staging_dir: Option<String>,
+    /// Check for the TapTap client this often and emit `ConnectionLost` /
+    /// `ConnectionRestored` events (default 0, disabled)
+    pub heartbeat_interval_ms: Option<u32>,
+    /// Synthesize a `CLOUD_SAVE_TIMEOUT` response for cloud save requests
+    /// without a response after this long (default 0, disabled)
+    pub request_timeout_ms: Option<u32>,
+    /// Only deliver events with these IDs (default: every event)
+    pub event_ids: Option<Vec<u32>>,
+    /// Poll for events on a background thread (default true)
+    ///
+    /// With `false`, no thread is started and events are only returned by
+    /// `runCallbacks()`.
+    pub auto_poll: Option<bool>,
+    /// How often the background thread polls for events, in milliseconds
+    /// (default 50, at least 1)
+    pub poll_interval_ms: Option<u32>,
+    /// How file contents in events are passed (default `buffer`)
+    ///
+    /// With `arrayBuffer` or `base64`, every event survives structured
+    /// cloning, e.g. over Electron IPC, unchanged.
+    pub binary_encoding: Option<BinaryEncoding>,
+    /// Deliver at most one system state, game playable and DLC playable
+    /// (per DLC) event per poll, the latest (default false)
+    pub coalesce_status_events: Option<bool>,
+}
+
+/// How often the background thread polls the SDK for events by default
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a platform shutdown handler may hold back the event by default
+const PLATFORM_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// State shared between the napi object and its background event thread
+#[derive(Default)]
+struct EventLoopState {
+    /// Last completed poll, in milliseconds since the Unix epoch (0 = never)
+    last_poll_ms: AtomicU64,
+    events_delivered: AtomicU64,
+    conversion_failures: AtomicU64,
+    alive: AtomicBool,
+    /// Set once the JavaScript platform shutdown handler has finished
+    platform_shutdown_handled: AtomicBool,
+}
+
+/// Marks the event loop as dead when the background thread exits,
+/// including when it unwinds from a panic
+struct AliveGuard(Arc<EventLoopState>);
+
+impl Drop for AliveGuard {
+    fn drop(&mut self) {
+        self.0.alive.store(false, Ordering::SeqCst);
+        if std::thread::panicking() {
+            tapsdk_pc::crash::report(tapsdk_pc::crash::FatalCondition::CallbackThreadDied {
+                thread: "tapsdk-pc-js event loop".to_string(),
+                message: "panicked while delivering events".to_string(),
+            });
+        }
+    }
+}
+
+/// An event on its way to JavaScript
+///
+/// Fields travel as JSON. File contents are handed over as an external
+/// `Buffer` backed by the downloaded `Vec<u8>` instead of being copied, or
+/// as chosen by the `binaryEncoding` option.
+pub struct JsEvent {
+    value: serde_json::Value,
+    data: Option<EventData>,
+}
+
+/// File contents attached to an event after its JSON fields
+enum EventData {
+    Buffer(Buffer),
+    ArrayBuffer(Vec<u8>),
+}
+
+impl JsEvent {
+    fn new(envelope: Envelope, encoding: BinaryEncoding) -> Self {
+        let JsonEvent { mut value, data } = json::envelope_to_json(envelope);
+        let data = data.and_then(|data| match encoding {
+            BinaryEncoding::Buffer => Some(EventData::Buffer(data.into())),
+            BinaryEncoding::ArrayBuffer => Some(EventData::ArrayBuffer(data)),
+            BinaryEncoding::Base64 => {
+                value["data"] = BASE64.encode(data).into();
+                None
+            }
+        });
+        JsEvent { value, data }
+    }
+}
+
+impl ToNapiValue for JsEvent {
+    /// Falls back to an `EventConversionFailed` event, so that one bad event
+    /// neither disappears nor fails the whole batch
+    unsafe fn to_napi_value(env: napi::sys::napi_env, val: Self) -> Result<napi::sys::napi_value> {
+        let source_event_id = val.value["eventId"].as_u64().unwrap_or(0);
+        match JsEvent::convert(env, val) {
+            Ok(object) => Ok(object),
+            Err(e) => {
+                log::warn!(event_id = source_event_id; "Failed to convert event: {}", e.reason);
+                let value = serde_json::json!({
+                    "eventId": event_id::EVENT_CONVERSION_FAILED,
+                    "sourceEventId": source_event_id,
+                    "reason": e.reason,
+                });
+                serde_json::Value::to_napi_value(env, value)
+            }
         }
-        TapEvent::CloudSaveList(data) => serde_json::to_value(CloudSaveListEvent {
-            event_id: event_id::CLOUD_SAVE_LIST,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            saves: data.saves.into_iter().map(CloudSaveInfo::from).collect(),
-        }),
-        TapEvent::CloudSaveCreate(data) => serde_json::to_value(CloudSaveCreateEvent {
-            event_id: event_id::CLOUD_SAVE_CREATE,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            save: data.save.map(CloudSaveInfo::from),
-        }),
-        TapEvent::CloudSaveUpdate(data) => serde_json::to_value(CloudSaveCreateEvent {
-            event_id: event_id::CLOUD_SAVE_UPDATE,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            save: data.save.map(CloudSaveInfo::from),
-        }),
-        TapEvent::CloudSaveDelete(data) => serde_json::to_value(CloudSaveDeleteEvent {
-            event_id: event_id::CLOUD_SAVE_DELETE,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            uuid: data.uuid,
-        }),
-        TapEvent::CloudSaveGetData(data) => serde_json::to_value(CloudSaveGetFileEvent {
-            event_id: event_id::CLOUD_SAVE_GET_DATA,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            data: Buffer::from(data.data),
-        }),
-        TapEvent::CloudSaveGetCover(data) => serde_json::to_value(CloudSaveGetFileEvent {
-            event_id: event_id::CLOUD_SAVE_GET_COVER,
-            request_id: data.request_id,
-            error: data.error.map(|(code, message)| SdkError { code, message }),
-            data: Buffer::from(data.data),
-        }),
-        TapEvent::Unknown { event_id: id } => serde_json::to_value(UnknownEvent { event_id: id }),
     }
 }
 
+impl JsEvent {
+    unsafe fn convert(env: napi::sys::napi_env, val: Self) -> Result<napi::sys::napi_value> {
+        let object = serde_json::Value::to_napi_value(env, val.value)?;
+        let data = match val.data {
+            None => return Ok(object),
+            Some(EventData::Buffer(buffer)) => Buffer::to_napi_value(env, buffer)?,
+            Some(EventData::ArrayBuffer(bytes)) => array_buffer(env, &bytes)?,
+        };
+        napi::check_status!(
+            napi::sys::napi_set_named_property(env, object, c"data".as_ptr(), data),
+            "Failed to attach event data"
+        )?;
+        Ok(object)
+    }
+}
+
+/// Copy `bytes` into a new `ArrayBuffer`
+unsafe fn array_buffer(env: napi::sys::napi_env, bytes: &[u8]) -> Result<napi::sys::napi_value> {
+    let mut contents = std::ptr::null_mut();
+    let mut array_buffer = std::ptr::null_mut();
+    napi::check_status!(
+        napi::sys::napi_create_arraybuffer(env, bytes.len(), &mut contents, &mut array_buffer),
+        "Failed to allocate event data"
+    )?;
+    if !bytes.is_empty() {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), contents.cast::<u8>(), bytes.len());
+    }
+    Ok(array_buffer)
+}
+
+/// Set the file names tried, in order, when loading the SDK library
+///
+/// Defaults to `taptap_api.dll`. Use this for versioned or renamed DLLs
+/// shipped with newer clients. Only has an effect before the first call to
+/// `restartAppIfNecessary()` or `new TapSdk()`; returns false afterwards.
+#[napi]
+pub fn set_library_names(names: Vec<String>) -> bool {
+    library::set_names(names)
+}
+
+/// Check if the app needs to restart (call before init)
+///
+/// If this returns true, TapTap will relaunch the game - exit immediately.
+///
+/// Throws an error with code `SdkLibraryMissing` if `taptap_api.dll` cannot
+/// be loaded.
+#[napi]
+pub fn restart_app_if_necessary(env: Env, client_id: String) -> Result<bool> {
+    load_library(&env)?;
+    tapsdk_pc::restart_app_if_necessary(&client_id).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Check whether the game was started by the TapTap client
+///
+/// Unlike `restartAppIfNecessary`, this never relaunches the game.
+#[napi]
+pub fn is_launched_by_taptap() -> bool {
+    tapsdk_pc::is_launched_by_taptap()
+}
+
+/// Single-instance lock held by this process, see `isAnotherInstanceRunning`
+static SINGLE_INSTANCE: Mutex<Option<tapsdk_pc::SingleInstance>> = Mutex::new(None);
+
 /// TapTap PC SDK wrapper for Node.js
 ///
 /// Events are automatically pushed to the provided callback via a background
-/// polling thread. There is no need to call `runCallbacks()` manually.
+/// polling thread. There is no need to call `runCallbacks()` manually, unless
+/// the `autoPoll: false` option turns the thread off.
 #[napi]
 pub struct TapSdk {
-    inner: Option<tapsdk_pc::TapSdk>,
+    instance: Arc<Mutex<Option<Instance>>>,
     running: Arc<AtomicBool>,
-    handle: Option<std::thread::JoinHandle<()>>,
+    loop_state: Arc<EventLoopState>,
+    /// Events to deliver when polling with `runCallbacks()`, which is
+    /// `None` while the event loop thread polls instead
+    manual_poll: Option<EventFilter>,
+    binary_encoding: BinaryEncoding,
+    poll_interval: Duration,
 }
 
+/// The initialized SDK and its event loop thread
+struct Instance {
+    sdk: tapsdk_pc::TapSdk,
+    /// `None` with the `autoPoll: false` option
+    event_loop: Option<std::thread::JoinHandle<()>>,
+    heartbeat: Option<tapsdk_pc::heartbeat::Heartbeat>,
+    watchdog: Option<tapsdk_pc::watchdog::Watchdog>,
+}
+
+/// `(level, message, fields)` passed to the JavaScript logger
+type LogArgs = FnArgs<(String, String, HashMap<String, String>)>;
+
+/// Running flag and instance shared with the cleanup hook and `CURRENT`
+type SharedInstance = (Arc<AtomicBool>, Arc<Mutex<Option<Instance>>>);
+
+/// The most recently constructed instance and the `napi_env` it belongs to
+///
+/// An Electron reload creates a new environment while the old one may still
+/// hold the SDK and a threadsafe function into a dead context.
+static CURRENT: Mutex<Option<(usize, SharedInstance)>> = Mutex::new(None);
+
 #[napi]
 impl TapSdk {
     /// Check if the app needs to restart (call before init)
     ///
     /// If this returns true, TapTap will relaunch the game - exit immediately.
-    #[napi]
-    pub fn restart_app_if_necessary(client_id: String) -> Result<bool> {
-        tapsdk_pc::restart_app_if_necessary(&client_id)
-            .map_err(|e| Error::from_reason(e.to_string()))
+    #[napi(js_name = "restartAppIfNecessary")]
+    pub fn restart_app_if_necessary_static(env: Env, client_id: String) -> Result<bool> {
+        restart_app_if_necessary(env, client_id)
     }
 
     /// Initialize the SDK and start the background event loop.
     ///
-    /// The provided callback will be called with each event as it arrives.
+    /// The provided callback is called once per poll with the events received
+    /// since the previous poll, split into batches of at most
+    /// `options.maxBatchSize` events.
+    ///
+    /// Throws an error with code `SdkLibraryMissing` if `taptap_api.dll`
+    /// cannot be loaded.
     #[napi(
         constructor,
-        ts_args_type = "pubKey: string, callback: (event: any) => void"
+        ts_args_type = "pubKey: string, callback: (events: TapEvent[]) => void, options?: TapSdkOptions"
     )]
-    pub fn new(pub_key: String, callback: Function<'_, serde_json::Value, ()>) -> Result<Self> {
-        let inner =
-            tapsdk_pc::TapSdk::init(&pub_key).map_err(|e| Error::from_reason(e.to_string()))?;
+    pub fn new(
+        env: Env,
+        pub_key: String,
+        callback: Function<'_, Vec<JsEvent>, ()>,
+        options: Option<TapSdkOptions>,
+    ) -> Result<Self> {
+        load_library(&env)?;
+        let env_id = env.raw() as usize;
+        let mut current = CURRENT.lock().unwrap_or_else(|e| e.into_inner());
+        // Tear down an instance left behind by another environment so it
+        // stops calling into that context and the SDK can initialize again.
+        // Within one environment, a second instance still fails to initialize.
+        if let Some((other_env, (running, instance))) = current.take() {
+            if other_env == env_id {
+                *current = Some((other_env, (running, instance)));
+            } else {
+                teardown(&running, &instance);
+            }
+        }
 
-        // Create a threadsafe function from the JS callback so we can call it
-        // from the background thread.
-        let tsfn = callback.build_threadsafe_function().build()?;
+        let options = options.unwrap_or_default();
+        let mut config = tapsdk_pc::TapSdkConfig::new(pub_key);
+        if let Some(capacity) = options.queue_capacity {
+            config.queue.capacity = Some(capacity as usize);
+        }
+        if let Some(policy) = options.overflow_policy {
+            config.queue.overflow = policy.into();
+        }
+        config.history_capacity = options.history_capacity.unwrap_or(0) as usize;
+        config.staging_dir = options.staging_dir.map(PathBuf::from);
+        let poll_interval = options
+            .poll_interval_ms
+            .map_or(POLL_INTERVAL, |ms| Duration::from_millis(ms.max(1) as u64));
+        config.poll_policy.interval = poll_interval;
+        let inner =
+            tapsdk_pc::TapSdk::init_with(config).map_err(|e| Error::from_reason(e.to_string()))?;
 
         let running = Arc::new(AtomicBool::new(true));
-        let running_clone = running.clone();
-
-        // Spawn a background thread with a tokio runtime that periodically
-        // polls the C SDK for events and pushes them to JavaScript.
-        let handle = std::thread::spawn(move || {
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_time()
-                .build()
-                .expect("Failed to create tokio runtime for event loop");
-
-            rt.block_on(async {
-                let mut interval = tokio::time::interval(std::time::Duration::from_millis(50));
-
-                while running_clone.load(Ordering::Relaxed) {
-                    interval.tick().await;
-                    let events = tapsdk_pc::callback::poll_events();
-                    for event in events {
-                        if let Ok(js_event) = convert_event_to_json(event) {
-                            tsfn.call(js_event, ThreadsafeFunctionCallMode::NonBlocking);
-                        }
-                    }
-                }
-            });
-        });
+        let loop_state = Arc::new(EventLoopState::default());
+        let binary_encoding = options.binary_encoding.unwrap_or_default();
+        let filter = EventFilter {
+            event_ids: options.event_ids,
+            coalesce_status_events: options.coalesce_status_events.unwrap_or(false),
+        };
+        let (event_loop, manual_poll) = if options.auto_poll.unwrap_or(true) {
+            let handle = spawn_event_loop(
+                callback,
+                options.max_batch_size,
+                poll_interval,
+                filter,
+                binary_encoding,
+                running.clone(),
+                loop_state.clone(),
+            )?;
+            (Some(handle), None)
+        } else {
+            (None, Some(filter))
+        };
+
+        let heartbeat = options
+            .heartbeat_interval_ms
+            .filter(|&ms| ms > 0)
+            .map(|ms| tapsdk_pc::heartbeat::start(Duration::from_millis(ms as u64)));
+        let watchdog = options
+            .request_timeout_ms
+            .filter(|&ms| ms > 0)
+            .map(|ms| tapsdk_pc::watchdog::start(Duration::from_millis(ms as u64)));
+
+        let instance = Arc::new(Mutex::new(Some(Instance {
+            sdk: inner,
+            event_loop,
+            heartbeat,
+            watchdog,
+        })));
+
+        // Tear down when the environment exits as well, in case `shutdown()`
+        // is never called. This is a no-op if it already was.
+        env.add_env_cleanup_hook(
+            (running.clone(), instance.clone()),
+            |(running, instance)| teardown(&running, &instance),
+        )?;
+        *current = Some((env_id, (running.clone(), instance.clone())));
 
         Ok(TapSdk {
-            inner: Some(inner),
+            instance,
             running,
-            handle: Some(handle),
+            loop_state,
+            manual_poll,
+            binary_encoding,
+            poll_interval,
         })
     }
 
+    /// Poll the SDK for events and return them, with the `autoPoll: false`
+    /// option
+    ///
+    /// Platform shutdown handlers are not supported in this mode.
+    #[napi(ts_return_type = "TapEvent[]")]
+    pub fn run_callbacks(&self) -> Result<Vec<JsEvent>, ErrorCode> {
+        self.check()?;
+        let filter = self.manual_poll.as_ref().ok_or_else(|| {
+            Error::new(
+                ErrorCode::GenericFailure,
+                "runCallbacks() requires the autoPoll: false option",
+            )
+        })?;
+        let envelopes = filter.poll();
+        self.loop_state
+            .events_delivered
+            .fetch_add(envelopes.len() as u64, Ordering::Relaxed);
+        self.loop_state
+            .last_poll_ms
+            .store(unix_millis(SystemTime::now()), Ordering::Relaxed);
+        Ok(envelopes
+            .into_iter()
+            .map(|envelope| JsEvent::new(envelope, self.binary_encoding))
+            .collect())
+    }
+
+    /// Get event pipeline diagnostics
+    #[napi]
+    pub fn get_diagnostics(&self) -> Diagnostics {
+        let stats = tapsdk_pc::callback::stats();
+        Diagnostics {
+            events_by_type: stats
+                .events_by_type
+                .into_iter()
+                .map(|(id, count)| (id.to_string(), count as i64))
+                .collect(),
+            total_events: stats.total_events as i64,
+            queue_high_water_mark: stats.queue_high_water_mark as u32,
+            dropped_conversions: (stats.dropped_conversions
+                + self.loop_state.conversion_failures.load(Ordering::Relaxed))
+                as i64,
+            poll_count: stats.poll_count as i64,
+            average_poll_latency_ms: stats.average_poll_latency.as_secs_f64() * 1000.0,
+            dropped_events: stats.dropped_events as i64,
+        }
+    }
+
+    /// Get statistics about the background event loop
+    ///
+    /// `isAlive` turns false if the background thread has stopped, either
+    /// because the SDK was shut down or because the thread died unexpectedly.
+    #[napi]
+    pub fn get_event_loop_stats(&self) -> EventLoopStats {
+        let last_poll_ms = self.loop_state.last_poll_ms.load(Ordering::Relaxed);
+        EventLoopStats {
+            poll_interval_ms: self.poll_interval.as_millis() as u32,
+            last_poll_time: (last_poll_ms != 0).then_some(last_poll_ms as f64),
+            events_delivered: self.loop_state.events_delivered.load(Ordering::Relaxed) as i64,
+            conversion_failures: self.loop_state.conversion_failures.load(Ordering::Relaxed) as i64,
+            is_alive: self.loop_state.alive.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Get the binding, SDK header and runtime DLL versions
+    #[napi]
+    pub fn version(&self) -> VersionInfo {
+        let version = tapsdk_pc::version();
+        VersionInfo {
+            binding_version: env!("CARGO_PKG_VERSION").to_string(),
+            sdk_header_version: version.sdk_header_version.to_string(),
+            dll_version: version.dll_version,
+        }
+    }
+
+    /// Get the command-line parameters the process was started with
+    #[napi]
+    pub fn get_launch_params(&self) -> LaunchParams {
+        let params = tapsdk_pc::sdk::launch_params();
+        LaunchParams {
+            args: params.args,
+            deep_link: params.deep_link,
+            params: params.params.into_iter().collect(),
+            launched_by_taptap: params.launched_by_taptap,
+        }
+    }
+
+    /// Keep the last `capacity` events for `getRecentEvents()` (0 disables)
+    #[napi]
+    pub fn set_event_history_capacity(&self, capacity: u32) {
+        tapsdk_pc::callback::set_history_capacity(capacity as usize);
+    }
+
+    /// Get the most recently received events, oldest first
+    ///
+    /// Empty unless enabled with `setEventHistoryCapacity()`.
+    #[napi(ts_return_type = "TapEvent[]")]
+    pub fn get_recent_events(&self) -> Vec<JsEvent> {
+        tapsdk_pc::callback::recent_events()
+            .into_iter()
+            .map(|envelope| JsEvent::new(envelope, self.binary_encoding))
+            .collect()
+    }
+
     /// Get the client ID
     #[napi]
-    pub fn get_client_id(&self) -> Option<String> {
-        self.inner.as_ref()?.get_client_id()
+    pub fn get_client_id(&self) -> Result<Option<String>, ErrorCode> {
+        let instance = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+        let instance = instance.as_ref().ok_or_else(shut_down_error)?;
+        Ok(instance.sdk.get_client_id())
     }
 
     /// Check if the SDK is initialized
@@ -392,158 +877,653 @@ impl TapSdk {
         tapsdk_pc::is_initialized()
     }
 
+    /// Check whether another copy of the game is already running
+    ///
+    /// The first call that finds no other copy claims the lock for the rest
+    /// of the process lifetime, so copies started later see this one.
+    /// Always false on platforms other than Windows.
+    #[napi]
+    pub fn is_another_instance_running(client_id: String) -> Result<bool> {
+        let mut held = SINGLE_INSTANCE.lock().unwrap_or_else(|e| e.into_inner());
+        if held.is_some() {
+            return Ok(false);
+        }
+        match tapsdk_pc::acquire_single_instance(&client_id) {
+            Ok(Some(instance)) => {
+                *held = Some(instance);
+                Ok(false)
+            }
+            Ok(None) => Ok(true),
+            Err(e) => Err(Error::from_reason(e.to_string())),
+        }
+    }
+
+    /// Receive the native log output, or stop receiving it with `null`
+    ///
+    /// `fields` always contains `target`, the Rust module that logged.
+    /// The logger may be called after `shutdown()` and does not keep the
+    /// process alive.
+    #[napi(
+        ts_args_type = "logger: ((level: 'error' | 'warn' | 'info' | 'debug' | 'trace', message: string, fields: Record<string, string>) => void) | null"
+    )]
+    pub fn set_logger(logger: Option<Function<'_, LogArgs, ()>>) -> Result<()> {
+        let Some(logger) = logger else {
+            logger::set(None);
+            return Ok(());
+        };
+        let tsfn = logger.build_threadsafe_function().weak::<true>().build()?;
+        logger::set(Some(Arc::new(move |level, message, fields| {
+            tsfn.call(
+                FnArgs::from((level.to_string(), message, fields)),
+                ThreadsafeFunctionCallMode::NonBlocking,
+            );
+        })));
+        Ok(())
+    }
+
     /// Request user authorization
     #[napi]
-    pub fn authorize(&self, scopes: String) -> Result<()> {
-        tapsdk_pc::user::authorize(&scopes).map_err(|e| Error::from_reason(e.to_string()))
+    pub fn authorize(&self, scopes: String) -> Result<(), ErrorCode> {
+        self.check()?;
+        tapsdk_pc::user::authorize(&scopes).map_err(sdk_error)
     }
 
     /// Get the current user's OpenID
     #[napi]
-    pub fn get_open_id(&self) -> Option<String> {
-        tapsdk_pc::user::get_open_id()
+    pub fn get_open_id(&self) -> Result<Option<String>, ErrorCode> {
+        self.check()?;
+        Ok(tapsdk_pc::user::get_open_id())
     }
 
     /// Check if the user owns the current game
     #[napi]
-    pub fn is_game_owned(&self) -> bool {
-        tapsdk_pc::ownership::is_game_owned()
+    pub fn is_game_owned(&self) -> Result<bool, ErrorCode> {
+        self.check()?;
+        Ok(tapsdk_pc::ownership::is_game_owned())
+    }
+
+    /// Check if the user can currently play the game
+    ///
+    /// Follows `GAME_PLAYABLE_STATUS_CHANGED` events, falling back to
+    /// ownership until the first one arrives.
+    #[napi]
+    pub fn is_game_playable(&self) -> Result<bool, ErrorCode> {
+        self.check()?;
+        Ok(tapsdk_pc::ownership::is_game_playable())
     }
 
     /// Check if the user owns a specific DLC
     #[napi]
-    pub fn is_dlc_owned(&self, dlc_id: String) -> bool {
-        tapsdk_pc::dlc::is_dlc_owned(&dlc_id)
+    pub fn is_dlc_owned(&self, dlc_id: String) -> Result<bool, ErrorCode> {
+        self.check()?;
+        Ok(tapsdk_pc::dlc::is_dlc_owned(&dlc_id))
+    }
+
+    /// Check if the user can currently play a specific DLC
+    ///
+    /// Follows `DLC_PLAYABLE_STATUS_CHANGED` events, falling back to
+    /// ownership until the first one for the DLC arrives.
+    #[napi]
+    pub fn is_dlc_playable(&self, dlc_id: String) -> Result<bool, ErrorCode> {
+        self.check()?;
+        Ok(tapsdk_pc::dlc::is_dlc_playable(&dlc_id))
     }
 
     /// Show the store page for a specific DLC
     #[napi]
-    pub fn show_dlc_store(&self, dlc_id: String) -> Result<bool> {
-        tapsdk_pc::dlc::show_dlc_store(&dlc_id).map_err(|e| Error::from_reason(e.to_string()))
+    pub fn show_dlc_store(&self, dlc_id: String) -> Result<bool, ErrorCode> {
+        self.check()?;
+        tapsdk_pc::dlc::show_dlc_store(&dlc_id).map_err(sdk_error)
+    }
+
+    /// Get a cloud save handle bound to this instance
+    ///
+    /// The handle fails with an error once this instance is shut down.
+    #[napi]
+    pub fn cloud_save(&self) -> Result<CloudSave, ErrorCode> {
+        self.check()?;
+        let inner = tapsdk_pc::CloudSave::get()
+            .ok_or_else(|| sdk_error("SDK not initialized or CloudSave unavailable"))?;
+        Ok(CloudSave {
+            inner,
+            sdk_running: Some(self.running.clone()),
+        })
+    }
+
+    /// Run a handler when TapTap announces it is shutting down.
+    ///
+    /// The `PlatformShutdown` state change is held back until
+    /// `platformShutdownHandled()` is called or `timeoutMs` (default 5000)
+    /// elapses, giving the handler time to start a final cloud save.
+    #[napi(ts_args_type = "handler: () => void, timeoutMs?: number")]
+    pub fn on_platform_shutdown(
+        &self,
+        handler: Function<'_, (), ()>,
+        timeout_ms: Option<u32>,
+    ) -> Result<(), ErrorCode> {
+        self.check()?;
+        // The handler would block the JavaScript thread that has to run it
+        if self.manual_poll.is_some() {
+            return Err(Error::new(
+                ErrorCode::GenericFailure,
+                "onPlatformShutdown() is not supported with the autoPoll: false option",
+            ));
+        }
+        let tsfn = handler
+            .build_threadsafe_function()
+            .build()
+            .map_err(sdk_error)?;
+        let timeout = timeout_ms.map_or(PLATFORM_SHUTDOWN_TIMEOUT, |ms| {
+            Duration::from_millis(ms as u64)
+        });
+        let running = self.running.clone();
+        let loop_state = self.loop_state.clone();
+
+        let instance = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+        let instance = instance.as_ref().ok_or_else(shut_down_error)?;
+        // Runs on the event loop thread, which `platformShutdownHandled()`
+        // and `shutdown()` unpark
+        instance.sdk.on_platform_shutdown(move || {
+            loop_state
+                .platform_shutdown_handled
+                .store(false, Ordering::SeqCst);
+            tsfn.call((), ThreadsafeFunctionCallMode::NonBlocking);
+            let deadline = std::time::Instant::now() + timeout;
+            while !loop_state.platform_shutdown_handled.load(Ordering::SeqCst)
+                && running.load(Ordering::Relaxed)
+            {
+                let now = std::time::Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                std::thread::park_timeout(deadline - now);
+            }
+        });
+        Ok(())
+    }
+
+    /// Signal that the platform shutdown handler has finished.
+    ///
+    /// Called by the TypeScript wrapper once the handler settles.
+    #[napi]
+    pub fn platform_shutdown_handled(&self) {
+        self.loop_state
+            .platform_shutdown_handled
+            .store(true, Ordering::SeqCst);
+        let instance = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(event_loop) = instance.as_ref().and_then(|i| i.event_loop.as_ref()) {
+            event_loop.thread().unpark();
+        }
     }
 
     /// Shut down the SDK and stop the background event loop.
     #[napi]
     pub fn shutdown(&mut self) {
-        // Signal the background thread to stop
-        self.running.store(false, Ordering::Relaxed);
+        teardown(&self.running, &self.instance);
+    }
 
-        // Wait for the background thread to finish
-        if let Some(handle) = self.handle.take() {
-            let _ = handle.join();
+    /// Wait for pending cloud save requests to complete, then shut down.
+    ///
+    /// Events keep being delivered to the callback while waiting. With the
+    /// `autoPoll: false` option the task polls by itself, and resolves to the
+    /// events it received for the caller to deliver. Shuts down anyway once
+    /// `timeoutMs` elapses.
+    #[napi(ts_return_type = "Promise<TapEvent[]>")]
+    pub fn shutdown_graceful(&self, timeout_ms: u32) -> AsyncTask<GracefulShutdown> {
+        AsyncTask::new(GracefulShutdown {
+            running: self.running.clone(),
+            instance: self.instance.clone(),
+            timeout: Duration::from_millis(timeout_ms as u64),
+            manual_poll: self.manual_poll.clone(),
+            binary_encoding: self.binary_encoding,
+        })
+    }
+}
+
+/// Background task behind `TapSdk.shutdownGraceful()`
+pub struct GracefulShutdown {
+    running: Arc<AtomicBool>,
+    instance: Arc<Mutex<Option<Instance>>>,
+    timeout: Duration,
+    /// Set when no event loop thread polls
+    manual_poll: Option<EventFilter>,
+    binary_encoding: BinaryEncoding,
+}
+
+impl Task for GracefulShutdown {
+    type Output = Vec<Envelope>;
+    type JsValue = Vec<JsEvent>;
+
+    fn compute(&mut self) -> Result<Vec<Envelope>> {
+        // Requests only resolve while something polls: the event loop thread,
+        // or else this task
+        let mut envelopes = Vec::new();
+        let deadline = std::time::Instant::now() + self.timeout;
+        while self.running.load(Ordering::Relaxed)
+            && !tapsdk_pc::callback::pending_requests().is_empty()
+            && std::time::Instant::now() < deadline
+        {
+            if let Some(filter) = &self.manual_poll {
+                envelopes.extend(filter.poll());
+            }
+            std::thread::sleep(Duration::from_millis(10));
         }
+        teardown(&self.running, &self.instance);
+        Ok(envelopes)
+    }
 
-        // Shut down the underlying SDK
-        if let Some(inner) = self.inner.take() {
-            inner.shutdown();
+    fn resolve(&mut self, _env: Env, envelopes: Vec<Envelope>) -> Result<Vec<JsEvent>> {
+        Ok(envelopes
+            .into_iter()
+            .map(|envelope| JsEvent::new(envelope, self.binary_encoding))
+            .collect())
+    }
+}
+
+impl TapSdk {
+    /// Fail with `SdkShutDown` after `shutdown()`
+    fn check(&self) -> Result<(), ErrorCode> {
+        if !self.running.load(Ordering::Relaxed) {
+            return Err(shut_down_error());
         }
+        Ok(())
     }
 }
 
 impl Drop for TapSdk {
     fn drop(&mut self) {
-        // Ensure the background thread is stopped if shutdown() wasn't called
-        self.running.store(false, Ordering::Relaxed);
-        if let Some(handle) = self.handle.take() {
-            let _ = handle.join();
+        // Ensure everything is torn down if shutdown() wasn't called
+        teardown(&self.running, &self.instance);
+    }
+}
+
+/// Which of the polled events to deliver
+#[derive(Clone)]
+struct EventFilter {
+    /// Only these event IDs, or every event
+    event_ids: Option<Vec<u32>>,
+    coalesce_status_events: bool,
+}
+
+impl EventFilter {
+    /// Poll the SDK for the events to deliver
+    fn poll(&self) -> Vec<Envelope> {
+        let envelopes = match &self.event_ids {
+            Some(ids) => tapsdk_pc::callback::poll_envelopes_filtered(ids),
+            None => tapsdk_pc::callback::poll_envelopes(),
+        };
+        if self.coalesce_status_events {
+            tapsdk_pc::callback::coalesce_status_events(envelopes)
+        } else {
+            envelopes
+        }
+    }
+}
+
+/// Spawn the background thread that polls the SDK for events and pushes
+/// them to JavaScript
+///
+/// It sleeps with `park_timeout` so shutdown can wake it immediately with
+/// `unpark`.
+fn spawn_event_loop(
+    callback: Function<'_, Vec<JsEvent>, ()>,
+    max_batch_size: Option<u32>,
+    poll_interval: Duration,
+    filter: EventFilter,
+    binary_encoding: BinaryEncoding,
+    running: Arc<AtomicBool>,
+    loop_state: Arc<EventLoopState>,
+) -> Result<std::thread::JoinHandle<()>> {
+    // Create a threadsafe function from the JS callback so we can call it
+    // from the background thread.
+    let tsfn = callback.build_threadsafe_function().build()?;
+    let max_batch_size = max_batch_size
+        .filter(|&size| size > 0)
+        .map_or(usize::MAX, |size| size as usize);
+    loop_state.alive.store(true, Ordering::SeqCst);
+
+    Ok(std::thread::spawn(move || {
+        let _alive = AliveGuard(loop_state.clone());
+
+        while running.load(Ordering::Relaxed) {
+            let mut events = filter
+                .poll()
+                .into_iter()
+                .map(|envelope| JsEvent::new(envelope, binary_encoding))
+                .peekable();
+            // One call into JavaScript per batch rather than per event
+            while events.peek().is_some() {
+                let batch: Vec<JsEvent> = events.by_ref().take(max_batch_size).collect();
+                loop_state
+                    .events_delivered
+                    .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                tsfn.call(batch, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+            loop_state
+                .last_poll_ms
+                .store(unix_millis(SystemTime::now()), Ordering::Relaxed);
+
+            std::thread::park_timeout(poll_interval);
+        }
+
+        // Release the callback on the way out so a torn-down instance
+        // no longer references its (possibly reloaded) context
+        drop(tsfn);
+    }))
+}
+
+/// Stop the event loop, wait for it to exit, then shut down the SDK
+///
+/// Shared by `shutdown()`, `Drop` and the environment cleanup hook; only the
+/// first call does anything.
+fn teardown(running: &AtomicBool, instance: &Mutex<Option<Instance>>) {
+    running.store(false, Ordering::Relaxed);
+    let instance = instance.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(Instance {
+        sdk,
+        event_loop,
+        heartbeat,
+        watchdog,
+    }) = instance
+    {
+        drop(heartbeat);
+        drop(watchdog);
+        if let Some(event_loop) = event_loop {
+            event_loop.thread().unpark();
+            let _ = event_loop.join();
+        }
+        sdk.shutdown();
+    }
+}
+
+const SHUT_DOWN_MESSAGE: &str = "TapSdk has been shut down";
+
+/// `code` of the errors thrown by `TapSdk` and `CloudSave` methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The SDK rejected the call
+    GenericFailure,
+    /// The SDK has been shut down
+    SdkShutDown,
+    /// The TapTap client is offline
+    PlatformOffline,
+}
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        match self {
+            ErrorCode::GenericFailure => "GenericFailure",
+            ErrorCode::SdkShutDown => error_code::SDK_SHUT_DOWN,
+            ErrorCode::PlatformOffline => error_code::PLATFORM_OFFLINE,
+        }
+    }
+}
+
+/// Load `taptap_api.dll`, or throw an error with code `SdkLibraryMissing`
+///
+/// The error carries the searched paths as `searchedPaths`.
+fn load_library(env: &Env) -> Result<()> {
+    let Err(searched) = library::load() else {
+        return Ok(());
+    };
+    let message = format!(
+        "taptap_api.dll could not be loaded. Searched:\n  {}",
+        searched.join("\n  ")
+    );
+    let mut error = env.create_error(Error::new(Status::GenericFailure, message))?;
+    error.set_named_property("code", error_code::SDK_LIBRARY_MISSING)?;
+    error.set_named_property("searchedPaths", searched)?;
+    env.throw(error)?;
+    Err(Error::from_status(Status::PendingException))
+}
+
+fn sdk_error(error: impl std::fmt::Display) -> Error<ErrorCode> {
+    Error::new(ErrorCode::GenericFailure, error.to_string())
+}
+
+/// Convert an error from a cloud save method
+///
+/// Requests the SDK refused to start throw an error carrying `resultCode`,
+/// `operation`, `requestId` and, for creates and updates, `saveName`.
+/// Requests not sent because the client is offline throw an error with
+/// code `PlatformOffline`.
+fn request_error(env: &Env, error: TapSdkError) -> Error<ErrorCode> {
+    if let TapSdkError::PlatformOffline = error {
+        return Error::new(ErrorCode::PlatformOffline, error.to_string());
+    }
+    let TapSdkError::CloudSaveRequestFailed {
+        code,
+        operation,
+        request_id,
+        save_name,
+        ..
+    } = &error
+    else {
+        return sdk_error(error);
+    };
+    let thrown = (|| -> Result<()> {
+        let message = error.to_string();
+        let mut js_error = env.create_error(Error::new(Status::GenericFailure, message))?;
+        js_error.set_named_property("resultCode", *code)?;
+        js_error.set_named_property("operation", operation_name(*operation))?;
+        js_error.set_named_property("requestId", *request_id)?;
+        if let Some(name) = save_name {
+            js_error.set_named_property("saveName", name.as_str())?;
         }
-        // inner's Drop will handle TapSDK_Shutdown() if not already taken
+        env.throw(js_error)
+    })();
+    if let Err(e) = thrown {
+        log::warn!("Failed to throw cloud save error: {}", e);
     }
+    // napi throws the pending exception instead of the error returned here
+    sdk_error(error)
+}
+
+/// Name of the `CloudSave` method that starts `operation`
+fn operation_name(operation: Operation) -> &'static str {
+    match operation {
+        Operation::Authorize => "authorize",
+        Operation::CloudSaveList => "list",
+        Operation::CloudSaveCreate => "create",
+        Operation::CloudSaveUpdate => "update",
+        Operation::CloudSaveDelete => "delete",
+        Operation::CloudSaveGetData => "getData",
+        Operation::CloudSaveGetCover => "getCover",
+    }
+}
+
+fn shut_down_error() -> Error<ErrorCode> {
+    Error::new(ErrorCode::SdkShutDown, SHUT_DOWN_MESSAGE)
 }
 
 /// Cloud save API
 #[napi]
 pub struct CloudSave {
     inner: tapsdk_pc::CloudSave,
+    /// Running flag of the owning `TapSdk`, for handles from `sdk.cloudSave()`
+    sdk_running: Option<Arc<AtomicBool>>,
 }
 
 #[napi]
 impl CloudSave {
     /// Get the cloud save singleton
     #[napi(factory)]
-    pub fn get() -> Result<Self> {
+    pub fn get() -> Result<Self, ErrorCode> {
         let inner = tapsdk_pc::CloudSave::get()
-            .ok_or_else(|| Error::from_reason("SDK not initialized or CloudSave unavailable"))?;
-        Ok(CloudSave { inner })
+            .ok_or_else(|| sdk_error("SDK not initialized or CloudSave unavailable"))?;
+        Ok(CloudSave {
+            inner,
+            sdk_running: None,
+        })
     }
 
     /// Request the list of cloud saves
     #[napi]
-    pub fn list(&self, request_id: i64) -> Result<()> {
+    pub fn list(&self, env: Env, request_id: i64) -> Result<(), ErrorCode> {
+        self.check()?;
         self.inner
             .list(request_id)
-            .map_err(|e| Error::from_reason(e.to_string()))
+            .map_err(|e| request_error(&env, e))
     }
 
     /// Create a new cloud save
+    ///
+    /// File contents given as a `Buffer` are written to a temporary file,
+    /// deleted once the response arrives.
     #[napi]
-    pub fn create(&self, request_id: i64, request: CreateSaveRequest) -> Result<()> {
-        let rust_request = tapsdk_pc::cloudsave::CreateSaveRequest {
-            name: request.name,
-            summary: request.summary,
-            extra: request.extra,
-            playtime: request.playtime,
-            data_file_path: PathBuf::from(request.data_file_path).into_boxed_path(),
-            cover_file_path: request
-                .cover_file_path
-                .map(|p| PathBuf::from(p).into_boxed_path()),
-        };
-
-        self.inner
-            .create(request_id, &rust_request)
-            .map_err(|e| Error::from_reason(e.to_string()))
+    pub fn create(
+        &self,
+        env: Env,
+        request_id: i64,
+        request: CreateSaveRequest,
+    ) -> Result<(), ErrorCode> {
+        self.check()?;
+        staged(&env, request_id, || {
+            let rust_request = tapsdk_pc::cloudsave::CreateSaveRequest {
+                name: request.name,
+                summary: request.summary,
+                extra: request.extra,
+                playtime: request_playtime(request.playtime)?,
+                data_file_path: save_file_path(request_id, request.data_file_path)?,
+                cover_file_path: request
+                    .cover_file_path
+                    .map(|file| save_file_path(request_id, file))
+                    .transpose()?,
+            };
+            self.inner.create(request_id, &rust_request)
+        })
     }
 
     /// Update an existing cloud save
+    ///
+    /// File contents given as a `Buffer` are written to a temporary file,
+    /// deleted once the response arrives.
     #[napi]
-    pub fn update(&self, request_id: i64, request: UpdateSaveRequest) -> Result<()> {
-        let rust_request = tapsdk_pc::cloudsave::UpdateSaveRequest {
-            uuid: request.uuid,
-            name: request.name,
-            summary: request.summary,
-            extra: request.extra,
-            playtime: request.playtime,
-            data_file_path: PathBuf::from(request.data_file_path).into_boxed_path(),
-            cover_file_path: request
-                .cover_file_path
-                .map(|p| PathBuf::from(p).into_boxed_path()),
-        };
-
-        self.inner
-            .update(request_id, &rust_request)
-            .map_err(|e| Error::from_reason(e.to_string()))
+    pub fn update(
+        &self,
+        env: Env,
+        request_id: i64,
+        request: UpdateSaveRequest,
+    ) -> Result<(), ErrorCode> {
+        self.check()?;
+        staged(&env, request_id, || {
+            let rust_request = tapsdk_pc::cloudsave::UpdateSaveRequest {
+                uuid: SaveUuid::new(request.uuid)?,
+                name: request.name,
+                summary: request.summary,
+                extra: request.extra,
+                playtime: request_playtime(request.playtime)?,
+                data_file_path: save_file_path(request_id, request.data_file_path)?,
+                cover_file_path: request
+                    .cover_file_path
+                    .map(|file| save_file_path(request_id, file))
+                    .transpose()?,
+            };
+            self.inner.update(request_id, &rust_request)
+        })
     }
 
     /// Delete a cloud save
     #[napi]
-    pub fn delete(&self, request_id: i64, uuid: String) -> Result<()> {
+    pub fn delete(&self, env: Env, request_id: i64, uuid: String) -> Result<(), ErrorCode> {
+        self.check()?;
+        let uuid = SaveUuid::new(uuid).map_err(sdk_error)?;
         self.inner
             .delete(request_id, &uuid)
-            .map_err(|e| Error::from_reason(e.to_string()))
+            .map_err(|e| request_error(&env, e))
     }
 
     /// Get the data file for a cloud save
     #[napi]
-    pub fn get_data(&self, request_id: i64, uuid: String, file_id: String) -> Result<()> {
+    pub fn get_data(
+        &self,
+        env: Env,
+        request_id: i64,
+        uuid: String,
+        file_id: String,
+    ) -> Result<(), ErrorCode> {
+        self.check()?;
+        let (uuid, file_id) = save_file_ids(uuid, file_id)?;
         self.inner
             .get_data(request_id, &uuid, &file_id)
-            .map_err(|e| Error::from_reason(e.to_string()))
+            .map_err(|e| request_error(&env, e))
     }
 
     /// Get the cover image for a cloud save
     #[napi]
-    pub fn get_cover(&self, request_id: i64, uuid: String, file_id: String) -> Result<()> {
+    pub fn get_cover(
+        &self,
+        env: Env,
+        request_id: i64,
+        uuid: String,
+        file_id: String,
+    ) -> Result<(), ErrorCode> {
+        self.check()?;
+        let (uuid, file_id) = save_file_ids(uuid, file_id)?;
         self.inner
             .get_cover(request_id, &uuid, &file_id)
-            .map_err(|e| Error::from_reason(e.to_string()))
+            .map_err(|e| request_error(&env, e))
+    }
+
+    /// Stop waiting for the response to a request
+    ///
+    /// The request no longer counts as pending, is not timed out by
+    /// `requestTimeoutMs`, and its response is dropped when it arrives.
+    /// Returns false if no request with this ID is pending.
+    #[napi]
+    pub fn cancel(&self, request_id: i64) -> bool {
+        tapsdk_pc::callback::cancel_request(request_id)
+    }
+
+    /// Fail with `SdkShutDown` once the SDK this handle came from is shut down
+    fn check(&self) -> Result<(), ErrorCode> {
+        let shut_down = match &self.sdk_running {
+            Some(running) => !running.load(Ordering::Relaxed),
+            None => !tapsdk_pc::is_initialized(),
+        };
+        if shut_down {
+            return Err(shut_down_error());
+        }
+        Ok(())
     }
 }
 
-fn system_state_to_u32(state: SystemState) -> u32 {
-    match state {
-        SystemState::Unknown => system_state::UNKNOWN,
-        SystemState::PlatformOnline => system_state::PLATFORM_ONLINE,
-        SystemState::PlatformOffline => system_state::PLATFORM_OFFLINE,
-        SystemState::PlatformShutdown => system_state::PLATFORM_SHUTDOWN,
+/// Playtime of a save request, which JS passes as a signed number
+fn request_playtime(playtime: i64) -> tapsdk_pc::Result<u64> {
+    u64::try_from(playtime).map_err(|_| {
+        TapSdkError::InvalidArgument(format!("playtime must not be negative: {}", playtime))
+    })
+}
+
+/// Validate the IDs of a save's files, passed as separate JS strings
+fn save_file_ids(uuid: String, file_id: String) -> Result<(SaveUuid, FileId), ErrorCode> {
+    let uuid = SaveUuid::new(uuid).map_err(sdk_error)?;
+    let file_id = FileId::new(file_id).map_err(sdk_error)?;
+    Ok((uuid, file_id))
+}
+
+/// Resolve a save file given as a path or as its contents
+fn save_file_path(request_id: i64, file: Either<String, Buffer>) -> tapsdk_pc::Result<Box<Path>> {
+    match file {
+        Either::A(path) => Ok(PathBuf::from(path).into_boxed_path()),
+        Either::B(data) => Ok(tapsdk_pc::staging::stage(request_id, &data)?.into_boxed_path()),
     }
 }
+
+/// Start a request that may stage files, deleting them if it fails to start
+fn staged(
+    env: &Env,
+    request_id: i64,
+    start: impl FnOnce() -> tapsdk_pc::Result<()>,
+) -> Result<(), ErrorCode> {
+    start().map_err(|e| {
+        tapsdk_pc::staging::release(request_id);
+        request_error(env, e)
+    })
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
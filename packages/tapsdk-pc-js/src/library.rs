@@ -0,0 +1,145 @@
+//! Lazy loading of `taptap_api.dll`
+//!
+//! The addon links `taptap_api.dll` with `/DELAYLOAD` (see `build.rs`), so
+//! `require()` succeeds without it. [`load`] maps the SDK library before the
+//! first call into the SDK, and a delay-load hook resolves imports of
+//! `taptap_api.dll` to whichever file was mapped.
+
+use std::sync::{Mutex, OnceLock};
+
+use tapsdk_pc::library::LibraryTrust;
+
+/// File names to probe, set with `setLibraryNames()` before the first load
+static NAMES: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Result of the first load, with the searched paths on failure
+static LOADED: OnceLock<Result<(), Vec<String>>> = OnceLock::new();
+
+/// Replace the file names probed for the SDK library
+///
+/// Returns `false` if loading has already been attempted.
+pub(crate) fn set_names(names: Vec<String>) -> bool {
+    if LOADED.get().is_some() {
+        return false;
+    }
+    *NAMES.lock().unwrap_or_else(|e| e.into_inner()) = Some(names);
+    true
+}
+
+/// Load the SDK library if it is not loaded yet
+///
+/// Each file name is tried in the directory of the addon first, then along
+/// the standard DLL search order. On failure, returns the searched paths.
+pub(crate) fn load() -> Result<(), Vec<String>> {
+    LOADED
+        .get_or_init(|| {
+            let names = NAMES.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let names: Vec<&str> = match &names {
+                Some(names) => names.iter().map(String::as_str).collect(),
+                None => tapsdk_pc::library::DEFAULT_NAMES.to_vec(),
+            };
+            let dirs: Vec<_> = platform::addon_dir().into_iter().collect();
+            match tapsdk_pc::library::load(&dirs, &names, &LibraryTrust::default()) {
+                Ok(_) => Ok(()),
+                Err(tapsdk_pc::TapSdkError::LibraryMissing { searched }) => Err(searched
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect()),
+                Err(e) => Err(vec![e.to_string()]),
+            }
+        })
+        .clone()
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::{c_char, c_void, CStr, OsString};
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::path::PathBuf;
+    use std::ptr;
+
+    const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+    const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: u32 = 0x4;
+    /// `dliNotePreLoadLibrary` from delayimp.h
+    const DLI_NOTE_PRE_LOAD_LIBRARY: u32 = 1;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: *mut *mut c_void)
+            -> i32;
+        fn GetModuleHandleW(module_name: *const u16) -> *mut c_void;
+        fn GetModuleFileNameW(module: *mut c_void, filename: *mut u16, size: u32) -> u32;
+    }
+
+    /// Directory containing the addon
+    ///
+    /// Node loads addons with their directory on the search path, but that
+    /// no longer applies once the addon itself has been loaded.
+    pub(super) fn addon_dir() -> Option<PathBuf> {
+        let mut module = ptr::null_mut();
+        let found = unsafe {
+            GetModuleHandleExW(
+                GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS
+                    | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+                addon_dir as *const () as *const u16,
+                &mut module,
+            )
+        };
+        if found == 0 {
+            return None;
+        }
+
+        let mut path = vec![0u16; 32768];
+        let len = unsafe { GetModuleFileNameW(module, path.as_mut_ptr(), path.len() as u32) };
+        if len == 0 || len as usize >= path.len() {
+            return None;
+        }
+        let path = PathBuf::from(OsString::from_wide(&path[..len as usize]));
+        path.parent().map(PathBuf::from)
+    }
+
+    /// `DelayLoadInfo` from delayimp.h, up to the fields read here
+    #[repr(C)]
+    struct DelayLoadInfo {
+        size: u32,
+        descriptor: *const c_void,
+        import_address: *mut c_void,
+        dll_name: *const c_char,
+    }
+
+    type DelayLoadHook = unsafe extern "system" fn(u32, *const DelayLoadInfo) -> *mut c_void;
+
+    /// Hand a renamed SDK library to the delay-load helper, which would
+    /// otherwise look for `taptap_api.dll` by name
+    unsafe extern "system" fn notify_hook(notify: u32, info: *const DelayLoadInfo) -> *mut c_void {
+        if notify != DLI_NOTE_PRE_LOAD_LIBRARY || info.is_null() {
+            return ptr::null_mut();
+        }
+        let name = unsafe { CStr::from_ptr((*info).dll_name) };
+        if !name.to_bytes().eq_ignore_ascii_case(b"taptap_api.dll") {
+            return ptr::null_mut();
+        }
+        match tapsdk_pc::library::loaded_path() {
+            Some(path) => {
+                let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+                unsafe { GetModuleHandleW(path.as_ptr()) }
+            }
+            None => ptr::null_mut(),
+        }
+    }
+
+    /// Picked up by delayimp.lib in place of its default (no) hook
+    #[no_mangle]
+    #[used]
+    #[allow(non_upper_case_globals)]
+    static __pfnDliNotifyHook2: DelayLoadHook = notify_hook;
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use std::path::PathBuf;
+
+    pub(super) fn addon_dir() -> Option<PathBuf> {
+        None
+    }
+}
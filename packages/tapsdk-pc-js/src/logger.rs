@@ -0,0 +1,80 @@
+//! Forwarding of the `log` output of `tapsdk-pc` to JavaScript
+//!
+//! Without a logger, records are discarded. `TapSdk.setLogger()` installs a
+//! sink that receives every record, from whichever thread emitted it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Once, RwLock};
+
+use log::kv::{self, VisitSource};
+
+type Sink = Arc<dyn Fn(&str, String, HashMap<String, String>) + Send + Sync>;
+
+/// Currently installed sink
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+static LOGGER: JsLogger = JsLogger;
+static INSTALL: Once = Once::new();
+
+/// Replace the sink receiving log records, or remove it with `None`
+pub(crate) fn set(sink: Option<Sink>) {
+    INSTALL.call_once(|| {
+        // Fails if the host process already installed a logger, in which
+        // case the records go there instead
+        let _ = log::set_logger(&LOGGER);
+    });
+    log::set_max_level(if sink.is_some() {
+        log::LevelFilter::Trace
+    } else {
+        log::LevelFilter::Off
+    });
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = sink;
+}
+
+/// Name of `level` as seen from JavaScript
+fn level_name(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warn",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+    }
+}
+
+/// Collects the key-values of a record as strings
+struct Fields(HashMap<String, String>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+struct JsLogger;
+
+impl log::Log for JsLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let sink = SINK.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let Some(sink) = sink else {
+            return;
+        };
+        let mut fields = Fields(HashMap::new());
+        fields
+            .0
+            .insert("target".to_string(), record.target().to_string());
+        let _ = record.key_values().visit(&mut fields);
+        sink(
+            level_name(record.level()),
+            record.args().to_string(),
+            fields.0,
+        );
+    }
+
+    fn flush(&self) {}
+}
@@ -2,18 +2,57 @@
  * TapTap PC SDK - Cloud Save functionality
  */
 
+import { fileURLToPath } from 'url';
 import { native } from './native.js';
-import type { CreateSaveRequest, UpdateSaveRequest } from './types.js';
+import type { TapSdk } from './sdk.js';
+import {
+  EventId,
+  type CloudSaveCreateEvent,
+  type CloudSaveDeleteEvent,
+  type CloudSaveGetCoverEvent,
+  type CloudSaveGetDataEvent,
+  type CloudSaveListEvent,
+  type CloudSaveProgressEvent,
+  type CloudSaveUpdateEvent,
+  type CreateSaveRequest,
+  type RequestOptions,
+  type SaveFile,
+  type TapEvent,
+  type TransferOptions,
+  type UpdateSaveRequest,
+} from './types.js';
+
+/** Turn `file://` URLs into paths; paths and contents pass through */
+function toNativeFile(file: SaveFile): string | Buffer {
+  if (file instanceof URL || (typeof file === 'string' && file.startsWith('file:'))) {
+    return fileURLToPath(file);
+  }
+  return file;
+}
+
+/** Convert the file fields of a save request for the native binding */
+function toNativeRequest<T extends CreateSaveRequest>(request: T) {
+  return {
+    ...request,
+    dataFilePath: toNativeFile(request.dataFilePath),
+    coverFilePath: request.coverFilePath === undefined
+      ? undefined
+      : toNativeFile(request.coverFilePath),
+  };
+}
 
 /**
  * Cloud save API
  *
+ * Every method throws an error with code `SdkShutDown` once the SDK has been
+ * shut down.
+ *
  * @example
  * ```typescript
  * import { TapSdk, CloudSave, EventId } from 'tapsdk-pc';
  *
  * const sdk = new TapSdk('your_public_key');
- * const cloudSave = CloudSave.get();
+ * const cloudSave = sdk.cloudSave();
  *
  * // Listen for cloud save events
  * sdk.on('event', (event) => {
@@ -28,9 +67,12 @@ import type { CreateSaveRequest, UpdateSaveRequest } from './types.js';
  */
 export class CloudSave {
   private readonly _native: ReturnType<typeof native.CloudSave.get>;
+  /** SDK delivering the events of this handle, for `onProgress` */
+  private readonly _sdk?: TapSdk;
 
-  private constructor(nativeInstance: ReturnType<typeof native.CloudSave.get>) {
+  private constructor(nativeInstance: ReturnType<typeof native.CloudSave.get>, sdk?: TapSdk) {
     this._native = nativeInstance;
+    this._sdk = sdk;
   }
 
   /**
@@ -44,6 +86,18 @@ export class CloudSave {
     return new CloudSave(nativeInstance);
   }
 
+  /**
+   * Wrap a native handle returned by the native `TapSdk.cloudSave()`
+   *
+   * @internal
+   */
+  static fromNative(
+    nativeInstance: ReturnType<typeof native.CloudSave.get>,
+    sdk?: TapSdk
+  ): CloudSave {
+    return new CloudSave(nativeInstance, sdk);
+  }
+
   /**
    * Request the list of cloud saves
    *
@@ -62,9 +116,12 @@ export class CloudSave {
    *
    * @param requestId - A unique ID to identify this request in the callback
    * @param request - The create request parameters
+   * @param options - `onProgress` receives the upload progress
    */
-  create(requestId: number, request: CreateSaveRequest): void {
-    this._native.create(requestId, request);
+  create(requestId: number, request: CreateSaveRequest, options?: TransferOptions): void {
+    this.withProgress(requestId, options, () =>
+      this._native.create(requestId, toNativeRequest(request))
+    );
   }
 
   /**
@@ -74,9 +131,12 @@ export class CloudSave {
    *
    * @param requestId - A unique ID to identify this request in the callback
    * @param request - The update request parameters
+   * @param options - `onProgress` receives the upload progress
    */
-  update(requestId: number, request: UpdateSaveRequest): void {
-    this._native.update(requestId, request);
+  update(requestId: number, request: UpdateSaveRequest, options?: TransferOptions): void {
+    this.withProgress(requestId, options, () =>
+      this._native.update(requestId, toNativeRequest(request))
+    );
   }
 
   /**
@@ -99,9 +159,10 @@ export class CloudSave {
    * @param requestId - A unique ID to identify this request in the callback
    * @param uuid - The unique ID of the cloud save
    * @param fileId - The file ID of the cloud save (from CloudSaveInfo)
+   * @param options - `onProgress` receives the download progress
    */
-  getData(requestId: number, uuid: string, fileId: string): void {
-    this._native.getData(requestId, uuid, fileId);
+  getData(requestId: number, uuid: string, fileId: string, options?: TransferOptions): void {
+    this.withProgress(requestId, options, () => this._native.getData(requestId, uuid, fileId));
   }
 
   /**
@@ -112,8 +173,205 @@ export class CloudSave {
    * @param requestId - A unique ID to identify this request in the callback
    * @param uuid - The unique ID of the cloud save
    * @param fileId - The file ID of the cloud save (from CloudSaveInfo)
+   * @param options - `onProgress` receives the download progress
+   */
+  getCover(requestId: number, uuid: string, fileId: string, options?: TransferOptions): void {
+    this.withProgress(requestId, options, () => this._native.getCover(requestId, uuid, fileId));
+  }
+
+  /**
+   * Request the list of cloud saves and wait for the response
+   *
+   * Like the other `*Async` methods, only available on handles from
+   * `sdk.cloudSave()`. SDK errors are reported in the response's `error`.
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param options - `signal` cancels waiting for the response
+   * @returns The CloudSaveList event
+   * @throws Error if the request fails to start, or the signal's reason if
+   *   it is aborted
+   */
+  listAsync(requestId: number, options?: RequestOptions): Promise<CloudSaveListEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_LIST, options, () =>
+      this._native.list(requestId)
+    );
+  }
+
+  /**
+   * Create a new cloud save and wait for the response
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param request - The create request parameters
+   * @param options - `onProgress` receives the upload progress, `signal`
+   *   cancels waiting for the response
+   * @returns The CloudSaveCreate event
+   */
+  createAsync(
+    requestId: number,
+    request: CreateSaveRequest,
+    options?: TransferOptions & RequestOptions
+  ): Promise<CloudSaveCreateEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_CREATE, options, () =>
+      this._native.create(requestId, toNativeRequest(request))
+    );
+  }
+
+  /**
+   * Update an existing cloud save and wait for the response
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param request - The update request parameters
+   * @param options - `onProgress` receives the upload progress, `signal`
+   *   cancels waiting for the response
+   * @returns The CloudSaveUpdate event
+   */
+  updateAsync(
+    requestId: number,
+    request: UpdateSaveRequest,
+    options?: TransferOptions & RequestOptions
+  ): Promise<CloudSaveUpdateEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_UPDATE, options, () =>
+      this._native.update(requestId, toNativeRequest(request))
+    );
+  }
+
+  /**
+   * Delete a cloud save and wait for the response
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param uuid - The unique ID of the cloud save to delete
+   * @param options - `signal` cancels waiting for the response
+   * @returns The CloudSaveDelete event
+   */
+  deleteAsync(
+    requestId: number,
+    uuid: string,
+    options?: RequestOptions
+  ): Promise<CloudSaveDeleteEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_DELETE, options, () =>
+      this._native.delete(requestId, uuid)
+    );
+  }
+
+  /**
+   * Get the data file for a cloud save and wait for it
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param uuid - The unique ID of the cloud save
+   * @param fileId - The file ID of the cloud save (from CloudSaveInfo)
+   * @param options - `onProgress` receives the download progress, `signal`
+   *   cancels waiting for the response
+   * @returns The CloudSaveGetData event
+   */
+  getDataAsync(
+    requestId: number,
+    uuid: string,
+    fileId: string,
+    options?: TransferOptions & RequestOptions
+  ): Promise<CloudSaveGetDataEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_GET_DATA, options, () =>
+      this._native.getData(requestId, uuid, fileId)
+    );
+  }
+
+  /**
+   * Get the cover image for a cloud save and wait for it
+   *
+   * @param requestId - A unique ID to identify this request
+   * @param uuid - The unique ID of the cloud save
+   * @param fileId - The file ID of the cloud save (from CloudSaveInfo)
+   * @param options - `onProgress` receives the download progress, `signal`
+   *   cancels waiting for the response
+   * @returns The CloudSaveGetCover event
+   */
+  getCoverAsync(
+    requestId: number,
+    uuid: string,
+    fileId: string,
+    options?: TransferOptions & RequestOptions
+  ): Promise<CloudSaveGetCoverEvent> {
+    return this.request(requestId, EventId.CLOUD_SAVE_GET_COVER, options, () =>
+      this._native.getCover(requestId, uuid, fileId)
+    );
+  }
+
+  /**
+   * Start a request and resolve to its response
+   *
+   * Aborting cancels the request in the native pending-request registry,
+   * so its response is dropped and it is not timed out later.
+   */
+  private async request<E extends TapEvent & { requestId: number }>(
+    requestId: number,
+    eventId: E['eventId'],
+    options: (TransferOptions & RequestOptions) | undefined,
+    start: () => void
+  ): Promise<E> {
+    const sdk = this._sdk;
+    if (!sdk) {
+      throw new Error('Promise-returning methods are only available on handles from sdk.cloudSave()');
+    }
+    const signal = options?.signal;
+    signal?.throwIfAborted();
+    // Events are never emitted while `start` runs, so listening afterwards
+    // cannot miss the response
+    const stopProgress = this.withProgress(requestId, options, start);
+    const onAbort = () => {
+      this._native.cancel(requestId);
+      stopProgress();
+    };
+    signal?.addEventListener('abort', onAbort, { once: true });
+    try {
+      return await sdk.waitFor<E>(
+        (event) => event.eventId === eventId && (event as E).requestId === requestId,
+        `the response to request ${requestId}`,
+        undefined,
+        signal
+      );
+    } finally {
+      signal?.removeEventListener('abort', onAbort);
+    }
+  }
+
+  /**
+   * Start a request, forwarding its progress events to `options.onProgress`
+   *
+   * @returns A function that stops forwarding them
    */
-  getCover(requestId: number, uuid: string, fileId: string): void {
-    this._native.getCover(requestId, uuid, fileId);
+  private withProgress(
+    requestId: number,
+    options: TransferOptions | undefined,
+    start: () => void
+  ): () => void {
+    const onProgress = options?.onProgress;
+    if (!onProgress) {
+      start();
+      return () => {};
+    }
+    const sdk = this._sdk;
+    if (!sdk) {
+      throw new Error('onProgress is only available on handles from sdk.cloudSave()');
+    }
+    const listener = (event: TapEvent) => {
+      if (event.eventId !== EventId.CLOUD_SAVE_PROGRESS) {
+        return;
+      }
+      const progress = event as CloudSaveProgressEvent;
+      if (progress.requestId !== requestId) {
+        return;
+      }
+      if (progress.stage === 'done') {
+        sdk.off('event', listener);
+      }
+      onProgress(progress);
+    };
+    sdk.on('event', listener);
+    try {
+      start();
+    } catch (e) {
+      sdk.off('event', listener);
+      throw e;
+    }
+    return () => sdk.off('event', listener);
   }
 }
@@ -6,16 +6,23 @@
  * @packageDocumentation
  */
 
-export { TapSdk } from './sdk.js';
+export { TapSdk, isLaunchedByTaptap, restartAppIfNecessary, setLibraryNames } from './sdk.js';
 export { CloudSave } from './cloudsave.js';
 export {
   EventId,
   SystemState,
+  ErrorCode,
+  SdkErrorCode,
+  EventName,
+  type SdkLibraryMissingError,
+  type CloudSaveRequestError,
   type AuthToken,
   type SdkError,
   type CloudSaveInfo,
+  type SaveFile,
   type CreateSaveRequest,
   type UpdateSaveRequest,
+  type BaseEvent,
   type SystemStateChangedEvent,
   type AuthorizeFinishedEvent,
   type GamePlayableStatusChangedEvent,
@@ -26,7 +33,26 @@ export {
   type CloudSaveDeleteEvent,
   type CloudSaveGetDataEvent,
   type CloudSaveGetCoverEvent,
+  type CloudSaveProgressEvent,
+  type TransferStage,
+  type TransferOptions,
+  type InternalErrorEvent,
+  type ParseErrorEvent,
+  type EventConversionFailedEvent,
+  type ConnectionEvent,
   type UnknownEvent,
   type TapEvent,
+  type TapEventMap,
   type TapSdkEvents,
+  type TapSdkOptions,
+  type OverflowPolicy,
+  type BinaryEncoding,
+  type WaitForEventOptions,
+  type RequestOptions,
+  type Diagnostics,
+  type EventLoopStats,
+  type VersionInfo,
+  type LaunchParams,
+  type LogLevel,
+  type Logger,
 } from './types.js';
@@ -10,9 +10,9 @@
 
 import { describe, it, expect } from 'vitest';
 import { native } from './native.js';
-import { TapSdk } from './sdk.js';
+import { TapSdk, isLaunchedByTaptap, restartAppIfNecessary } from './sdk.js';
 import { CloudSave } from './cloudsave.js';
-import { EventId, SystemState } from './types.js';
+import { ErrorCode, EventId, EventName, SdkErrorCode, SystemState } from './types.js';
 
 describe('Native Module Loading', () => {
   it('should load the native module', () => {
@@ -27,6 +27,16 @@ describe('Native Module Loading', () => {
     expect(native.event_id.AUTHORIZE_FINISHED).toBe(2002);
   });
 
+  it('should have error_code namespace', () => {
+    expect(native.error_code).toBeDefined();
+    expect(native.error_code.SDK_SHUT_DOWN).toBe(ErrorCode.SDK_SHUT_DOWN);
+    expect(native.error_code.SDK_LIBRARY_MISSING).toBe(ErrorCode.SDK_LIBRARY_MISSING);
+  });
+
+  it('should have sdk_error_code namespace', () => {
+    expect(native.sdk_error_code.CLOUD_SAVE_TIMEOUT).toBe(SdkErrorCode.CLOUD_SAVE_TIMEOUT);
+  });
+
   it('should have system_state namespace', () => {
     expect(native.system_state).toBeDefined();
     expect(native.system_state.PLATFORM_ONLINE).toBe(1);
@@ -47,12 +57,19 @@ describe('EventId Constants', () => {
     expect(EventId.CLOUD_SAVE_DELETE).toBe(6004);
     expect(EventId.CLOUD_SAVE_GET_DATA).toBe(6005);
     expect(EventId.CLOUD_SAVE_GET_COVER).toBe(6006);
+    expect(EventId.INTERNAL_ERROR).toBe(90001);
+    expect(EventId.PARSE_ERROR).toBe(90002);
+    expect(EventId.EVENT_CONVERSION_FAILED).toBe(90003);
+    expect(EventId.CONNECTION_LOST).toBe(90004);
+    expect(EventId.CONNECTION_RESTORED).toBe(90005);
+    expect(EventId.CLOUD_SAVE_PROGRESS).toBe(90006);
   });
 
   it('should match native module constants', () => {
     expect(EventId.SYSTEM_STATE_CHANGED).toBe(native.event_id.SYSTEM_STATE_CHANGED);
     expect(EventId.AUTHORIZE_FINISHED).toBe(native.event_id.AUTHORIZE_FINISHED);
     expect(EventId.CLOUD_SAVE_LIST).toBe(native.event_id.CLOUD_SAVE_LIST);
+    expect(EventId.EVENT_CONVERSION_FAILED).toBe(native.event_id.EVENT_CONVERSION_FAILED);
   });
 });
 
@@ -79,6 +96,16 @@ describe('TapSdk Static Methods', () => {
     expect(TapSdk.restartAppIfNecessary).toBeInstanceOf(Function);
   });
 
+  it('should not find another instance', () => {
+    expect(TapSdk.isAnotherInstanceRunning('test_client_id')).toBe(false);
+    expect(TapSdk.isAnotherInstanceRunning('test_client_id')).toBe(false);
+  });
+
+  it('should accept and clear a logger', () => {
+    expect(() => TapSdk.setLogger(() => {})).not.toThrow();
+    expect(() => TapSdk.setLogger(null)).not.toThrow();
+  });
+
   it('should report SDK as not initialized', () => {
     expect(TapSdk.isInitialized()).toBe(false);
   });
@@ -89,6 +116,22 @@ describe('TapSdk Static Methods', () => {
   });
 });
 
+describe('restartAppIfNecessary', () => {
+  it('should be exported by the native module', () => {
+    expect(native.restartAppIfNecessary).toBeInstanceOf(Function);
+  });
+
+  it('should return false when not in TapTap', () => {
+    expect(restartAppIfNecessary('test_client_id')).toBe(false);
+  });
+});
+
+describe('isLaunchedByTaptap', () => {
+  it('should return false when not in TapTap', () => {
+    expect(isLaunchedByTaptap()).toBe(false);
+  });
+});
+
 describe('TapSdk Initialization', () => {
   it('should fail initialization without TapTap client', () => {
     expect(() => new TapSdk('test_public_key')).toThrow();
@@ -128,13 +171,31 @@ describe('CloudSave', () => {
 
 describe('TapSdk Prototype Methods', () => {
   const methods = [
+    'on',
+    'off',
+    'once',
+    'addListener',
+    'removeListener',
+    'waitForEvent',
     'getClientId',
     'authorize',
+    'authorizeAsync',
     'getOpenId',
     'isGameOwned',
+    'isGamePlayable',
     'isDlcOwned',
+    'isDlcPlayable',
     'showDlcStore',
+    'cloudSave',
+    'getDiagnostics',
+    'getEventLoopStats',
+    'runCallbacks',
+    'version',
+    'setEventHistoryCapacity',
+    'getRecentEvents',
+    'onPlatformShutdown',
     'shutdown',
+    'shutdownGraceful',
   ] as const;
 
   it.each(methods)('should have %s method on prototype', (method) => {
@@ -150,6 +211,12 @@ describe('CloudSave Prototype Methods', () => {
     'delete',
     'getData',
     'getCover',
+    'listAsync',
+    'createAsync',
+    'updateAsync',
+    'deleteAsync',
+    'getDataAsync',
+    'getCoverAsync',
   ] as const;
 
   it.each(methods)('should have %s method on prototype', (method) => {
@@ -166,6 +233,13 @@ describe('Type Exports', () => {
   it('should export SystemState as const object', () => {
     expect(Object.keys(SystemState).length).toBe(4);
   });
+
+  it('should name every event except UNKNOWN', () => {
+    for (const id of Object.values(EventId)) {
+      expect(id in EventName).toBe(id !== EventId.UNKNOWN);
+    }
+    expect(EventName[EventId.CLOUD_SAVE_LIST]).toBe('cloudSaveList');
+  });
 });
 
 describe('Error Handling', () => {
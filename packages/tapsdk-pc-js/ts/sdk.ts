@@ -2,12 +2,90 @@
  * TapTap PC SDK - Main SDK class
  */
 
+import { CloudSave } from './cloudsave.js';
 import { native } from './native.js';
-import type { TapEvent, TapSdkEvents } from './types.js';
+import { EventId, EventName } from './types.js';
+import type {
+  AuthorizeFinishedEvent,
+  Diagnostics,
+  EventLoopStats,
+  LaunchParams,
+  Logger,
+  RequestOptions,
+  TapEvent,
+  TapSdkEvents,
+  TapSdkOptions,
+  UnknownEvent,
+  VersionInfo,
+  WaitForEventOptions,
+} from './types.js';
 
 type TapSdkEventName = keyof TapSdkEvents;
+
+/**
+ * Check if the app needs to restart (call before init)
+ *
+ * If this returns true, TapTap will relaunch the game - exit immediately.
+ * This does not construct or initialize anything.
+ *
+ * @example
+ * ```typescript
+ * import { restartAppIfNecessary } from 'tapsdk-pc';
+ *
+ * if (restartAppIfNecessary('your_client_id')) {
+ *   process.exit(0);
+ * }
+ * ```
+ *
+ * @param clientId - The client ID from TapTap developer center
+ * @returns true if app needs restart, false otherwise
+ * @throws `SdkLibraryMissingError` if `taptap_api.dll` cannot be loaded
+ */
+export function restartAppIfNecessary(clientId: string): boolean {
+  return native.restartAppIfNecessary(clientId);
+}
+
+/**
+ * Check whether the game was started by the TapTap client
+ *
+ * Unlike {@link restartAppIfNecessary}, this never relaunches the game and
+ * does not load the SDK, so launchers and crash reporters can branch on it
+ * safely. Always false on platforms other than Windows.
+ *
+ * @returns true if the parent process is the TapTap client
+ */
+export function isLaunchedByTaptap(): boolean {
+  return native.isLaunchedByTaptap();
+}
+
+/**
+ * Set the file names tried, in order, when loading the SDK library
+ *
+ * Defaults to `['taptap_api.dll']`. Use this for versioned or renamed DLLs
+ * shipped with newer TapTap clients. Each name is looked up next to the
+ * native addon, then along the standard DLL search order.
+ *
+ * @example
+ * ```typescript
+ * import { setLibraryNames } from 'tapsdk-pc';
+ *
+ * setLibraryNames(['taptap_api_v2.dll', 'taptap_api.dll']);
+ * ```
+ *
+ * @param names - DLL file names, most preferred first
+ * @returns false if the library was already loaded by
+ *   {@link restartAppIfNecessary} or `new TapSdk()`, in which case nothing changes
+ */
+export function setLibraryNames(names: string[]): boolean {
+  return native.setLibraryNames(names);
+}
 type TapSdkEventListener<K extends TapSdkEventName> = (...args: TapSdkEvents[K]) => void;
 
+/** The event type delivered under `Id`, or `UnknownEvent` for IDs without one */
+type EventOf<Id extends number> = [Extract<TapEvent, { eventId: Id }>] extends [never]
+  ? UnknownEvent
+  : Extract<TapEvent, { eventId: Id }>;
+
 /**
  * TapTap PC SDK wrapper for Node.js
  *
@@ -16,10 +94,10 @@ type TapSdkEventListener<K extends TapSdkEventName> = (...args: TapSdkEvents[K])
  *
  * @example
  * ```typescript
- * import { TapSdk, EventId, SystemState } from 'tapsdk-pc';
+ * import { TapSdk, EventId, SystemState, restartAppIfNecessary } from 'tapsdk-pc';
  *
  * // Check if restart is needed
- * if (TapSdk.restartAppIfNecessary('your_client_id')) {
+ * if (restartAppIfNecessary('your_client_id')) {
  *   process.exit(0);
  * }
  *
@@ -56,9 +134,10 @@ export class TapSdk {
    *
    * @param clientId - The client ID from TapTap developer center
    * @returns true if app needs restart, false otherwise
+   * @throws `SdkLibraryMissingError` if `taptap_api.dll` cannot be loaded
    */
   static restartAppIfNecessary(clientId: string): boolean {
-    return native.TapSdk.restartAppIfNecessary(clientId);
+    return restartAppIfNecessary(clientId);
   }
 
   /**
@@ -70,18 +149,95 @@ export class TapSdk {
     return native.TapSdk.isInitialized();
   }
 
+  /**
+   * Check whether another copy of the game is already running
+   *
+   * Players often launch a game twice from TapTap. Call this at startup and
+   * exit, or focus the existing window, if it returns true. The first call
+   * that finds no other copy claims the lock until the process exits.
+   *
+   * @example
+   * ```typescript
+   * if (TapSdk.isAnotherInstanceRunning('your_client_id')) {
+   *   app.quit();
+   * }
+   * ```
+   *
+   * @param clientId - The client ID from TapTap developer center
+   * @returns true if another copy holds the lock. Always false on platforms
+   * other than Windows.
+   */
+  static isAnotherInstanceRunning(clientId: string): boolean {
+    return native.TapSdk.isAnotherInstanceRunning(clientId);
+  }
+
+  /**
+   * Receive the native SDK log output
+   *
+   * Native logs are discarded unless a logger is set. The logger may be
+   * called from any point in the SDK lifecycle, including after
+   * `shutdown()`, and does not keep the process alive.
+   *
+   * @example
+   * ```typescript
+   * TapSdk.setLogger((level, message, fields) => {
+   *   log[level]({ ...fields, source: 'tapsdk' }, message);
+   * });
+   * ```
+   *
+   * @param logger - Called for each log record, or `null` to stop logging
+   */
+  static setLogger(logger: Logger | null): void {
+    native.TapSdk.setLogger(logger);
+  }
+
   /**
    * Initialize the SDK and start the background event loop.
    *
-   * Events will be emitted via the 'event' event.
+   * Events will be emitted via the 'event' event. Native code hands them
+   * over in batches, also emitted via the 'events' event.
    *
    * @param pubKey - The public key from TapTap developer center
-   * @throws Error if SDK initialization fails
+   * @param options - Initialization and event loop options
+   * @throws Error if SDK initialization fails, or
+   *   `SdkLibraryMissingError` if `taptap_api.dll` cannot be loaded
    */
-  constructor(pubKey: string) {
-    this._native = new native.TapSdk(pubKey, (event: TapEvent) => {
-      this.emit('event', event);
-    });
+  constructor(pubKey: string, options?: TapSdkOptions) {
+    this._native = new native.TapSdk(
+      pubKey,
+      (events: TapEvent[]) => this.deliver(events),
+      options
+    );
+  }
+
+  /**
+   * Poll for events and emit them, with the `autoPoll: false` option
+   *
+   * Listeners are called synchronously before this returns, so an engine
+   * with its own main loop can pump events once per frame. Handlers set
+   * with {@link onPlatformShutdown} are not supported in this mode.
+   *
+   * @example
+   * ```typescript
+   * const sdk = new TapSdk('your_public_key', { autoPoll: false });
+   * function frame() {
+   *   for (const event of sdk.runCallbacks()) {
+   *     handleEvent(event);
+   *   }
+   *   render();
+   * }
+   * ```
+   *
+   * @returns The events emitted, oldest first
+   * @throws Error if the SDK was created without `autoPoll: false`, or with
+   *   code `SdkShutDown` after {@link shutdown}
+   */
+  runCallbacks(): TapEvent[] {
+    const events = this._native.runCallbacks() as TapEvent[];
+    if (events.length > 0) {
+      this.deliver(events);
+    }
+    return events;
   }
 
   /**
@@ -116,6 +272,48 @@ export class TapSdk {
     return this;
   }
 
+  /**
+   * Add a listener for SDK events.
+   *
+   * Any number of listeners can be attached, so separate subsystems (such
+   * as an auth UI and a save system) can subscribe independently.
+   * `addListener(listener)` is shorthand for `on('event', listener)`.
+   *
+   * @param listener - Called with each event
+   * @returns This instance for chaining
+   */
+  addListener(listener: TapSdkEventListener<'event'>): this;
+  addListener<K extends TapSdkEventName>(eventName: K, listener: TapSdkEventListener<K>): this;
+  addListener<K extends TapSdkEventName>(
+    eventNameOrListener: K | TapSdkEventListener<'event'>,
+    listener?: TapSdkEventListener<K>
+  ): this {
+    if (typeof eventNameOrListener === 'function') {
+      return this.on('event', eventNameOrListener);
+    }
+    return this.on(eventNameOrListener, listener!);
+  }
+
+  /**
+   * Remove a listener added with {@link addListener} or {@link on}.
+   *
+   * Other listeners stay attached.
+   *
+   * @param listener - Listener to remove
+   * @returns This instance for chaining
+   */
+  removeListener(listener: TapSdkEventListener<'event'>): this;
+  removeListener<K extends TapSdkEventName>(eventName: K, listener: TapSdkEventListener<K>): this;
+  removeListener<K extends TapSdkEventName>(
+    eventNameOrListener: K | TapSdkEventListener<'event'>,
+    listener?: TapSdkEventListener<K>
+  ): this {
+    if (typeof eventNameOrListener === 'function') {
+      return this.off('event', eventNameOrListener);
+    }
+    return this.off(eventNameOrListener, listener!);
+  }
+
   /**
    * Register a one-time event listener.
    *
@@ -131,6 +329,81 @@ export class TapSdk {
     return this.on(eventName, wrapped);
   }
 
+  /**
+   * Wait for the next event with the given ID.
+   *
+   * @example
+   * ```typescript
+   * sdk.authorize('public_profile');
+   * const result = await sdk.waitForEvent(EventId.AUTHORIZE_FINISHED, 30000);
+   * if (result.token) {
+   *   console.log('Authorized');
+   * }
+   * ```
+   *
+   * @param eventId - Event ID to wait for
+   * @param timeoutMs - How long to wait before rejecting, in milliseconds
+   * @param options - `signal` cancels the wait, e.g. when the window closes
+   * @returns The matching event
+   * @throws Error if no matching event arrives within `timeoutMs`, or the
+   *   signal's reason if it is aborted
+   */
+  waitForEvent<Id extends number>(
+    eventId: Id,
+    timeoutMs: number,
+    options: WaitForEventOptions = {}
+  ): Promise<EventOf<Id>> {
+    return this.waitFor(
+      (event) => event.eventId === eventId,
+      `event ${eventId}`,
+      timeoutMs,
+      options.signal
+    );
+  }
+
+  /**
+   * Resolve to the first event `match` accepts, see {@link waitForEvent}
+   *
+   * @internal
+   */
+  waitFor<E extends TapEvent>(
+    match: (event: TapEvent) => boolean,
+    description: string,
+    timeoutMs?: number,
+    signal?: AbortSignal
+  ): Promise<E> {
+    return new Promise((resolve, reject) => {
+      if (signal?.aborted) {
+        reject(signal.reason);
+        return;
+      }
+      const cleanup = () => {
+        clearTimeout(timer);
+        this.off('event', listener);
+        signal?.removeEventListener('abort', onAbort);
+      };
+      const listener = (event: TapEvent) => {
+        if (match(event)) {
+          cleanup();
+          resolve(event as E);
+        }
+      };
+      const onAbort = () => {
+        cleanup();
+        reject(signal!.reason);
+      };
+      const timer =
+        timeoutMs === undefined
+          ? undefined
+          : setTimeout(() => {
+              cleanup();
+              reject(new Error(`Timed out after ${timeoutMs}ms waiting for ${description}`));
+            }, timeoutMs);
+      this.on('event', listener);
+      signal?.addEventListener('abort', onAbort, { once: true });
+    });
+  }
+
   /**
    * Remove all listeners, or all listeners for one event.
    *
@@ -158,10 +431,22 @@ export class TapSdk {
     }
   }
 
+  private deliver(events: TapEvent[]): void {
+    this.emit('events', events);
+    for (const event of events) {
+      this.emit('event', event);
+      const name = EventName[event.eventId as keyof typeof EventName];
+      if (name !== undefined) {
+        this.emit(name, event as never);
+      }
+    }
+  }
+
   /**
    * Get the client ID
    *
    * @returns The client ID or null if not available
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
    */
   getClientId(): string | null {
     return this._native.getClientId();
@@ -171,16 +456,49 @@ export class TapSdk {
    * Request user authorization
    *
    * @param scopes - Permission scopes to request (e.g., "public_profile")
-   * @throws Error if authorization request fails
+   * @throws Error if authorization request fails, or with code
+   *   `SdkShutDown` after {@link shutdown}
    */
   authorize(scopes: string): void {
     this._native.authorize(scopes);
   }
 
+  /**
+   * Request user authorization and wait for the result
+   *
+   * @example
+   * ```typescript
+   * const controller = new AbortController();
+   * window.addEventListener('beforeunload', () => controller.abort());
+   * const result = await sdk.authorizeAsync('public_profile', { signal: controller.signal });
+   * ```
+   *
+   * @param scopes - Permission scopes to request (e.g., "public_profile")
+   * @param options - `signal` stops waiting, e.g. when the window closes
+   * @returns The `AUTHORIZE_FINISHED` event
+   * @throws Error if the authorization request fails, with code
+   *   `SdkShutDown` after {@link shutdown}, or the signal's reason if it is
+   *   aborted
+   */
+  async authorizeAsync(
+    scopes: string,
+    options: RequestOptions = {}
+  ): Promise<AuthorizeFinishedEvent> {
+    options.signal?.throwIfAborted();
+    this.authorize(scopes);
+    return this.waitFor(
+      (event) => event.eventId === EventId.AUTHORIZE_FINISHED,
+      'authorization',
+      undefined,
+      options.signal
+    );
+  }
+
   /**
    * Get the current user's OpenID
    *
    * @returns The user's OpenID or null if not available
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
    */
   getOpenId(): string | null {
     return this._native.getOpenId();
@@ -190,38 +508,205 @@ export class TapSdk {
    * Check if the user owns the current game
    *
    * @returns true if user owns the game, false otherwise
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
    */
   isGameOwned(): boolean {
     return this._native.isGameOwned();
   }
 
+  /**
+   * Check if the user can currently play the game
+   *
+   * The SDK only reports changes with `GAME_PLAYABLE_STATUS_CHANGED`
+   * events. This returns the state of the last one, or ownership until the
+   * first arrives, so the main menu can be gated at startup.
+   *
+   * @returns true if the game is playable, false otherwise
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
+   */
+  isGamePlayable(): boolean {
+    return this._native.isGamePlayable();
+  }
+
   /**
    * Check if the user owns a specific DLC
    *
    * @param dlcId - The DLC identifier
    * @returns true if user owns the DLC, false otherwise
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
    */
   isDlcOwned(dlcId: string): boolean {
     return this._native.isDlcOwned(dlcId);
   }
 
+  /**
+   * Check if the user can currently play a specific DLC
+   *
+   * Owning a DLC does not mean it is playable, e.g. while it is still
+   * downloading. This returns the state of the last
+   * `DLC_PLAYABLE_STATUS_CHANGED` event for the DLC, or ownership until the
+   * first arrives.
+   *
+   * @param dlcId - The DLC identifier
+   * @returns true if the DLC is playable, false otherwise
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
+   */
+  isDlcPlayable(dlcId: string): boolean {
+    return this._native.isDlcPlayable(dlcId);
+  }
+
   /**
    * Show the store page for a specific DLC
    *
    * @param dlcId - The DLC identifier
    * @returns true if store page opened, false otherwise
+   * @throws Error with code `SdkShutDown` after {@link shutdown}
    */
   showDlcStore(dlcId: string): boolean {
     return this._native.showDlcStore(dlcId);
   }
 
+  /**
+   * Get a cloud save handle bound to this SDK instance
+   *
+   * Unlike {@link CloudSave.get}, the returned handle throws once this
+   * instance has been shut down.
+   *
+   * @returns CloudSave instance
+   * @throws Error with code `SdkShutDown` if this instance has been shut down
+   */
+  cloudSave(): CloudSave {
+    return CloudSave.fromNative(this._native.cloudSave(), this);
+  }
+
+  /**
+   * Get event pipeline diagnostics
+   *
+   * Useful for support tooling and bug reports.
+   *
+   * @returns Event counts, queue high-water mark, dropped conversions and poll latency
+   */
+  getDiagnostics(): Diagnostics {
+    return this._native.getDiagnostics();
+  }
+
+  /**
+   * Get statistics about the background event loop
+   *
+   * `isAlive` turns false if the background thread has stopped, either
+   * because the SDK was shut down or because the thread died unexpectedly.
+   *
+   * @returns Poll interval, last poll time, delivery counters and thread liveness
+   */
+  getEventLoopStats(): EventLoopStats {
+    return this._native.getEventLoopStats();
+  }
+
+  /**
+   * Get the binding, SDK header and runtime DLL versions
+   *
+   * Include this in bug reports to diagnose version mismatches.
+   *
+   * @returns Binding version, SDK header version and DLL file version
+   */
+  version(): VersionInfo {
+    return this._native.version();
+  }
+
+  /**
+   * Get the command-line parameters the process was started with
+   *
+   * A deep link such as `mygame://continue?from=store` on the command line
+   * is picked out and its query decoded into `params`, so the game can
+   * route flows that start outside of it.
+   *
+   * @returns Arguments, deep link and its query parameters
+   */
+  getLaunchParams(): LaunchParams {
+    return this._native.getLaunchParams();
+  }
+
+  /**
+   * Keep the last `capacity` received events for {@link getRecentEvents}
+   *
+   * History is disabled by default. Pass 0 to disable it again.
+   *
+   * @param capacity - Number of events to keep
+   */
+  setEventHistoryCapacity(capacity: number): void {
+    this._native.setEventHistoryCapacity(capacity);
+  }
+
+  /**
+   * Get the most recently received events, oldest first
+   *
+   * Useful for attaching what the SDK actually delivered to bug reports.
+   *
+   * @returns Recorded events, empty unless history is enabled
+   */
+  getRecentEvents(): TapEvent[] {
+    return this._native.getRecentEvents() as TapEvent[];
+  }
+
+  /**
+   * Run a handler when TapTap announces it is shutting down.
+   *
+   * The `PLATFORM_SHUTDOWN` state change is emitted only after the handler
+   * (or the promise it returns) settles, or after `timeoutMs` at most. Use
+   * it to start a final cloud save before the client closes. Replaces any
+   * previously registered handler.
+   *
+   * @example
+   * ```typescript
+   * sdk.onPlatformShutdown(async () => {
+   *   await writeSaveFile();
+   *   cloudSave.update(nextRequestId(), saveRequest);
+   * });
+   * ```
+   *
+   * @param handler - Called once when the shutdown is announced
+   * @param timeoutMs - Maximum time to wait for the handler (default 5000)
+   * @throws Error with code `SdkShutDown` after {@link shutdown}, or if the
+   *   SDK was created with `autoPoll: false`
+   */
+  onPlatformShutdown(handler: () => void | Promise<void>, timeoutMs?: number): void {
+    this._native.onPlatformShutdown(() => {
+      void Promise.resolve()
+        .then(handler)
+        .finally(() => this._native.platformShutdownHandled());
+    }, timeoutMs);
+  }
+
   /**
    * Shut down the SDK and stop the background event loop.
    *
-   * This releases all resources. The SDK instance cannot be used after this.
+   * This releases all resources. SDK methods called afterwards throw an
+   * error with code `SdkShutDown`; diagnostics and event history remain
+   * readable.
+   *
+   * If this is never called, the SDK is shut down when the process exits.
    */
   shutdown(): void {
     this._native.shutdown();
     this.removeAllListeners();
   }
+
+  /**
+   * Wait for pending cloud save requests to complete, then shut down.
+   *
+   * Events, including the responses being waited for, keep being emitted
+   * while waiting. With `autoPoll: false`, the SDK is polled in the
+   * background while waiting, and the events received are emitted just
+   * before the promise resolves. Once `timeoutMs` elapses the SDK is shut
+   * down anyway, abandoning any requests still in flight.
+   *
+   * @param timeoutMs - Maximum time to wait, in milliseconds
+   */
+  async shutdownGraceful(timeoutMs: number): Promise<void> {
+    const events = (await this._native.shutdownGraceful(timeoutMs)) as TapEvent[];
+    if (events.length > 0) {
+      this.deliver(events);
+    }
+    this.removeAllListeners();
+  }
 }
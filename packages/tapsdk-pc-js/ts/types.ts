@@ -27,10 +27,20 @@ export interface CloudSaveInfo {
   summary?: string;
   extra?: string;
   playtime: number;
+  /** Creation time in milliseconds since the Unix epoch, as used by `Date` */
   createdTime: number;
+  /** Last modification time in milliseconds since the Unix epoch */
   modifiedTime: number;
 }
 
+/**
+ * A save or cover file: a path, a `file://` URL, or the file contents
+ *
+ * Contents are written to a temporary file that is deleted once the
+ * response event arrives.
+ */
+export type SaveFile = string | URL | Buffer;
+
 /** Request to create a cloud save */
 export interface CreateSaveRequest {
   /** Save name (max 60 bytes, no Chinese characters) */
@@ -39,12 +49,12 @@ export interface CreateSaveRequest {
   summary: string;
   /** Developer-defined extra data (max 1000 bytes, optional) */
   extra?: string;
-  /** Game playtime in seconds */
+  /** Game playtime in seconds (at most 2^32 - 1, the SDK's limit) */
   playtime: number;
-  /** Path to the save data file (max 10MB) */
-  dataFilePath: string;
-  /** Path to the cover image file (max 512KB, optional) */
-  coverFilePath?: string;
+  /** Save data file (max 10MB) */
+  dataFilePath: SaveFile;
+  /** Cover image file (max 512KB, optional) */
+  coverFilePath?: SaveFile;
 }
 
 /** Request to update a cloud save */
@@ -57,24 +67,30 @@ export interface UpdateSaveRequest {
   summary: string;
   /** Developer-defined extra data (max 1000 bytes, optional) */
   extra?: string;
-  /** Game playtime in seconds */
+  /** Game playtime in seconds (at most 2^32 - 1, the SDK's limit) */
   playtime: number;
-  /** Path to the save data file (max 10MB) */
-  dataFilePath: string;
-  /** Path to the cover image file (max 512KB, optional) */
-  coverFilePath?: string;
+  /** Save data file (max 10MB) */
+  dataFilePath: SaveFile;
+  /** Cover image file (max 512KB, optional) */
+  coverFilePath?: SaveFile;
 }
 
 // Event Types
 
+/** Fields shared by every event */
+export interface BaseEvent {
+  /** Time the event was received from the SDK, in milliseconds since the Unix epoch */
+  receivedAt: number;
+}
+
 /** System state changed event */
-export interface SystemStateChangedEvent {
+export interface SystemStateChangedEvent extends BaseEvent {
   eventId: typeof EventId.SYSTEM_STATE_CHANGED;
   state: SystemState;
 }
 
 /** Authorization finished event */
-export interface AuthorizeFinishedEvent {
+export interface AuthorizeFinishedEvent extends BaseEvent {
   eventId: typeof EventId.AUTHORIZE_FINISHED;
   isCancel: boolean;
   error?: string;
@@ -82,20 +98,20 @@ export interface AuthorizeFinishedEvent {
 }
 
 /** Game playable status changed event */
-export interface GamePlayableStatusChangedEvent {
+export interface GamePlayableStatusChangedEvent extends BaseEvent {
   eventId: typeof EventId.GAME_PLAYABLE_STATUS_CHANGED;
   isPlayable: boolean;
 }
 
 /** DLC playable status changed event */
-export interface DlcPlayableStatusChangedEvent {
+export interface DlcPlayableStatusChangedEvent extends BaseEvent {
   eventId: typeof EventId.DLC_PLAYABLE_STATUS_CHANGED;
   dlcId: string;
   isPlayable: boolean;
 }
 
 /** Cloud save list event */
-export interface CloudSaveListEvent {
+export interface CloudSaveListEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_LIST;
   requestId: number;
   error?: SdkError;
@@ -103,7 +119,7 @@ export interface CloudSaveListEvent {
 }
 
 /** Cloud save create event */
-export interface CloudSaveCreateEvent {
+export interface CloudSaveCreateEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_CREATE;
   requestId: number;
   error?: SdkError;
@@ -111,7 +127,7 @@ export interface CloudSaveCreateEvent {
 }
 
 /** Cloud save update event */
-export interface CloudSaveUpdateEvent {
+export interface CloudSaveUpdateEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_UPDATE;
   requestId: number;
   error?: SdkError;
@@ -119,7 +135,7 @@ export interface CloudSaveUpdateEvent {
 }
 
 /** Cloud save delete event */
-export interface CloudSaveDeleteEvent {
+export interface CloudSaveDeleteEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_DELETE;
   requestId: number;
   error?: SdkError;
@@ -127,7 +143,7 @@ export interface CloudSaveDeleteEvent {
 }
 
 /** Cloud save get data event */
-export interface CloudSaveGetDataEvent {
+export interface CloudSaveGetDataEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_GET_DATA;
   requestId: number;
   error?: SdkError;
@@ -135,15 +151,77 @@ export interface CloudSaveGetDataEvent {
 }
 
 /** Cloud save get cover event */
-export interface CloudSaveGetCoverEvent {
+export interface CloudSaveGetCoverEvent extends BaseEvent {
   eventId: typeof EventId.CLOUD_SAVE_GET_COVER;
   requestId: number;
   error?: SdkError;
+  /** Detected image type, absent if the data is not PNG, JPEG or WebP */
+  contentType?: 'image/png' | 'image/jpeg' | 'image/webp';
   data: Buffer;
 }
 
+/** Stage of a cloud save transfer, see {@link CloudSaveProgressEvent} */
+export type TransferStage = 'reading' | 'uploading' | 'waitingForServer' | 'done';
+
+/**
+ * Progress of a cloud save upload or download
+ *
+ * The SDK reports no progress of its own. Uploads go through `reading`,
+ * `uploading` and `done`; downloads through `waitingForServer`, `reading`
+ * (once per MiB copied) and `done`. `done` comes right before the response.
+ */
+export interface CloudSaveProgressEvent extends BaseEvent {
+  eventId: typeof EventId.CLOUD_SAVE_PROGRESS;
+  requestId: number;
+  stage: TransferStage;
+  /** Bytes transferred so far */
+  bytes: number;
+  /** Total size in bytes, or 0 while unknown */
+  total: number;
+}
+
+/** Options for cloud save uploads and downloads */
+export interface TransferOptions {
+  /**
+   * Called with each progress event of the request, up to and including
+   * the `done` stage
+   *
+   * Only available on handles from `sdk.cloudSave()`.
+   */
+  onProgress?: (progress: CloudSaveProgressEvent) => void;
+}
+
+/** Emitted when handling an SDK callback failed inside the native module */
+export interface InternalErrorEvent extends BaseEvent {
+  eventId: typeof EventId.INTERNAL_ERROR;
+  /** ID of the SDK event that was being handled, or 0 if none */
+  sourceEventId: number;
+  message: string;
+}
+
+/** Emitted when an SDK event carried invalid data and was discarded */
+export interface ParseErrorEvent extends BaseEvent {
+  eventId: typeof EventId.PARSE_ERROR;
+  /** ID of the SDK event that failed to parse */
+  sourceEventId: number;
+  reason: string;
+}
+
+/** Emitted when a received event could not be converted to a JavaScript value */
+export interface EventConversionFailedEvent extends BaseEvent {
+  eventId: typeof EventId.EVENT_CONVERSION_FAILED;
+  /** ID of the event that could not be converted */
+  sourceEventId: number;
+  reason: string;
+}
+
+/** Emitted when the TapTap client stops running, or runs again after that */
+export interface ConnectionEvent extends BaseEvent {
+  eventId: typeof EventId.CONNECTION_LOST | typeof EventId.CONNECTION_RESTORED;
+}
+
 /** Unknown event */
-export interface UnknownEvent {
+export interface UnknownEvent extends BaseEvent {
   eventId: number;
 }
 
@@ -159,8 +237,182 @@ export type TapEvent =
   | CloudSaveDeleteEvent
   | CloudSaveGetDataEvent
   | CloudSaveGetCoverEvent
+  | CloudSaveProgressEvent
+  | InternalErrorEvent
+  | ParseErrorEvent
+  | EventConversionFailedEvent
+  | ConnectionEvent
   | UnknownEvent;
 
+// Diagnostics
+
+/** Event pipeline diagnostics for support tooling */
+export interface Diagnostics {
+  /** Number of events received, keyed by event ID */
+  eventsByType: Record<string, number>;
+  /** Total number of events received */
+  totalEvents: number;
+  /** Largest number of events that were waiting in the queue at once */
+  queueHighWaterMark: number;
+  /** Events that could not be converted and were dropped */
+  droppedConversions: number;
+  /** Number of completed polls */
+  pollCount: number;
+  /** Average time spent in a single poll, in milliseconds */
+  averagePollLatencyMs: number;
+  /** Events discarded because the queue was full */
+  droppedEvents: number;
+}
+
+/** Background event loop statistics */
+export interface EventLoopStats {
+  /** Interval between polls, in milliseconds */
+  pollIntervalMs: number;
+  /** Time of the last completed poll, in milliseconds since the Unix epoch */
+  lastPollTime: number | null;
+  /** Number of events delivered to the JavaScript callback */
+  eventsDelivered: number;
+  /** Number of events whose JSON conversion failed */
+  conversionFailures: number;
+  /** Whether the background thread is still running */
+  isAlive: boolean;
+}
+
+/** Versions of the bindings, SDK headers and runtime DLL */
+export interface VersionInfo {
+  /** Version of the native bindings */
+  bindingVersion: string;
+  /** TapTap PC SDK release the bindings were generated from */
+  sdkHeaderVersion: string;
+  /** File version of the loaded `taptap_api.dll`, if it could be read */
+  dllVersion: string | null;
+}
+
+/** Severity of a native log record */
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/**
+ * Receives native log records, see `TapSdk.setLogger()`
+ *
+ * `fields` always contains `target`, the native module that logged, plus
+ * any structured values attached to the record.
+ */
+export type Logger = (level: LogLevel, message: string, fields: Record<string, string>) => void;
+
+/** Command-line parameters returned by `sdk.getLaunchParams()` */
+export interface LaunchParams {
+  /** Arguments of the native process, without the executable path */
+  args: string[];
+  /** The first argument that looks like a URL (`scheme://...`) */
+  deepLink: string | null;
+  /** Decoded query parameters of the deep link */
+  params: Record<string, string>;
+  /** Whether the TapTap client started the process */
+  launchedByTaptap: boolean;
+}
+
+/** Options for `TapSdk.waitForEvent()` */
+export interface WaitForEventOptions {
+  /** Stops waiting and rejects with the signal's reason when aborted */
+  signal?: AbortSignal;
+}
+
+/** Options for the promise-returning request methods, such as `cloudSave.listAsync()` */
+export interface RequestOptions {
+  /**
+   * Stops waiting and rejects with the signal's reason when aborted
+   *
+   * An aborted cloud save request no longer counts as pending, is not
+   * timed out by `requestTimeoutMs`, and its response is dropped.
+   */
+  signal?: AbortSignal;
+}
+
+/** What to do when an event arrives while the event queue is full */
+export type OverflowPolicy =
+  /** Discard the oldest queued event to make room */
+  | 'dropOldest'
+  /** Discard the incoming event */
+  | 'dropNewest'
+  /** Keep only the latest status event of each kind */
+  | 'coalesce';
+
+/**
+ * How the file contents of cloud save data and cover events are passed
+ */
+export type BinaryEncoding =
+  /** A `Buffer` wrapping the downloaded bytes */
+  | 'buffer'
+  /** A copy in a plain `ArrayBuffer` */
+  | 'arrayBuffer'
+  /** A base64 string */
+  | 'base64';
+
+/** Options for {@link TapSdk} */
+export interface TapSdkOptions {
+  /**
+   * Maximum number of events delivered from native code at once
+   *
+   * Defaults to every event received in one poll. Set to 1 to cross into
+   * JavaScript once per event.
+   */
+  maxBatchSize?: number;
+  /** Maximum number of events waiting to be delivered (default 4096) */
+  queueCapacity?: number;
+  /** What to do when the queue is full (default `'dropOldest'`) */
+  overflowPolicy?: OverflowPolicy;
+  /** Number of events kept for `getRecentEvents()` (default 0, disabled) */
+  historyCapacity?: number;
+  /**
+   * Directory for temporary files holding `Buffer` save contents
+   *
+   * Defaults to `tapsdk-pc` in the system temp directory.
+   */
+  stagingDir?: string;
+  /**
+   * Check for the TapTap client this often, in milliseconds, and emit
+   * `CONNECTION_LOST` / `CONNECTION_RESTORED` events (default 0, disabled)
+   */
+  heartbeatIntervalMs?: number;
+  /**
+   * Synthesize a response with error code `SdkErrorCode.CLOUD_SAVE_TIMEOUT`
+   * for cloud save requests without a response after this many
+   * milliseconds (default 0, disabled)
+   */
+  requestTimeoutMs?: number;
+  /**
+   * Only emit events with these IDs, e.g. `[EventId.CLOUD_SAVE_LIST]`
+   *
+   * Defaults to every event. Other events, including the internal ones such
+   * as `INTERNAL_ERROR`, are discarded in native code.
+   */
+  eventIds?: number[];
+  /**
+   * Poll for events on a background thread (default true)
+   *
+   * With `false`, no thread is started and events are only delivered when
+   * the game calls `runCallbacks()`, e.g. once per frame of its main loop.
+   */
+  autoPoll?: boolean;
+  /** How often the background thread polls for events, in milliseconds (default 50, at least 1) */
+  pollIntervalMs?: number;
+  /**
+   * How the `data` of `CLOUD_SAVE_GET_DATA` and `CLOUD_SAVE_GET_COVER`
+   * events is passed (default `'buffer'`)
+   *
+   * With `'arrayBuffer'` or `'base64'`, every event can be forwarded with
+   * `postMessage` or Electron IPC and arrives unchanged. The event types
+   * still declare `data` as a `Buffer`; cast it to the chosen encoding.
+   */
+  binaryEncoding?: BinaryEncoding;
+  /**
+   * Emit at most one `SYSTEM_STATE_CHANGED`, one
+   * `GAME_PLAYABLE_STATUS_CHANGED` and one `DLC_PLAYABLE_STATUS_CHANGED`
+   * per DLC in each poll, the latest of each (default false)
+   */
+  coalesceStatusEvents?: boolean;
+}
+
 // Constants
 
 /** Event ID constants */
@@ -176,6 +428,12 @@ export const EventId = {
   CLOUD_SAVE_DELETE: 6004,
   CLOUD_SAVE_GET_DATA: 6005,
   CLOUD_SAVE_GET_COVER: 6006,
+  INTERNAL_ERROR: 90001,
+  PARSE_ERROR: 90002,
+  EVENT_CONVERSION_FAILED: 90003,
+  CONNECTION_LOST: 90004,
+  CONNECTION_RESTORED: 90005,
+  CLOUD_SAVE_PROGRESS: 90006,
 } as const;
 
 /** System state constants */
@@ -188,7 +446,94 @@ export const SystemState = {
 
 export type SystemState = (typeof SystemState)[keyof typeof SystemState];
 
+/** `code` values of errors thrown by SDK methods */
+export const ErrorCode = {
+  /** The method was called after `shutdown()` */
+  SDK_SHUT_DOWN: 'SdkShutDown',
+  /** `taptap_api.dll` could not be loaded */
+  SDK_LIBRARY_MISSING: 'SdkLibraryMissing',
+  /** A cloud save method was called while the TapTap client is offline */
+  PLATFORM_OFFLINE: 'PlatformOffline',
+} as const;
+
+export type ErrorCode = (typeof ErrorCode)[keyof typeof ErrorCode];
+
+/** SDK `error.code` values also used by responses generated by the binding */
+export const SdkErrorCode = {
+  /** No response arrived within the `requestTimeoutMs` option */
+  CLOUD_SAVE_TIMEOUT: 400006,
+} as const;
+
+/** Error thrown when `taptap_api.dll` cannot be loaded */
+export interface SdkLibraryMissingError extends Error {
+  code: typeof ErrorCode.SDK_LIBRARY_MISSING;
+  /** Paths where the DLL was looked for */
+  searchedPaths: string[];
+}
+
+/** Error thrown when the SDK refuses to start a cloud save request */
+export interface CloudSaveRequestError extends Error {
+  code: 'GenericFailure';
+  /** Result code returned by the SDK */
+  resultCode: number;
+  /** `CloudSave` method that failed */
+  operation: 'list' | 'create' | 'update' | 'delete' | 'getData' | 'getCover';
+  requestId: number;
+  /** Name of the save being created or updated */
+  saveName?: string;
+}
+
+/**
+ * Events by name, so listeners for one kind receive its type directly
+ *
+ * `sdk.on('cloudSaveList', (event) => ...)` receives only
+ * {@link CloudSaveListEvent}s.
+ */
+export interface TapEventMap {
+  systemStateChanged: SystemStateChangedEvent;
+  authorizeFinished: AuthorizeFinishedEvent;
+  gamePlayableStatusChanged: GamePlayableStatusChangedEvent;
+  dlcPlayableStatusChanged: DlcPlayableStatusChangedEvent;
+  cloudSaveList: CloudSaveListEvent;
+  cloudSaveCreate: CloudSaveCreateEvent;
+  cloudSaveUpdate: CloudSaveUpdateEvent;
+  cloudSaveDelete: CloudSaveDeleteEvent;
+  cloudSaveGetData: CloudSaveGetDataEvent;
+  cloudSaveGetCover: CloudSaveGetCoverEvent;
+  cloudSaveProgress: CloudSaveProgressEvent;
+  internalError: InternalErrorEvent;
+  parseError: ParseErrorEvent;
+  eventConversionFailed: EventConversionFailedEvent;
+  connectionLost: ConnectionEvent;
+  connectionRestored: ConnectionEvent;
+}
+
+/** The {@link TapEventMap} name each event is also emitted under */
+export const EventName = {
+  [EventId.SYSTEM_STATE_CHANGED]: 'systemStateChanged',
+  [EventId.AUTHORIZE_FINISHED]: 'authorizeFinished',
+  [EventId.GAME_PLAYABLE_STATUS_CHANGED]: 'gamePlayableStatusChanged',
+  [EventId.DLC_PLAYABLE_STATUS_CHANGED]: 'dlcPlayableStatusChanged',
+  [EventId.CLOUD_SAVE_LIST]: 'cloudSaveList',
+  [EventId.CLOUD_SAVE_CREATE]: 'cloudSaveCreate',
+  [EventId.CLOUD_SAVE_UPDATE]: 'cloudSaveUpdate',
+  [EventId.CLOUD_SAVE_DELETE]: 'cloudSaveDelete',
+  [EventId.CLOUD_SAVE_GET_DATA]: 'cloudSaveGetData',
+  [EventId.CLOUD_SAVE_GET_COVER]: 'cloudSaveGetCover',
+  [EventId.CLOUD_SAVE_PROGRESS]: 'cloudSaveProgress',
+  [EventId.INTERNAL_ERROR]: 'internalError',
+  [EventId.PARSE_ERROR]: 'parseError',
+  [EventId.EVENT_CONVERSION_FAILED]: 'eventConversionFailed',
+  [EventId.CONNECTION_LOST]: 'connectionLost',
+  [EventId.CONNECTION_RESTORED]: 'connectionRestored',
+} as const satisfies Record<number, keyof TapEventMap>;
+
 /** Event map for typed EventEmitter usage */
-export interface TapSdkEvents {
+export interface TapSdkEvents extends TapEventListenerArgs {
   event: [TapEvent];
+  /** Events received from native code in one batch, before `event` fires for each */
+  events: [TapEvent[]];
 }
+
+/** Listener arguments of the {@link TapEventMap} names */
+type TapEventListenerArgs = { [K in keyof TapEventMap]: [TapEventMap[K]] };